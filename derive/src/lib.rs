@@ -0,0 +1,161 @@
+//! Derive macros for `IntoValue` and `FromValue`, which convert Rust types to and from values.
+//! These are re-exported by `unnamed_language::interpreter::value` with the `derive` feature.
+//!
+//! Structs with named fields are converted to and from maps with a string key for each field,
+//! enums with only unit variants are converted to and from the names of their variants.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Generics};
+
+#[proc_macro_derive(IntoValue)]
+pub fn derive_into_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_value(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromValue)]
+pub fn derive_from_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_value(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn into_value(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let generics = bound(
+        &input.generics,
+        parse_quote!(::unnamed_language::interpreter::value::IntoValue),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = named_fields(&data.fields, &input)?;
+            let keys = fields.iter().map(|field| field.to_string());
+            quote! {
+                let mut map = ::unnamed_language::interpreter::value::MapBuilder::new();
+                #(map.field(heap, #keys, self.#fields);)*
+                map.build(heap)
+            }
+        }
+        Data::Enum(data) => {
+            let variants = unit_variants(data, &input)?;
+            let names = variants.iter().map(|variant| variant.to_string());
+            quote! {
+                let name = match self {
+                    #(Self::#variants => #names,)*
+                };
+                heap.alloc_string(name)
+            }
+        }
+        Data::Union(_) => return Err(Error::new_spanned(&input.ident, "unions aren't supported")),
+    };
+
+    // The conversion only keeps what the fields' own conversions keep, which is nothing since
+    // `IntoValue` is unsafe to implement.
+    Ok(quote! {
+        unsafe impl #impl_generics ::unnamed_language::interpreter::value::IntoValue for #name #ty_generics #where_clause {
+            fn into_value(
+                self,
+                heap: &mut ::unnamed_language::interpreter::heap::Heap,
+            ) -> ::unnamed_language::interpreter::value::Value {
+                #body
+            }
+        }
+    })
+}
+
+fn from_value(input: DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let generics = bound(
+        &input.generics,
+        parse_quote!(::unnamed_language::interpreter::value::FromValue),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = named_fields(&data.fields, &input)?;
+            let keys = fields.iter().map(|field| field.to_string());
+            quote! {
+                let fields = ::unnamed_language::interpreter::value::Fields::new(value)?;
+                Ok(Self {
+                    #(#fields: fields.get(#keys)?,)*
+                })
+            }
+        }
+        Data::Enum(data) => {
+            let variants = unit_variants(data, &input)?;
+            let names = variants.iter().map(|variant| variant.to_string());
+            let unknown = format!("unknown variant '{{}}' of {name}");
+            quote! {
+                match <&str>::try_from(value)? {
+                    #(#names => Ok(Self::#variants),)*
+                    other => Err(::unnamed_language::interpreter::RuntimeErrorKind::InvalidArgument(
+                        format!(#unknown, other),
+                    )
+                    .into()),
+                }
+            }
+        }
+        Data::Union(_) => return Err(Error::new_spanned(&input.ident, "unions aren't supported")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::unnamed_language::interpreter::value::FromValue for #name #ty_generics #where_clause {
+            fn from_value(
+                value: &::unnamed_language::interpreter::value::Value,
+            ) -> ::std::result::Result<Self, ::unnamed_language::interpreter::RuntimeError> {
+                #body
+            }
+        }
+    })
+}
+
+/// Adds `bound` to every type parameter.
+fn bound(generics: &Generics, bound: syn::TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+
+    generics
+}
+
+fn named_fields<'a>(fields: &'a Fields, input: &DeriveInput) -> Result<Vec<&'a syn::Ident>, Error> {
+    match fields {
+        Fields::Named(fields) => Ok(fields
+            .named
+            .iter()
+            .map(|field| field.ident.as_ref().unwrap())
+            .collect()),
+        _ => Err(Error::new_spanned(
+            &input.ident,
+            "only structs with named fields are supported",
+        )),
+    }
+}
+
+fn unit_variants<'a>(
+    data: &'a syn::DataEnum,
+    input: &DeriveInput,
+) -> Result<Vec<&'a syn::Ident>, Error> {
+    data.variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(&variant.ident),
+            _ => Err(Error::new_spanned(
+                variant,
+                format!(
+                    "only enums with unit variants are supported, {} has fields",
+                    input.ident
+                ),
+            )),
+        })
+        .collect()
+}
@@ -0,0 +1,57 @@
+/* C interface of the unnamed language, built into the shared library with the `ffi` feature:
+ *
+ *     cargo build --release --features ffi
+ *
+ * Strings returned by an engine belong to it and stay valid until the next call made with the
+ * engine. See `src/ffi.rs` for the details of each function. */
+
+#ifndef UNNAMED_LANGUAGE_H
+#define UNNAMED_LANGUAGE_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct UlEngine UlEngine;
+
+typedef enum UlStatus {
+    UL_OK = 0,
+    /* The call failed, `ul_diagnostics` describes why. */
+    UL_ERROR = 1,
+} UlStatus;
+
+typedef enum UlKind {
+    UL_NIL = 0,
+    UL_BOOL = 1,
+    UL_NUMBER = 2,
+    UL_STRING = 3,
+    /* Any other value, which is returned as its printed form. */
+    UL_OBJECT = 4,
+} UlKind;
+
+/* Only the field matching `kind` is meaningful. */
+typedef struct UlValue {
+    /* One of the `UlKind` values, others are rejected. */
+    int kind;
+    bool boolean;
+    double number;
+    /* Contents of a string, or the printed form of an object. */
+    const char *string;
+} UlValue;
+
+UlEngine *ul_engine_new(void);
+void ul_engine_free(UlEngine *engine);
+
+UlStatus ul_eval(UlEngine *engine, const char *source, UlValue *value);
+const char *ul_diagnostics(const UlEngine *engine);
+
+UlStatus ul_set_global(UlEngine *engine, const char *name, const UlValue *value);
+UlStatus ul_get_global(UlEngine *engine, const char *name, UlValue *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
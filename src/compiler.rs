@@ -1,2 +1,24 @@
+//! Front end of the language. Source is scanned and parsed by [`parser`], the sugar in the
+//! syntax tree is removed by [`lower`] and the variables it uses are found by [`resolver`], after
+//! which the script can be run by the interpreter. [`Parser::parse`] runs all of these steps.
+//!
+//! The types most users need are re-exported here, so they can be imported from this module
+//! rather than from where they are defined.
+
 pub mod parser;
+pub mod check;
 pub mod bytecode;
+pub mod formatter;
+pub mod highlight;
+pub mod incremental;
+pub mod lower;
+pub mod sexpr;
+pub mod resolver;
+pub mod warnings;
+
+pub use parser::{
+    ast::Script,
+    scanner::{ScanError, Scanner},
+    token::{Location, Span, Token, TokenKind},
+    ParseError, Parser,
+};
@@ -0,0 +1,73 @@
+//! Validation of a script without running it: the source is scanned, parsed and resolved and
+//! everything found along the way is returned as diagnostics. Nothing is evaluated, so checking
+//! is fast and has no side effects, which makes it suitable for editors and CI.
+
+use super::{
+    parser::{scanner::Scanner, Parser},
+    warnings::Levels,
+};
+use crate::diagnostic::{Diagnostic, Severity};
+
+/// Returns the diagnostics of the source in the order they were found, warnings are reported at
+/// the given levels.
+///
+/// Every scanning error is reported, but since parse errors may well be caused by invalid
+/// tokens, the source is only parsed if it scans cleanly. The parser then reports the first error
+/// of each top level declaration.
+pub fn check(source: &str, levels: &Levels) -> Vec<Diagnostic> {
+    let diagnostics: Vec<_> = Scanner::new(source)
+        .filter_map(|token| token.err())
+        .map(|error| Diagnostic::from(&error))
+        .collect();
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    match Parser::new(source).parse_all() {
+        Ok(script) => script
+            .warnings
+            .iter()
+            .filter_map(|warning| Diagnostic::from_warning(warning, levels))
+            .collect(),
+        Err(errors) => errors.iter().map(Diagnostic::from).collect(),
+    }
+}
+
+/// Whether any of the diagnostics are errors.
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, has_errors};
+    use crate::{
+        compiler::warnings::{Level, Levels},
+        diagnostic::{ErrorCode, Severity},
+    };
+
+    #[test]
+    fn diagnostics() {
+        let diagnostics = check("let a = @; let b = #;", &Levels::default());
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [Some(ErrorCode::UNEXPECTED_CHARACTER); 2]);
+        assert!(has_errors(&diagnostics));
+
+        let diagnostics = check("let a = ; func f() { a +; } f(", &Levels::default());
+        assert_eq!(diagnostics.len(), 3);
+
+        let source = "{ let a = 1; }";
+        let diagnostics = check(source, &Levels::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(!has_errors(&diagnostics));
+
+        let mut levels = Levels::default();
+        levels.set_by_name("unused_variable", Level::Deny);
+        assert!(has_errors(&check(source, &levels)));
+        levels.set_by_name("all", Level::Allow);
+        assert!(check(source, &levels).is_empty());
+    }
+}
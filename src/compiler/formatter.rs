@@ -0,0 +1,269 @@
+//! Prints scripts back as source code in a canonical layout, used by the `fmt` command.
+//!
+//! Blocks are indented by four spaces, functions declared at the top level are separated from
+//! their neighbours by blank lines, and parentheses are only kept where they are needed to
+//! preserve the meaning of expressions.
+
+use super::parser::ast::{
+    Decl, DeclKind, Expr, ExprKind, Function, Literal, Operator, Script, Stmt, StmtKind,
+};
+
+const INDENT: &str = "    ";
+
+/// Precedence of assignment, the loosest binding expression.
+const ASSIGN: u8 = 1;
+/// Precedence of calls, indexing and member access, the tightest binding operators.
+const POSTFIX: u8 = 21;
+/// Precedence of expressions which never need parentheses, such as literals.
+const ATOM: u8 = u8::MAX;
+
+/// Formats a script, the result always ends with a newline unless the script is empty.
+pub fn format(script: &Script) -> String {
+    let mut formatter = Formatter {
+        output: String::new(),
+        depth: 0,
+    };
+
+    for (i, decl) in script.decls.iter().enumerate() {
+        let is_func = |decl: &Decl| matches!(&decl.kind, DeclKind::Func(_) | DeclKind::Test(_));
+        if i > 0 && (is_func(decl) || is_func(&script.decls[i - 1])) {
+            formatter.output.push('\n');
+        }
+        formatter.decl(decl);
+    }
+
+    formatter.output
+}
+
+struct Formatter {
+    output: String,
+    /// Number of blocks the current line is nested in.
+    depth: usize,
+}
+
+impl Formatter {
+    fn line(&mut self, text: &str) {
+        self.output.push_str(&INDENT.repeat(self.depth));
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn decl(&mut self, decl: &Decl) {
+        match &decl.kind {
+            DeclKind::Var(name, None) => self.line(&format!("let {};", name.as_ref())),
+            DeclKind::Var(name, Some(init)) => {
+                self.line(&format!("let {} = {};", name.as_ref(), expr(init)))
+            }
+            DeclKind::Import(name) => self.line(&format!("import \"{}\";", name.as_ref())),
+            DeclKind::Func(function) => self.function(function),
+            DeclKind::Test(function) => {
+                let header = format!("test \"{}\"", function.name.as_ref());
+                self.stmt(&function.body, &header);
+            }
+            DeclKind::Stmt(stmt) => self.stmt(stmt, ""),
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        let params: Vec<_> = function.params.iter().map(AsRef::as_ref).collect();
+        let header = format!("func {}({})", function.name.as_ref(), params.join(", "));
+        self.stmt(&function.body, &header);
+    }
+
+    /// Formats a statement, starting its first line with `prefix` such as `if x`.
+    fn stmt(&mut self, stmt: &Stmt, prefix: &str) {
+        // Statements following a prefix are separated from it by a space.
+        let prefix = if prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{prefix} ")
+        };
+
+        match &stmt.kind {
+            StmtKind::Block(decls) if decls.is_empty() => self.line(&format!("{prefix}{{}}")),
+            StmtKind::Block(decls) => {
+                self.line(&format!("{prefix}{{"));
+                self.depth += 1;
+                for decl in decls {
+                    self.decl(decl);
+                }
+                self.depth -= 1;
+                self.line("}");
+            }
+            StmtKind::If(predicate, consequent, alternative) => {
+                let header = format!("{prefix}if {}", expr(predicate));
+                self.stmt(consequent, &header);
+                if let Some(alternative) = alternative {
+                    self.else_branch(alternative);
+                }
+            }
+            StmtKind::While(predicate, body) => {
+                self.stmt(body, &format!("{prefix}while {}", expr(predicate)));
+            }
+            StmtKind::For(name, iterable, body) => {
+                let header = format!("{prefix}for {} in {}", name.as_ref(), expr(iterable));
+                self.stmt(body, &header);
+            }
+            StmtKind::Expr(e) => self.line(&format!("{prefix}{};", expr(e))),
+            StmtKind::Return(None, _) => self.line(&format!("{prefix}return;")),
+            StmtKind::Return(Some(e), _) => self.line(&format!("{prefix}return {};", expr(e))),
+        }
+    }
+
+    /// Formats the alternative of an if statement, joining it onto the closing brace of the
+    /// consequent when there is one.
+    fn else_branch(&mut self, alternative: &Stmt) {
+        if self.output.ends_with("}\n") {
+            self.output.pop();
+            // Temporarily unindent since the keyword continues the previous line.
+            let depth = std::mem::take(&mut self.depth);
+            self.output.push(' ');
+            let start = self.output.len();
+            self.stmt(alternative, "else");
+            // Lines after the first were indented from zero, indent them properly.
+            let rest = self.output.split_off(start);
+            self.depth = depth;
+            let mut lines = rest.lines();
+            if let Some(first) = lines.next() {
+                self.output.push_str(first);
+                self.output.push('\n');
+            }
+            for line in lines {
+                self.line(line);
+            }
+        } else {
+            self.stmt(alternative, "else");
+        }
+    }
+}
+
+/// Formats an expression on a single line.
+fn expr(e: &Expr) -> String {
+    match &e.kind {
+        ExprKind::Literal(Literal::Number(n)) => n.to_string(),
+        ExprKind::Literal(Literal::String(s)) => format!("\"{s}\""),
+        ExprKind::Literal(Literal::Bool(b)) => b.to_string(),
+        ExprKind::Literal(Literal::Nil) => "nil".to_owned(),
+        ExprKind::Identifier(name) => name.as_ref().to_owned(),
+        ExprKind::Assignment(target, value) => format!("{} = {}", expr(target), expr(value)),
+        ExprKind::CompoundAssignment(op, target, value) => {
+            format!("{} {}= {}", expr(target), op.node, expr(value))
+        }
+        ExprKind::Binary(op, left, right) => {
+            let (l_bp, r_bp) = op.node.infix_binding_power().unwrap();
+            let precedence = l_bp.min(r_bp);
+            // Operands on the side the operator associates towards may have the same precedence.
+            let (left_min, right_min) = if l_bp < r_bp {
+                (precedence, precedence + 1)
+            } else {
+                (precedence + 1, precedence)
+            };
+            format!(
+                "{} {} {}",
+                operand(left, left_min),
+                op.node,
+                operand(right, right_min)
+            )
+        }
+        ExprKind::Unary(op, operand_expr) => {
+            let (_, bp) = op.node.prefix_binding_power().unwrap();
+            let separator = if op.node == Operator::Not { " " } else { "" };
+            format!("{}{separator}{}", op.node, operand(operand_expr, bp))
+        }
+        ExprKind::Call(callee, args, _) => {
+            let args: Vec<_> = args.iter().map(expr).collect();
+            format!("{}({})", operand(callee, POSTFIX), args.join(", "))
+        }
+        ExprKind::Index(object, index, _) => {
+            format!("{}[{}]", operand(object, POSTFIX), expr(index))
+        }
+        ExprKind::Member(object, name) => format!("{}.{}", operand(object, POSTFIX), name.as_ref()),
+        ExprKind::List(items) => {
+            let items: Vec<_> = items.iter().map(expr).collect();
+            format!("[{}]", items.join(", "))
+        }
+        ExprKind::Map(entries) if entries.is_empty() => "{}".to_owned(),
+        ExprKind::Map(entries) => {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(key, value)| format!("{}: {}", expr(key), expr(value)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+    }
+}
+
+/// Formats an operand, adding parentheses if it binds looser than `min_precedence`.
+fn operand(e: &Expr, min_precedence: u8) -> String {
+    if precedence(e) < min_precedence {
+        format!("({})", expr(e))
+    } else {
+        expr(e)
+    }
+}
+
+fn precedence(e: &Expr) -> u8 {
+    match &e.kind {
+        ExprKind::Assignment(..) | ExprKind::CompoundAssignment(..) => ASSIGN,
+        ExprKind::Binary(op, ..) => {
+            let (l_bp, r_bp) = op.node.infix_binding_power().unwrap();
+            l_bp.min(r_bp)
+        }
+        ExprKind::Unary(op, _) => op.node.prefix_binding_power().unwrap().1,
+        ExprKind::Call(..) | ExprKind::Index(..) | ExprKind::Member(..) => POSTFIX,
+        _ => ATOM,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+    use crate::compiler::parser::Parser;
+
+    fn fmt(source: &str) -> String {
+        format(&Parser::new(source).parse_syntax().unwrap())
+    }
+
+    #[test]
+    fn layout() {
+        let source = r#"let x=1;func f(a,b){if a {return b;}else if b {}else return;
+            while (a) a=a-1;} for i in [1,2] {print(i);} let m = {"a":[f(1,2)]};"#;
+        let expected = r#"let x = 1;
+
+func f(a, b) {
+    if a {
+        return b;
+    } else if b {} else return;
+    while a a = a - 1;
+}
+
+for i in [1, 2] {
+    print(i);
+}
+let m = { "a": [f(1, 2)] };
+"#;
+        assert_eq!(fmt(source), expected);
+        assert_eq!(fmt(expected), expected);
+    }
+
+    #[test]
+    fn parentheses() {
+        let cases = [
+            ("(1 + 2) * 3;", "(1 + 2) * 3;\n"),
+            ("1 + (2 * 3);", "1 + 2 * 3;\n"),
+            ("(1 - 2) - 3;", "1 - 2 - 3;\n"),
+            ("1 - (2 - 3);", "1 - (2 - 3);\n"),
+            ("(2 ** 3) ** 2;", "(2 ** 3) ** 2;\n"),
+            ("2 ** (3 ** 2);", "2 ** 3 ** 2;\n"),
+            ("(-2) ** 2;", "-2 ** 2;\n"),
+            ("-(2 ** 2);", "-(2 ** 2);\n"),
+            ("(not a) == b;", "(not a) == b;\n"),
+            ("not (a == b);", "not a == b;\n"),
+            ("(a or b).c(d = 1);", "(a or b).c(d = 1);\n"),
+            ("a[i] += (b -= 1) * 2;", "a[i] += (b -= 1) * 2;\n"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(fmt(source), expected, "{source}");
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Classification of the tokens of a script for syntax highlighting.
+//!
+//! Tokens come from the scanner so that every part of the source is classified even if it
+//! doesn't parse, identifiers are then given a [`Role`] from the references recorded by the
+//! resolver when it does. The language has no comments, so whitespace is the only part of the
+//! source which isn't covered by a token.
+
+use std::collections::HashMap;
+
+use crate::compiler::parser::{
+    ast::{Decl, DeclKind, Expr, ExprKind, Function, Identifier, Script, Slot, Stmt, StmtKind},
+    scanner::Scanner,
+    token::{Location, TokenKind},
+    visit::{walk_decl, walk_expr, walk_function, walk_stmt, Visitor},
+};
+
+/// Classified range of the source.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SemanticToken {
+    pub location: Location,
+    pub class: Class,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Class {
+    Keyword,
+    /// Whether the identifier is where its variable is declared is recorded along with its role.
+    Identifier {
+        role: Role,
+        declaration: bool,
+    },
+    Number,
+    String,
+    /// `true`, `false` and `nil`.
+    Constant,
+    Operator,
+    /// Brackets, separators and the `.` of member access.
+    Punctuation,
+    /// Source the scanner couldn't make a token of, such as an unterminated string.
+    Invalid,
+}
+
+/// What an identifier refers to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Role {
+    Function,
+    Parameter,
+    /// Variable scoped to a block or function, including loop variables.
+    Local,
+    /// Variable declared at the top level.
+    Global,
+    /// Global which isn't declared by the script, usually a builtin.
+    Builtin,
+    /// Name of a field or method after a `.`.
+    Member,
+    /// Identifier of a script which doesn't parse, so it hasn't been resolved.
+    Unknown,
+}
+
+/// Classifies the tokens of the source in order, `script` should be the source parsed if it
+/// parses.
+pub fn semantic_tokens(source: &str, script: Option<&Script>) -> Vec<SemanticToken> {
+    let mut roles = HashMap::new();
+    let mut declarations = HashMap::new();
+    if let Some(script) = script {
+        let mut collector = Roles::default();
+        collector.visit_script(script);
+        roles = collector.roles;
+        for reference in &script.references {
+            let role = match reference.declaration {
+                Some(declaration) => roles.get(&declaration.start).copied(),
+                None => Some(Role::Builtin),
+            };
+            if let Some(role) = role {
+                declarations.insert(
+                    reference.usage.start,
+                    (role, reference.declaration == Some(reference.usage)),
+                );
+            }
+        }
+    }
+
+    let mut tokens = vec![];
+    for token in Scanner::new(source) {
+        let (location, kind) = match token {
+            Ok(token) if token.is_eof() => break,
+            Ok(token) => (token.location(), Some(token.kind())),
+            Err(error) => (error.location, None),
+        };
+
+        let class = match kind {
+            Some(TokenKind::Identifier) => {
+                let (role, declaration) = match declarations.get(&location.start) {
+                    Some(&identifier) => identifier,
+                    None => match roles.get(&location.start) {
+                        Some(&role) => (role, role != Role::Member),
+                        None => (Role::Unknown, false),
+                    },
+                };
+                Class::Identifier { role, declaration }
+            }
+            Some(kind) => classify(kind),
+            None => Class::Invalid,
+        };
+        tokens.push(SemanticToken { location, class });
+
+        // Errors which don't consume anything would otherwise be returned forever.
+        if location.end >= source.len() {
+            break;
+        }
+    }
+
+    tokens
+}
+
+fn classify(kind: TokenKind) -> Class {
+    match kind {
+        TokenKind::True | TokenKind::False | TokenKind::Nil => Class::Constant,
+        TokenKind::Number => Class::Number,
+        TokenKind::String => Class::String,
+        TokenKind::Not | TokenKind::Or | TokenKind::And => Class::Operator,
+        TokenKind::LParen
+        | TokenKind::RParen
+        | TokenKind::LBrack
+        | TokenKind::RBrack
+        | TokenKind::LBrace
+        | TokenKind::RBrace
+        | TokenKind::Period
+        | TokenKind::Semicolon
+        | TokenKind::Colon
+        | TokenKind::Comma => Class::Punctuation,
+        TokenKind::StarStar
+        | TokenKind::Star
+        | TokenKind::Slash
+        | TokenKind::Percent
+        | TokenKind::Plus
+        | TokenKind::Minus
+        | TokenKind::Equal
+        | TokenKind::PlusEqual
+        | TokenKind::MinusEqual
+        | TokenKind::StarEqual
+        | TokenKind::SlashEqual
+        | TokenKind::PercentEqual
+        | TokenKind::EqualEqual
+        | TokenKind::BangEqual
+        | TokenKind::Less
+        | TokenKind::LessEqual
+        | TokenKind::GreaterEqual
+        | TokenKind::Greater => Class::Operator,
+        TokenKind::Identifier | TokenKind::Whitespace | TokenKind::Eof => Class::Invalid,
+        _ => Class::Keyword,
+    }
+}
+
+/// Roles of the declarations and member names of a script by the offset they start at.
+#[derive(Default)]
+struct Roles {
+    roles: HashMap<usize, Role>,
+}
+
+impl Roles {
+    fn insert(&mut self, name: &Identifier, role: Role) {
+        self.roles.insert(name.location().start, role);
+    }
+}
+
+impl Visitor for Roles {
+    fn visit_decl(&mut self, decl: &Decl) {
+        match &decl.kind {
+            // Only variables declared at the top level are stored in globals.
+            DeclKind::Var(name, _) if name.slot() == Slot::Global => {
+                self.insert(name, Role::Global)
+            }
+            DeclKind::Var(name, _) => self.insert(name, Role::Local),
+            // Modules are named by strings, which are highlighted as such.
+            DeclKind::Import(_) => {}
+            DeclKind::Func(function) => self.insert(&function.name, Role::Function),
+            DeclKind::Test(_) | DeclKind::Stmt(_) => {}
+        }
+        walk_decl(self, decl);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        for param in &function.params {
+            self.insert(param, Role::Parameter);
+        }
+        walk_function(self, function);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let StmtKind::For(name, _, _) = &stmt.kind {
+            self.insert(name, Role::Local);
+        }
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Member(_, name) = &expr.kind {
+            self.insert(name, Role::Member);
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{semantic_tokens, Class, Role};
+    use crate::compiler::parser::Parser;
+
+    /// Classifies the source, returning the text of each token with its class.
+    fn classes(source: &str) -> Vec<(&str, Class)> {
+        let script = Parser::new(source).parse().ok();
+        semantic_tokens(source, script.as_ref())
+            .into_iter()
+            .map(|token| {
+                (
+                    &source[token.location.start..token.location.end],
+                    token.class,
+                )
+            })
+            .collect()
+    }
+
+    fn identifier(role: Role, declaration: bool) -> Class {
+        Class::Identifier { role, declaration }
+    }
+
+    #[test]
+    fn roles() {
+        let source = "let g = nil; func f(a) { let b = a.len; for i in b { print(i + g); } } f(1);";
+        let identifiers: Vec<_> = classes(source)
+            .into_iter()
+            .filter(|(_, class)| matches!(class, Class::Identifier { .. }))
+            .collect();
+        assert_eq!(
+            identifiers,
+            [
+                ("g", identifier(Role::Global, true)),
+                ("f", identifier(Role::Function, true)),
+                ("a", identifier(Role::Parameter, true)),
+                ("b", identifier(Role::Local, true)),
+                ("a", identifier(Role::Parameter, false)),
+                ("len", identifier(Role::Member, false)),
+                ("i", identifier(Role::Local, true)),
+                ("b", identifier(Role::Local, false)),
+                ("print", identifier(Role::Builtin, false)),
+                ("i", identifier(Role::Local, false)),
+                ("g", identifier(Role::Global, false)),
+                ("f", identifier(Role::Function, false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn classes_without_parsing() {
+        assert_eq!(
+            classes("if x >= 1.5 { \"a\" @ true"),
+            [
+                ("if", Class::Keyword),
+                ("x", identifier(Role::Unknown, false)),
+                (">=", Class::Operator),
+                ("1.5", Class::Number),
+                ("{", Class::Punctuation),
+                ("\"a\"", Class::String),
+                ("@", Class::Invalid),
+                ("true", Class::Constant),
+            ]
+        );
+        assert_eq!(classes("\"abc"), [("\"abc", Class::Invalid)]);
+        assert_eq!(classes(""), []);
+    }
+}
@@ -0,0 +1,288 @@
+//! Incremental re-parsing of a document as it is edited.
+//!
+//! A [`Document`] remembers the range of the source each declaration at the top level was parsed
+//! from. When the source is edited only the declarations the edit touches are parsed again, the
+//! ones before it are kept as they are and the ones after it are kept with their locations moved
+//! to where they ended up. Top level declarations always end with a `;` or `}` so they can be
+//! parsed independently of each other. The whole script is resolved again after every edit, as
+//! an edit anywhere can change what a name refers to.
+
+use std::ops::Range;
+
+use crate::compiler::{
+    lower,
+    parser::{
+        ast::{Decl, Identifier, NodeId, Script},
+        token::{Location, Span},
+        visit::VisitorMut,
+        Parser,
+    },
+    resolver,
+};
+use crate::source_map::FileId;
+
+/// Source which is parsed again incrementally as it is edited.
+pub struct Document {
+    source: String,
+    /// Resolved script and the range of the source each of its declarations was parsed from,
+    /// `None` if the source doesn't parse.
+    parsed: Option<(Script, Vec<Range<usize>>)>,
+    /// Id the next node parsed is given, declarations which are parsed again get new ids so they
+    /// don't clash with those of the declarations which are kept.
+    next_id: NodeId,
+}
+
+impl Document {
+    pub fn new(source: String) -> Self {
+        let mut next_id = NodeId::default();
+        let parsed = parse(&source, &mut next_id);
+        Self {
+            source,
+            parsed,
+            next_id,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the script if the source parses, the error can be found by parsing the source.
+    pub fn script(&self) -> Option<&Script> {
+        self.parsed.as_ref().map(|(script, _)| script)
+    }
+
+    /// Replaces the byte range of the source with the text and parses it again, reusing the
+    /// declarations which weren't affected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or not on character boundaries, or if a function of
+    /// the script is shared since it has to be resolved again.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) {
+        let old_end = point(&self.source, range.end);
+        self.source.replace_range(range.clone(), text);
+        let new_end = point(&self.source, range.start + text.len());
+
+        self.parsed = match self.parsed.take() {
+            Some((script, extents)) => {
+                let edit = Edit {
+                    range,
+                    len: text.len(),
+                    old_end,
+                    new_end,
+                };
+                reparse(&self.source, script, extents, &edit, &mut self.next_id)
+                    .or_else(|| parse(&self.source, &mut self.next_id))
+            }
+            None => parse(&self.source, &mut self.next_id),
+        };
+    }
+}
+
+struct Edit {
+    /// Range of the old source which was replaced.
+    range: Range<usize>,
+    /// Length of the text it was replaced with.
+    len: usize,
+    /// Position of the end of the replaced range in the old source and in the new source.
+    old_end: Location,
+    new_end: Location,
+}
+
+fn parse(source: &str, next_id: &mut NodeId) -> Option<(Script, Vec<Range<usize>>)> {
+    let mut parser = Parser::new(source).with_next_id(*next_id);
+    let decls = parser.decls_with_extents().ok()?;
+    *next_id = parser.next_id();
+    let (decls, extents) = decls.into_iter().unzip();
+
+    Some((resolve(decls, next_id), extents))
+}
+
+/// Parses the declarations the edit touched again and resolves them along with the others,
+/// returning `None` if they don't parse on their own.
+fn reparse(
+    source: &str,
+    script: Script,
+    mut extents: Vec<Range<usize>>,
+    edit: &Edit,
+    next_id: &mut NodeId,
+) -> Option<(Script, Vec<Range<usize>>)> {
+    // Declarations which end right where the edit starts or start right where it ends are parsed
+    // again too, the edit may have joined them with a token of their own.
+    let before = extents
+        .iter()
+        .take_while(|extent| extent.end < edit.range.start)
+        .count();
+    let after = extents
+        .iter()
+        .position(|extent| extent.start > edit.range.end)
+        .unwrap_or(extents.len());
+
+    let start = before.checked_sub(1).map_or(0, |i| extents[i].end);
+    let end = match extents.get(after) {
+        Some(extent) => extent.start - edit.range.end + edit.range.start + edit.len,
+        None => source.len(),
+    };
+    let mut parser = Parser::new(&source[start..end]).with_next_id(*next_id);
+    let mut middle = parser.decls_with_extents().ok()?;
+    *next_id = parser.next_id();
+    let from = point(source, 0);
+    let to = point(source, start);
+    for (decl, extent) in &mut middle {
+        shift_decl(decl, from, to);
+        *extent = extent.start + start..extent.end + start;
+    }
+
+    let mut decls = script.decls;
+    let suffix: Vec<_> = decls
+        .drain(after..)
+        .zip(extents.drain(after..))
+        .map(|(mut decl, extent)| {
+            shift_decl(&mut decl, edit.old_end, edit.new_end);
+            let extent = shift(extent.start, edit.old_end, edit.new_end)
+                ..shift(extent.end, edit.old_end, edit.new_end);
+            (decl, extent)
+        })
+        .collect();
+    decls.truncate(before);
+    extents.truncate(before);
+    for (decl, extent) in middle.into_iter().chain(suffix) {
+        decls.push(decl);
+        extents.push(extent);
+    }
+
+    Some((resolve(decls, next_id), extents))
+}
+
+/// Lowers and resolves the declarations as a script, nodes added by lowering take their ids from
+/// `next_id`. Declarations which were kept have already been lowered, so lowering leaves them as
+/// they are.
+fn resolve(decls: Vec<Decl>, next_id: &mut NodeId) -> Script {
+    let mut script = Script {
+        decls,
+        slot_count: 0,
+        warnings: vec![],
+        references: vec![],
+        next_id: *next_id,
+    };
+    lower::lower(&mut script);
+    resolver::resolve(&mut script);
+    *next_id = script.next_id;
+
+    script
+}
+
+/// Location of the offset in the source.
+fn point(source: &str, offset: usize) -> Location {
+    Location::new(source, Span::new(FileId::default(), offset, offset))
+}
+
+/// Moves an offset after `from` to be the same distance after `to`.
+fn shift(offset: usize, from: Location, to: Location) -> usize {
+    offset - from.start + to.start
+}
+
+/// Moves a location after `from` to be in the same position relative to `to`, the columns only
+/// change on the line `from` is on.
+fn shift_location(location: &mut Location, from: Location, to: Location) {
+    location.start = shift(location.start, from, to);
+    location.end = shift(location.end, from, to);
+    if location.line == from.line {
+        location.column = location.column - from.column + to.column;
+    }
+    location.line = location.line - from.line + to.line;
+}
+
+/// Moves every location of the nodes it visits from after `from` to after `to`.
+struct Shift {
+    from: Location,
+    to: Location,
+}
+
+impl VisitorMut for Shift {
+    fn visit_identifier_mut(&mut self, name: &mut Identifier) {
+        let mut location = name.location();
+        shift_location(&mut location, self.from, self.to);
+        name.set_location(location);
+    }
+
+    fn visit_location_mut(&mut self, location: &mut Location) {
+        shift_location(location, self.from, self.to);
+    }
+}
+
+fn shift_decl(decl: &mut Decl, from: Location, to: Location) {
+    Shift { from, to }.visit_decl_mut(decl);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Document;
+    use crate::compiler::{
+        parser::{ast::DeclKind, Parser},
+        sexpr::{self, Options},
+    };
+
+    /// Applies the edit to the document and checks that it ends up the same as parsing the new
+    /// source from scratch, spans included.
+    fn edit(document: &mut Document, old: &str, new: &str) {
+        let start = document.source().find(old).unwrap();
+        document.edit(start..start + old.len(), new);
+
+        let script = Parser::new(document.source()).parse().ok();
+        let options = Options::default();
+        assert_eq!(
+            document
+                .script()
+                .map(|script| sexpr::script(script, &options)),
+            script.map(|script| sexpr::script(&script, &options)),
+        );
+        assert_eq!(
+            document.script().map(|script| &script.references),
+            Parser::new(document.source())
+                .parse()
+                .ok()
+                .map(|script| script.references)
+                .as_ref(),
+        );
+    }
+
+    #[test]
+    fn reuses_declarations() {
+        let mut document = Document::new(
+            "func f(a) {\n  return a + 1;\n}\nlet x = f(1);\nfunc g() { return x; }".to_owned(),
+        );
+        let function =
+            |document: &Document, i: usize| match &document.script().unwrap().decls[i].kind {
+                DeclKind::Func(function) => Arc::as_ptr(function),
+                _ => panic!("expected a function"),
+            };
+        let (f, g) = (function(&document, 0), function(&document, 2));
+
+        edit(&mut document, "f(1)", "f(100)\n  + f(2)");
+        assert_eq!(function(&document, 0), f);
+        assert_eq!(function(&document, 2), g);
+
+        edit(&mut document, "let x", "let y = 2; let x");
+        assert_eq!(function(&document, 3), g);
+        edit(&mut document, "return x;", "return x + y;");
+        assert_eq!(function(&document, 0), f);
+    }
+
+    #[test]
+    fn errors() {
+        let mut document = Document::new("let a = 1;\nlet b = 2;".to_owned());
+        edit(&mut document, "1;", "(");
+        assert!(document.script().is_none());
+        edit(&mut document, "(", "1 + 1;");
+        assert!(document.script().is_some());
+
+        edit(&mut document, "2;", "2; }");
+        assert!(document.script().is_none());
+        edit(&mut document, " }", "");
+        assert!(document.script().is_some());
+    }
+}
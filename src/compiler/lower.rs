@@ -0,0 +1,108 @@
+//! Lowering of syntactic sugar into the core of the language, so that the resolver and the
+//! interpreter don't have to know about it. Each kind of sugar is removed by its own
+//! [`Rewriter`], [`lower`] runs them all between parsing and resolving a script.
+
+use super::parser::{
+    ast::{Expr, ExprKind, NodeId, Script},
+    rewrite::{rewrite_script, Rewriter},
+    visit::{walk_expr_mut, VisitorMut},
+};
+
+/// Replaces the sugar in the script, the nodes it adds take their ids from the script.
+///
+/// # Panics
+///
+/// Panics if any function in the script is shared.
+pub fn lower(script: &mut Script) {
+    let mut compound_assignments = CompoundAssignments {
+        next_id: script.next_id,
+    };
+    rewrite_script(script, &mut compound_assignments);
+    script.next_id = compound_assignments.next_id;
+}
+
+/// Rewrites `a += b` as `a = a + b`, and likewise for the other operators. The target is written
+/// out twice, so the object and index of an indexed target are evaluated twice.
+struct CompoundAssignments {
+    next_id: NodeId,
+}
+
+impl Rewriter for CompoundAssignments {
+    fn rewrite_expr(&mut self, expr: Expr) -> Expr {
+        match expr.kind {
+            ExprKind::CompoundAssignment(operator, target, value) => {
+                let mut operand = target.clone();
+                Renumber(&mut self.next_id).visit_expr_mut(&mut operand);
+                let binary = Expr {
+                    id: self.next_id.take(),
+                    location: expr.location,
+                    kind: ExprKind::Binary(operator, operand, value),
+                };
+                Expr {
+                    kind: ExprKind::Assignment(target, Box::new(binary)),
+                    ..expr
+                }
+            }
+            kind => Expr { kind, ..expr },
+        }
+    }
+}
+
+/// Gives every expression it visits a new id.
+struct Renumber<'a>(&'a mut NodeId);
+
+impl VisitorMut for Renumber<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        expr.id = self.0.take();
+        walk_expr_mut(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::compiler::{
+        parser::{
+            ast::{Expr, NodeId},
+            visit::{walk_expr, Visitor},
+            Parser,
+        },
+        sexpr::{self, Options},
+    };
+
+    #[test]
+    fn compound_assignments() {
+        let lowered = Parser::new("a[f()] *= b += 2;").parse().unwrap();
+        let expected = Parser::new("a[f()] = a[f()] * (b = b + 2);")
+            .parse()
+            .unwrap();
+        let options = Options::compact();
+        assert_eq!(
+            sexpr::script(&lowered, &options),
+            sexpr::script(&expected, &options)
+        );
+
+        // The repeated target is a copy with ids of its own.
+        struct Ids {
+            seen: HashSet<NodeId>,
+            next_id: NodeId,
+        }
+
+        impl Visitor for Ids {
+            fn visit_expr(&mut self, expr: &Expr) {
+                assert!(self.seen.insert(expr.id), "{:?} is repeated", expr.id);
+                assert!(expr.id < self.next_id);
+                walk_expr(self, expr);
+            }
+        }
+        let mut ids = Ids {
+            seen: HashSet::new(),
+            next_id: lowered.next_id,
+        };
+        ids.visit_script(&lowered);
+
+        let output = crate::run_str("let a = [2]; a[0] *= 3; let b = 1; b += a[0]; print(a, b);");
+        assert_eq!(output.unwrap(), "[6] 7");
+    }
+}
@@ -1,19 +1,51 @@
-use crate::interpreter::value::Value;
+use std::{iter::FusedIterator, ops::Range, sync::Arc};
 
 use self::{
-    ast::{Decl, Expr, Operator, Script, Stmt},
-    scanner::Scanner,
-    token::{Span, Token, TokenKind},
+    ast::{
+        Decl, DeclKind, Expr, ExprKind, Function, Identifier, Literal, NodeId, Operator, Script,
+        Spanned, Stmt, StmtKind,
+    },
+    scanner::{ScanError, Scanner},
+    token::{Location, Token, TokenKind},
+};
+use thiserror::Error;
+
+use super::{lower, resolver};
+use crate::{
+    diagnostic::{suggest::suggest, ErrorCode, Label},
+    source_map::FileId,
 };
 
 pub mod ast;
 mod cursor;
+mod lookahead;
+pub mod rewrite;
 pub mod scanner;
 pub mod token;
+pub mod visit;
+
+/// Maximum number of statements and expressions which may be nested inside each other, deeper
+/// nesting is an error rather than overflowing the stack while parsing or later when walking the
+/// tree.
+pub const MAX_DEPTH: usize = 128;
 
 pub struct Parser<'a> {
     scanner: Scanner<'a>,
     peeked: Option<Token<'a>>,
+    /// First error returned by the scanner, the parser continues past invalid tokens but the
+    /// error is returned instead of the script.
+    scan_error: Option<ParseError>,
+    /// Number of statements and expressions currently being parsed inside each other.
+    depth: usize,
+    /// Braces opened and not yet closed by the tokens advanced past, used to find the end of
+    /// the top level declaration an error is in.
+    braces: usize,
+    /// End of the last token advanced past.
+    previous_end: usize,
+    /// Id of the next node parsed.
+    next_id: NodeId,
+    /// Whether iterating over the declarations has reached the end of the source or an error.
+    finished: bool,
 }
 
 impl<'a> Parser<'a> {
@@ -21,13 +53,105 @@ impl<'a> Parser<'a> {
         Self {
             scanner: Scanner::new(source),
             peeked: None,
+            scan_error: None,
+            depth: 0,
+            braces: 0,
+            previous_end: 0,
+            next_id: NodeId::default(),
+            finished: false,
+        }
+    }
+
+    /// Sets the file the locations of the script and errors are in, by default it is the first
+    /// file of a source map.
+    pub fn with_file(self, file: FileId) -> Self {
+        Self {
+            scanner: self.scanner.with_file(file),
+            ..self
         }
     }
 
+    /// Sets the id the first node parsed is given, so that nodes parsed separately don't share
+    /// ids.
+    pub(super) fn with_next_id(self, next_id: NodeId) -> Self {
+        Self { next_id, ..self }
+    }
+
+    /// Id the next node parsed will be given.
+    pub(super) fn next_id(&self) -> NodeId {
+        self.next_id
+    }
+
     fn advance(&mut self) -> Token<'a> {
-        match self.peeked.take() {
+        let token = match self.peeked.take() {
             Some(token) => token,
             None => self.next_token(),
+        };
+        self.previous_end = token.span().end();
+        match token.kind() {
+            TokenKind::LBrace => self.braces += 1,
+            TokenKind::RBrace => self.braces = self.braces.saturating_sub(1),
+            _ => {}
+        }
+
+        token
+    }
+
+    /// Skips the rest of the top level declaration an error was found in, stopping after a
+    /// semicolon or closing brace outside of any braces, or before a keyword which starts a
+    /// declaration.
+    fn synchronize(&mut self) {
+        loop {
+            match self.peek().kind() {
+                TokenKind::Eof => return,
+                TokenKind::Let | TokenKind::Func | TokenKind::Import | TokenKind::Test
+                    if self.braces == 0 =>
+                {
+                    return
+                }
+                _ => {}
+            }
+
+            let token = self.advance();
+            if self.braces == 0 && matches!(token.kind(), TokenKind::Semicolon | TokenKind::RBrace)
+            {
+                return;
+            }
+        }
+    }
+
+    /// Location from the start of `start` to the end of the last token advanced past.
+    fn location_from(&self, start: Location) -> Location {
+        Location {
+            end: self.previous_end.max(start.start),
+            ..start
+        }
+    }
+
+    /// Makes a declaration which started at `start` and ends at the last token advanced past.
+    fn decl_node(&mut self, start: Location, kind: DeclKind) -> Decl {
+        Decl {
+            id: self.next_id.take(),
+            location: self.location_from(start),
+            kind,
+        }
+    }
+
+    /// Makes a statement which started at `start`, see [`Parser::decl_node`].
+    fn stmt_node(&mut self, start: Location, kind: StmtKind) -> Stmt {
+        Stmt {
+            id: self.next_id.take(),
+            location: self.location_from(start),
+            kind,
+        }
+    }
+
+    /// Makes an expression which started at `start`, see [`Parser::decl_node`].
+    fn expr_node(&mut self, start: Location, kind: ExprKind) -> Expr {
+        Expr {
+            id: self.next_id.take(),
+            location: self.location_from(start),
+            kind,
         }
     }
 
@@ -51,19 +175,49 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Scans until the scanner returns a token, reporting all errors.
+    /// Scans until the scanner returns a token, skipping invalid ones and keeping the first
+    /// error.
     fn next_token(&mut self) -> Token<'a> {
         loop {
-            match self.scanner.scan() {
+            match self.scanner.scan_token() {
                 Ok(token) => return token,
-                // Report scanning errors.
-                Err(_) => todo!(),
+                Err(error) => {
+                    self.scan_error.get_or_insert(error.into());
+                }
             }
         }
     }
 
+    /// Runs `parse` one level deeper, returning an error at `token` if that is deeper than
+    /// [`MAX_DEPTH`].
+    fn nested<T>(
+        &mut self,
+        token: &Token<'a>,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.descend(token)?;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn descend(&mut self, token: &Token<'a>) -> Result<(), ParseError> {
+        if self.depth == MAX_DEPTH {
+            let message = format!("code can't be nested more than {MAX_DEPTH} levels deep");
+            return Err(ParseError::new(
+                ErrorCode::TOO_DEEPLY_NESTED,
+                token,
+                message,
+            ));
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
     /// Advances if next token equals `expected`, otherwise returns `ParseError`.
-    fn expect(&mut self, expected: TokenKind) -> Result<Token<'a>, ParseError<'a>> {
+    fn expect(&mut self, expected: TokenKind) -> Result<Token<'a>, ParseError> {
         let token = self.peek();
         if token.kind() == expected {
             Ok(self.advance())
@@ -82,36 +236,202 @@ impl<'a> Parser<'a> {
                 quote_maybe(token.kind())
             );
 
-            Err(ParseError::new(&token, message))
+            Err(ParseError::new(ErrorCode::EXPECTED_TOKEN, &token, message))
         }
     }
 
-    pub fn parse(&mut self) -> Result<Script, ParseError<'a>> {
-        self.script()
+    /// Expects the bracket which closes the one at `opening`, pointing the error at the opening
+    /// bracket too.
+    fn expect_closing(
+        &mut self,
+        expected: TokenKind,
+        opening: Location,
+    ) -> Result<Token<'a>, ParseError> {
+        self.expect(expected).map_err(|error| {
+            let bracket = match expected {
+                TokenKind::RParen => TokenKind::LParen,
+                TokenKind::RBrack => TokenKind::LBrack,
+                _ => TokenKind::LBrace,
+            };
+            error.with_label(Label::new(
+                opening,
+                format!("unclosed '{bracket}' opened here"),
+            ))
+        })
+    }
+
+    /// Parses a whole script, lowers it and resolves the variables it uses.
+    pub fn parse(&mut self) -> Result<Script, ParseError> {
+        let mut script = self.parse_syntax()?;
+        lower::lower(&mut script);
+        resolver::resolve(&mut script);
+
+        Ok(script)
     }
 
-    fn script(&mut self) -> Result<Script, ParseError<'a>> {
+    /// Parses a whole script like [`Parser::parse`], but rather than stopping at the first error
+    /// skips to the next top level declaration and carries on, returning every error found. Only
+    /// the first invalid token is reported, see [`Scanner`] to find all of them.
+    pub fn parse_all(&mut self) -> Result<Script, Vec<ParseError>> {
         let mut decls = vec![];
+        let mut errors = vec![];
         while self.peek().kind() != TokenKind::Eof {
-            decls.push(self.decl()?);
+            match self.top_level_decl() {
+                Ok(decl) => decls.push(decl),
+                Err(error) => {
+                    errors.push(error);
+                    self.synchronize();
+                }
+            }
+        }
+        if let Some(error) = self.scan_error.take() {
+            errors.insert(0, error);
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut script = Script {
+            decls,
+            slot_count: 0,
+            warnings: vec![],
+            references: vec![],
+            next_id: self.next_id,
+        };
+        lower::lower(&mut script);
+        resolver::resolve(&mut script);
+
+        Ok(script)
+    }
+
+    /// Parses a whole script as it is written, without lowering or resolving it. This is for
+    /// tools which work on the source such as the formatter, the script can't be run.
+    pub fn parse_syntax(&mut self) -> Result<Script, ParseError> {
+        let script = self.script();
+        // Invalid tokens come before the point where parsing stopped, and any parse error may
+        // well have been caused by them.
+        if let Some(error) = self.scan_error.take() {
+            return Err(error);
         }
 
-        Ok(Script { decls })
+        script
+    }
+
+    /// Parses a single expression which makes up the whole source, such as `a + b`. The
+    /// expression is returned as written, like the declarations of [`Parser::parse_syntax`].
+    pub fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.fragment(Self::expr)
+    }
+
+    /// Parses a single statement which makes up the whole source, such as `if a { b(); }`, see
+    /// [`Parser::parse_expr`].
+    pub fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
+        self.fragment(Self::stmt)
     }
 
-    fn decl(&mut self) -> Result<Decl, ParseError<'a>> {
+    /// Runs `parse` and expects the end of the source after it.
+    fn fragment<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let result = parse(self).and_then(|node| {
+            self.expect(TokenKind::Eof)?;
+            Ok(node)
+        });
+        if let Some(error) = self.scan_error.take() {
+            return Err(error);
+        }
+
+        result
+    }
+
+    /// Parses the declarations of a whole script without resolving them, along with the range of
+    /// the source from the first to the last token of each.
+    pub(super) fn decls_with_extents(&mut self) -> Result<Vec<(Decl, Range<usize>)>, ParseError> {
+        let mut decls = vec![];
+        let result = loop {
+            let start = self.peek().span().start();
+            if self.peek().kind() == TokenKind::Eof {
+                break Ok(decls);
+            }
+            match self.top_level_decl() {
+                Ok(decl) => decls.push((decl, start..self.previous_end)),
+                Err(error) => break Err(error),
+            }
+        };
+        if let Some(error) = self.scan_error.take() {
+            return Err(error);
+        }
+
+        result
+    }
+
+    fn script(&mut self) -> Result<Script, ParseError> {
+        let mut decls = vec![];
+        while self.peek().kind() != TokenKind::Eof {
+            decls.push(self.top_level_decl()?);
+        }
+
+        Ok(Script {
+            decls,
+            slot_count: 0,
+            warnings: vec![],
+            references: vec![],
+            next_id: self.next_id,
+        })
+    }
+
+    fn top_level_decl(&mut self) -> Result<Decl, ParseError> {
+        if self.peek().kind() == TokenKind::Test {
+            self.test_decl()
+        } else {
+            self.decl()
+        }
+    }
+
+    fn decl(&mut self) -> Result<Decl, ParseError> {
         let kind = self.peek().kind();
         match kind {
             TokenKind::Let => self.var_decl(),
             TokenKind::Func => self.func_decl(),
-            _ => Ok(Decl::Stmt(Box::new(self.stmt()?))),
+            TokenKind::Import => self.import_decl(),
+            TokenKind::Test => Err(ParseError::new(
+                ErrorCode::UNEXPECTED_TOKEN,
+                &self.peek(),
+                "tests can only be declared at the top level".to_owned(),
+            )),
+            _ => {
+                let stmt = self.stmt()?;
+                let start = stmt.location;
+                Ok(self.decl_node(start, DeclKind::Stmt(Box::new(stmt))))
+            }
         }
     }
 
-    fn var_decl(&mut self) -> Result<Decl, ParseError<'a>> {
-        self.expect(TokenKind::Let)?;
+    fn test_decl(&mut self) -> Result<Decl, ParseError> {
+        let start = self.expect(TokenKind::Test)?.location();
+
+        let token = self.expect(TokenKind::String)?;
+        // Strip the surrounding quotes.
+        let slice = token.slice();
+        let name = Identifier::new(&slice[1..slice.len() - 1], token.location());
+        let body = Box::new(self.block_stmt()?);
+
+        let function = Arc::new(Function {
+            name,
+            params: vec![],
+            body,
+            slot_count: 0,
+            captures: vec![],
+        });
+
+        Ok(self.decl_node(start, DeclKind::Test(function)))
+    }
+
+    fn var_decl(&mut self) -> Result<Decl, ParseError> {
+        let start = self.expect(TokenKind::Let)?.location();
 
-        let name = self.expect(TokenKind::Identifier)?.slice().into();
+        let name = self.expect(TokenKind::Identifier)?.into();
         let init_expr = if self.advance_if(TokenKind::Equal) {
             Some(Box::new(self.expr()?))
         } else {
@@ -120,40 +440,78 @@ impl<'a> Parser<'a> {
 
         self.expect(TokenKind::Semicolon)?;
 
-        Ok(Decl::Var(name, init_expr))
+        Ok(self.decl_node(start, DeclKind::Var(name, init_expr)))
     }
 
-    fn func_decl(&mut self) -> Result<Decl, ParseError<'a>> {
-        self.expect(TokenKind::Func)?;
+    fn import_decl(&mut self) -> Result<Decl, ParseError> {
+        let start = self.expect(TokenKind::Import)?.location();
 
-        let name = self.expect(TokenKind::Identifier)?.slice().into();
+        let token = self.expect(TokenKind::String)?;
+        // Strip the surrounding quotes, the module is bound to a variable of the same name so it
+        // has to be a valid identifier.
+        let slice = token.slice();
+        let name = &slice[1..slice.len() - 1];
+        if !scanner::is_identifier(name) {
+            return Err(ParseError::new(
+                ErrorCode::UNEXPECTED_TOKEN,
+                &token,
+                format!("module name {slice} is not a valid identifier"),
+            ));
+        }
+        let name = Identifier::new(name, token.location());
+        self.expect(TokenKind::Semicolon)?;
+
+        Ok(self.decl_node(start, DeclKind::Import(name)))
+    }
 
-        self.expect(TokenKind::LParen)?;
+    fn func_decl(&mut self) -> Result<Decl, ParseError> {
+        let start = self.expect(TokenKind::Func)?.location();
+
+        let name = self.expect(TokenKind::Identifier)?.into();
+
+        let opening = self.expect(TokenKind::LParen)?.location();
         let mut params = vec![];
         while !matches!(self.peek().kind(), TokenKind::Eof | TokenKind::RParen) {
-            params.push(self.expect(TokenKind::Identifier)?.slice().into());
+            params.push(self.expect(TokenKind::Identifier)?.into());
             // We only want to continue if there are more params, but we also allow for trailing
             // commas, this is handled by the loop condition.
             if !self.advance_if(TokenKind::Comma) {
                 break;
             }
         }
-        self.expect(TokenKind::RParen)?;
+        self.expect_closing(TokenKind::RParen, opening)?;
+
+        let body = Box::new(self.block_stmt()?);
+
+        let function = Arc::new(Function {
+            name,
+            params,
+            body,
+            slot_count: 0,
+            captures: vec![],
+        });
 
-        Ok(Decl::Func(name, params, Box::new(self.block_stmt()?)))
+        Ok(self.decl_node(start, DeclKind::Func(function)))
     }
 
-    fn stmt(&mut self) -> Result<Stmt, ParseError<'a>> {
+    fn stmt(&mut self) -> Result<Stmt, ParseError> {
+        let token = self.peek();
+        self.nested(&token, Self::stmt_unchecked)
+    }
+
+    fn stmt_unchecked(&mut self) -> Result<Stmt, ParseError> {
         match self.peek().kind() {
             TokenKind::LBrace => self.block_stmt(),
             TokenKind::If => self.if_stmt(),
             TokenKind::While => self.while_stmt(),
+            TokenKind::For => self.for_stmt(),
+            TokenKind::Return => self.return_stmt(),
             _ => self.expr_stmt(),
         }
     }
 
-    fn block_stmt(&mut self) -> Result<Stmt, ParseError<'a>> {
-        self.expect(TokenKind::LBrace)?;
+    fn block_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.expect(TokenKind::LBrace)?.location();
 
         let mut declarations = vec![];
         loop {
@@ -164,13 +522,13 @@ impl<'a> Parser<'a> {
             declarations.push(self.decl()?);
         }
 
-        self.expect(TokenKind::RBrace)?;
+        self.expect_closing(TokenKind::RBrace, start)?;
 
-        Ok(Stmt::Block(declarations))
+        Ok(self.stmt_node(start, StmtKind::Block(declarations)))
     }
 
-    fn if_stmt(&mut self) -> Result<Stmt, ParseError<'a>> {
-        self.expect(TokenKind::If)?;
+    fn if_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.expect(TokenKind::If)?.location();
         let predicate = self.expr()?;
         let consequent = self.stmt()?;
         let alternative = if self.advance_if(TokenKind::Else) {
@@ -179,65 +537,173 @@ impl<'a> Parser<'a> {
             None
         };
 
-        Ok(Stmt::If(
+        let kind = StmtKind::If(
             Box::new(predicate),
             Box::new(consequent),
             alternative.map(Box::new),
-        ))
+        );
+
+        Ok(self.stmt_node(start, kind))
     }
 
-    fn while_stmt(&mut self) -> Result<Stmt, ParseError<'a>> {
-        self.expect(TokenKind::While)?;
+    fn while_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.expect(TokenKind::While)?.location();
         let predicate = self.expr()?;
         let consequent = self.stmt()?;
 
-        Ok(Stmt::While(Box::new(predicate), Box::new(consequent)))
+        let kind = StmtKind::While(Box::new(predicate), Box::new(consequent));
+
+        Ok(self.stmt_node(start, kind))
     }
 
-    fn expr_stmt(&mut self) -> Result<Stmt, ParseError<'a>> {
-        let expr = self.expr()?;
+    fn for_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.expect(TokenKind::For)?.location();
+        let name = self.expect(TokenKind::Identifier)?.into();
+        self.expect(TokenKind::In)?;
+        let iterable = self.expr()?;
+        let body = self.stmt()?;
+
+        let kind = StmtKind::For(name, Box::new(iterable), Box::new(body));
+
+        Ok(self.stmt_node(start, kind))
+    }
+
+    fn return_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.expect(TokenKind::Return)?;
+        let expr = if self.peek().kind() != TokenKind::Semicolon {
+            Some(Box::new(self.expr()?))
+        } else {
+            None
+        };
 
         self.expect(TokenKind::Semicolon)?;
 
-        Ok(Stmt::Expr(Box::new(expr)))
+        let location = keyword.location();
+
+        Ok(self.stmt_node(location, StmtKind::Return(expr, location)))
+    }
+
+    fn expr_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expr()?;
+
+        // A name followed by anything other than the end of the statement is likely a
+        // misspelled keyword, as in `fucn f() {}`.
+        let suggestion = match &expr.kind {
+            ExprKind::Identifier(name)
+                if !matches!(self.peek().kind(), TokenKind::Semicolon | TokenKind::Eof) =>
+            {
+                suggest(name.as_ref(), TokenKind::KEYWORDS.iter().copied())
+            }
+            _ => None,
+        };
+        self.expect(TokenKind::Semicolon)
+            .map_err(|error| match suggestion {
+                Some(keyword) => error.with_suggestion(keyword),
+                None => error,
+            })?;
+
+        let start = expr.location;
+
+        Ok(self.stmt_node(start, StmtKind::Expr(Box::new(expr))))
     }
 
-    fn expr(&mut self) -> Result<Expr, ParseError<'a>> {
+    fn expr(&mut self) -> Result<Expr, ParseError> {
         self.expr_bp(0)
     }
 
-    fn expr_bp(&mut self, min_bp: u8) -> Result<Expr, ParseError<'a>> {
+    fn expr_bp(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
+        // Operators applied to the expression nest it further without recursing, so the depth
+        // is restored at the end rather than by `nested`.
+        let depth = self.depth;
+        let expr = self.expr_bp_unchecked(min_bp);
+        self.depth = depth;
+
+        expr
+    }
+
+    fn expr_bp_unchecked(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
         let token = self.advance();
-        let mut expr = match token.kind() {
-            TokenKind::Identifier => Expr::Identifier(token.slice().into()),
-            TokenKind::Number => Expr::Literal(Value::Number(token.slice().parse().unwrap())),
+        self.descend(&token)?;
+        let start = token.location();
+        if token.kind() == TokenKind::LParen {
+            let mut expr = self.expr_bp(0)?;
+            self.expect_closing(TokenKind::RParen, start)?;
+            // The parentheses are part of the grouped expression rather than a node of their own.
+            expr.location = self.location_from(start);
+            return self.operators(expr, min_bp);
+        }
+
+        let kind = match token.kind() {
+            TokenKind::Identifier => ExprKind::Identifier(token.into()),
+            TokenKind::Number => ExprKind::Literal(Literal::Number(token.slice().parse().unwrap())),
             TokenKind::String => {
-                todo!();
-            }
-            TokenKind::False => Expr::Literal(Value::Bool(false)),
-            TokenKind::True => Expr::Literal(Value::Bool(true)),
-            TokenKind::Nil => Expr::Literal(Value::Nil),
-            // Grouping
-            TokenKind::LParen => {
-                let expr = self.expr_bp(0)?;
-                self.expect(TokenKind::RParen)?;
-                expr
+                // Strip the surrounding quotes.
+                let slice = token.slice();
+                ExprKind::Literal(Literal::String(slice[1..slice.len() - 1].to_owned()))
             }
+            TokenKind::False => ExprKind::Literal(Literal::Bool(false)),
+            TokenKind::True => ExprKind::Literal(Literal::Bool(true)),
+            TokenKind::Nil => ExprKind::Literal(Literal::Nil),
+            TokenKind::LBrack => ExprKind::List(self.list(start)?),
+            TokenKind::LBrace => ExprKind::Map(self.map(start)?),
             _ => {
                 // The only remaining types of tokens valid in prefix position are those
                 // representing prefix operators.
                 let operator: Operator = token.try_into()?;
                 if let Some(((), r_bp)) = operator.prefix_binding_power() {
-                    Expr::Unary(operator, Box::new(self.expr_bp(r_bp)?))
+                    let operator = Spanned::new(operator, start);
+                    ExprKind::Unary(operator, Box::new(self.expr_bp(r_bp)?))
                 } else {
-                    // Unexpected token.
-                    todo!("error reporting");
+                    let message = format!("expected an expression got '{}'", token.kind());
+                    return Err(ParseError::new(
+                        ErrorCode::UNEXPECTED_TOKEN,
+                        &token,
+                        message,
+                    ));
                 }
             }
         };
+        let expr = self.expr_node(start, kind);
 
+        self.operators(expr, min_bp)
+    }
+
+    /// Parses the postfix and infix operators applied to `expr`, as long as they bind at least
+    /// as tightly as `min_bp`.
+    fn operators(&mut self, mut expr: Expr, min_bp: u8) -> Result<Expr, ParseError> {
         // We only continue if the peeked token is a valid operator.
         while let Ok(operator) = Operator::try_from(self.peek()) {
+            let operator_token = self.peek();
+            let location = operator_token.location();
+            let start = expr.location;
+            // Handle postfix case.
+            if let Some((l_bp, ())) = operator.postfix_binding_power() {
+                if l_bp < min_bp {
+                    break;
+                }
+
+                self.advance();
+                self.descend(&operator_token)?;
+                let kind = match operator {
+                    Operator::Call => {
+                        ExprKind::Call(Box::new(expr), self.args(location)?, location)
+                    }
+                    Operator::Index => {
+                        let index = self.expr()?;
+                        self.expect_closing(TokenKind::RBrack, location)?;
+                        ExprKind::Index(Box::new(expr), Box::new(index), location)
+                    }
+                    Operator::Member => {
+                        let name = self.expect(TokenKind::Identifier)?.into();
+                        ExprKind::Member(Box::new(expr), name)
+                    }
+                    _ => unreachable!(),
+                };
+                expr = self.expr_node(start, kind);
+
+                continue;
+            }
+
             // Handle infix case.
             if let Some((l_bp, r_bp)) = operator.infix_binding_power() {
                 if l_bp < min_bp {
@@ -247,11 +713,15 @@ impl<'a> Parser<'a> {
                 // We only advance if the peeked token is a valid infix operator, otherwise we
                 // leave the token to be handled elsewhere.
                 self.advance();
-                if min_bp == 0 && operator == Operator::Assign {
-                    expr = Expr::Assignment(Box::new(expr), Box::new(self.expr()?));
+                self.descend(&operator_token)?;
+                let kind = if min_bp == 0 && operator == Operator::Assign {
+                    check_assignment_target(&expr, &operator_token)?;
+                    ExprKind::Assignment(Box::new(expr), Box::new(self.expr()?))
                 } else {
-                    expr = Expr::Binary(operator, Box::new(expr), Box::new(self.expr_bp(r_bp)?));
-                }
+                    let operator = Spanned::new(operator, location);
+                    ExprKind::Binary(operator, Box::new(expr), Box::new(self.expr_bp(r_bp)?))
+                };
+                expr = self.expr_node(start, kind);
 
                 continue;
             } else {
@@ -259,33 +729,400 @@ impl<'a> Parser<'a> {
             }
         }
 
-        if self.peek().kind() == TokenKind::Equal {
-            todo!("invalid assignment target");
+        let token = self.peek();
+        if let Some(operator) = compound_operator(token.kind()) {
+            if min_bp == 0 {
+                self.advance();
+                self.descend(&token)?;
+                check_assignment_target(&expr, &token)?;
+                let operator = Spanned::new(operator, token.location());
+                let start = expr.location;
+                let kind =
+                    ExprKind::CompoundAssignment(operator, Box::new(expr), Box::new(self.expr()?));
+                return Ok(self.expr_node(start, kind));
+            }
+        }
+        // Assignment has the lowest precedence, so an `=` left over here follows an operand of
+        // another operator, as in `a + b = c`.
+        if token.kind() == TokenKind::Equal || compound_operator(token.kind()).is_some() {
+            return Err(invalid_assignment_target(&token));
         }
 
         Ok(expr)
     }
+
+    /// Parses call arguments, the opening parenthesis at `opening` is expected to have been
+    /// consumed already.
+    fn args(&mut self, opening: Location) -> Result<Vec<Expr>, ParseError> {
+        let mut args = vec![];
+        while !matches!(self.peek().kind(), TokenKind::Eof | TokenKind::RParen) {
+            args.push(self.expr()?);
+            // Trailing commas are allowed, same as with parameters.
+            if !self.advance_if(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_closing(TokenKind::RParen, opening)?;
+
+        Ok(args)
+    }
+
+    /// Parses the elements of a list literal, the opening bracket at `opening` is expected to
+    /// have been consumed already.
+    fn list(&mut self, opening: Location) -> Result<Vec<Expr>, ParseError> {
+        let mut items = vec![];
+        while !matches!(self.peek().kind(), TokenKind::Eof | TokenKind::RBrack) {
+            items.push(self.expr()?);
+            if !self.advance_if(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_closing(TokenKind::RBrack, opening)?;
+
+        Ok(items)
+    }
+
+    /// Parses the entries of a map literal, the opening brace at `opening` is expected to have
+    /// been consumed already.
+    fn map(&mut self, opening: Location) -> Result<Vec<(Expr, Expr)>, ParseError> {
+        let mut entries = vec![];
+        while !matches!(self.peek().kind(), TokenKind::Eof | TokenKind::RBrace) {
+            let key = self.expr()?;
+            self.expect(TokenKind::Colon)?;
+            entries.push((key, self.expr()?));
+            if !self.advance_if(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect_closing(TokenKind::RBrace, opening)?;
+
+        Ok(entries)
+    }
+}
+
+/// Parses the top level declarations one at a time as they are written, so a script can be run
+/// as it is parsed without keeping all of it in memory. A declaration can be run by lowering and
+/// resolving a script made of it, as [`Parser::parse`] does for a whole script. Iteration ends
+/// after the first error.
+impl Iterator for Parser<'_> {
+    type Item = Result<Decl, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.peek().kind() == TokenKind::Eof {
+            self.finished = true;
+            return self.scan_error.take().map(Err);
+        }
+
+        let decl = self.top_level_decl();
+        let decl = match self.scan_error.take() {
+            Some(error) => Err(error),
+            None => decl,
+        };
+        self.finished = decl.is_err();
+
+        Some(decl)
+    }
+}
+
+impl FusedIterator for Parser<'_> {}
+
+/// Returns an error unless `target` can be assigned to, `equal` is the assignment's `=` token.
+fn check_assignment_target<'a>(target: &Expr, equal: &Token<'a>) -> Result<(), ParseError> {
+    match target.kind {
+        ExprKind::Identifier(_) | ExprKind::Index(..) => Ok(()),
+        ExprKind::Member(..) => Err(ParseError::new(
+            ErrorCode::UNEXPECTED_TOKEN,
+            equal,
+            "members can't be assigned to, use set_field instead".to_owned(),
+        )),
+        _ => Err(invalid_assignment_target(equal)),
+    }
 }
 
-#[derive(Debug)]
-pub struct ParseError<'a> {
-    span: Span<'a>,
+/// Binary operator a compound assignment token such as `+=` applies.
+fn compound_operator(kind: TokenKind) -> Option<Operator> {
+    let operator = match kind {
+        TokenKind::PlusEqual => Operator::Add,
+        TokenKind::MinusEqual => Operator::Sub,
+        TokenKind::StarEqual => Operator::Mul,
+        TokenKind::SlashEqual => Operator::Div,
+        TokenKind::PercentEqual => Operator::Mod,
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+fn invalid_assignment_target<'a>(equal: &Token<'a>) -> ParseError {
+    let message = "invalid assignment target, only variables and indexes can be assigned to";
+    ParseError::new(ErrorCode::UNEXPECTED_TOKEN, equal, message.to_owned())
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("{message}")]
+pub struct ParseError {
+    code: ErrorCode,
+    location: Location,
     message: String,
+    incomplete: bool,
+    suggestion: Option<String>,
+    /// Other code involved in the error, such as the bracket which wasn't closed.
+    labels: Vec<Label>,
 }
 
-impl<'a> ParseError<'a> {
-    pub fn new(token: &Token<'a>, message: String) -> Self {
+impl ParseError {
+    pub fn new(code: ErrorCode, token: &Token, message: String) -> Self {
         Self {
-            span: token.span(),
+            code,
+            location: token.location(),
             message,
+            incomplete: token.is_eof(),
+            suggestion: None,
+            labels: vec![],
         }
     }
 
-    pub fn span(&self) -> Span<'a> {
-        self.span
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    /// Adds what was likely meant instead of the code which caused the error.
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+
+    /// Whether the source ended before the error was found, meaning more input could make it
+    /// valid, as opposed to it containing something which is never valid.
+    pub fn is_incomplete(&self) -> bool {
+        self.incomplete
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
     }
 
     pub fn message(&self) -> &str {
         &self.message
     }
 }
+
+impl From<ScanError> for ParseError {
+    fn from(error: ScanError) -> Self {
+        Self {
+            // A string missing its closing quote can be finished by more input.
+            incomplete: error.code == ErrorCode::UNTERMINATED_STRING,
+            code: error.code,
+            location: error.location,
+            message: error.message,
+            suggestion: None,
+            labels: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ast::{DeclKind, ExprKind, StmtKind},
+        token::Location,
+        Parser,
+    };
+    use crate::{
+        compiler::sexpr::{self, Options},
+        diagnostic::ErrorCode,
+    };
+
+    fn parse(source: &str) -> String {
+        let script = Parser::new(source).parse().unwrap();
+        sexpr::script(&script, &Options::compact())
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            (
+                "1 + 2 * 3 ** 4;",
+                "(expr (binary + (number 1) (binary * (number 2) (binary ** (number 3) (number 4)))))",
+            ),
+            (
+                "a = b = -c.d(1)[2];",
+                "(expr (assign (ident a) (assign (ident b) (unary - (index (call (member (ident c) d) (number 1)) (number 2))))))",
+            ),
+            (
+                "not a == b and c < d or e;",
+                "(expr (binary or (binary and (unary not (binary == (ident a) (ident b))) (binary < (ident c) (ident d))) (ident e)))",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source), format!("(script {expected})"), "{source}");
+        }
+    }
+
+    #[test]
+    fn keyword_suggestion() {
+        let error = Parser::new("fucn f() {}").parse().err().unwrap();
+        assert_eq!(error.suggestion(), Some("func"));
+        let error = Parser::new("retrun 1;").parse().err().unwrap();
+        assert_eq!(error.suggestion(), Some("return"));
+        let error = Parser::new("x y;").parse().err().unwrap();
+        assert_eq!(error.suggestion(), None);
+    }
+
+    #[test]
+    fn incomplete() {
+        for source in ["if x {", "f(1,", "1 +", "let x = [1", "x", "print(\"a"] {
+            let error = Parser::new(source).parse().err().unwrap();
+            assert!(error.is_incomplete(), "{source}");
+        }
+        for source in ["let 1;", "f(1;", "x )"] {
+            let error = Parser::new(source).parse().err().unwrap();
+            assert!(!error.is_incomplete(), "{source}");
+        }
+    }
+
+    #[test]
+    fn fragments() {
+        let options = Options::compact();
+        let expr = Parser::new("a += f(1)[2]").parse_expr().unwrap();
+        assert_eq!(
+            sexpr::expr(&expr, &options),
+            "(assign += (ident a) (index (call (ident f) (number 1)) (number 2)))"
+        );
+        let stmt = Parser::new("while x { x -= 1; }").parse_stmt().unwrap();
+        assert!(matches!(stmt.kind, StmtKind::While(..)));
+
+        for source in ["1; 2", "1 2", "a +"] {
+            assert!(Parser::new(source).parse_expr().is_err(), "{source}");
+        }
+        let error = Parser::new("a @ b").parse_expr().err().unwrap();
+        assert_eq!(error.code(), ErrorCode::UNEXPECTED_CHARACTER);
+        assert!(Parser::new("f(); g();").parse_stmt().is_err());
+    }
+
+    #[test]
+    fn declarations() {
+        let decls: Vec<_> = Parser::new("let a = 1; func f() {} test \"t\" {} a += 1;").collect();
+        assert_eq!(decls.len(), 4);
+        assert!(decls.iter().all(Result::is_ok));
+        assert!(Parser::new("").next().is_none());
+
+        // Iteration ends at the first error, and invalid tokens are reported with the
+        // declaration they are in.
+        let mut parser = Parser::new("let a = 1; let b = @; let c = 3;");
+        assert!(parser.next().unwrap().is_ok());
+        let error = parser.next().unwrap().err().unwrap();
+        assert_eq!(error.code(), ErrorCode::UNEXPECTED_CHARACTER);
+        assert!(parser.next().is_none());
+        let mut parser = Parser::new("f(; g();");
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+        let mut parser = Parser::new("f(); @");
+        assert!(parser.next().unwrap().is_ok());
+        assert!(parser.next().unwrap().is_err());
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("let x = 1 @ 2;", ErrorCode::UNEXPECTED_CHARACTER),
+            ("let x = @;", ErrorCode::UNEXPECTED_CHARACTER),
+            ("* 1;", ErrorCode::UNEXPECTED_TOKEN),
+            ("a + b = c;", ErrorCode::UNEXPECTED_TOKEN),
+            ("-a = 1;", ErrorCode::UNEXPECTED_TOKEN),
+            ("a.b = 1;", ErrorCode::UNEXPECTED_TOKEN),
+            ("f() = 1;", ErrorCode::UNEXPECTED_TOKEN),
+            ("a + b += c;", ErrorCode::UNEXPECTED_TOKEN),
+            ("a.b -= 1;", ErrorCode::UNEXPECTED_TOKEN),
+        ];
+        for (source, code) in cases {
+            let error = Parser::new(source).parse().err().unwrap();
+            assert_eq!(error.code(), code, "{source}");
+        }
+        assert!(Parser::new("a[0] = b = 1;").parse().is_ok());
+        assert!(Parser::new("a[0] += b = c -= 1;").parse().is_ok());
+    }
+
+    #[test]
+    fn parse_all() {
+        let source = "
+            let a = ;
+            func f() {
+                let b = 1 +;
+                if b { }
+            }
+            let c = 1;
+            * 2;
+            f(;
+            let d = 2;
+        ";
+        let errors = Parser::new(source).parse_all().err().unwrap();
+        let lines: Vec<_> = errors
+            .iter()
+            .map(|error| source[..error.location().start].lines().count())
+            .collect();
+        assert_eq!(lines, [2, 4, 8, 9]);
+
+        let script = Parser::new("let a = 1; print(a);").parse_all().unwrap();
+        assert_eq!(script.decls.len(), 2);
+    }
+
+    #[test]
+    fn node_locations() {
+        let source = "let x = (1 + f(2)) * 3;\nwhile x > 0 { x = x - 1; }";
+        let script = Parser::new(source).parse().unwrap();
+        let slice = |location: Location| &source[location.start..location.end];
+
+        let DeclKind::Var(_, Some(init)) = &script.decls[0].kind else {
+            panic!("expected a variable");
+        };
+        assert_eq!(slice(script.decls[0].location), "let x = (1 + f(2)) * 3;");
+        assert_eq!(slice(init.location), "(1 + f(2)) * 3");
+        let ExprKind::Binary(_, left, right) = &init.kind else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(slice(left.location), "(1 + f(2))");
+        assert_eq!(slice(right.location), "3");
+
+        let DeclKind::Stmt(stmt) = &script.decls[1].kind else {
+            panic!("expected a statement");
+        };
+        assert_eq!(slice(stmt.location), "while x > 0 { x = x - 1; }");
+        assert_eq!((stmt.location.line, stmt.location.column), (2, 1));
+        let StmtKind::While(predicate, body) = &stmt.kind else {
+            panic!("expected a loop");
+        };
+        assert_eq!(slice(predicate.location), "x > 0");
+        assert_eq!(slice(body.location), "{ x = x - 1; }");
+
+        let ids = [
+            script.decls[0].id,
+            init.id,
+            left.id,
+            right.id,
+            script.decls[1].id,
+            stmt.id,
+            predicate.id,
+            body.id,
+        ];
+        let unique: std::collections::HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+    }
+}
@@ -1,56 +1,251 @@
+use std::{fmt::Display, sync::Arc};
+
 use crate::compiler::parser::{
-    token::{Token, TokenKind},
+    token::{Location, Token, TokenKind},
     ParseError,
 };
-use crate::interpreter::value::Value;
+use crate::compiler::{resolver::Reference, warnings::Warning};
+use crate::diagnostic::ErrorCode;
 
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Script {
     pub decls: Vec<Decl>,
+    /// Number of local variable slots used by blocks at the top level, assigned by the resolver.
+    pub slot_count: usize,
+    /// Likely mistakes found by the resolver, in the order they were found.
+    pub warnings: Vec<Warning>,
+    /// Declarations and uses of variables, in the order they appear.
+    pub references: Vec<Reference>,
+    /// Id the next node added to the script is given, passes which add nodes take their ids
+    /// from here so they don't clash with those of the parsed nodes.
+    pub next_id: NodeId,
+}
+
+/// Identifies a node of the syntax tree, the nodes of a script each have a different id.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Returns this id and replaces it with the one after it.
+    pub fn take(&mut self) -> Self {
+        let id = *self;
+        self.0 += 1;
+        id
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Decl {
+    pub id: NodeId,
+    /// Location from the first to the last token of the declaration.
+    pub location: Location,
+    pub kind: DeclKind,
 }
 
 #[derive(Debug)]
-pub enum Decl {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeclKind {
     Var(Identifier, Option<Box<Expr>>),
-    Func(Identifier, Vec<Identifier>, Box<Stmt>),
+    /// Function declarations are reference counted since function objects created from them
+    /// need to outlive the script they were declared in.
+    Func(Arc<Function>),
+    /// Test declared with `test "name" { ... }`, only allowed at the top level. The body is a
+    /// function without parameters whose name is the name of the test.
+    Test(Arc<Function>),
+    /// Module imported with `import "name";`, which declares a variable named after the module.
+    /// The identifier's location is that of the module's name.
+    Import(Identifier),
     Stmt(Box<Stmt>),
 }
 
 #[derive(Debug)]
-pub enum Stmt {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stmt {
+    pub id: NodeId,
+    /// Location from the first to the last token of the statement.
+    pub location: Location,
+    pub kind: StmtKind,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StmtKind {
     /// Neither consequent or alternative statements should be any kind of declaration.
     If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
     While(Box<Expr>, Box<Stmt>),
+    /// Runs the body once for each item of a list or key of a map, the variable is scoped to the
+    /// loop.
+    For(Identifier, Box<Expr>, Box<Stmt>),
     Expr(Box<Expr>),
     Block(Vec<Decl>),
-    Print(Box<Expr>),
+    /// The location is that of the `return` keyword.
+    Return(Option<Box<Expr>>, Location),
 }
 
-#[derive(Debug)]
-pub enum Expr {
-    Literal(Value),
+/// Expressions can be cloned so that lowering passes can repeat them, the copies keep the ids
+/// of the nodes they were cloned from.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expr {
+    pub id: NodeId,
+    /// Location from the first to the last token of the expression, including the parentheses
+    /// around it.
+    pub location: Location,
+    pub kind: ExprKind,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExprKind {
+    Literal(Literal),
     Identifier(Identifier),
-    /// Currently the only valid value for `0` is `Identifier`.
+    /// The target is either an `Identifier` or an `Index`.
     Assignment(Box<Expr>, Box<Expr>),
-    Binary(Operator, Box<Expr>, Box<Expr>),
-    Unary(Operator, Box<Expr>),
+    /// Assignment combined with a binary operator such as `+=`, the target is the same as that of
+    /// an `Assignment`. It is [lowered](crate::compiler::lower) to an `Assignment` before the
+    /// script is resolved, so only scripts parsed with [`Parser::parse_syntax`] contain it.
+    ///
+    /// [`Parser::parse_syntax`]: crate::compiler::parser::Parser::parse_syntax
+    CompoundAssignment(Spanned<Operator>, Box<Expr>, Box<Expr>),
+    Binary(Spanned<Operator>, Box<Expr>, Box<Expr>),
+    Unary(Spanned<Operator>, Box<Expr>),
+    /// The location is that of the opening parenthesis.
+    Call(Box<Expr>, Vec<Expr>, Location),
+    /// The location is that of the opening bracket.
+    Index(Box<Expr>, Box<Expr>, Location),
+    /// Access of the named member of an object, such as a field or method.
+    Member(Box<Expr>, Identifier),
+    List(Vec<Expr>),
+    /// Key value pairs in the order they were written.
+    Map(Vec<(Expr, Expr)>),
+}
+
+/// Node paired with the location it was parsed from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spanned<T> {
+    pub node: T,
+    pub location: Location,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, location: Location) -> Self {
+        Self { node, location }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub name: Identifier,
+    pub params: Vec<Identifier>,
+    /// Always a `Stmt::Block`.
+    pub body: Box<Stmt>,
+    /// Number of local variable slots used by the function, including its parameters, assigned
+    /// by the resolver.
+    pub slot_count: usize,
+    /// Variables of enclosing functions used by this function, assigned by the resolver.
+    pub captures: Vec<Capture>,
+}
+
+/// Variable captured by a function when it is declared, functions capture the cells variables
+/// are stored in so they see assignments made after they were declared.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Capture {
+    /// Cell in a local slot of the enclosing function.
+    Local(usize),
+    /// Cell captured by the enclosing function.
+    Capture(usize),
+}
+
+/// Literal values, these are kept separate from runtime values since some of them require heap
+/// allocation.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Literal {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Nil,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Identifier(String);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Identifier {
+    name: String,
+    location: Location,
+    slot: Slot,
+}
 
-impl From<&str> for Identifier {
-    fn from(value: &str) -> Self {
-        Self(value.to_owned())
+impl Identifier {
+    pub fn new(name: &str, location: Location) -> Self {
+        Self {
+            name: name.to_owned(),
+            location,
+            slot: Slot::Global,
+        }
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    pub fn set_slot(&mut self, slot: Slot) {
+        self.slot = slot;
+    }
+
+    pub fn set_location(&mut self, location: Location) {
+        self.location = location;
+    }
+}
+
+/// Where the variable an identifier refers to is stored.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Slot {
+    /// Looked up by name in the global scope, identifiers which haven't been resolved are
+    /// treated as globals.
+    #[default]
+    Global,
+    /// Index into the local variables of the current call.
+    Local(usize),
+    /// Index into the local variables of the current call of a variable which is captured by a
+    /// function, the slot holds a cell which contains the value.
+    Cell(usize),
+    /// Index into the cells captured by the current function.
+    Capture(usize),
+}
+
+impl<'a> From<Token<'a>> for Identifier {
+    fn from(token: Token<'a>) -> Self {
+        Self::new(token.slice(), token.location())
     }
 }
 
 impl AsRef<str> for Identifier {
     fn as_ref(&self) -> &str {
-        self.0.as_ref()
+        self.name.as_ref()
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator {
     Assign,
     Or,
@@ -68,6 +263,9 @@ pub enum Operator {
     Div,
     Mod,
     Exp,
+    Call,
+    Index,
+    Member,
 }
 
 impl Operator {
@@ -77,6 +275,10 @@ impl Operator {
         matches!(self, Not | Sub)
     }
 
+    pub fn is_postfix(self) -> bool {
+        matches!(self, Operator::Call | Operator::Index | Operator::Member)
+    }
+
     pub fn is_infix(self) -> bool {
         use Operator::*;
 
@@ -86,7 +288,7 @@ impl Operator {
         )
     }
 
-    pub(super) fn prefix_binding_power(self) -> Option<((), u8)> {
+    pub(crate) fn prefix_binding_power(self) -> Option<((), u8)> {
         use Operator::*;
 
         let bp = match self {
@@ -98,7 +300,7 @@ impl Operator {
         Some(bp)
     }
 
-    pub(super) fn infix_binding_power(self) -> Option<(u8, u8)> {
+    pub(crate) fn infix_binding_power(self) -> Option<(u8, u8)> {
         use Operator::*;
 
         let bp = match self {
@@ -116,19 +318,50 @@ impl Operator {
         Some(bp)
     }
 
-    // pub(super) fn postfix_binding_power(self) -> Option<(u8, ())> {
-    //     use Operator::*;
+    pub(super) fn postfix_binding_power(self) -> Option<(u8, ())> {
+        use Operator::*;
 
-    //     let bp = match self {
-    //         _ => return None,
-    //     };
+        let bp = match self {
+            Call | Index | Member => (21, ()),
+            _ => return None,
+        };
+
+        Some(bp)
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Operator::*;
+
+        let s = match self {
+            Assign => "=",
+            Or => "or",
+            And => "and",
+            Not => "not",
+            Eq => "==",
+            Ne => "!=",
+            Lt => "<",
+            Gt => ">",
+            Le => "<=",
+            Ge => ">=",
+            Add => "+",
+            Sub => "-",
+            Mul => "*",
+            Div => "/",
+            Mod => "%",
+            Exp => "**",
+            Call => "()",
+            Index => "[]",
+            Member => ".",
+        };
 
-    //     Some(bp)
-    // }
+        f.write_str(s)
+    }
 }
 
 impl<'a> TryFrom<Token<'a>> for Operator {
-    type Error = ParseError<'a>;
+    type Error = ParseError;
 
     fn try_from(token: Token<'a>) -> Result<Self, Self::Error> {
         let op = match token.kind() {
@@ -148,15 +381,53 @@ impl<'a> TryFrom<Token<'a>> for Operator {
             TokenKind::Slash => Self::Div,
             TokenKind::Percent => Self::Mod,
             TokenKind::StarStar => Self::Exp,
+            TokenKind::LParen => Self::Call,
+            TokenKind::LBrack => Self::Index,
+            TokenKind::Period => Self::Member,
             _ => {
-                let message = format!("unexpected token: {:?}", token);
-                return Err(ParseError {
-                    span: token.span(),
+                let message = if token.is_eof() {
+                    "unexpected end of input".to_owned()
+                } else {
+                    format!("unexpected token '{}'", token.slice())
+                };
+                return Err(ParseError::new(
+                    ErrorCode::UNEXPECTED_TOKEN,
+                    &token,
                     message,
-                });
+                ));
             }
         };
 
         Ok(op)
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::Script;
+    use crate::compiler::{
+        parser::Parser,
+        sexpr::{self, Options},
+    };
+
+    #[test]
+    fn round_trip() {
+        let source = "let a = [1, \"b\", {c: nil}];
+func f(x) { for y in x { return -y.z(a[0]); } }
+test \"t\" { while not false { f(a); } }";
+        let script = Parser::new(source).parse().unwrap();
+        let json = serde_json::to_string(&script).unwrap();
+        let parsed: Script = serde_json::from_str(&json).unwrap();
+
+        let options = Options {
+            spans: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            sexpr::script(&parsed, &options),
+            sexpr::script(&script, &options)
+        );
+        assert_eq!(parsed.references, script.references);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+    }
+}
@@ -1,42 +1,74 @@
 use std::str::Chars;
 
-use super::token::Span;
+use super::{lookahead::Lookahead, token::Location};
+use crate::source_map::FileId;
+
+/// Character returned past the end of the input.
+pub const EOF_CHAR: char = '\0';
 
 /// `Cursor` iterates over its `input` string keeping a `start` index which marks the beginning of
-/// and returning `Span`s.
+/// and returning `Span`s. Up to `N` characters can be looked ahead at.
 #[derive(Debug)]
-pub struct Cursor<'a> {
+pub struct Cursor<'a, const N: usize> {
     input: &'a str,
-    iter: Chars<'a>,
+    iter: Lookahead<Chars<'a>, N>,
     start: usize,
+    /// Line and column of `start`.
+    start_position: (usize, usize),
+    /// Index in the input of the next character.
+    current: usize,
+    /// Line and column of `current`, kept up to date as characters are advanced past so that
+    /// finding the location of a span doesn't scan the input from the beginning.
+    current_position: (usize, usize),
+    /// File the spans returned are in.
+    file: FileId,
 }
 
-impl<'a> Cursor<'a> {
-    pub const EOF_CHAR: char = '\0';
-
-    pub fn new(input: &'a str) -> Cursor<'a> {
+impl<'a, const N: usize> Cursor<'a, N> {
+    pub fn new(input: &'a str) -> Self {
         Cursor {
             input,
-            iter: input.chars(),
+            iter: Lookahead::new(input.chars()),
             start: 0,
+            start_position: (1, 1),
+            current: 0,
+            current_position: (1, 1),
+            file: FileId::default(),
         }
     }
 
-    pub fn advance(&mut self) -> char {
-        self.iter.next().unwrap_or(Self::EOF_CHAR)
+    pub fn set_file(&mut self, file: FileId) {
+        self.file = file;
     }
 
-    pub fn lookahead(&self, n: usize) -> char {
-        self.iter.clone().nth(n).unwrap_or(Self::EOF_CHAR)
+    pub fn input(&self) -> &'a str {
+        self.input
     }
 
-    /// Returns the number of bytes left.
-    pub fn bytes_remaining(&self) -> usize {
-        self.iter.as_str().len()
+    pub fn advance(&mut self) -> char {
+        match self.iter.next() {
+            Some(c) => {
+                self.current += c.len_utf8();
+                let (line, column) = &mut self.current_position;
+                if c == '\n' {
+                    *line += 1;
+                    *column = 1;
+                } else {
+                    *column += 1;
+                }
+                c
+            }
+            None => EOF_CHAR,
+        }
     }
 
-    pub fn is_at_end(&self) -> bool {
-        self.lookahead(0) == Self::EOF_CHAR
+    /// Returns the character `n` places after the next one, `n` must be less than `N`.
+    pub fn lookahead(&mut self, n: usize) -> char {
+        self.iter.peek(n).copied().unwrap_or(EOF_CHAR)
+    }
+
+    pub fn is_at_end(&mut self) -> bool {
+        self.lookahead(0) == EOF_CHAR
     }
 
     pub fn start_index(&self) -> usize {
@@ -46,73 +78,107 @@ impl<'a> Cursor<'a> {
     /// Returns the current index in the input and the end index of the current span
     /// (exclusive).
     pub fn current_index(&self) -> usize {
-        self.input.len() - self.bytes_remaining()
+        self.current
     }
 
     /// Resets the starting index and returns the old value.
     pub fn reset_start_index(&mut self) -> usize {
         let offset = self.start;
         self.start = self.current_index();
+        self.start_position = self.current_position;
         offset
     }
 
-    /// Returns the current span.
-    pub fn span(&self) -> Span<'a> {
-        Span::new(self.input, self.start_index(), self.current_index())
+    /// Returns the text of the current span.
+    pub fn slice(&self) -> &'a str {
+        &self.input[self.start_index()..self.current_index()]
     }
 
-    /// Returns the current span and resets the starting index.
-    pub fn reset_span(&mut self) -> Span<'a> {
-        Span::new(self.input, self.reset_start_index(), self.current_index())
+    /// Returns the location of the current span and resets the starting index.
+    pub fn reset_location(&mut self) -> Location {
+        let (line, column) = self.start_position;
+        let start = self.reset_start_index();
+        Location {
+            file: self.file,
+            start,
+            end: self.current_index(),
+            line,
+            column,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Cursor;
+    use super::{Cursor, EOF_CHAR};
 
     #[test]
     fn offset() {
-        let mut c = Cursor::new("one");
-        assert_eq!(c.bytes_remaining(), 3);
+        let mut c = Cursor::<1>::new("oné");
+        assert_eq!(c.current_index(), 0);
+        c.advance();
+        assert_eq!(c.current_index(), 1);
+        assert_eq!(c.lookahead(0), 'n');
+        assert_eq!(c.current_index(), 1);
         c.advance();
-        assert_eq!(c.bytes_remaining(), 2);
         c.advance();
-        assert_eq!(c.bytes_remaining(), 1);
+        assert_eq!(c.current_index(), 4);
         c.advance();
-        assert_eq!(c.bytes_remaining(), 0);
+        assert_eq!(c.current_index(), 4);
 
-        let mut c = Cursor::new("");
-        assert_eq!(c.bytes_remaining(), 0);
+        let mut c = Cursor::<1>::new("");
+        assert_eq!(c.current_index(), 0);
+        c.advance();
+        assert_eq!(c.current_index(), 0);
+    }
+
+    #[test]
+    fn location() {
+        let mut c = Cursor::<1>::new("ab\né\nc");
+        c.advance();
+        c.advance();
+        let location = c.reset_location();
+        assert_eq!((location.start, location.end), (0, 2));
+        assert_eq!((location.line, location.column), (1, 1));
+        c.advance();
+        c.reset_start_index();
+        c.advance();
+        c.advance();
+        let location = c.reset_location();
+        assert_eq!((location.start, location.end), (3, 6));
+        assert_eq!((location.line, location.column), (2, 1));
         c.advance();
-        assert_eq!(c.bytes_remaining(), 0);
+        let location = c.reset_location();
+        assert_eq!((location.line, location.column), (3, 1));
     }
 
     #[test]
     fn advance() {
-        let mut c = Cursor::new("one");
+        let mut c = Cursor::<1>::new("one");
         assert_eq!(c.advance(), 'o');
         assert_eq!(c.advance(), 'n');
         assert_eq!(c.advance(), 'e');
-        assert_eq!(c.advance(), Cursor::EOF_CHAR);
+        assert_eq!(c.advance(), EOF_CHAR);
 
-        let mut c = Cursor::new("");
-        assert_eq!(c.advance(), Cursor::EOF_CHAR);
+        let mut c = Cursor::<1>::new("");
+        assert_eq!(c.advance(), EOF_CHAR);
     }
 
     #[test]
     fn lookahead() {
-        let mut c = Cursor::new("one");
+        let mut c = Cursor::<2>::new("one");
         assert_eq!(c.lookahead(0), 'o');
+        assert_eq!(c.lookahead(1), 'n');
         assert_eq!(c.advance(), 'o');
+        assert_eq!(c.lookahead(1), 'e');
 
-        let c = Cursor::new("");
-        assert_eq!(c.lookahead(0), Cursor::EOF_CHAR);
+        let mut c = Cursor::<1>::new("");
+        assert_eq!(c.lookahead(0), EOF_CHAR);
     }
 
     #[test]
     fn is_at_end() {
-        let c = Cursor::new("");
+        let mut c = Cursor::<1>::new("");
         assert!(c.is_at_end());
     }
 }
@@ -0,0 +1,81 @@
+use std::{array, iter::FusedIterator};
+
+/// `Lookahead` wraps an iterator so that up to `N` of its next items can be peeked at, the items
+/// peeked at are kept in a fixed size ring buffer until they are returned.
+#[derive(Debug)]
+pub struct Lookahead<I: Iterator, const N: usize> {
+    iter: I,
+    buffer: [Option<I::Item>; N],
+    /// Index in `buffer` of the first item peeked at.
+    head: usize,
+    /// Number of items in `buffer`.
+    len: usize,
+}
+
+impl<I: Iterator, const N: usize> Lookahead<I, N> {
+    pub fn new(iter: I) -> Self {
+        Self {
+            iter,
+            buffer: array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the item `n` places after the next one without advancing, or `None` if the
+    /// iterator ends before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` isn't less than `N`.
+    pub fn peek(&mut self, n: usize) -> Option<&I::Item> {
+        assert!(n < N, "can't look {n} items ahead, only {N} are buffered");
+        while self.len <= n {
+            let item = self.iter.next()?;
+            self.buffer[(self.head + self.len) % N] = Some(item);
+            self.len += 1;
+        }
+
+        self.buffer[(self.head + n) % N].as_ref()
+    }
+}
+
+impl<I: Iterator, const N: usize> Iterator for Lookahead<I, N> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return self.iter.next();
+        }
+        let item = self.buffer[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+
+        item
+    }
+}
+
+impl<I: FusedIterator, const N: usize> FusedIterator for Lookahead<I, N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::Lookahead;
+
+    #[test]
+    fn peek() {
+        let mut l = Lookahead::<_, 2>::new(1..=5);
+        assert_eq!(l.peek(1), Some(&2));
+        assert_eq!(l.peek(0), Some(&1));
+        assert_eq!(l.next(), Some(1));
+        assert_eq!(l.peek(1), Some(&3));
+        assert_eq!(l.next(), Some(2));
+        assert_eq!(l.next(), Some(3));
+        assert_eq!(l.peek(1), Some(&5));
+        assert_eq!(l.next(), Some(4));
+        assert_eq!(l.peek(1), None);
+        assert_eq!(l.peek(0), Some(&5));
+        assert_eq!(l.next(), Some(5));
+        assert_eq!(l.peek(0), None);
+        assert_eq!(l.next(), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Rewriting of the syntax tree by replacing its nodes, used by [lowering](crate::compiler::lower)
+//! and by tools which change scripts such as codemods.
+//!
+//! A [`Rewriter`] is given each node once its children have been rewritten, and returns the node
+//! to put in its place. The node it returns isn't rewritten again, so a rewriter which produces
+//! nodes it would rewrite itself has to handle them before returning. Nodes are rewritten in
+//! place otherwise, declarations of functions keep the same function unless it is replaced.
+
+use std::mem;
+
+use crate::compiler::parser::{
+    ast::{Decl, DeclKind, Expr, ExprKind, Literal, NodeId, Script, Stmt, StmtKind},
+    token::Location,
+    visit::{walk_decl_mut, walk_expr_mut, walk_stmt_mut, VisitorMut},
+};
+
+pub trait Rewriter {
+    fn rewrite_decl(&mut self, decl: Decl) -> Decl {
+        decl
+    }
+
+    fn rewrite_stmt(&mut self, stmt: Stmt) -> Stmt {
+        stmt
+    }
+
+    fn rewrite_expr(&mut self, expr: Expr) -> Expr {
+        expr
+    }
+}
+
+/// Rewrites every node of the script from the innermost out.
+///
+/// # Panics
+///
+/// Panics if a function of the script is shared, as with [`VisitorMut`].
+pub fn rewrite_script<R: Rewriter + ?Sized>(script: &mut Script, rewriter: &mut R) {
+    Rewrite(rewriter).visit_script_mut(script);
+}
+
+/// Visits the nodes for a rewriter, taking each node out of the tree to give it to the rewriter
+/// and leaving an empty node in its place until it is replaced.
+struct Rewrite<'a, R: ?Sized>(&'a mut R);
+
+impl<R: Rewriter + ?Sized> VisitorMut for Rewrite<'_, R> {
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        walk_decl_mut(self, decl);
+        let empty = Decl {
+            kind: DeclKind::Stmt(Box::new(empty_stmt(decl.id, decl.location))),
+            ..*decl
+        };
+        *decl = self.0.rewrite_decl(mem::replace(decl, empty));
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+        let empty = empty_stmt(stmt.id, stmt.location);
+        *stmt = self.0.rewrite_stmt(mem::replace(stmt, empty));
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+        let empty = Expr {
+            id: expr.id,
+            location: expr.location,
+            kind: ExprKind::Literal(Literal::Nil),
+        };
+        *expr = self.0.rewrite_expr(mem::replace(expr, empty));
+    }
+}
+
+fn empty_stmt(id: NodeId, location: Location) -> Stmt {
+    Stmt {
+        id,
+        location,
+        kind: StmtKind::Block(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{rewrite_script, Rewriter};
+    use crate::compiler::{
+        formatter::format,
+        parser::{
+            ast::{DeclKind, Expr, ExprKind, Literal},
+            Parser,
+        },
+    };
+
+    /// Doubles every number.
+    struct Double;
+
+    impl Rewriter for Double {
+        fn rewrite_expr(&mut self, expr: Expr) -> Expr {
+            match expr.kind {
+                ExprKind::Literal(Literal::Number(n)) => Expr {
+                    kind: ExprKind::Literal(Literal::Number(n * 2.0)),
+                    ..expr
+                },
+                kind => Expr { kind, ..expr },
+            }
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        let mut script = Parser::new("func f(a) { return a[1] + 2; } f([3, 4]);")
+            .parse_syntax()
+            .unwrap();
+        let DeclKind::Func(function) = &script.decls[0].kind else {
+            panic!("expected a function");
+        };
+        let function = Arc::as_ptr(function);
+
+        rewrite_script(&mut script, &mut Double);
+        assert_eq!(
+            format(&script),
+            "func f(a) {\n    return a[2] + 4;\n}\n\nf([6, 8]);\n"
+        );
+        assert!(matches!(
+            &script.decls[0].kind,
+            DeclKind::Func(rewritten) if Arc::as_ptr(rewritten) == function
+        ));
+    }
+}
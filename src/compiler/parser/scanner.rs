@@ -1,24 +1,62 @@
-use super::token::{Span, TokenKind};
+use std::iter::FusedIterator;
 
-use super::{cursor::Cursor, token::Token};
+use thiserror::Error;
+
+use super::token::{Location, TokenKind};
+use crate::{diagnostic::ErrorCode, source_map::FileId};
+
+use super::{
+    cursor::{Cursor, EOF_CHAR},
+    token::Token,
+};
+
+/// Number of characters after the current one the scanner looks at to decide what token it is
+/// scanning, `1.5` is only a number if there is a digit after the `.`.
+const LOOKAHEAD: usize = 2;
 
 #[derive(Debug)]
 pub struct Scanner<'a> {
-    cursor: Cursor<'a>,
+    cursor: Cursor<'a, LOOKAHEAD>,
+    /// Whether whitespace is returned as tokens rather than skipped.
+    trivia: bool,
+    /// Whether iterating has returned the end of file token.
+    finished: bool,
 }
 
 impl<'a> Scanner<'a> {
     pub fn new(source: &'a str) -> Self {
         Self {
             cursor: Cursor::new(source),
+            trivia: false,
+            finished: false,
         }
     }
 
-    pub fn scan(&mut self) -> Result<Token<'a>, ScanError<'a>> {
+    /// Makes the scanner return whitespace as [`TokenKind::Whitespace`] tokens, so that the spans
+    /// of the tokens and errors it returns cover the whole source and the source can be
+    /// reconstructed exactly from them.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    /// Sets the file the spans of the tokens and errors are in, by default it is the first file
+    /// of a source map.
+    pub fn with_file(mut self, file: FileId) -> Self {
+        self.cursor.set_file(file);
+        self
+    }
+
+    pub fn scan_token(&mut self) -> Result<Token<'a>, ScanError> {
+        self.cursor.reset_start_index();
         while self.cursor.lookahead(0).is_ascii_whitespace() {
             self.cursor.advance();
         }
 
+        if self.trivia && self.cursor.current_index() > self.cursor.start_index() {
+            return Ok(self.token(TokenKind::Whitespace));
+        }
+
         self.cursor.reset_start_index();
 
         let kind = match (self.cursor.advance(), self.cursor.lookahead(0)) {
@@ -42,7 +80,12 @@ impl<'a> Scanner<'a> {
             (']', _) => TokenKind::RBrack,
             (';', _) => TokenKind::Semicolon,
             (',', _) => TokenKind::Comma,
+            (':', _) => TokenKind::Colon,
             ('.', _) => TokenKind::Period,
+            ('+', '=') => {
+                self.cursor.advance();
+                TokenKind::PlusEqual
+            }
             ('-', '=') => {
                 self.cursor.advance();
                 TokenKind::MinusEqual
@@ -88,15 +131,24 @@ impl<'a> Scanner<'a> {
             ('<', _) => TokenKind::Less,
             ('>', _) => TokenKind::Greater,
 
-            (Cursor::EOF_CHAR, _) => TokenKind::Eof,
+            (EOF_CHAR, _) => TokenKind::Eof,
 
             (c, _) => {
                 let message = format!("unexpected character '{c}'");
-                return Err(ScanError::new(message, self.cursor.reset_span()));
+                return Err(self.error(ErrorCode::UNEXPECTED_CHARACTER, message));
             }
         };
 
-        Ok(Token::new(self.cursor.reset_span(), kind))
+        Ok(self.token(kind))
+    }
+
+    fn token(&mut self, kind: TokenKind) -> Token<'a> {
+        Token::new(self.cursor.input(), self.cursor.reset_location(), kind)
+    }
+
+    fn error(&mut self, code: ErrorCode, message: String) -> ScanError {
+        let location = self.cursor.reset_location();
+        ScanError::new(code, message, location)
     }
 
     fn identifier(&mut self) -> TokenKind {
@@ -104,11 +156,10 @@ impl<'a> Scanner<'a> {
             self.cursor.advance();
         }
 
-        TokenKind::keyword_kind_from_str(self.cursor.span().slice())
-            .unwrap_or(TokenKind::Identifier)
+        TokenKind::keyword_kind_from_str(self.cursor.slice()).unwrap_or(TokenKind::Identifier)
     }
 
-    fn string(&mut self) -> Result<(), ScanError<'a>> {
+    fn string(&mut self) -> Result<(), ScanError> {
         // Consume everything until we find a closing quote or we reach the end of the source.
         while !self.cursor.is_at_end() && self.cursor.lookahead(0) != '"' {
             self.cursor.advance();
@@ -116,7 +167,7 @@ impl<'a> Scanner<'a> {
 
         if self.cursor.lookahead(0) != '"' {
             let message = "expected closing quotes".to_owned();
-            Err(ScanError::new(message, self.cursor.reset_span()))
+            Err(self.error(ErrorCode::UNTERMINATED_STRING, message))
         } else {
             self.cursor.advance();
             Ok(())
@@ -143,6 +194,31 @@ impl<'a> Scanner<'a> {
     }
 }
 
+/// Returns the tokens up to and including the end of file token, and the errors where invalid
+/// tokens are found.
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Result<Token<'a>, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let token = self.scan_token();
+        self.finished = matches!(&token, Ok(token) if token.is_eof());
+
+        Some(token)
+    }
+}
+
+impl FusedIterator for Scanner<'_> {}
+
+/// Whether `s` would be scanned as a single identifier, rather than a keyword or other tokens.
+pub(super) fn is_identifier(s: &str) -> bool {
+    s.starts_with(is_identifier_start)
+        && s.chars().all(is_identifier_continue)
+        && TokenKind::keyword_kind_from_str(s).is_none()
+}
+
 fn is_identifier_start(c: char) -> bool {
     c.is_ascii_alphabetic() || c == '_'
 }
@@ -151,34 +227,87 @@ fn is_identifier_continue(c: char) -> bool {
     c.is_ascii_alphanumeric() || c == '_'
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ScanError<'a> {
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct ScanError {
+    pub code: ErrorCode,
     pub message: String,
-    pub span: Span<'a>,
+    pub location: Location,
 }
 
-impl<'a> ScanError<'a> {
-    pub fn new(message: String, span: Span<'a>) -> Self {
-        Self { message, span }
+impl ScanError {
+    pub fn new(code: ErrorCode, message: String, location: Location) -> Self {
+        Self {
+            code,
+            message,
+            location,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Scanner, Span, Token, TokenKind};
+    use super::{FileId, Scanner, Token, TokenKind};
+    use crate::compiler::parser::token::{Location, Span};
 
     macro_rules! t {
         ($src:expr, $start:expr, $end:expr, $kind:expr) => {
-            Token::new(Span::new($src, $start, $end), $kind)
+            Token::new(
+                $src,
+                Location::new($src, Span::new(FileId::default(), $start, $end)),
+                $kind,
+            )
         };
     }
 
+    #[test]
+    fn scan_trivia() {
+        use TokenKind::*;
+        let src = "let  x =\n\t@1 ";
+        let mut scanner = Scanner::new(src).with_trivia();
+
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 0, 3, Let)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 3, 5, Whitespace)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 5, 6, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 6, 7, Whitespace)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 7, 8, Equal)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 8, 10, Whitespace)));
+        let error = scanner.scan_token().unwrap_err();
+        assert_eq!(error.location.span(), Span::new(FileId::default(), 10, 11));
+        assert_eq!((error.location.line, error.location.column), (2, 2));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 11, 12, Number)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 12, 13, Whitespace)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 13, 13, Eof)));
+    }
+
     #[test]
     fn scan_eof() {
         use TokenKind::*;
 
         let src = "";
-        assert_eq!(Scanner::new(src).scan(), Ok(t!(src, 0, 0, Eof)))
+        assert_eq!(Scanner::new(src).scan_token(), Ok(t!(src, 0, 0, Eof)))
+    }
+
+    #[test]
+    fn iterate() {
+        use TokenKind::*;
+        let src = "a @ 1";
+        let mut scanner = Scanner::new(src);
+
+        assert_eq!(scanner.next(), Some(Ok(t!(src, 0, 1, Identifier))));
+        assert!(scanner.next().unwrap().is_err());
+        assert_eq!(scanner.next(), Some(Ok(t!(src, 4, 5, Number))));
+        assert_eq!(scanner.next(), Some(Ok(t!(src, 5, 5, Eof))));
+        assert_eq!(scanner.next(), None);
+        assert_eq!(scanner.next(), None);
+
+        let kinds: Vec<_> = Scanner::new("f(x);")
+            .filter_map(|token| Some(token.ok()?.kind()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [Identifier, LParen, Identifier, RParen, Semicolon, Eof]
+        );
     }
 
     #[test]
@@ -187,16 +316,16 @@ mod tests {
         let src = "256.log2()";
         let mut scanner = Scanner::new(src);
 
-        assert_eq!(scanner.scan(), Ok(t!(src, 0, 3, Number)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 3, 4, Period)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 4, 8, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 8, 9, LParen)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 9, 10, RParen)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 0, 3, Number)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 3, 4, Period)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 4, 8, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 8, 9, LParen)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 9, 10, RParen)));
 
         let src = "12.34";
         let mut scanner = Scanner::new(src);
 
-        assert_eq!(scanner.scan(), Ok(t!(src, 0, 5, Number)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 0, 5, Number)));
     }
 
     #[test]
@@ -205,12 +334,12 @@ mod tests {
         let src = "let x = 10;";
         let mut scanner = Scanner::new(src);
 
-        assert_eq!(scanner.scan(), Ok(t!(src, 0, 3, Let)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 4, 5, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 6, 7, Equal)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 8, 10, Number)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 10, 11, Semicolon)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 11, 11, Eof)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 0, 3, Let)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 4, 5, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 6, 7, Equal)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 8, 10, Number)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 10, 11, Semicolon)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 11, 11, Eof)));
     }
 
     #[test]
@@ -219,19 +348,19 @@ mod tests {
         let src = "func add(x, y) { return x + y; }";
         let mut scanner = Scanner::new(src);
 
-        assert_eq!(scanner.scan(), Ok(t!(src, 0, 4, Func)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 5, 8, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 8, 9, LParen)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 9, 10, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 10, 11, Comma)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 12, 13, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 13, 14, RParen)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 15, 16, LBrace)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 17, 23, Return)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 24, 25, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 26, 27, Plus)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 28, 29, Identifier)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 29, 30, Semicolon)));
-        assert_eq!(scanner.scan(), Ok(t!(src, 31, 32, RBrace)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 0, 4, Func)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 5, 8, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 8, 9, LParen)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 9, 10, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 10, 11, Comma)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 12, 13, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 13, 14, RParen)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 15, 16, LBrace)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 17, 23, Return)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 24, 25, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 26, 27, Plus)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 28, 29, Identifier)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 29, 30, Semicolon)));
+        assert_eq!(scanner.scan_token(), Ok(t!(src, 31, 32, RBrace)));
     }
 }
@@ -1,22 +1,34 @@
 use std::{fmt::Display, ops::Add};
 
+use crate::source_map::FileId;
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Token<'a> {
-    span: Span<'a>,
+    /// Reference to the entire source string.
+    source: &'a str,
+    location: Location,
     kind: TokenKind,
 }
 
 impl<'a> Token<'a> {
-    pub fn new(span: Span<'a>, kind: TokenKind) -> Self {
-        Self { span, kind }
+    pub fn new(source: &'a str, location: Location, kind: TokenKind) -> Self {
+        Self {
+            source,
+            location,
+            kind,
+        }
     }
 
-    pub fn span(self) -> Span<'a> {
-        self.span
+    pub fn span(self) -> Span {
+        self.location.span()
     }
 
     pub fn slice(self) -> &'a str {
-        self.span().slice()
+        &self.source[self.location.start..self.location.end]
+    }
+
+    pub fn location(self) -> Location {
+        self.location
     }
 
     pub fn kind(&self) -> TokenKind {
@@ -28,19 +40,25 @@ impl<'a> Token<'a> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct Span<'a> {
-    /// Reference to the entire source string.
-    source: &'a str,
+/// Range of bytes of a file, which is looked up in a [`SourceMap`](crate::source_map::SourceMap)
+/// by its id.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    file: FileId,
     /// Start index of the token.
     start: usize,
     /// End index of the token (exclusive).
     end: usize,
 }
 
-impl<'a> Span<'a> {
-    pub fn new(source: &'a str, start: usize, end: usize) -> Self {
-        Self { source, start, end }
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    pub fn file(&self) -> FileId {
+        self.file
     }
 
     pub fn start(&self) -> usize {
@@ -50,26 +68,48 @@ impl<'a> Span<'a> {
     pub fn end(&self) -> usize {
         self.end
     }
+}
 
-    pub fn line_number(&self) -> usize {
-        self.source[..self.start]
-            .chars()
-            .filter(|c| *c == '\n')
-            .count()
-            .add(1)
+/// Span along with the line and column it starts at, so it can be shown without the source. It
+/// is stored in the AST and in errors.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Finds the line and column of the span in the source of its file, by scanning the source
+    /// up to the span. Use a [`LineIndex`](crate::source_map::LineIndex) to find the locations
+    /// of many spans.
+    pub fn new(source: &str, span: Span) -> Self {
+        let before = &source[..span.start];
+        Self {
+            file: span.file,
+            start: span.start,
+            end: span.end,
+            line: before.chars().filter(|c| *c == '\n').count().add(1),
+            column: before
+                .chars()
+                .rev()
+                .take_while(|c| *c != '\n')
+                .count()
+                .add(1),
+        }
     }
 
-    pub fn column_number(&self) -> usize {
-        self.source[..self.start]
-            .chars()
-            .rev()
-            .take_while(|c| *c != '\n')
-            .count()
-            .add(1)
+    pub fn span(&self) -> Span {
+        Span::new(self.file, self.start, self.end)
     }
+}
 
-    pub fn slice(&self) -> &'a str {
-        &self.source[self.start..self.end]
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
     }
 }
 
@@ -83,6 +123,7 @@ pub enum TokenKind {
     RBrace,
     Period,
     Semicolon,
+    Colon,
     Comma,
     Identifier,
     Let,
@@ -93,10 +134,13 @@ pub enum TokenKind {
     Or,
     And,
     For,
+    In,
     While,
     If,
     Else,
     Return,
+    Test,
+    Import,
     This,
     True,
     False,
@@ -121,10 +165,17 @@ pub enum TokenKind {
     LessEqual,
     GreaterEqual,
     Greater,
+    /// Run of whitespace, only produced by scanners which keep trivia.
+    Whitespace,
     Eof,
 }
 
 impl TokenKind {
+    pub const KEYWORDS: &'static [&'static str] = &[
+        "let", "mut", "func", "class", "not", "or", "and", "for", "in", "while", "if", "else",
+        "return", "test", "import", "this", "true", "false", "nil",
+    ];
+
     pub fn keyword_kind_from_str(s: &str) -> Option<TokenKind> {
         let kind = match s {
             "let" => TokenKind::Let,
@@ -135,10 +186,13 @@ impl TokenKind {
             "or" => TokenKind::Or,
             "and" => TokenKind::And,
             "for" => TokenKind::For,
+            "in" => TokenKind::In,
             "while" => TokenKind::While,
             "if" => TokenKind::If,
             "else" => TokenKind::Else,
             "return" => TokenKind::Return,
+            "test" => TokenKind::Test,
+            "import" => TokenKind::Import,
             "this" => TokenKind::This,
             "true" => TokenKind::True,
             "false" => TokenKind::False,
@@ -152,9 +206,15 @@ impl TokenKind {
     pub fn is_variable_length(self) -> bool {
         matches!(
             self,
-            TokenKind::Identifier | TokenKind::String | TokenKind::Number
+            TokenKind::Identifier | TokenKind::String | TokenKind::Number | TokenKind::Whitespace
         )
     }
+
+    /// Whether the token has no meaning to the parser and only matters for reproducing the
+    /// source.
+    pub fn is_trivia(self) -> bool {
+        self == TokenKind::Whitespace
+    }
 }
 
 impl Display for TokenKind {
@@ -168,6 +228,7 @@ impl Display for TokenKind {
             TokenKind::RBrace => "}",
             TokenKind::Period => ".",
             TokenKind::Semicolon => ";",
+            TokenKind::Colon => ":",
             TokenKind::Comma => ",",
             TokenKind::Identifier => "<idenifier>",
             TokenKind::Let => "let",
@@ -178,10 +239,13 @@ impl Display for TokenKind {
             TokenKind::Or => "or",
             TokenKind::And => "and",
             TokenKind::For => "for",
+            TokenKind::In => "in",
             TokenKind::While => "while",
             TokenKind::If => "if",
             TokenKind::Else => "else",
             TokenKind::Return => "return",
+            TokenKind::Test => "test",
+            TokenKind::Import => "import",
             TokenKind::This => "this",
             TokenKind::True => "true",
             TokenKind::False => "false",
@@ -206,6 +270,7 @@ impl Display for TokenKind {
             TokenKind::LessEqual => "<=",
             TokenKind::GreaterEqual => ">=",
             TokenKind::Greater => ">",
+            TokenKind::Whitespace => "<whitespace>",
             TokenKind::Eof => "<eof>",
         };
 
@@ -215,25 +280,22 @@ impl Display for TokenKind {
 
 #[cfg(test)]
 mod tests {
-    use super::Span;
+    use super::{Location, Span};
+    use crate::source_map::FileId;
 
     #[test]
-    fn span() {
+    fn location() {
         let src = "let x = 10;\nx *= 2";
+        let location = |start, end| {
+            let location = Location::new(src, Span::new(FileId::default(), start, end));
+            (location.line, location.column)
+        };
 
-        // Span for 'let'.
-        let span = Span::new(src, 0, 2);
-        assert_eq!(span.line_number(), 1);
-        assert_eq!(span.column_number(), 1);
-
-        // Span for 'x' on line 1.
-        let span = Span::new(src, 4, 4);
-        assert_eq!(span.line_number(), 1);
-        assert_eq!(span.column_number(), 5);
-
-        // Span for 'x' on line 2.
-        let span = Span::new(src, 12, 12);
-        assert_eq!(span.line_number(), 2);
-        assert_eq!(span.column_number(), 1);
+        // Location of 'let'.
+        assert_eq!(location(0, 2), (1, 1));
+        // Location of 'x' on line 1.
+        assert_eq!(location(4, 4), (1, 5));
+        // Location of 'x' on line 2.
+        assert_eq!(location(12, 12), (2, 1));
     }
 }
@@ -0,0 +1,391 @@
+//! Traversal of the syntax tree.
+//!
+//! A pass implements [`Visitor`] or [`VisitorMut`] and overrides the methods for the nodes it is
+//! interested in, the default methods call the matching `walk_*` function which visits the
+//! children of the node in the order they appear in the source. An overridden method can call
+//! the `walk_*` function itself to carry on into the children, or leave it out to skip them.
+
+use std::sync::Arc;
+
+use crate::compiler::parser::{
+    ast::{Decl, DeclKind, Expr, ExprKind, Function, Identifier, Script, Stmt, StmtKind},
+    token::Location,
+};
+
+pub trait Visitor {
+    fn visit_script(&mut self, script: &Script) {
+        walk_script(self, script);
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        walk_decl(self, decl);
+    }
+
+    /// Visits the function of a function or test declaration.
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    /// Visits every identifier, whether it declares a variable, uses one or names a member.
+    fn visit_identifier(&mut self, _name: &Identifier) {}
+}
+
+pub fn walk_script<V: Visitor + ?Sized>(visitor: &mut V, script: &Script) {
+    for decl in &script.decls {
+        visitor.visit_decl(decl);
+    }
+}
+
+pub fn walk_decl<V: Visitor + ?Sized>(visitor: &mut V, decl: &Decl) {
+    match &decl.kind {
+        DeclKind::Var(name, init) => {
+            visitor.visit_identifier(name);
+            if let Some(init) = init {
+                visitor.visit_expr(init);
+            }
+        }
+        DeclKind::Import(name) => visitor.visit_identifier(name),
+        DeclKind::Func(function) | DeclKind::Test(function) => visitor.visit_function(function),
+        DeclKind::Stmt(stmt) => visitor.visit_stmt(stmt),
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &Function) {
+    visitor.visit_identifier(&function.name);
+    for param in &function.params {
+        visitor.visit_identifier(param);
+    }
+    visitor.visit_stmt(&function.body);
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::If(predicate, consequent, alternative) => {
+            visitor.visit_expr(predicate);
+            visitor.visit_stmt(consequent);
+            if let Some(alternative) = alternative {
+                visitor.visit_stmt(alternative);
+            }
+        }
+        StmtKind::While(predicate, body) => {
+            visitor.visit_expr(predicate);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::For(name, iterable, body) => {
+            visitor.visit_identifier(name);
+            visitor.visit_expr(iterable);
+            visitor.visit_stmt(body);
+        }
+        StmtKind::Expr(expr) => visitor.visit_expr(expr),
+        StmtKind::Block(decls) => {
+            for decl in decls {
+                visitor.visit_decl(decl);
+            }
+        }
+        StmtKind::Return(value, _) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::Literal(_) => {}
+        ExprKind::Identifier(name) => visitor.visit_identifier(name),
+        ExprKind::Assignment(target, value)
+        | ExprKind::CompoundAssignment(_, target, value)
+        | ExprKind::Binary(_, target, value) => {
+            visitor.visit_expr(target);
+            visitor.visit_expr(value);
+        }
+        ExprKind::Unary(_, operand) => visitor.visit_expr(operand),
+        ExprKind::Call(callee, args, _) => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Index(object, index, _) => {
+            visitor.visit_expr(object);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Member(object, name) => {
+            visitor.visit_expr(object);
+            visitor.visit_identifier(name);
+        }
+        ExprKind::List(items) => {
+            for item in items {
+                visitor.visit_expr(item);
+            }
+        }
+        ExprKind::Map(entries) => {
+            for (key, value) in entries {
+                visitor.visit_expr(key);
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+/// Visitor which can change the nodes it visits.
+///
+/// Functions are reference counted, the walk functions panic if a function of the tree is shared
+/// since it can't be changed then. Functions are only shared once the script has run.
+pub trait VisitorMut {
+    fn visit_script_mut(&mut self, script: &mut Script) {
+        walk_script_mut(self, script);
+    }
+
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        walk_decl_mut(self, decl);
+    }
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        walk_function_mut(self, function);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_identifier_mut(&mut self, _name: &mut Identifier) {}
+
+    /// Visits every location of the tree other than those of identifiers, which are visited
+    /// along with the identifier. That is the location of each node followed by the locations of
+    /// its operator, parenthesis, bracket or keyword.
+    fn visit_location_mut(&mut self, _location: &mut Location) {}
+}
+
+pub fn walk_script_mut<V: VisitorMut + ?Sized>(visitor: &mut V, script: &mut Script) {
+    for decl in &mut script.decls {
+        visitor.visit_decl_mut(decl);
+    }
+}
+
+/// # Panics
+///
+/// Panics if the declaration is of a function which is shared.
+pub fn walk_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, decl: &mut Decl) {
+    visitor.visit_location_mut(&mut decl.location);
+    match &mut decl.kind {
+        DeclKind::Var(name, init) => {
+            visitor.visit_identifier_mut(name);
+            if let Some(init) = init {
+                visitor.visit_expr_mut(init);
+            }
+        }
+        DeclKind::Import(name) => visitor.visit_identifier_mut(name),
+        DeclKind::Func(function) | DeclKind::Test(function) => {
+            visitor.visit_function_mut(function_mut(function));
+        }
+        DeclKind::Stmt(stmt) => visitor.visit_stmt_mut(stmt),
+    }
+}
+
+/// Returns the function of a declaration to be changed.
+///
+/// # Panics
+///
+/// Panics if the function is shared.
+pub fn function_mut(function: &mut Arc<Function>) -> &mut Function {
+    Arc::get_mut(function).expect("function should not be shared while visiting")
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut Function) {
+    visitor.visit_identifier_mut(&mut function.name);
+    for param in &mut function.params {
+        visitor.visit_identifier_mut(param);
+    }
+    visitor.visit_stmt_mut(&mut function.body);
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    visitor.visit_location_mut(&mut stmt.location);
+    match &mut stmt.kind {
+        StmtKind::If(predicate, consequent, alternative) => {
+            visitor.visit_expr_mut(predicate);
+            visitor.visit_stmt_mut(consequent);
+            if let Some(alternative) = alternative {
+                visitor.visit_stmt_mut(alternative);
+            }
+        }
+        StmtKind::While(predicate, body) => {
+            visitor.visit_expr_mut(predicate);
+            visitor.visit_stmt_mut(body);
+        }
+        StmtKind::For(name, iterable, body) => {
+            visitor.visit_identifier_mut(name);
+            visitor.visit_expr_mut(iterable);
+            visitor.visit_stmt_mut(body);
+        }
+        StmtKind::Expr(expr) => visitor.visit_expr_mut(expr),
+        StmtKind::Block(decls) => {
+            for decl in decls {
+                visitor.visit_decl_mut(decl);
+            }
+        }
+        StmtKind::Return(value, location) => {
+            visitor.visit_location_mut(location);
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    visitor.visit_location_mut(&mut expr.location);
+    match &mut expr.kind {
+        ExprKind::Literal(_) => {}
+        ExprKind::Identifier(name) => visitor.visit_identifier_mut(name),
+        ExprKind::Assignment(target, value) => {
+            visitor.visit_expr_mut(target);
+            visitor.visit_expr_mut(value);
+        }
+        ExprKind::CompoundAssignment(operator, left, right)
+        | ExprKind::Binary(operator, left, right) => {
+            visitor.visit_location_mut(&mut operator.location);
+            visitor.visit_expr_mut(left);
+            visitor.visit_expr_mut(right);
+        }
+        ExprKind::Unary(operator, operand) => {
+            visitor.visit_location_mut(&mut operator.location);
+            visitor.visit_expr_mut(operand);
+        }
+        ExprKind::Call(callee, args, location) => {
+            visitor.visit_location_mut(location);
+            visitor.visit_expr_mut(callee);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprKind::Index(object, index, location) => {
+            visitor.visit_location_mut(location);
+            visitor.visit_expr_mut(object);
+            visitor.visit_expr_mut(index);
+        }
+        ExprKind::Member(object, name) => {
+            visitor.visit_expr_mut(object);
+            visitor.visit_identifier_mut(name);
+        }
+        ExprKind::List(items) => {
+            for item in items {
+                visitor.visit_expr_mut(item);
+            }
+        }
+        ExprKind::Map(entries) => {
+            for (key, value) in entries {
+                visitor.visit_expr_mut(key);
+                visitor.visit_expr_mut(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_expr, Visitor, VisitorMut};
+    use crate::compiler::parser::{
+        ast::{Expr, ExprKind, Identifier},
+        token::Location,
+        Parser,
+    };
+
+    /// Records the identifiers visited and counts the calls, without going into their arguments.
+    #[derive(Default)]
+    struct Names {
+        names: Vec<String>,
+        calls: usize,
+    }
+
+    impl Visitor for Names {
+        fn visit_expr(&mut self, expr: &Expr) {
+            match &expr.kind {
+                ExprKind::Call(callee, _, _) => {
+                    self.calls += 1;
+                    self.visit_expr(callee);
+                }
+                _ => walk_expr(self, expr),
+            }
+        }
+
+        fn visit_identifier(&mut self, name: &Identifier) {
+            self.names.push(name.as_ref().to_owned());
+        }
+    }
+
+    #[test]
+    fn order() {
+        let source = "let a = [b, {c: d.e}];
+func f(g) { for h in g { return -h; } }
+test \"t\" { while i(j) { k[l] = m; } }";
+        let script = Parser::new(source).parse().unwrap();
+        let mut names = Names::default();
+        names.visit_script(&script);
+        assert_eq!(
+            names.names,
+            ["a", "b", "c", "d", "e", "f", "g", "h", "g", "h", "t", "i", "k", "l", "m"]
+        );
+        assert_eq!(names.calls, 1);
+    }
+
+    /// Moves every location to the next line.
+    struct NextLine;
+
+    impl VisitorMut for NextLine {
+        fn visit_identifier_mut(&mut self, name: &mut Identifier) {
+            let mut location = name.location();
+            self.visit_location_mut(&mut location);
+            name.set_location(location);
+        }
+
+        fn visit_location_mut(&mut self, location: &mut Location) {
+            location.line += 1;
+        }
+    }
+
+    #[test]
+    fn every_location() {
+        let source = "func f(a) { return not a[0] + f(a.b); }";
+        let mut script = Parser::new(source).parse().unwrap();
+        NextLine.visit_script_mut(&mut script);
+
+        /// Checks that every location it can see is on the second line.
+        struct Lines;
+
+        impl Visitor for Lines {
+            fn visit_expr(&mut self, expr: &Expr) {
+                assert_eq!(expr.location.line, 2);
+                match &expr.kind {
+                    ExprKind::Binary(operator, _, _) | ExprKind::Unary(operator, _) => {
+                        assert_eq!(operator.location.line, 2)
+                    }
+                    ExprKind::Call(_, _, location) | ExprKind::Index(_, _, location) => {
+                        assert_eq!(location.line, 2)
+                    }
+                    _ => {}
+                }
+                walk_expr(self, expr);
+            }
+
+            fn visit_identifier(&mut self, name: &Identifier) {
+                assert_eq!(name.location().line, 2);
+            }
+        }
+        Lines.visit_script(&script);
+    }
+}
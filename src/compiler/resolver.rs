@@ -0,0 +1,505 @@
+//! Assigns every variable a slot so that the interpreter doesn't have to look locals up by name.
+//!
+//! Variables declared at the top level of a script are globals and are still looked up by name,
+//! every other variable is a local which is stored in a slot of the function (or top level
+//! script) it was declared in. Slots of variables which have gone out of scope are reused by later
+//! blocks.
+//!
+//! Functions which use locals of enclosing functions capture them by reference when they are
+//! declared: captured locals are stored in cells which are shared between the declaring scope and
+//! every function that captured them, so assignments on either side are seen by the other. Each
+//! execution of a declaration creates a new cell, so functions declared in a loop body each see
+//! the variables of their own iteration.
+//!
+//! Since the resolver sees every declaration and use of a variable it also collects the
+//! [warnings](super::warnings) of a script, and records which declaration each identifier refers
+//! to for tools such as the language server.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    parser::{
+        ast::{
+            Capture, Decl, DeclKind, Expr, ExprKind, Function, Identifier, Literal, Script, Slot,
+            Stmt, StmtKind,
+        },
+        token::Location,
+        visit::{function_mut, walk_decl_mut, walk_expr_mut, walk_stmt_mut, VisitorMut},
+    },
+    warnings::{Warning, WarningKind},
+};
+use crate::diagnostic::Label;
+
+/// Resolves every identifier in `script`.
+///
+/// # Panics
+///
+/// Panics if any function in the script is shared, which can only happen if the script was
+/// already resolved.
+pub fn resolve(script: &mut Script) {
+    // Whether a local has to be stored in a cell depends on functions declared after it, so the
+    // script is resolved twice, the first pass only finds out which locals are captured.
+    let mut captured = HashSet::new();
+    let mut globals = HashMap::new();
+    for pass in 0..2 {
+        let mut resolver = Resolver {
+            functions: vec![FunctionScope::default()],
+            captured,
+            globals,
+            next_id: 0,
+            warnings: vec![],
+            references: vec![],
+            // Both passes see the same problems.
+            report: pass == 1,
+        };
+
+        resolver.decls(&mut script.decls);
+
+        script.slot_count = resolver.functions.pop().unwrap().slot_count;
+        script.warnings = resolver.warnings;
+        script.references = resolver.references;
+        captured = resolver.captured;
+        globals = resolver.globals;
+    }
+}
+
+struct Resolver {
+    /// Functions being resolved, the first is the top level of the script.
+    functions: Vec<FunctionScope>,
+    /// Ids of the locals captured by functions.
+    captured: HashSet<usize>,
+    /// Id of the next local to be declared, locals are numbered in the order they are declared
+    /// which is the same in both passes.
+    next_id: usize,
+    /// Location of the first declaration of each global, kept between passes so that globals
+    /// can be used before they are declared.
+    globals: HashMap<String, Location>,
+    warnings: Vec<Warning>,
+    references: Vec<Reference>,
+    /// Whether to collect warnings during this pass.
+    report: bool,
+}
+
+/// Identifier along with the declaration of the variable it refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Reference {
+    pub usage: Location,
+    /// Missing for globals which aren't declared by the script, such as builtins. Declarations
+    /// refer to themselves.
+    pub declaration: Option<Location>,
+}
+
+struct Local {
+    name: String,
+    slot: usize,
+    id: usize,
+    location: Location,
+    is_function: bool,
+    used: bool,
+}
+
+#[derive(Default)]
+struct FunctionScope {
+    /// Names and slots of the variables declared in each block, innermost last. Variables
+    /// declared outside of any block at the top level are globals so they aren't tracked.
+    blocks: Vec<Vec<Local>>,
+    /// Slot the next variable will be stored in.
+    next_slot: usize,
+    slot_count: usize,
+    captures: Vec<Capture>,
+}
+
+impl FunctionScope {
+    fn find(&mut self, name: &str) -> Option<&mut Local> {
+        self.blocks
+            .iter_mut()
+            .rev()
+            .flat_map(|block| block.iter_mut().rev())
+            .find(|local| local.name == name)
+    }
+
+    fn capture(&mut self, capture: Capture) -> usize {
+        match self.captures.iter().position(|&c| c == capture) {
+            Some(index) => index,
+            None => {
+                self.captures.push(capture);
+                self.captures.len() - 1
+            }
+        }
+    }
+}
+
+impl Resolver {
+    fn function(&mut self) -> &mut FunctionScope {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn warn(&mut self, kind: WarningKind, message: String, location: Option<Location>) {
+        if self.report {
+            self.warnings.push(Warning::new(kind, message, location));
+        }
+    }
+
+    fn reference(&mut self, usage: Location, declaration: Option<Location>) {
+        let reference = Reference { usage, declaration };
+        // Lowering can repeat an identifier, `a += 1` uses `a` twice as `a = a + 1`.
+        if self.report && self.references.last() != Some(&reference) {
+            self.references.push(reference);
+        }
+    }
+
+    fn declare(&mut self, name: &mut Identifier, is_function: bool) {
+        let id = self.next_id;
+        let is_captured = self.captured.contains(&id);
+        self.reference(name.location(), Some(name.location()));
+        let function = self.function();
+        if function.blocks.is_empty() {
+            name.set_slot(Slot::Global);
+            self.globals
+                .entry(name.as_ref().to_owned())
+                .or_insert(name.location());
+            return;
+        }
+
+        if let Some(shadowed) = function.find(name.as_ref()).map(|local| local.location) {
+            if !name.as_ref().starts_with('_') && self.report {
+                let message = format!(
+                    "'{}' shadows a variable in an enclosing scope",
+                    name.as_ref()
+                );
+                let warning =
+                    Warning::new(WarningKind::ShadowedBinding, message, Some(name.location()))
+                        .with_label(Label::new(shadowed, "shadowed variable declared here"));
+                self.warnings.push(warning);
+            }
+        }
+
+        let function = self.function();
+        let slot = function.next_slot;
+        function.blocks.last_mut().unwrap().push(Local {
+            name: name.as_ref().to_owned(),
+            slot,
+            id,
+            location: name.location(),
+            is_function,
+            used: false,
+        });
+        function.next_slot += 1;
+        function.slot_count = function.slot_count.max(function.next_slot);
+        self.next_id += 1;
+
+        name.set_slot(if is_captured {
+            Slot::Cell(slot)
+        } else {
+            Slot::Local(slot)
+        });
+    }
+
+    fn resolve(&mut self, name: &mut Identifier) {
+        let slot = self.lookup(name.as_ref(), self.functions.len() - 1);
+        name.set_slot(slot);
+
+        let declaration = self
+            .functions
+            .iter_mut()
+            .rev()
+            .find_map(|function| function.find(name.as_ref()).map(|local| local.location))
+            .or_else(|| self.globals.get(name.as_ref()).copied());
+        self.reference(name.location(), declaration);
+    }
+
+    /// Finds the variable called `name` as seen from the function at `depth`, capturing it in
+    /// every function between its declaration and `depth`.
+    fn lookup(&mut self, name: &str, depth: usize) -> Slot {
+        if let Some(local) = self.functions[depth].find(name) {
+            local.used = true;
+            let (slot, id) = (local.slot, local.id);
+            if depth < self.functions.len() - 1 {
+                // Found in an enclosing function.
+                self.captured.insert(id);
+            }
+
+            return if self.captured.contains(&id) {
+                Slot::Cell(slot)
+            } else {
+                Slot::Local(slot)
+            };
+        }
+
+        if depth == 0 {
+            return Slot::Global;
+        }
+
+        let capture = match self.lookup(name, depth - 1) {
+            Slot::Global => return Slot::Global,
+            Slot::Local(slot) | Slot::Cell(slot) => Capture::Local(slot),
+            Slot::Capture(index) => Capture::Capture(index),
+        };
+
+        Slot::Capture(self.functions[depth].capture(capture))
+    }
+
+    fn block(&mut self, decls: &mut [Decl]) {
+        self.scoped(|resolver| resolver.decls(decls));
+    }
+
+    fn decls(&mut self, decls: &mut [Decl]) {
+        let mut returned = false;
+        for decl in decls {
+            if returned {
+                let message = "unreachable code after return".to_owned();
+                self.warn(WarningKind::UnreachableCode, message, Some(decl.location));
+                // Only the first unreachable declaration is reported.
+                returned = false;
+            }
+            if let DeclKind::Stmt(stmt) = &mut decl.kind {
+                returned = matches!(&stmt.kind, StmtKind::Return(..));
+            }
+
+            self.visit_decl_mut(decl);
+        }
+    }
+
+    /// Reports the locals of a block which has ended if they were never used.
+    fn unused(&mut self, block: Vec<Local>) {
+        for local in block {
+            if local.used || local.name.starts_with('_') {
+                continue;
+            }
+
+            let (kind, description) = if local.is_function {
+                (WarningKind::UnusedFunction, "function")
+            } else {
+                (WarningKind::UnusedVariable, "variable")
+            };
+            let message = format!("unused {description} '{}'", local.name);
+            self.warn(kind, message, Some(local.location));
+        }
+    }
+
+    /// Runs `f` in a new block, the slots of variables declared in it are freed afterwards.
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        let function = self.function();
+        function.blocks.push(vec![]);
+        let start = function.next_slot;
+
+        f(self);
+
+        let function = self.function();
+        let block = function.blocks.pop().unwrap();
+        function.next_slot = start;
+        self.unused(block);
+    }
+}
+
+impl VisitorMut for Resolver {
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        match &mut decl.kind {
+            DeclKind::Var(name, init_expr) => {
+                // The variable isn't in scope in its own initializer.
+                if let Some(init_expr) = init_expr {
+                    self.visit_expr_mut(init_expr);
+                }
+                self.declare(name, false);
+            }
+            DeclKind::Import(name) => self.declare(name, false),
+            DeclKind::Func(function) => {
+                let function = function_mut(function);
+                // Functions are in scope in their own body to allow recursion.
+                self.declare(&mut function.name, true);
+                self.visit_function_mut(function);
+            }
+            DeclKind::Test(_) | DeclKind::Stmt(_) => walk_decl_mut(self, decl),
+        }
+    }
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        self.functions.push(FunctionScope {
+            blocks: vec![vec![]],
+            ..FunctionScope::default()
+        });
+
+        for param in &mut function.params {
+            self.declare(param, false);
+        }
+        self.visit_stmt_mut(&mut function.body);
+
+        let mut scope = self.functions.pop().unwrap();
+        for block in std::mem::take(&mut scope.blocks) {
+            self.unused(block);
+        }
+        function.slot_count = scope.slot_count;
+        function.captures = scope.captures;
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::If(predicate, _, _) => {
+                if let ExprKind::Literal(literal) = &predicate.kind {
+                    let message = format!("condition is always {}", truthiness(literal));
+                    let location = Some(predicate.location);
+                    self.warn(WarningKind::ConstantCondition, message, location);
+                }
+                walk_stmt_mut(self, stmt);
+            }
+            StmtKind::While(predicate, _) => {
+                match &predicate.kind {
+                    // Loops which only end by returning are written `while true`.
+                    ExprKind::Literal(Literal::Bool(true)) => {}
+                    ExprKind::Literal(literal) => {
+                        let message = format!("condition is always {}", truthiness(literal));
+                        let location = Some(predicate.location);
+                        self.warn(WarningKind::ConstantCondition, message, location);
+                    }
+                    _ => {}
+                }
+                walk_stmt_mut(self, stmt);
+            }
+            StmtKind::For(name, iterable, body) => {
+                self.visit_expr_mut(iterable);
+                self.scoped(|resolver| {
+                    resolver.declare(name, false);
+                    resolver.visit_stmt_mut(body);
+                });
+            }
+            StmtKind::Block(decls) => self.block(decls),
+            StmtKind::Expr(_) | StmtKind::Return(..) => walk_stmt_mut(self, stmt),
+        }
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Identifier(name) => self.resolve(name),
+            // Member names aren't variables.
+            ExprKind::Member(object, _) => self.visit_expr_mut(object),
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+/// Whether the literal counts as true or false in a condition.
+fn truthiness(literal: &Literal) -> bool {
+    !matches!(literal, Literal::Bool(false) | Literal::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::{
+        parser::{
+            ast::{Capture, DeclKind, ExprKind, Slot, StmtKind},
+            Parser,
+        },
+        warnings::WarningKind,
+    };
+
+    #[test]
+    fn slots() {
+        let source = "
+            let g = 1;
+            func f(a) {
+                { let b = a; }
+                let c = g;
+                func inner() { return c; }
+            }
+        ";
+        let script = Parser::new(source).parse().unwrap();
+        let DeclKind::Func(f) = &script.decls[1].kind else {
+            panic!("expected function");
+        };
+        // `a`, `c` and `inner`, `b` goes out of scope so its slot is reused by `c`.
+        assert_eq!(f.slot_count, 3);
+
+        let StmtKind::Block(body) = &f.body.kind else {
+            panic!("expected block");
+        };
+        let DeclKind::Var(c, Some(init)) = &body[1].kind else {
+            panic!("expected variable");
+        };
+        assert_eq!(c.slot(), Slot::Cell(1));
+        let ExprKind::Identifier(g) = &init.kind else {
+            panic!("expected identifier");
+        };
+        assert_eq!(g.slot(), Slot::Global);
+
+        let DeclKind::Func(inner) = &body[2].kind else {
+            panic!("expected function");
+        };
+        assert_eq!(inner.captures, [Capture::Local(1)]);
+    }
+
+    #[test]
+    fn references() {
+        let source = "func f(a) { return a + g; } let g = 1; print(f);";
+        let script = Parser::new(source).parse().unwrap();
+        let references: Vec<_> = script
+            .references
+            .iter()
+            .map(|reference| {
+                let usage = &source[reference.usage.start..reference.usage.end];
+                let declaration = reference.declaration.map(|location| location.start);
+                (usage, reference.usage.start, declaration)
+            })
+            .collect();
+        let expected = [
+            ("f", 5, Some(5)),
+            ("a", 7, Some(7)),
+            ("a", 19, Some(7)),
+            ("g", 23, Some(32)),
+            ("g", 32, Some(32)),
+            ("print", 39, None),
+            ("f", 45, Some(5)),
+        ];
+        assert_eq!(references, expected);
+    }
+
+    #[test]
+    fn warnings() {
+        let source = "
+            let g = 1;
+            func f(a, _b) {
+                let c = 1;
+                func unused() {}
+                if true { let a = g; return a; print(g); }
+                while true { }
+                while 0 { }
+                for i in [] { }
+            }
+        ";
+        let script = Parser::new(source).parse().unwrap();
+        let warnings: Vec<_> = script
+            .warnings
+            .iter()
+            .map(|warning| (warning.kind, warning.message.as_str()))
+            .collect();
+        let expected = [
+            (WarningKind::ConstantCondition, "condition is always true"),
+            (
+                WarningKind::ShadowedBinding,
+                "'a' shadows a variable in an enclosing scope",
+            ),
+            (
+                WarningKind::UnreachableCode,
+                "unreachable code after return",
+            ),
+            (WarningKind::ConstantCondition, "condition is always true"),
+            (WarningKind::UnusedVariable, "unused variable 'i'"),
+            (WarningKind::UnusedVariable, "unused variable 'c'"),
+            (WarningKind::UnusedFunction, "unused function 'unused'"),
+            (WarningKind::UnusedVariable, "unused variable 'a'"),
+        ];
+        assert_eq!(warnings, expected);
+
+        // Constant conditions point at the condition.
+        let conditions: Vec<_> = script
+            .warnings
+            .iter()
+            .filter(|warning| warning.kind == WarningKind::ConstantCondition)
+            .map(|warning| {
+                let location = warning.location.unwrap();
+                &source[location.start..location.end]
+            })
+            .collect();
+        assert_eq!(conditions, ["true", "0"]);
+    }
+}
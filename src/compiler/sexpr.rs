@@ -0,0 +1,330 @@
+//! Renders the AST as S-expressions such as `(binary + (number 1) (number 2))`, used by
+//! `--dump-ast` and by tools which want to look at parser output.
+//!
+//! Nodes which record where they were parsed from can be followed by their location, as in
+//! `(ident x @1:5)`.
+//!
+//! The output only depends on the tree, so with [`Options::compact`] it is suitable for
+//! snapshotting parser output in tests. Strings are quoted with `"` and `\` escaped, along with
+//! newlines, tabs and carriage returns.
+
+use super::parser::ast::{
+    Decl, DeclKind, Expr, ExprKind, Function, Identifier, Literal, Script, Stmt, StmtKind,
+};
+use super::parser::token::Location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Whether to include the locations of nodes.
+    pub spans: bool,
+    /// Width lists may take up before they are broken over several lines, `None` keeps
+    /// everything on one line.
+    pub max_width: Option<usize>,
+}
+
+impl Options {
+    /// Everything on one line without locations, for comparing trees in tests.
+    pub fn compact() -> Self {
+        Self {
+            spans: false,
+            max_width: None,
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            spans: false,
+            max_width: Some(80),
+        }
+    }
+}
+
+/// Renders a whole script as a `script` list of its declarations.
+pub fn script(script: &Script, options: &Options) -> String {
+    let builder = Builder { options };
+    let mut items = vec![Sexpr::atom("script")];
+    items.extend(script.decls.iter().map(|decl| builder.decl(decl)));
+
+    Sexpr::List(items).render(options.max_width)
+}
+
+pub fn expr(expr: &Expr, options: &Options) -> String {
+    Builder { options }.expr(expr).render(options.max_width)
+}
+
+enum Sexpr {
+    Atom(String),
+    List(Vec<Sexpr>),
+}
+
+impl Sexpr {
+    fn atom(text: impl Into<String>) -> Self {
+        Self::Atom(text.into())
+    }
+
+    fn render(&self, max_width: Option<usize>) -> String {
+        let mut output = String::new();
+        self.write(&mut output, 0, max_width);
+        output
+    }
+
+    /// Length of the expression when rendered on a single line.
+    fn width(&self) -> usize {
+        match self {
+            Sexpr::Atom(text) => text.len(),
+            Sexpr::List(items) => items.iter().map(|item| item.width() + 1).sum::<usize>() + 1,
+        }
+    }
+
+    fn write(&self, output: &mut String, indent: usize, max_width: Option<usize>) {
+        let items = match self {
+            Sexpr::Atom(text) => return output.push_str(text),
+            Sexpr::List(items) => items,
+        };
+
+        let fits = max_width.is_none_or(|max_width| indent + self.width() <= max_width);
+        output.push('(');
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                // The head and any atoms directly after it, such as names and locations, stay
+                // on the first line.
+                let leading = items[..=i]
+                    .iter()
+                    .all(|item| matches!(item, Sexpr::Atom(_)));
+                if fits || leading {
+                    output.push(' ');
+                } else {
+                    output.push('\n');
+                    output.push_str(&" ".repeat(indent + 2));
+                }
+            }
+            item.write(output, indent + 2, max_width);
+        }
+        output.push(')');
+    }
+}
+
+struct Builder<'a> {
+    options: &'a Options,
+}
+
+impl Builder<'_> {
+    fn list(&self, head: &str, location: Option<Location>, rest: Vec<Sexpr>) -> Sexpr {
+        let mut items = vec![Sexpr::atom(head)];
+        items.extend(location.and_then(|location| self.location(location)));
+        items.extend(rest);
+        Sexpr::List(items)
+    }
+
+    fn location(&self, location: Location) -> Option<Sexpr> {
+        self.options
+            .spans
+            .then(|| Sexpr::atom(format!("@{location}")))
+    }
+
+    /// Renders the name followed by its location.
+    fn name(&self, name: &Identifier) -> Vec<Sexpr> {
+        let mut items = vec![Sexpr::atom(name.as_ref())];
+        items.extend(self.location(name.location()));
+        items
+    }
+
+    fn decl(&self, decl: &Decl) -> Sexpr {
+        match &decl.kind {
+            DeclKind::Var(name, init) => {
+                let mut rest = self.name(name);
+                rest.extend(init.as_deref().map(|init| self.expr(init)));
+                self.list("let", None, rest)
+            }
+            DeclKind::Import(name) => self.list("import", None, self.name(name)),
+            DeclKind::Func(function) => self.function(function),
+            DeclKind::Test(function) => {
+                let mut rest = vec![Sexpr::atom(quote(function.name.as_ref()))];
+                rest.extend(self.location(function.name.location()));
+                rest.push(self.stmt(&function.body));
+                self.list("test", None, rest)
+            }
+            DeclKind::Stmt(stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn function(&self, function: &Function) -> Sexpr {
+        let mut rest = self.name(&function.name);
+        let params = function
+            .params
+            .iter()
+            .flat_map(|param| self.name(param))
+            .collect();
+        rest.push(self.list("params", None, params));
+        rest.push(self.stmt(&function.body));
+        self.list("func", None, rest)
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> Sexpr {
+        match &stmt.kind {
+            StmtKind::If(predicate, consequent, alternative) => {
+                let mut rest = vec![self.expr(predicate), self.stmt(consequent)];
+                rest.extend(
+                    alternative
+                        .as_deref()
+                        .map(|alternative| self.stmt(alternative)),
+                );
+                self.list("if", None, rest)
+            }
+            StmtKind::While(predicate, body) => {
+                self.list("while", None, vec![self.expr(predicate), self.stmt(body)])
+            }
+            StmtKind::For(name, iterable, body) => {
+                let mut rest = self.name(name);
+                rest.push(self.expr(iterable));
+                rest.push(self.stmt(body));
+                self.list("for", None, rest)
+            }
+            StmtKind::Expr(expr) => self.list("expr", None, vec![self.expr(expr)]),
+            StmtKind::Block(decls) => self.list(
+                "block",
+                None,
+                decls.iter().map(|decl| self.decl(decl)).collect(),
+            ),
+            StmtKind::Return(expr, _) => {
+                let rest = expr.iter().map(|expr| self.expr(expr)).collect();
+                self.list("return", None, rest)
+            }
+        }
+    }
+
+    fn expr(&self, expr: &Expr) -> Sexpr {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Number(n)) => {
+                self.list("number", None, vec![Sexpr::atom(n.to_string())])
+            }
+            ExprKind::Literal(Literal::String(s)) => {
+                self.list("string", None, vec![Sexpr::atom(quote(s))])
+            }
+            ExprKind::Literal(Literal::Bool(b)) => {
+                self.list("bool", None, vec![Sexpr::atom(b.to_string())])
+            }
+            ExprKind::Literal(Literal::Nil) => self.list("nil", None, vec![]),
+            ExprKind::Identifier(name) => self.list("ident", None, self.name(name)),
+            ExprKind::Assignment(target, value) => {
+                self.list("assign", None, vec![self.expr(target), self.expr(value)])
+            }
+            ExprKind::CompoundAssignment(op, target, value) => {
+                let mut rest = vec![Sexpr::atom(format!("{}=", op.node))];
+                rest.extend(self.location(op.location));
+                rest.extend([self.expr(target), self.expr(value)]);
+                self.list("assign", None, rest)
+            }
+            ExprKind::Binary(op, left, right) => {
+                let mut rest = vec![Sexpr::atom(op.node.to_string())];
+                rest.extend(self.location(op.location));
+                rest.extend([self.expr(left), self.expr(right)]);
+                self.list("binary", None, rest)
+            }
+            ExprKind::Unary(op, operand) => {
+                let mut rest = vec![Sexpr::atom(op.node.to_string())];
+                rest.extend(self.location(op.location));
+                rest.push(self.expr(operand));
+                self.list("unary", None, rest)
+            }
+            ExprKind::Call(callee, args, location) => {
+                let mut rest = vec![self.expr(callee)];
+                rest.extend(args.iter().map(|arg| self.expr(arg)));
+                self.list("call", Some(*location), rest)
+            }
+            ExprKind::Index(object, index, location) => self.list(
+                "index",
+                Some(*location),
+                vec![self.expr(object), self.expr(index)],
+            ),
+            ExprKind::Member(object, name) => {
+                let mut rest = vec![self.expr(object)];
+                rest.extend(self.name(name));
+                self.list("member", None, rest)
+            }
+            ExprKind::List(items) => self.list(
+                "list",
+                None,
+                items.iter().map(|item| self.expr(item)).collect(),
+            ),
+            ExprKind::Map(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| Sexpr::List(vec![self.expr(key), self.expr(value)]))
+                    .collect();
+                self.list("map", None, entries)
+            }
+        }
+    }
+}
+
+/// Quotes a string with its own escapes rather than Rust's `Debug`, whose escapes may change.
+fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expr, script, Options};
+    use crate::compiler::parser::{ast::DeclKind, ast::StmtKind, Parser};
+
+    #[test]
+    fn spans() {
+        let source = "let x = f(1, \"a\")[0];\nfunc g(a) { return -a + x.y; }";
+        let script_ = Parser::new(source).parse().unwrap();
+        let options = Options {
+            spans: true,
+            max_width: Some(60),
+        };
+        let expected = r#"(script
+  (let x @1:5
+    (index @1:18
+      (call @1:10 (ident f @1:9) (number 1) (string "a"))
+      (number 0)))
+  (func g @2:6
+    (params a @2:8)
+    (block
+      (return
+        (binary + @2:23
+          (unary - @2:20 (ident a @2:21))
+          (member (ident x @2:25) y @2:27))))))"#;
+        assert_eq!(script(&script_, &options), expected);
+    }
+
+    #[test]
+    fn compact() {
+        let source = "test \"t\" { print(\"a\\b\n\"); }";
+        let script_ = Parser::new(source).parse().unwrap();
+        assert_eq!(
+            script(&script_, &Options::compact()),
+            r#"(script (test "t" (block (expr (call (ident print) (string "a\\b\n"))))))"#
+        );
+
+        let script_ = Parser::new("x = not a or b;").parse().unwrap();
+        let DeclKind::Stmt(stmt) = &script_.decls[0].kind else {
+            panic!("expected a statement");
+        };
+        let StmtKind::Expr(expr_) = &stmt.kind else {
+            panic!("expected an expression statement");
+        };
+        assert_eq!(
+            expr(expr_, &Options::compact()),
+            "(assign (ident x) (binary or (unary not (ident a)) (ident b)))"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Problems found by the resolver which don't stop a script from running, but are likely
+//! mistakes. Each kind of warning has a name used to allow it, or deny it so that it is treated
+//! as an error.
+
+use std::{collections::HashMap, fmt::Display};
+
+use thiserror::Error;
+
+use super::parser::token::Location;
+use crate::diagnostic::Label;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WarningKind {
+    /// Local variable or parameter which is never used, names starting with `_` are exempt.
+    UnusedVariable,
+    /// Function declared in a block which is never used.
+    UnusedFunction,
+    /// Statement after a `return` in the same block.
+    UnreachableCode,
+    /// Local variable declared with the same name as another local in scope.
+    ShadowedBinding,
+    /// `if` or `while` whose condition is a literal, other than the `while true` idiom.
+    ConstantCondition,
+}
+
+impl WarningKind {
+    pub const ALL: [WarningKind; 5] = [
+        WarningKind::UnusedVariable,
+        WarningKind::UnusedFunction,
+        WarningKind::UnreachableCode,
+        WarningKind::ShadowedBinding,
+        WarningKind::ConstantCondition,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningKind::UnusedVariable => "unused_variable",
+            WarningKind::UnusedFunction => "unused_function",
+            WarningKind::UnreachableCode => "unreachable_code",
+            WarningKind::ShadowedBinding => "shadowed_binding",
+            WarningKind::ConstantCondition => "constant_condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+impl Display for WarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[error("{message}")]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+    /// Where the problem is, this is missing for nodes which don't record their location.
+    pub location: Option<Location>,
+    /// Other code involved in the problem, such as the variable another one shadows.
+    pub labels: Vec<Label>,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, message: String, location: Option<Location>) -> Self {
+        Self {
+            kind,
+            message,
+            location,
+            labels: vec![],
+        }
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+}
+
+/// How a kind of warning is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Level of each kind of warning, every kind is a warning by default.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Levels(HashMap<WarningKind, Level>);
+
+impl Levels {
+    pub fn level(&self, kind: WarningKind) -> Level {
+        self.0.get(&kind).copied().unwrap_or(Level::Warn)
+    }
+
+    pub fn set(&mut self, kind: WarningKind, level: Level) {
+        self.0.insert(kind, level);
+    }
+
+    /// Sets the level of the warning with the given name, or of every warning if the name is
+    /// `all`. Returns false if there is no such warning.
+    pub fn set_by_name(&mut self, name: &str, level: Level) -> bool {
+        if name == "all" {
+            for kind in WarningKind::ALL {
+                self.set(kind, level);
+            }
+            return true;
+        }
+
+        match WarningKind::from_name(name) {
+            Some(kind) => {
+                self.set(kind, level);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, Levels, WarningKind};
+
+    #[test]
+    fn levels() {
+        let mut levels = Levels::default();
+        assert_eq!(levels.level(WarningKind::UnusedVariable), Level::Warn);
+
+        assert!(levels.set_by_name("all", Level::Allow));
+        assert!(levels.set_by_name("unused_function", Level::Deny));
+        assert!(!levels.set_by_name("unused", Level::Deny));
+        assert_eq!(levels.level(WarningKind::UnusedVariable), Level::Allow);
+        assert_eq!(levels.level(WarningKind::UnusedFunction), Level::Deny);
+    }
+}
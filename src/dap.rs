@@ -0,0 +1,471 @@
+//! Debug adapter speaking the Debug Adapter Protocol over stdin and stdout, so that editors can
+//! debug scripts.
+//!
+//! The adapter launches the script itself once it has been configured, there is a single thread
+//! and requests are only handled while the script is paused or after it has ended. Output of the
+//! script is forwarded to the editor as output events since stdout carries the protocol.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use unnamed_language::{
+    compiler::{Location, Parser},
+    diagnostic::{Diagnostic, Renderer},
+    interpreter::{
+        debug::{Debugger, Frontend, Paused, Resume, StopReason},
+        inspect::{inspect, InspectOptions},
+        io::Io,
+        Interpreter, RuntimeErrorKind,
+    },
+    json::Json,
+};
+
+use crate::lsp::{read_message, write_message};
+
+/// The only thread scripts have.
+const THREAD_ID: f64 = 1.0;
+/// Variables reference of the globals scope, the locals of frame `n` are `n + LOCALS`.
+const GLOBALS: usize = 1;
+const LOCALS: usize = 2;
+
+/// Serves requests until the client disconnects, returning whether the session ended cleanly.
+pub fn run() -> bool {
+    let connection = Arc::new(Mutex::new(Connection::default()));
+    let mut session = Session::default();
+    loop {
+        let request = match connection.lock().unwrap().read() {
+            Ok(Some(request)) => request,
+            Ok(None) => return false,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return false;
+            }
+        };
+
+        match session.handle(&request, &connection) {
+            Ok(true) => {}
+            Ok(false) => return true,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return false;
+            }
+        }
+    }
+}
+
+/// Sends messages with increasing sequence numbers.
+#[derive(Debug, Default)]
+struct Connection {
+    seq: usize,
+    /// Set once the client disconnects while the script is running.
+    disconnected: bool,
+}
+
+impl Connection {
+    fn read(&mut self) -> io::Result<Option<Json>> {
+        read_message(&mut io::stdin().lock())
+    }
+
+    fn send(&mut self, kind: &str, mut members: Vec<(&str, Json)>) -> io::Result<()> {
+        self.seq += 1;
+        members.insert(0, ("seq", self.seq.into()));
+        members.insert(1, ("type", kind.into()));
+        write_message(&mut io::stdout().lock(), &Json::object(members))
+    }
+
+    fn respond(&mut self, request: &Json, body: Json) -> io::Result<()> {
+        self.send(
+            "response",
+            vec![
+                ("request_seq", request.get("seq").cloned().into()),
+                ("success", true.into()),
+                ("command", request.get("command").cloned().into()),
+                ("body", body),
+            ],
+        )
+    }
+
+    fn respond_error(&mut self, request: &Json, message: &str) -> io::Result<()> {
+        self.send(
+            "response",
+            vec![
+                ("request_seq", request.get("seq").cloned().into()),
+                ("success", false.into()),
+                ("command", request.get("command").cloned().into()),
+                ("message", message.into()),
+            ],
+        )
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send("event", vec![("event", event.into()), ("body", body)])
+    }
+
+    fn output(&mut self, category: &str, output: &str) -> io::Result<()> {
+        let body = Json::object([("category", category.into()), ("output", output.into())]);
+        self.event("output", body)
+    }
+}
+
+/// Launch configuration, the script runs once it has been launched and configured.
+#[derive(Debug, Default)]
+struct Session {
+    program: Option<PathBuf>,
+    args: Vec<String>,
+    stop_on_entry: bool,
+    launched: bool,
+    configured: bool,
+    /// Lines with breakpoints by source path.
+    breakpoints: HashMap<PathBuf, BTreeSet<usize>>,
+}
+
+impl Session {
+    /// Handles a request while the script isn't running, returning false once the client has
+    /// disconnected.
+    fn handle(&mut self, request: &Json, connection: &Arc<Mutex<Connection>>) -> io::Result<bool> {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let arguments = request.get("arguments").unwrap_or(&Json::Null);
+        let mut connection_ref = connection.lock().unwrap();
+        match command {
+            "initialize" => {
+                let capabilities =
+                    Json::object([("supportsConfigurationDoneRequest", true.into())]);
+                connection_ref.respond(request, capabilities)?;
+                connection_ref.event("initialized", Json::Null)?;
+            }
+            "launch" => {
+                let Some(program) = arguments.get("program").and_then(Json::as_str) else {
+                    return connection_ref
+                        .respond_error(request, "launch requires a program")
+                        .map(|()| true);
+                };
+                self.program = Some(PathBuf::from(program));
+                self.args = arguments
+                    .get("args")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|arg| Some(arg.as_str()?.to_owned()))
+                    .collect();
+                self.stop_on_entry = arguments.get("stopOnEntry") == Some(&Json::Bool(true));
+                self.launched = true;
+                connection_ref.respond(request, Json::Null)?;
+            }
+            "setBreakpoints" => {
+                let (path, lines) = requested_breakpoints(arguments);
+                let body = breakpoints_body(&lines);
+                self.breakpoints.insert(canonical(&path), lines);
+                connection_ref.respond(request, body)?;
+            }
+            "configurationDone" => {
+                self.configured = true;
+                connection_ref.respond(request, Json::Null)?;
+            }
+            "threads" => connection_ref.respond(request, threads())?,
+            "disconnect" | "terminate" => {
+                connection_ref.respond(request, Json::Null)?;
+                return Ok(false);
+            }
+            _ => connection_ref
+                .respond_error(request, &format!("unsupported request '{command}'"))?,
+        }
+        drop(connection_ref);
+
+        if self.launched && self.configured {
+            self.launched = false;
+            self.launch(connection)?;
+            if connection.lock().unwrap().disconnected {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Runs the script until it ends, then reports its exit code.
+    fn launch(&mut self, connection: &Arc<Mutex<Connection>>) -> io::Result<()> {
+        let path = self.program.clone().unwrap_or_default();
+        let name = path.display().to_string();
+        let exit_code = match std::fs::read_to_string(&path) {
+            Ok(source) => self.run_script(&source, &name, &path, connection)?,
+            Err(error) => {
+                let message = format!("error: file {name:?} could not be read: {error}\n");
+                connection.lock().unwrap().output("stderr", &message)?;
+                1
+            }
+        };
+
+        let mut connection = connection.lock().unwrap();
+        connection.event(
+            "exited",
+            Json::object([("exitCode", (exit_code as f64).into())]),
+        )?;
+        connection.event("terminated", Json::Null)
+    }
+
+    /// Runs the script, returning its exit code.
+    fn run_script(
+        &mut self,
+        source: &str,
+        name: &str,
+        path: &Path,
+        connection: &Arc<Mutex<Connection>>,
+    ) -> io::Result<i32> {
+        let renderer = Renderer::new(source, name);
+        let script = match Parser::new(source).parse() {
+            Ok(script) => Arc::new(script),
+            Err(error) => {
+                let rendered = renderer.render(&Diagnostic::from(&error));
+                connection.lock().unwrap().output("stderr", &rendered)?;
+                return Ok(1);
+            }
+        };
+
+        let adapter = Adapter {
+            connection: connection.clone(),
+            path: path.to_owned(),
+        };
+        let mut debugger = Debugger::new(script.clone(), adapter);
+        if !self.stop_on_entry {
+            debugger = debugger.without_stop_on_entry();
+        }
+        if let Some(lines) = self.breakpoints.get(&canonical(path)) {
+            debugger.breakpoints_mut().extend(lines);
+        }
+
+        let mut interpreter = Interpreter::default();
+        interpreter.set_io(Box::new(OutputEvents(connection.clone())));
+        interpreter.set_args(std::mem::take(&mut self.args));
+        interpreter.set_debug_hook(Some(Box::new(debugger)));
+        let exit_code = match interpreter.interpret(&script) {
+            Ok(()) => 0,
+            Err(error) => match error.kind() {
+                RuntimeErrorKind::Exit(code) => *code,
+                _ => {
+                    let rendered = renderer.render(&Diagnostic::from(&error));
+                    connection.lock().unwrap().output("stderr", &rendered)?;
+                    1
+                }
+            },
+        };
+
+        Ok(exit_code)
+    }
+}
+
+/// Frontend which reports pauses to the client and handles its requests until it resumes.
+#[derive(Debug)]
+struct Adapter {
+    connection: Arc<Mutex<Connection>>,
+    path: PathBuf,
+}
+
+impl Frontend for Adapter {
+    fn paused(&mut self, mut paused: Paused<'_>, reason: StopReason) -> Resume {
+        // The connection is broken if this fails, which is reported once the script has stopped
+        // and the exit is sent.
+        self.serve(&mut paused, reason).unwrap_or(Resume::Stop)
+    }
+}
+
+impl Adapter {
+    fn serve(&mut self, paused: &mut Paused<'_>, reason: StopReason) -> io::Result<Resume> {
+        let reason = match reason {
+            StopReason::Entry => "entry",
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+        };
+        let mut connection = self.connection.lock().unwrap();
+        let body = Json::object([
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ]);
+        connection.event("stopped", body)?;
+
+        loop {
+            let Some(request) = connection.read()? else {
+                connection.disconnected = true;
+                return Ok(Resume::Stop);
+            };
+            let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+            let arguments = request.get("arguments").unwrap_or(&Json::Null);
+            let resume = match command {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepInto),
+                "stepOut" => Some(Resume::StepOut),
+                "disconnect" | "terminate" => {
+                    connection.disconnected = command == "disconnect";
+                    Some(Resume::Stop)
+                }
+                _ => None,
+            };
+            if let Some(resume) = resume {
+                let body = match resume {
+                    Resume::Continue => Json::object([("allThreadsContinued", true.into())]),
+                    _ => Json::Null,
+                };
+                connection.respond(&request, body)?;
+                return Ok(resume);
+            }
+
+            match command {
+                "threads" => connection.respond(&request, threads())?,
+                "stackTrace" => {
+                    let frames = self.stack_frames(paused);
+                    let body = Json::object([
+                        ("totalFrames", frames.len().into()),
+                        ("stackFrames", frames.into()),
+                    ]);
+                    connection.respond(&request, body)?;
+                }
+                "scopes" => {
+                    let frame = arguments
+                        .get("frameId")
+                        .and_then(Json::as_usize)
+                        .unwrap_or(0);
+                    let scope = |name: &str, reference: usize, expensive: bool| {
+                        Json::object([
+                            ("name", name.into()),
+                            ("variablesReference", reference.into()),
+                            ("expensive", expensive.into()),
+                        ])
+                    };
+                    let scopes = vec![
+                        scope("Locals", frame + LOCALS, false),
+                        scope("Globals", GLOBALS, true),
+                    ];
+                    connection.respond(&request, Json::object([("scopes", scopes.into())]))?;
+                }
+                "variables" => {
+                    let variables = match arguments
+                        .get("variablesReference")
+                        .and_then(Json::as_usize)
+                    {
+                        Some(GLOBALS) => paused.globals(),
+                        Some(reference) if reference >= LOCALS => paused.locals(reference - LOCALS),
+                        _ => vec![],
+                    };
+                    let variables: Vec<_> = variables
+                        .iter()
+                        .map(|(name, value)| {
+                            Json::object([
+                                ("name", name.as_str().into()),
+                                ("value", inspect(value, &InspectOptions::compact()).into()),
+                                ("type", value.type_name().into()),
+                                ("variablesReference", 0.0.into()),
+                            ])
+                        })
+                        .collect();
+                    let body = Json::object([("variables", variables.into())]);
+                    connection.respond(&request, body)?;
+                }
+                "setBreakpoints" => {
+                    let (path, lines) = requested_breakpoints(arguments);
+                    let body = breakpoints_body(&lines);
+                    // Breakpoints in other files can never be hit.
+                    if canonical(&path) == canonical(&self.path) {
+                        *paused.breakpoints_mut() = lines;
+                    }
+                    connection.respond(&request, body)?;
+                }
+                _ => connection
+                    .respond_error(&request, &format!("unsupported request '{command}'"))?,
+            }
+        }
+    }
+
+    fn stack_frames(&self, paused: &Paused<'_>) -> Vec<Json> {
+        let source = Json::object([
+            ("name", self.path.display().to_string().into()),
+            ("path", self.path.display().to_string().into()),
+        ]);
+
+        paused
+            .stack()
+            .into_iter()
+            .enumerate()
+            .map(|(id, frame)| {
+                let mut members = vec![("id", id.into()), ("name", frame.function.into())];
+                match frame.location {
+                    Some(Location { line, column, .. }) => {
+                        members.push(("source", source.clone()));
+                        members.push(("line", line.into()));
+                        members.push(("column", column.into()));
+                    }
+                    None => {
+                        members.push(("line", 0.0.into()));
+                        members.push(("column", 0.0.into()));
+                    }
+                }
+                Json::object(members)
+            })
+            .collect()
+    }
+}
+
+/// Output of the script, sent to the client as output events.
+#[derive(Debug)]
+struct OutputEvents(Arc<Mutex<Connection>>);
+
+impl Io for OutputEvents {
+    /// Stdin carries the protocol, so there is no input for the script.
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        self.0.lock().unwrap().output("stdout", s)
+    }
+
+    fn write_error(&mut self, s: &str) -> io::Result<()> {
+        self.0.lock().unwrap().output("stderr", s)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn threads() -> Json {
+    let thread = Json::object([("id", THREAD_ID.into()), ("name", "main".into())]);
+    Json::object([("threads", vec![thread].into())])
+}
+
+/// Returns the source path and lines of a `setBreakpoints` request.
+fn requested_breakpoints(arguments: &Json) -> (PathBuf, BTreeSet<usize>) {
+    let path = arguments
+        .get("source")
+        .and_then(|source| source.get("path"))
+        .and_then(Json::as_str)
+        .unwrap_or("");
+    let lines = arguments
+        .get("breakpoints")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|breakpoint| breakpoint.get("line")?.as_usize())
+        .collect();
+
+    (PathBuf::from(path), lines)
+}
+
+fn breakpoints_body(lines: &BTreeSet<usize>) -> Json {
+    let breakpoints: Vec<_> = lines
+        .iter()
+        .map(|&line| Json::object([("verified", true.into()), ("line", line.into())]))
+        .collect();
+
+    Json::object([("breakpoints", breakpoints.into())])
+}
+
+/// Paths are compared after resolving them, since clients may give them in another form than
+/// the one given to `launch`.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
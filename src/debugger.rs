@@ -0,0 +1,219 @@
+//! Console debugger, the script pauses before its first statement and is then controlled with
+//! commands read from the terminal, see [`COMMANDS`]. An empty line repeats the last command.
+
+use std::{path::Path, sync::Arc};
+
+use unnamed_language::{
+    compiler::Parser,
+    diagnostic::Diagnostic,
+    interpreter::{
+        debug::{Debugger, Frontend, Paused, Resume, StopReason},
+        inspect::{inspect, InspectOptions},
+        value::Value,
+    },
+};
+
+use crate::{editor::Editor, read_source, report, report_runtime_error, RunOptions, Status};
+
+/// Names, arguments and descriptions of the commands, listed by `help`.
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("continue, c", "", "run until the next breakpoint"),
+    ("step, s", "", "run to the next statement, entering calls"),
+    (
+        "next, n",
+        "",
+        "run to the next statement, stepping over calls",
+    ),
+    ("finish, f", "", "run until the current function returns"),
+    ("break, b", "[line]", "set a breakpoint, or list them"),
+    ("delete, d", "<line>", "remove a breakpoint"),
+    ("backtrace, bt", "", "print the active calls"),
+    ("frame", "<n>", "select a call from the backtrace"),
+    (
+        "locals",
+        "",
+        "print the local variables of the selected call",
+    ),
+    ("print, p", "<name>", "print the value of a variable"),
+    ("list, l", "", "print the source around the selected call"),
+    ("quit, q", "", "end the script"),
+];
+
+/// Runs a script in the debugger.
+pub fn run(path: &Path, args: Vec<String>, options: &RunOptions) -> Status {
+    let Some(source) = read_source(path) else {
+        return Status::Failure;
+    };
+    let name = path.display().to_string();
+    let script = match Parser::new(&source).parse() {
+        Ok(script) => Arc::new(script),
+        Err(error) => {
+            report(&Diagnostic::from(&error), &source, &name);
+            return Status::Failure;
+        }
+    };
+
+    let console = Console {
+        source: source.clone(),
+        name: name.clone(),
+        frame: 0,
+        last_command: String::new(),
+    };
+    let mut interpreter = options.interpreter();
+    interpreter.set_args(args);
+    interpreter.set_debug_hook(Some(Box::new(Debugger::new(script.clone(), console))));
+
+    match interpreter.interpret(&script) {
+        Ok(()) => Status::Success,
+        Err(error) => report_runtime_error(&error, &source, &name),
+    }
+}
+
+#[derive(Debug)]
+struct Console {
+    source: String,
+    name: String,
+    /// Index of the selected call in the backtrace, innermost first.
+    frame: usize,
+    last_command: String,
+}
+
+impl Frontend for Console {
+    fn paused(&mut self, mut paused: Paused<'_>, reason: StopReason) -> Resume {
+        self.frame = 0;
+        let location = paused.location();
+        let reason = match reason {
+            StopReason::Entry => "paused at the start",
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "stepped",
+        };
+        println!("{reason} at {}:{location}", self.name);
+        self.print_lines(location.line, 0);
+
+        let Ok(mut editor) = Editor::new(None) else {
+            return Resume::Stop;
+        };
+        loop {
+            let line = match editor.read_line("(debug) ") {
+                Ok(Some(line)) => line,
+                Ok(None) | Err(_) => return Resume::Stop,
+            };
+            let line = match line.trim() {
+                "" => self.last_command.clone(),
+                line => line.to_owned(),
+            };
+            editor.add_history(&line);
+            self.last_command.clone_from(&line);
+
+            if let Some(resume) = self.command(&line, &mut paused) {
+                return resume;
+            }
+        }
+    }
+}
+
+impl Console {
+    /// Runs a command, returning how to resume if it resumes the script.
+    fn command(&mut self, line: &str, paused: &mut Paused<'_>) -> Option<Resume> {
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+        let line_number = || arg.parse::<usize>().ok().filter(|&line| line > 0);
+
+        match (name, arg) {
+            ("continue" | "c", "") => return Some(Resume::Continue),
+            ("step" | "s", "") => return Some(Resume::StepInto),
+            ("next" | "n", "") => return Some(Resume::StepOver),
+            ("finish" | "f", "") => return Some(Resume::StepOut),
+            ("quit" | "q", "") => return Some(Resume::Stop),
+            ("break" | "b", "") => {
+                for line in paused.breakpoints_mut().iter() {
+                    println!("{}:{line}", self.name);
+                }
+            }
+            ("break" | "b", _) if line_number().is_some() => {
+                paused.breakpoints_mut().insert(line_number().unwrap());
+            }
+            ("delete" | "d", _) if line_number().is_some() => {
+                if !paused.breakpoints_mut().remove(&line_number().unwrap()) {
+                    eprintln!("error: there is no breakpoint on line {arg}");
+                }
+            }
+            ("backtrace" | "bt", "") => {
+                for (i, frame) in paused.stack().iter().enumerate() {
+                    let marker = if i == self.frame { '*' } else { ' ' };
+                    match frame.location {
+                        Some(location) => {
+                            println!("{marker}{i:>3} {} at {location}", frame.function)
+                        }
+                        None => println!("{marker}{i:>3} {}", frame.function),
+                    }
+                }
+            }
+            ("frame", _) => match arg.parse() {
+                Ok(frame) if frame < paused.stack().len() => self.frame = frame,
+                _ => eprintln!("error: there is no frame {arg}"),
+            },
+            ("locals", "") => {
+                for (name, value) in paused.locals(self.frame) {
+                    println!("{name} = {}", show(&value));
+                }
+            }
+            ("print" | "p", name) if !name.is_empty() => {
+                // Later locals shadow earlier ones, and locals shadow globals.
+                let value = paused
+                    .locals(self.frame)
+                    .into_iter()
+                    .rev()
+                    .find(|(local, _)| local == name)
+                    .map(|(_, value)| value)
+                    .or_else(|| {
+                        paused
+                            .interpreter()
+                            .globals()
+                            .find(|(global, _)| *global == name)
+                            .map(|(_, value)| value.clone())
+                    });
+                match value {
+                    Some(value) => println!("{name} = {}", show(&value)),
+                    None => eprintln!("error: '{name}' is not defined here"),
+                }
+            }
+            ("list" | "l", "") => match paused.stack()[self.frame].location {
+                Some(location) => self.print_lines(location.line, 3),
+                None => eprintln!("error: the selected call has no source"),
+            },
+            ("help" | "h", "") => {
+                println!("commands:");
+                for (name, arg, description) in COMMANDS {
+                    let usage = format!("{name} {arg}");
+                    println!("  {usage:<22} {description}");
+                }
+            }
+            _ => eprintln!("error: unknown command '{line}', see help"),
+        }
+
+        None
+    }
+
+    /// Prints the line with the given number and `context` lines on either side of it.
+    fn print_lines(&self, line: usize, context: usize) {
+        let first = line.saturating_sub(context).max(1);
+        for (number, text) in self
+            .source
+            .lines()
+            .enumerate()
+            .map(|(i, text)| (i + 1, text))
+            .skip(first - 1)
+            .take(line + context + 1 - first)
+        {
+            let marker = if number == line { '>' } else { ' ' };
+            println!("{marker}{number:>4} | {text}");
+        }
+    }
+}
+
+fn show(value: &Value) -> String {
+    inspect(value, &InspectOptions::compact())
+}
@@ -0,0 +1,417 @@
+//! Errors and other messages about a script, rendered along with the part of the source they
+//! refer to:
+//!
+//! ```text
+//! error: expected ';' got <eof>
+//!  --> script.ul:1:6
+//!   |
+//! 1 | let x
+//!   |      ^
+//! ```
+
+use std::fmt::{Display, Write};
+
+mod codes;
+pub mod suggest;
+
+pub use self::codes::ErrorCode;
+use crate::{
+    compiler::{
+        parser::{scanner::ScanError, token::Location, ParseError},
+        warnings::{Level, Levels, Warning},
+    },
+    interpreter::RuntimeError,
+    source_map::{LineIndex, SourceMap},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    /// Part of the source the diagnostic is about, if it is about a particular part.
+    pub location: Option<Location>,
+    /// Other parts of the source which are related to the diagnostic, each shown after the
+    /// location with its message.
+    pub labels: Vec<Label>,
+    /// Additional information printed after the source snippets.
+    pub notes: Vec<String>,
+}
+
+/// Part of the source related to a diagnostic, such as where a variable was first declared.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub location: Location,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(location: Location, message: impl Into<String>) -> Self {
+        Self {
+            location,
+            message: message.into(),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, location: Option<Location>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            location,
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn error(message: impl Into<String>, location: Option<Location>) -> Self {
+        Self::new(Severity::Error, message, location)
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_labels(mut self, labels: impl IntoIterator<Item = Label>) -> Self {
+        self.labels.extend(labels);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Creates a diagnostic for the warning at its level, or `None` if it is allowed.
+    pub fn from_warning(warning: &Warning, levels: &Levels) -> Option<Self> {
+        let kind = warning.kind;
+        let diagnostic = match levels.level(kind) {
+            Level::Allow => return None,
+            Level::Warn => Self::new(Severity::Warning, &warning.message, warning.location)
+                .with_note(format!(
+                    "{kind} is a warning by default, use --allow {kind} to hide it"
+                )),
+            Level::Deny => Self::error(&warning.message, warning.location)
+                .with_note(format!("{kind} is an error because of --deny {kind}")),
+        };
+
+        Some(diagnostic.with_labels(warning.labels.iter().cloned()))
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        let diagnostic = Self::error(error.message(), Some(error.location()))
+            .with_code(error.code())
+            .with_labels(error.labels().iter().cloned());
+        match error.suggestion() {
+            Some(suggestion) => diagnostic.with_note(format!("did you mean '{suggestion}'?")),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<&ScanError> for Diagnostic {
+    fn from(error: &ScanError) -> Self {
+        Self::error(&error.message, Some(error.location)).with_code(error.code)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        let diagnostic = Self::error(error.to_string(), error.location())
+            .with_code(error.kind().code())
+            .with_labels(error.labels().iter().cloned());
+        match error.suggestion() {
+            Some(suggestion) => diagnostic.with_note(format!("did you mean '{suggestion}'?")),
+            None => diagnostic,
+        }
+    }
+}
+
+/// Renders diagnostics about a single source, or about the files of a source map.
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer<'a> {
+    source: &'a str,
+    /// Name the source is referred to by, usually the path of the file it was read from.
+    name: &'a str,
+    /// Files other than the source which locations may be in.
+    source_map: Option<&'a SourceMap>,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str, name: &'a str) -> Self {
+        Self {
+            source,
+            name,
+            source_map: None,
+            color: false,
+        }
+    }
+
+    /// Shows locations in the files of the source map with the source of their file rather than
+    /// the source the renderer was created with.
+    pub fn with_source_map(mut self, source_map: &'a SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// Highlights the output with ANSI escape codes.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut output = String::new();
+        let color = match diagnostic.severity {
+            Severity::Error => "31",
+            Severity::Warning => "33",
+            Severity::Note => "36",
+        };
+        let severity = match diagnostic.code {
+            Some(code) => format!("{}[{code}]", diagnostic.severity),
+            None => diagnostic.severity.to_string(),
+        };
+        let severity = self.paint(&severity, color);
+        let message = self.paint(&format!(": {}", diagnostic.message), "");
+        writeln!(output, "{severity}{message}").unwrap();
+
+        match diagnostic.location {
+            Some(location) => self.snippet(&mut output, location, color, None),
+            None => writeln!(output, " {} {}", self.paint("-->", "34"), self.name).unwrap(),
+        }
+        for label in &diagnostic.labels {
+            self.snippet(&mut output, label.location, color, Some(&label.message));
+        }
+
+        for note in &diagnostic.notes {
+            writeln!(output, " {} {note}", self.paint("=", "34")).unwrap();
+        }
+
+        output
+    }
+
+    /// Shows the line the location starts on with the location underlined, followed by the
+    /// message of the label if this is a label's location.
+    fn snippet(&self, output: &mut String, location: Location, color: &str, label: Option<&str>) {
+        let (source, name, lines) = match self.source_map.and_then(|map| map.get(location.file)) {
+            Some(file) => (file.source(), file.name(), Some(file.lines())),
+            None => (self.source, self.name, None),
+        };
+        let arrow = self.paint(if label.is_some() { ":::" } else { "-->" }, "34");
+        write!(output, " {arrow} {name}:{location}").unwrap();
+
+        // Locations from other sources, such as the prelude, can't be shown.
+        let Some((line_start, line)) = line(source, lines, location) else {
+            match label {
+                Some(message) => writeln!(output, ": {message}").unwrap(),
+                None => output.push('\n'),
+            }
+            return;
+        };
+        output.push('\n');
+
+        let number = location.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = self.paint("|", "34");
+        writeln!(output, "{gutter} {bar}").unwrap();
+        writeln!(output, "{} {bar} {line}", self.paint(&number, "34")).unwrap();
+
+        // The underline covers the rest of the line at most, and at least one column so the
+        // location of an empty span, such as the end of the source, is still marked.
+        let end = location.end.min(line_start + line.len());
+        let width = source
+            .get(location.start..end)
+            .map_or(1, |text| text.chars().count())
+            .max(1);
+        let padding = " ".repeat(location.column - 1);
+        let underline = match label {
+            Some(message) => self.paint(&format!("{} {message}", "-".repeat(width)), "34"),
+            None => self.paint(&"^".repeat(width), color),
+        };
+        writeln!(output, "{gutter} {bar} {padding}{underline}").unwrap();
+    }
+
+    /// Wraps the text in a bold ANSI escape code with the given color, an empty color only
+    /// makes the text bold.
+    fn paint(&self, text: &str, color: &str) -> String {
+        match (self.color, color) {
+            (false, _) => text.to_owned(),
+            (true, "") => format!("\x1b[1m{text}\x1b[0m"),
+            (true, color) => format!("\x1b[1;{color}m{text}\x1b[0m"),
+        }
+    }
+}
+
+/// Returns the offset and text of the line the location starts on, if the location fits the
+/// source. The line is looked up in the index of the source if there is one, rather than by
+/// counting the lines before it.
+fn line<'a>(
+    source: &'a str,
+    lines: Option<&LineIndex>,
+    location: Location,
+) -> Option<(usize, &'a str)> {
+    let before = source.get(..location.start)?;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_number = match lines {
+        Some(lines) => lines.line(location.start),
+        None => before.matches('\n').count() + 1,
+    };
+    let column = before[line_start..].chars().count() + 1;
+    if line_number != location.line || column != location.column {
+        return None;
+    }
+
+    let rest = &source[line_start..];
+    Some((line_start, rest.lines().next().unwrap_or("")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Renderer};
+    use crate::{
+        compiler::{
+            parser::{token::Location, Parser},
+            warnings::Levels,
+        },
+        interpreter::Interpreter,
+    };
+
+    #[test]
+    fn parse_error() {
+        let source = "let x = 1;\nlet y = (x +\n  2;";
+        let error = Parser::new(source).parse().err().unwrap();
+        let rendered = Renderer::new(source, "a.ul").render(&Diagnostic::from(&error));
+        let expected = "\
+error[E0100]: expected ')' got ';'
+ --> a.ul:3:4
+  |
+3 |   2;
+  |    ^
+ ::: a.ul:2:9
+  |
+2 | let y = (x +
+  |         - unclosed '(' opened here
+";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn unexpected_token() {
+        let render = |source| {
+            let error = Parser::new(source).parse().err().unwrap();
+            Renderer::new(source, "a.ul").render(&Diagnostic::from(&error))
+        };
+        let expected = "\
+error[E0101]: unexpected end of input
+ --> a.ul:1:4
+  |
+1 | x +
+  |    ^
+";
+        assert_eq!(render("x +"), expected);
+        assert!(render("x + )").starts_with("error[E0101]: unexpected token ')'\n"));
+    }
+
+    #[test]
+    fn labels() {
+        let source = "func f(a) {\n    { let a = 1; print(a); }\n}\nf();";
+        let script = Parser::new(source).parse().unwrap();
+        let warning = Diagnostic::from_warning(&script.warnings[0], &Levels::default()).unwrap();
+        let expected = "\
+warning: 'a' shadows a variable in an enclosing scope
+ --> a.ul:2:11
+  |
+2 |     { let a = 1; print(a); }
+  |           ^
+ ::: a.ul:1:8
+  |
+1 | func f(a) {
+  |        - shadowed variable declared here
+ = shadowed_binding is a warning by default, use --allow shadowed_binding to hide it
+";
+        assert_eq!(Renderer::new(source, "a.ul").render(&warning), expected);
+
+        let error = Interpreter::default().interpret(&script).unwrap_err();
+        let expected = "\
+error[E0211]: expected 1 arguments got 0
+ --> a.ul:4:2
+  |
+4 | f();
+  |  ^
+ ::: a.ul:1:6
+  |
+1 | func f(a) {
+  |      - 'f' declared here
+";
+        let rendered = Renderer::new(source, "a.ul").render(&Diagnostic::from(&error));
+        assert_eq!(rendered, expected);
+
+        // Labels in other sources are shown with their message only.
+        let diagnostic = Diagnostic::from(&error);
+        assert_eq!(
+            Renderer::new("", "b.ul").render(&diagnostic),
+            "error[E0211]: expected 1 arguments got 0\n --> b.ul:4:2\n ::: b.ul:1:6: 'f' declared here\n"
+        );
+    }
+
+    #[test]
+    fn end_of_source() {
+        let source = "let x";
+        let error = Parser::new(source).parse().err().unwrap();
+        let diagnostic = Diagnostic::from(&error).with_note("statements end with ';'");
+        let expected = "\
+error[E0100]: expected ';' got <eof>
+ --> -:1:6
+  |
+1 | let x
+  |      ^
+ = statements end with ';'
+";
+        assert_eq!(Renderer::new(source, "-").render(&diagnostic), expected);
+    }
+
+    #[test]
+    fn foreign_location() {
+        let location = Location {
+            start: 2,
+            end: 3,
+            line: 2,
+            column: 1,
+            ..Location::default()
+        };
+        let diagnostic = Diagnostic::error("oops", Some(location));
+        let rendered = Renderer::new("abc", "b.ul").render(&diagnostic);
+        assert_eq!(rendered, "error: oops\n --> b.ul:2:1\n");
+    }
+}
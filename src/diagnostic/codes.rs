@@ -0,0 +1,323 @@
+//! Codes identifying each kind of error, along with longer explanations of them printed by
+//! `--explain`.
+
+use std::fmt::Display;
+
+/// Code such as `E0203`, errors found while scanning are numbered from 1, while parsing from
+/// 100 and while running from 200.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorCode(u16);
+
+impl ErrorCode {
+    pub const UNEXPECTED_CHARACTER: Self = Self(1);
+    pub const UNTERMINATED_STRING: Self = Self(2);
+
+    pub const EXPECTED_TOKEN: Self = Self(100);
+    pub const UNEXPECTED_TOKEN: Self = Self(101);
+    pub const TOO_DEEPLY_NESTED: Self = Self(102);
+
+    pub const INVALID_OPERANDS: Self = Self(200);
+    pub const INVALID_OPERAND: Self = Self(201);
+    pub const DIVISION_BY_ZERO: Self = Self(202);
+    pub const UNDEFINED_VARIABLE: Self = Self(203);
+    pub const NOT_CALLABLE: Self = Self(204);
+    pub const NOT_ITERABLE: Self = Self(205);
+    pub const NOT_INDEXABLE: Self = Self(206);
+    pub const INDEX_OUT_OF_RANGE: Self = Self(207);
+    pub const OUT_OF_MEMORY: Self = Self(208);
+    pub const OUT_OF_FUEL: Self = Self(209);
+    pub const STACK_OVERFLOW: Self = Self(210);
+    pub const ARITY_MISMATCH: Self = Self(211);
+    pub const IO: Self = Self(212);
+    pub const UNDEFINED_MEMBER: Self = Self(213);
+    pub const EXIT: Self = Self(214);
+    pub const PERMISSION_DENIED: Self = Self(215);
+    pub const ASSERTION_FAILED: Self = Self(216);
+    pub const SYNTAX: Self = Self(217);
+    pub const INVALID_ARGUMENT: Self = Self(218);
+    pub const TYPE_MISMATCH: Self = Self(219);
+    pub const INTERRUPTED: Self = Self(220);
+    pub const IMPORT: Self = Self(221);
+
+    /// Parses a code written as `E0203`, the `E` and leading zeros are optional.
+    pub fn parse(code: &str) -> Option<Self> {
+        let digits = code.strip_prefix(['E', 'e']).unwrap_or(code);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        digits.parse().ok().map(Self)
+    }
+
+    /// Longer description of the error, with examples of code which causes it.
+    pub fn explanation(self) -> Option<&'static str> {
+        EXPLANATIONS
+            .iter()
+            .find(|(code, _)| *code == self)
+            .map(|(_, explanation)| *explanation)
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "E{:04}", self.0)
+    }
+}
+
+const EXPLANATIONS: &[(ErrorCode, &str)] = &[
+    (
+        ErrorCode::UNEXPECTED_CHARACTER,
+        "\
+A character which isn't part of any token was found outside of a string.
+
+    let x = 1 @ 2;
+
+Only letters, digits, underscores, whitespace, quotes and the characters used by operators and
+punctuation may appear outside of strings.",
+    ),
+    (
+        ErrorCode::UNTERMINATED_STRING,
+        "\
+A string was started but the source ended before its closing quote.
+
+    let greeting = \"hello;
+
+Strings may span several lines, so the missing quote may be far from where the error is
+reported.",
+    ),
+    (
+        ErrorCode::EXPECTED_TOKEN,
+        "\
+A particular token was required but a different one was found.
+
+    let x = 1
+    print(x);
+
+Here the declaration needs a ';' before the next statement. Statements other than blocks,
+`if`, `while`, `for` and function declarations end with a semicolon.",
+    ),
+    (
+        ErrorCode::UNEXPECTED_TOKEN,
+        "\
+A token appeared where it can't be used, usually where an expression was expected.
+
+    let x = 1 + ;
+
+Check for missing operands and misplaced punctuation.",
+    ),
+    (
+        ErrorCode::TOO_DEEPLY_NESTED,
+        "\
+Statements or expressions were nested inside each other too many levels deep.
+
+    let x = ((((((((((((((((((((1))))))))))))))))))));
+
+Nesting is limited to 128 levels, counting blocks, parentheses and each operator applied to an
+expression. Move parts of the code into variables or functions.",
+    ),
+    (
+        ErrorCode::INVALID_OPERANDS,
+        "\
+A binary operator was used with operands of types it doesn't support.
+
+    let x = 1 + nil;
+
+Arithmetic operators and comparisons other than `==` and `!=` need numbers, `+` also joins two
+strings.",
+    ),
+    (
+        ErrorCode::INVALID_OPERAND,
+        "\
+A unary operator was used with an operand of a type it doesn't support.
+
+    let x = -\"a\";
+
+Negation needs a number.",
+    ),
+    (
+        ErrorCode::DIVISION_BY_ZERO,
+        "\
+A number was divided by zero, or the remainder of dividing by zero was taken.
+
+    let x = 1 / 0;
+
+Check the divisor first if it may be zero.",
+    ),
+    (
+        ErrorCode::UNDEFINED_VARIABLE,
+        "\
+A variable was used which hasn't been declared, or has been declared in a scope which has
+already ended.
+
+    print(count);
+    let count = 1;
+
+Variables have to be declared with `let` before they are used.",
+    ),
+    (
+        ErrorCode::NOT_CALLABLE,
+        "\
+A value which isn't a function was called.
+
+    let x = 1;
+    x();",
+    ),
+    (
+        ErrorCode::NOT_ITERABLE,
+        "\
+A `for` loop, or a builtin which iterates, was given a value which can't be iterated.
+
+    for x in 10 { }
+
+Lists, maps, bytes and iterators can be iterated, `range(10)` counts up to a number.",
+    ),
+    (
+        ErrorCode::NOT_INDEXABLE,
+        "\
+A value which doesn't support indexing was indexed.
+
+    let x = 1;
+    print(x[0]);
+
+Lists, maps and bytes can be indexed.",
+    ),
+    (
+        ErrorCode::INDEX_OUT_OF_RANGE,
+        "\
+A list or bytes was indexed with a position it doesn't have.
+
+    let x = [1, 2];
+    print(x[2]);
+
+Indices start at 0, negative indices count back from the end.",
+    ),
+    (
+        ErrorCode::OUT_OF_MEMORY,
+        "\
+The script allocated more memory than the host allows, even after collecting garbage.
+
+Hosts may set a memory limit on the interpreter when running untrusted scripts.",
+    ),
+    (
+        ErrorCode::OUT_OF_FUEL,
+        "\
+The script ran more statements than the host allows.
+
+Hosts may limit how long scripts run for by giving the interpreter fuel, which each statement
+uses up.",
+    ),
+    (
+        ErrorCode::STACK_OVERFLOW,
+        "\
+Functions were nested more deeply than the maximum call depth, usually because of recursion
+without a base case.
+
+    func f(n) { return f(n + 1); }
+    f(0);",
+    ),
+    (
+        ErrorCode::ARITY_MISMATCH,
+        "\
+A function was called with the wrong number of arguments.
+
+    func add(a, b) { return a + b; }
+    add(1);",
+    ),
+    (
+        ErrorCode::IO,
+        "\
+Reading or writing a file, stream or connection failed, the message includes the error from
+the operating system.",
+    ),
+    (
+        ErrorCode::UNDEFINED_MEMBER,
+        "\
+A field or method was accessed which the value doesn't have.
+
+    let x = [1, 2];
+    print(x.size());
+
+`methods(value)` lists the methods of a value and `fields(value)` the fields of an object.",
+    ),
+    (
+        ErrorCode::EXIT,
+        "\
+The script called `exit`, this stops the script with the given status rather than being
+reported.",
+    ),
+    (
+        ErrorCode::PERMISSION_DENIED,
+        "\
+The script used a capability which the host has disabled, such as the filesystem when running
+with `--sandbox`.",
+    ),
+    (
+        ErrorCode::ASSERTION_FAILED,
+        "\
+An assertion such as `assert_eq` failed, or the script called `fail`.
+
+    assert_eq(1 + 1, 3);",
+    ),
+    (
+        ErrorCode::SYNTAX,
+        "\
+Code passed to `eval` couldn't be parsed.
+
+    eval(\"let = 1;\");",
+    ),
+    (
+        ErrorCode::INVALID_ARGUMENT,
+        "\
+A builtin was given an argument it can't use, such as a step of zero.
+
+    let x = range(0, 10, 0);",
+    ),
+    (
+        ErrorCode::TYPE_MISMATCH,
+        "\
+A builtin was given an argument of the wrong type.
+
+    let x = abs(\"a\");",
+    ),
+    (
+        ErrorCode::INTERRUPTED,
+        "\
+The host stopped the script while it was running.
+
+Hosts may interrupt scripts from another thread, for example when the user cancels them or they
+have run for too long.",
+    ),
+    (
+        ErrorCode::IMPORT,
+        "\
+A module couldn't be imported, because the host hasn't registered it and no plugin with its name
+could be loaded.
+
+    import \"missing\";",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorCode, EXPLANATIONS};
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            ErrorCode::parse("E0203"),
+            Some(ErrorCode::UNDEFINED_VARIABLE)
+        );
+        assert_eq!(ErrorCode::parse("e100"), Some(ErrorCode::EXPECTED_TOKEN));
+        assert_eq!(ErrorCode::parse("E"), None);
+        assert_eq!(ErrorCode::parse("E+12"), None);
+        assert_eq!(ErrorCode::UNEXPECTED_CHARACTER.to_string(), "E0001");
+    }
+
+    #[test]
+    fn explanations() {
+        for (i, (code, _)) in EXPLANATIONS.iter().enumerate() {
+            assert!(code.explanation().is_some());
+            assert!(!EXPLANATIONS[..i].iter().any(|(other, _)| other == code));
+        }
+    }
+}
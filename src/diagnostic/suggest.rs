@@ -0,0 +1,80 @@
+//! Suggestions for misspelled names, based on edit distance.
+
+/// Shortest name suggestions are made for.
+const MIN_LEN: usize = 3;
+
+/// Returns the candidate closest to `name`, if any is close enough to likely be what was meant.
+///
+/// Candidates may be at most a third of the length of the name away, and at least one edit, so
+/// short names only match candidates which are very similar. Names of one or two characters
+/// are a single edit away from too many others for a suggestion to mean anything, so they get
+/// none. Ties go to the candidate which comes first alphabetically to keep suggestions stable.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let len = name.chars().count();
+    if len < MIN_LEN {
+        return None;
+    }
+
+    let max_distance = (len / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Number of insertions, deletions, substitutions and transpositions of adjacent characters
+/// needed to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Only the previous two rows of the table are needed.
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let substitution = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, suggest};
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("lenght", "length"), 1);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn suggestions() {
+        let names = ["length", "list", "print", "println"];
+        assert_eq!(suggest("lenght", names), Some("length"));
+        assert_eq!(suggest("prnt", names), Some("print"));
+        assert_eq!(suggest("lst", names), Some("list"));
+        assert_eq!(suggest("xyz", names), None);
+        assert_eq!(suggest("print", names), None);
+        assert_eq!(suggest("y", ["E", "x"]), None);
+        assert_eq!(suggest("ls", names), None);
+    }
+}
@@ -0,0 +1,117 @@
+//! Line editing for the REPL, supporting cursor movement, the usual readline shortcuts and
+//! history which is kept across sessions.
+//!
+//! Editing is done by rustyline, which reads a line at a time instead when input isn't a
+//! terminal. History is saved here rather than by rustyline, so that each line is appended to the
+//! file as soon as it is entered.
+
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use rustyline::{error::ReadlineError, Config, DefaultEditor};
+
+/// Entries beyond this are dropped from the start of the history file when it is loaded.
+const MAX_HISTORY: usize = 1000;
+
+pub struct Editor {
+    editor: DefaultEditor,
+    history_path: Option<PathBuf>,
+}
+
+impl Editor {
+    /// Creates an editor using history stored in the given file, which is created when the first
+    /// line is added to it.
+    pub fn new(history_path: Option<PathBuf>) -> io::Result<Self> {
+        let config = Config::builder()
+            .max_history_size(MAX_HISTORY)
+            .and_then(|config| config.history_ignore_dups(true))
+            .map_err(into_io_error)?
+            .build();
+        let mut editor = DefaultEditor::with_config(config).map_err(into_io_error)?;
+        let history = history_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        for line in history.lines() {
+            editor.add_history_entry(line).map_err(into_io_error)?;
+        }
+
+        Ok(Self {
+            editor,
+            history_path,
+        })
+    }
+
+    /// History file in the user's home directory.
+    pub fn default_history_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".unnamed_language_history"))
+    }
+
+    /// Reads a line, without the line terminator, returning `None` at the end of input. Pressing
+    /// Ctrl-C discards the line and starts a new one.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        loop {
+            match self.editor.readline(prompt) {
+                Ok(line) => return Ok(Some(line)),
+                Err(ReadlineError::Interrupted) => {}
+                Err(ReadlineError::Eof) => return Ok(None),
+                Err(error) => return Err(into_io_error(error)),
+            }
+        }
+    }
+
+    /// Adds a line to the history, lines which are blank or the same as the previous one are
+    /// skipped.
+    pub fn add_history(&mut self, line: &str) {
+        // History is a convenience, failing to save it isn't worth interrupting the user.
+        if line.trim().is_empty() || !self.editor.add_history_entry(line).unwrap_or(false) {
+            return;
+        }
+
+        if let Some(path) = &self.history_path {
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{line}"));
+        }
+    }
+}
+
+fn into_io_error(error: ReadlineError) -> io::Error {
+    match error {
+        ReadlineError::Io(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use rustyline::history::History;
+
+    use super::Editor;
+
+    #[test]
+    fn history() {
+        let path = env::temp_dir().join(format!("unnamed-language-history-{}", std::process::id()));
+        fs::write(&path, "first\n").unwrap();
+
+        let mut editor = Editor::new(Some(path.clone())).unwrap();
+        for line in ["second", "second", "  ", "first"] {
+            editor.add_history(line);
+        }
+        assert_eq!(editor.editor.history().len(), 3);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\nfirst\n");
+
+        let editor = Editor::new(Some(path.clone())).unwrap();
+        assert_eq!(editor.editor.history().len(), 3);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,467 @@
+//! Embedding the language in Rust applications. An [`Engine`] owns an interpreter along with its
+//! configuration, and runs source code without the application having to parse, resolve and
+//! interpret scripts itself:
+//!
+//! ```
+//! use unnamed_language::{interpreter::capabilities::Capabilities, Engine};
+//!
+//! let mut engine = Engine::new()
+//!     .with_capabilities(Capabilities::none())
+//!     .with_fuel(Some(10_000));
+//! engine.eval("func square(x) { return x * x; }").unwrap();
+//! let value = engine.eval("square(4)").unwrap();
+//! assert_eq!(value.to_string(), "16");
+//! ```
+//!
+//! Globals defined by one call are seen by the next, so an engine can be used like a session of
+//! the REPL.
+//!
+//! Engines are `Send`, so servers can keep a pool of them and run each request on whichever thread
+//! is free. They aren't `Sync` since the heap of an engine is only ever used by one script at a
+//! time, an engine is shared by moving it or putting it behind a `Mutex`. Values are returned as
+//! [`HostValue`]s, which are copied out of the heap and don't depend on the engine. Unlike the
+//! [`Interpreter`], an engine never hands out handles into its heap, which is what allows it to
+//! be moved.
+//!
+//! Functions, classes, modules and instruments registered with an engine are run with the
+//! values of scripts though, so registering them is unsafe: they must not keep any value they are
+//! given or allocate once they return, since it could then be used from another thread, or after
+//! the garbage collector has freed its object.
+
+#[cfg(feature = "plugins")]
+use std::path::PathBuf;
+use std::{any::Any, fmt::Display, fs, path::Path};
+
+use crate::{
+    compiler::{
+        warnings::{Level, Levels},
+        Parser, Script,
+    },
+    interpreter::{
+        capabilities::Capabilities,
+        class::ClassBuilder,
+        instrument::Instrument,
+        interrupt::InterruptHandle,
+        io::Io,
+        module::Module,
+        native::IntoNative,
+        object::Arity,
+        usage::Usage,
+        value::{IntoValue, Value},
+        Interpreter, RuntimeError,
+    },
+    Error,
+};
+
+/// Value returned to the host by an engine.
+///
+/// Values of scripts are handles into the heap of the engine, which the garbage collector may free
+/// as soon as the engine runs again, so results are copied out of it: primitives and strings as
+/// they are, anything else as its printed form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// Any other value, such as a list or a function, as its printed form.
+    Object(String),
+}
+
+impl From<&Value> for HostValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Nil => HostValue::Nil,
+            Value::Bool(b) => HostValue::Bool(*b),
+            Value::Number(n) => HostValue::Number(*n),
+            Value::Obj(_) => match value.as_str() {
+                Some(s) => HostValue::String(s.to_owned()),
+                None => HostValue::Object(value.to_string()),
+            },
+        }
+    }
+}
+
+/// Values are printed the way scripts print them, strings without quotes.
+impl Display for HostValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HostValue::Nil => Value::Nil.fmt(f),
+            HostValue::Bool(b) => Value::Bool(*b).fmt(f),
+            HostValue::Number(n) => Value::Number(*n).fmt(f),
+            HostValue::String(s) | HostValue::Object(s) => f.write_str(s),
+        }
+    }
+}
+
+pub struct Engine {
+    interpreter: Interpreter,
+    /// Levels warnings are checked at, scripts with denied warnings aren't run.
+    warnings: Levels,
+}
+
+// SAFETY: the interpreter isn't `Send` because the values it returns are handles into its heap,
+// which would be used from the old thread while the interpreter runs on the new one. Moving the
+// engine is sound as long as no handle into its heap is reachable from outside of it, which holds
+// because:
+//
+// - The interpreter is private, and is only given to Rust code while the engine runs it.
+// - Results and globals are returned as `HostValue`s, copied out of the heap.
+// - Values passed in by `set_global` are converted by `IntoValue`, an unsafe trait whose
+//   implementations must not keep the values they return.
+// - Natives, classes, modules and instruments, which are given values and the interpreter, can
+//   only be registered with unsafe methods, whose callers promise that they don't keep them.
+//
+// Everything else the interpreter owns, such as native functions, foreign objects and its `Io`,
+// is required to be `Send`.
+unsafe impl Send for Engine {}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::from_interpreter(Interpreter::default())
+    }
+}
+
+impl Engine {
+    /// Creates an engine with the whole standard library, including the prelude, and access to
+    /// everything on the host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an engine without the prelude, see [`Interpreter::without_prelude`].
+    pub fn without_prelude() -> Self {
+        Self::from_interpreter(Interpreter::without_prelude())
+    }
+
+    /// Creates an engine which runs scripts with `interpreter`. This is private since values the
+    /// caller kept from the interpreter would outlive it being moved to another thread.
+    fn from_interpreter(interpreter: Interpreter) -> Self {
+        Self {
+            interpreter,
+            warnings: Levels::default(),
+        }
+    }
+
+    /// Sets what scripts are allowed to access on the host.
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.interpreter.set_capabilities(capabilities);
+        self
+    }
+
+    /// Limits the number of statements scripts run, see [`Interpreter::set_fuel`].
+    pub fn with_fuel(mut self, fuel: Option<u64>) -> Self {
+        self.interpreter.set_fuel(fuel);
+        self
+    }
+
+    /// Limits the number of bytes scripts may have allocated at once.
+    pub fn with_memory_limit(mut self, memory_limit: Option<usize>) -> Self {
+        self.interpreter.heap_mut().set_memory_limit(memory_limit);
+        self
+    }
+
+    /// Limits the number of nested calls, see [`Interpreter::set_max_call_depth`].
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.interpreter.set_max_call_depth(max_call_depth);
+        self
+    }
+
+    /// Makes runs reproducible, see [`Interpreter::set_deterministic`].
+    pub fn with_deterministic(mut self, seed: u64) -> Self {
+        self.interpreter.set_deterministic(seed);
+        self
+    }
+
+    /// Sets the streams builtins such as `print` use, standard output and input by default.
+    pub fn with_io(mut self, io: Box<dyn Io>) -> Self {
+        self.interpreter.set_io(io);
+        self
+    }
+
+    /// Sets the callback run before statements, see [`instrument`](crate::interpreter::instrument).
+    ///
+    /// # Safety
+    ///
+    /// The callback must not keep the interpreter's values, or anything else referring to its
+    /// heap, once it returns. See [`Engine`].
+    pub unsafe fn with_instrument(mut self, instrument: Instrument) -> Self {
+        self.interpreter.set_instrument(Some(instrument));
+        self
+    }
+
+    /// Adds a directory to search for plugins, see [`plugin`](crate::interpreter::plugin).
+    #[cfg(feature = "plugins")]
+    pub fn with_plugin_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.interpreter.add_plugin_path(path.into());
+        self
+    }
+
+    /// Sets the levels warnings are checked at, a script with a denied warning returns it as an
+    /// error instead of running. Warnings which aren't denied are ignored.
+    pub fn with_warnings(mut self, warnings: Levels) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Returns a handle which other threads can use to stop the script being run, see
+    /// [`interrupt`](crate::interpreter::interrupt).
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interpreter.interrupt_handle()
+    }
+
+    /// Defines a global variable, replacing any existing one with the same name. This is how
+    /// hosts pass configuration to scripts.
+    pub fn set_global(&mut self, name: &str, value: impl IntoValue) {
+        let value = value.into_value(self.interpreter.heap_mut());
+        self.interpreter.define_global(name, value);
+    }
+
+    /// Defines a global variable holding a Rust value, see [`Interpreter::alloc_object`]. This is
+    /// how hosts share objects with scripts, which can use them through the methods of their
+    /// class.
+    pub fn set_object<T: Any + Send>(&mut self, name: &str, data: T) {
+        let object = self.interpreter.alloc_object(data);
+        self.interpreter.define_global(name, object);
+    }
+
+    /// Returns the value of a global variable, or `None` if it isn't defined. This is how hosts
+    /// read results back after running scripts.
+    pub fn get_global(&self, name: &str) -> Option<HostValue> {
+        self.interpreter.get_global(name).map(HostValue::from)
+    }
+
+    /// Defines a global function implemented in Rust, see [`native`](crate::interpreter::native)
+    /// for the functions which can be registered.
+    ///
+    /// # Safety
+    ///
+    /// The function, and the [`FromValue`](crate::interpreter::value::FromValue) conversions of
+    /// its parameters, must not keep any [`Value`] they are given once they return. See
+    /// [`Engine`].
+    pub unsafe fn register_fn<Args, F: IntoNative<Args>>(
+        &mut self,
+        name: &'static str,
+        function: F,
+    ) {
+        self.interpreter.define_fn(name, function);
+    }
+
+    /// Defines a global function implemented in Rust which takes its arguments unconverted, along
+    /// with the interpreter to allocate its results with.
+    ///
+    /// # Safety
+    ///
+    /// The function must not keep its arguments, or any other value of the interpreter, once it
+    /// returns. See [`Engine`].
+    pub unsafe fn register_native(
+        &mut self,
+        name: &'static str,
+        arity: Arity,
+        function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.interpreter.define_closure(name, arity, function);
+    }
+
+    /// Exposes a Rust type to scripts as a class, see [`class`](crate::interpreter::class).
+    ///
+    /// # Safety
+    ///
+    /// As for [`Engine::register_fn`], the methods of the class must not keep any value they are
+    /// given.
+    pub unsafe fn register_class<T: Any + Send>(&mut self, class: ClassBuilder<T>) {
+        self.interpreter.define_class(class);
+    }
+
+    /// Makes a module importable by scripts, see [`module`](crate::interpreter::module).
+    ///
+    /// # Safety
+    ///
+    /// As for [`Engine::register_fn`] and [`Engine::register_native`], the functions of the
+    /// module must not keep any value they are given.
+    pub unsafe fn register_module(&mut self, name: &str, module: Module) {
+        self.interpreter.define_module(name, module);
+    }
+
+    /// Returns the resources used by the scripts the engine has run, see
+    /// [`usage`](crate::interpreter::usage).
+    pub fn usage(&self) -> Usage {
+        self.interpreter.usage()
+    }
+
+    /// Starts counting usage again, see [`Interpreter::reset_usage`].
+    pub fn reset_usage(&mut self) {
+        self.interpreter.reset_usage();
+    }
+
+    /// Runs the source and returns its value, which is the value of its last expression
+    /// statement. A final expression doesn't need a semicolon, so `eval("1 + 2")` returns 3.
+    pub fn eval(&mut self, source: &str) -> Result<HostValue, Error> {
+        let script = self.parse(source)?;
+        let value = self.interpreter.evaluate(&script)?;
+        Ok(HostValue::from(value))
+    }
+
+    /// Reads the file and runs it like [`Engine::eval`].
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<HostValue, Error> {
+        let source = fs::read_to_string(path)?;
+        self.eval(&source)
+    }
+
+    fn parse(&self, source: &str) -> Result<Script, Error> {
+        let script = match Parser::new(source).parse() {
+            // Try again with the semicolon a final expression may be missing, keeping the
+            // original error if that doesn't help.
+            Err(error) if error.is_incomplete() => {
+                let completed = format!("{};", source.trim_end());
+                Parser::new(&completed).parse().map_err(|_| error)?
+            }
+            script => script?,
+        };
+        let denied = script
+            .warnings
+            .iter()
+            .find(|warning| self.warnings.level(warning.kind) == Level::Deny);
+        if let Some(warning) = denied {
+            return Err(warning.clone().into());
+        }
+
+        Ok(script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{Engine, HostValue};
+    use crate::{
+        compiler::warnings::{Level, Levels, WarningKind},
+        diagnostic::ErrorCode,
+        interpreter::{capabilities::Capabilities, io::BufferIo},
+        Error,
+    };
+
+    #[test]
+    fn eval() {
+        let io = BufferIo::new("");
+        let mut engine = Engine::new().with_io(Box::new(io.clone()));
+        assert_eq!(engine.eval("let x = 2; x * 3").unwrap().to_string(), "6");
+        assert_eq!(engine.eval("print(x);").unwrap().to_string(), "nil");
+        assert_eq!(io.take_output(), "2");
+
+        let error = engine.eval("x +").unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::UNEXPECTED_TOKEN));
+        let error = engine.eval("y").unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::UNDEFINED_VARIABLE));
+    }
+
+    #[test]
+    fn collected() {
+        // Results are copied out of the heap, so collections made by later calls don't free them.
+        let mut engine = Engine::new();
+        let list = engine.eval("let l = [\"a\", [1, 2]]; l").unwrap();
+        let string = engine.eval("let s = \"b\" + \"bb\"; s").unwrap();
+        let collections = engine.usage().heap.collections;
+        engine
+            .eval("l = nil; s = nil; for i in range(200000) { let t = [i]; }")
+            .unwrap();
+        assert!(engine.usage().heap.collections > collections);
+        assert_eq!(list, HostValue::Object("[\"a\", [1, 2]]".to_owned()));
+        assert_eq!(string, HostValue::String("bbb".to_owned()));
+        assert_eq!(engine.eval("nil").unwrap(), HostValue::Nil);
+        assert_eq!(engine.eval("1 < 2").unwrap().to_string(), "true");
+    }
+
+    #[test]
+    fn configuration() {
+        let mut engine = Engine::without_prelude()
+            .with_capabilities(Capabilities::none())
+            .with_fuel(Some(100));
+        #[cfg(feature = "fs")]
+        {
+            let error = engine.eval("fs.read_text(\"/etc/passwd\")").unwrap_err();
+            assert_eq!(error.code(), Some(ErrorCode::PERMISSION_DENIED));
+        }
+        let error = engine.eval("while true {}").unwrap_err();
+        assert_eq!(error.code(), Some(ErrorCode::OUT_OF_FUEL));
+
+        let mut warnings = Levels::default();
+        warnings.set(WarningKind::UnusedVariable, Level::Deny);
+        let mut engine = Engine::new().with_warnings(warnings);
+        assert!(matches!(
+            engine.eval("{ let a = 1; }"),
+            Err(Error::Resolve(_))
+        ));
+    }
+
+    #[test]
+    fn globals() {
+        let mut engine = Engine::new();
+        engine.set_global("limit", 3i64);
+        engine
+            .eval("let total = 0; for i in range(limit) { total = total + i; }")
+            .unwrap();
+        assert_eq!(engine.get_global("total"), Some(HostValue::Number(3.0)));
+        assert_eq!(engine.get_global("missing"), None);
+
+        // Globals read back stay valid once the engine has collected their objects.
+        engine.eval("let names = [\"a\"];").unwrap();
+        let names = engine.get_global("names").unwrap();
+        engine
+            .eval("names = nil; for i in range(200000) { let t = [i]; }")
+            .unwrap();
+        assert!(engine.usage().heap.collections > 0);
+        assert_eq!(names.to_string(), "[\"a\"]");
+    }
+
+    #[test]
+    fn run_file() {
+        let path = std::env::temp_dir().join(format!("engine-{}.ul", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "func f() {{ return 5; }}\nf()").unwrap();
+
+        let mut engine = Engine::new();
+        assert_eq!(engine.run_file(&path).unwrap().to_string(), "5");
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(engine.run_file(&path), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn workers() {
+        // A pool of engines shared by worker threads, each engine runs on whichever worker takes
+        // it and keeps its globals between requests.
+        let pool: Vec<_> = (0..2)
+            .map(|i| {
+                let mut engine = Engine::new();
+                // SAFETY: the function doesn't take or keep any values.
+                unsafe { engine.register_fn("worker_id", move || i as f64) };
+                engine.eval("let served = 0;").unwrap();
+                engine
+            })
+            .collect();
+        let pool = std::sync::Mutex::new(pool);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut engine = pool.lock().unwrap().pop().unwrap();
+                    engine.eval("served = served + 1;").unwrap();
+                    pool.lock().unwrap().push(engine);
+                });
+            }
+        });
+
+        let mut served = 0.0;
+        for engine in pool.lock().unwrap().iter() {
+            let Some(HostValue::Number(n)) = engine.get_global("served") else {
+                panic!("served should be a number");
+            };
+            served += n;
+        }
+        assert_eq!(served, 4.0);
+    }
+}
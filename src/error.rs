@@ -0,0 +1,125 @@
+//! Error type covering every step a script goes through, so that code which parses and runs
+//! scripts can return one kind of error and propagate each step's errors with `?`. None of the
+//! errors borrow the source, their locations refer to it by file id.
+
+use std::{io, sync::Arc};
+
+use thiserror::Error;
+
+use crate::{
+    compiler::{warnings::Warning, Location, ParseError, ScanError},
+    diagnostic::{Diagnostic, ErrorCode, Severity},
+    interpreter::RuntimeError,
+};
+
+#[derive(Debug, Error, Clone)]
+pub enum Error {
+    /// Error reading a script, the error is shared so that errors can be cloned.
+    #[error(transparent)]
+    Io(Arc<io::Error>),
+    #[error(transparent)]
+    Scan(#[from] ScanError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// Likely mistake found while resolving, it is only an error if its kind of warning is
+    /// denied.
+    #[error(transparent)]
+    Resolve(#[from] Warning),
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl Error {
+    /// Severity of the error when it is reported, warnings found while resolving are reported as
+    /// warnings.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Error::Resolve(_) => Severity::Warning,
+            Error::Io(_) | Error::Scan(_) | Error::Parse(_) | Error::Runtime(_) => Severity::Error,
+        }
+    }
+
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::Io(_) => None,
+            Error::Scan(error) => Some(error.code),
+            Error::Parse(error) => Some(error.code()),
+            Error::Resolve(_) => None,
+            Error::Runtime(error) => Some(error.kind().code()),
+        }
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            Error::Io(_) => None,
+            Error::Scan(error) => Some(error.location),
+            Error::Parse(error) => Some(error.location()),
+            Error::Resolve(warning) => warning.location,
+            Error::Runtime(error) => error.location(),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(Arc::new(error))
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Io(error) => Self::error(error.to_string(), None),
+            Error::Scan(error) => Self::from(error),
+            Error::Parse(error) => Self::from(error),
+            Error::Resolve(warning) => {
+                Self::new(Severity::Warning, &warning.message, warning.location)
+                    .with_labels(warning.labels.iter().cloned())
+            }
+            Error::Runtime(error) => Self::from(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::Error;
+    use crate::{
+        compiler::Parser,
+        diagnostic::{ErrorCode, Severity},
+        interpreter::{io::BufferIo, Interpreter},
+    };
+
+    fn run(source: &str) -> Result<(), Error> {
+        let script = Parser::new(source).parse()?;
+        if let Some(warning) = script.warnings.first() {
+            return Err(warning.clone().into());
+        }
+        let mut interpreter = Interpreter::default();
+        interpreter.set_io(Box::new(BufferIo::new("")));
+        interpreter.interpret(&script)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn steps() {
+        assert!(run("print(1);").is_ok());
+
+        let error = run("let x = 1 +;").unwrap_err();
+        assert!(matches!(error, Error::Parse(_)));
+        assert_eq!(error.code(), Some(ErrorCode::UNEXPECTED_TOKEN));
+        assert_eq!(error.severity(), Severity::Error);
+
+        let error = run("{ let x = 1; }").unwrap_err();
+        assert!(matches!(error, Error::Resolve(_)));
+        assert_eq!(error.severity(), Severity::Warning);
+
+        let error = run("print(1 / 0);").unwrap_err();
+        assert_eq!(error.to_string(), "division by zero is undefined");
+        assert_eq!(error.location().map(|location| location.column), Some(9));
+        assert!(error.source().is_none());
+    }
+}
@@ -0,0 +1,409 @@
+//! C interface for embedding the language in applications which aren't written in Rust, enabled by
+//! the `ffi` feature. The shared library built from the crate exports these functions, which are
+//! declared for C and C++ in `include/unnamed_language.h`:
+//!
+//! ```c
+//! UlEngine *engine = ul_engine_new();
+//! UlValue value;
+//! if (ul_eval(engine, "1 + 2", &value) == UL_OK) {
+//!     printf("%g\n", value.number);
+//! } else {
+//!     fputs(ul_diagnostics(engine), stderr);
+//! }
+//! ul_engine_free(engine);
+//! ```
+//!
+//! Strings returned by an engine belong to it and stay valid until the next call made with the
+//! engine. Only primitive values and strings are exchanged, other values are returned as their
+//! printed form. Panics abort the process rather than unwinding into the host.
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+};
+
+use crate::{
+    diagnostic::{Diagnostic, Renderer},
+    engine::HostValue,
+    interpreter::value::Value,
+    Engine,
+};
+
+/// Name sources passed to [`ul_eval`] are referred to by in diagnostics.
+const EVAL_NAME: &str = "<eval>";
+
+/// Engine along with the strings it has handed out to the host.
+pub struct UlEngine {
+    engine: Engine,
+    /// Rendered error of the last call, empty if it succeeded.
+    diagnostics: CString,
+    /// Contents of the last string or object returned.
+    string: CString,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UlStatus {
+    Ok = 0,
+    /// The call failed, [`ul_diagnostics`] describes why.
+    Error = 1,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UlKind {
+    Nil = 0,
+    Bool = 1,
+    Number = 2,
+    String = 3,
+    /// Any other value, such as a list or a function, which is returned as its printed form.
+    Object = 4,
+}
+
+impl TryFrom<c_int> for UlKind {
+    type Error = c_int;
+
+    fn try_from(kind: c_int) -> Result<Self, Self::Error> {
+        Ok(match kind {
+            0 => UlKind::Nil,
+            1 => UlKind::Bool,
+            2 => UlKind::Number,
+            3 => UlKind::String,
+            4 => UlKind::Object,
+            _ => return Err(kind),
+        })
+    }
+}
+
+/// Value exchanged with the host, only the field matching `kind` is meaningful.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UlValue {
+    /// One of the [`UlKind`]s. This is an integer rather than the enum since hosts can pass any
+    /// integer, kinds which don't exist are rejected.
+    pub kind: c_int,
+    pub boolean: bool,
+    pub number: f64,
+    /// Contents of a string, or the printed form of an object, as a NUL terminated UTF-8 string.
+    pub string: *const c_char,
+}
+
+impl UlEngine {
+    fn fail(&mut self, message: String) -> UlStatus {
+        self.diagnostics = c_string(message);
+        UlStatus::Error
+    }
+
+    /// Converts a value for the host, any string it contains is owned by the engine.
+    fn export(&mut self, value: &HostValue) -> UlValue {
+        let mut exported = UlValue {
+            kind: UlKind::Nil as c_int,
+            boolean: false,
+            number: 0.0,
+            string: ptr::null(),
+        };
+        match value {
+            HostValue::Nil => {}
+            HostValue::Bool(b) => {
+                exported.kind = UlKind::Bool as c_int;
+                exported.boolean = *b;
+            }
+            HostValue::Number(n) => {
+                exported.kind = UlKind::Number as c_int;
+                exported.number = *n;
+            }
+            HostValue::String(s) | HostValue::Object(s) => {
+                exported.kind = match value {
+                    HostValue::String(_) => UlKind::String as c_int,
+                    _ => UlKind::Object as c_int,
+                };
+                self.string = c_string(s.clone());
+                exported.string = self.string.as_ptr();
+            }
+        }
+
+        exported
+    }
+}
+
+/// Converts a string for the host, which sees it end at the first NUL if it contains any.
+fn c_string(s: String) -> CString {
+    let mut bytes = s.into_bytes();
+    if let Some(nul) = bytes.iter().position(|&b| b == 0) {
+        bytes.truncate(nul);
+    }
+
+    CString::new(bytes).expect("NUL bytes should have been removed")
+}
+
+/// Reads a string passed by the host.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, what: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{what} is null"));
+    }
+
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| format!("{what} is not valid UTF-8"))
+}
+
+/// Creates an engine with the whole standard library and access to everything on the host, it
+/// has to be freed with [`ul_engine_free`].
+#[no_mangle]
+pub extern "C" fn ul_engine_new() -> *mut UlEngine {
+    let engine = UlEngine {
+        engine: Engine::new(),
+        diagnostics: CString::default(),
+        string: CString::default(),
+    };
+
+    Box::into_raw(Box::new(engine))
+}
+
+/// Frees an engine, along with every string it returned.
+///
+/// # Safety
+///
+/// `engine` must have been created by [`ul_engine_new`] and not freed already, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn ul_engine_free(engine: *mut UlEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Runs source code, storing the value of its final expression in `value` if it isn't null.
+/// Globals defined by one call are seen by the next.
+///
+/// # Safety
+///
+/// `engine` must be a live engine, `source` a NUL terminated string and `value` either null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ul_eval(
+    engine: *mut UlEngine,
+    source: *const c_char,
+    value: *mut UlValue,
+) -> UlStatus {
+    let engine = &mut *engine;
+    let source = match str_arg(source, "source") {
+        Ok(source) => source,
+        Err(message) => return engine.fail(message),
+    };
+
+    match engine.engine.eval(source) {
+        Ok(result) => {
+            engine.diagnostics = CString::default();
+            if !value.is_null() {
+                *value = engine.export(&result);
+            }
+            UlStatus::Ok
+        }
+        Err(error) => {
+            let rendered = Renderer::new(source, EVAL_NAME).render(&Diagnostic::from(&error));
+            engine.fail(rendered)
+        }
+    }
+}
+
+/// Returns why the last call failed, or an empty string if it succeeded.
+///
+/// # Safety
+///
+/// `engine` must be a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn ul_diagnostics(engine: *const UlEngine) -> *const c_char {
+    (*engine).diagnostics.as_ptr()
+}
+
+/// Defines a global variable, replacing any existing one with the same name. Objects can't be
+/// passed to scripts.
+///
+/// # Safety
+///
+/// `engine` must be a live engine, `name` a NUL terminated string and `value` either null or a
+/// pointer to a value whose string, if it is a string, is null or NUL terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ul_set_global(
+    engine: *mut UlEngine,
+    name: *const c_char,
+    value: *const UlValue,
+) -> UlStatus {
+    let engine = &mut *engine;
+    let name = match str_arg(name, "name") {
+        Ok(name) => name,
+        Err(message) => return engine.fail(message),
+    };
+
+    let Some(value) = value.as_ref() else {
+        return engine.fail("value is null".to_owned());
+    };
+    let Ok(kind) = UlKind::try_from(value.kind) else {
+        return engine.fail(format!("invalid value kind {}", value.kind));
+    };
+    match kind {
+        UlKind::Nil => engine.engine.set_global(name, Value::Nil),
+        UlKind::Bool => engine.engine.set_global(name, value.boolean),
+        UlKind::Number => engine.engine.set_global(name, value.number),
+        UlKind::String => match str_arg(value.string, "string") {
+            Ok(s) => engine.engine.set_global(name, s),
+            Err(message) => return engine.fail(message),
+        },
+        UlKind::Object => return engine.fail("objects can't be passed to scripts".to_owned()),
+    }
+    engine.diagnostics = CString::default();
+
+    UlStatus::Ok
+}
+
+/// Reads a global variable into `value`, failing if it isn't defined.
+///
+/// # Safety
+///
+/// `engine` must be a live engine, `name` a NUL terminated string and `value` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ul_get_global(
+    engine: *mut UlEngine,
+    name: *const c_char,
+    value: *mut UlValue,
+) -> UlStatus {
+    let engine = &mut *engine;
+    let name = match str_arg(name, "name") {
+        Ok(name) => name,
+        Err(message) => return engine.fail(message),
+    };
+
+    match engine.engine.get_global(name) {
+        Some(global) => {
+            engine.diagnostics = CString::default();
+            *value = engine.export(&global);
+            UlStatus::Ok
+        }
+        None => engine.fail(format!("variable '{name}' is not defined")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{c_int, CStr, CString},
+        ptr,
+    };
+
+    use super::{
+        ul_diagnostics, ul_engine_free, ul_engine_new, ul_eval, ul_get_global, ul_set_global,
+        UlKind, UlStatus, UlValue,
+    };
+
+    fn empty() -> UlValue {
+        UlValue {
+            kind: UlKind::Nil as c_int,
+            boolean: false,
+            number: 0.0,
+            string: ptr::null(),
+        }
+    }
+
+    #[test]
+    fn eval() {
+        unsafe {
+            let engine = ul_engine_new();
+            let mut value = empty();
+            let source = CString::new("let s = \"ab\" + \"c\"; s").unwrap();
+            assert_eq!(ul_eval(engine, source.as_ptr(), &mut value), UlStatus::Ok);
+            assert_eq!(UlKind::try_from(value.kind), Ok(UlKind::String));
+            assert_eq!(CStr::from_ptr(value.string).to_str(), Ok("abc"));
+            assert_eq!(CStr::from_ptr(ul_diagnostics(engine)).to_bytes(), b"");
+
+            let source = CString::new("[1, 2]").unwrap();
+            assert_eq!(ul_eval(engine, source.as_ptr(), &mut value), UlStatus::Ok);
+            assert_eq!(UlKind::try_from(value.kind), Ok(UlKind::Object));
+            assert_eq!(CStr::from_ptr(value.string).to_str(), Ok("[1, 2]"));
+
+            let source = CString::new("1 +").unwrap();
+            assert_eq!(
+                ul_eval(engine, source.as_ptr(), ptr::null_mut()),
+                UlStatus::Error
+            );
+            let diagnostics = CStr::from_ptr(ul_diagnostics(engine)).to_str().unwrap();
+            assert!(diagnostics.starts_with("error[E0101]"));
+            assert!(diagnostics.contains("<eval>:1:"));
+
+            ul_engine_free(engine);
+        }
+    }
+
+    #[test]
+    fn globals() {
+        unsafe {
+            let engine = ul_engine_new();
+            let limit = CString::new("limit").unwrap();
+            let number = UlValue {
+                kind: UlKind::Number as c_int,
+                number: 3.0,
+                ..empty()
+            };
+            assert_eq!(ul_set_global(engine, limit.as_ptr(), &number), UlStatus::Ok);
+            let greeting = CString::new("greeting").unwrap();
+            let text = CString::new("hi").unwrap();
+            let string = UlValue {
+                kind: UlKind::String as c_int,
+                string: text.as_ptr(),
+                ..empty()
+            };
+            assert_eq!(
+                ul_set_global(engine, greeting.as_ptr(), &string),
+                UlStatus::Ok
+            );
+
+            let source = CString::new("let done = greeting == \"hi\" and limit > 2;").unwrap();
+            assert_eq!(
+                ul_eval(engine, source.as_ptr(), ptr::null_mut()),
+                UlStatus::Ok
+            );
+            let done = CString::new("done").unwrap();
+            let mut value = empty();
+            assert_eq!(
+                ul_get_global(engine, done.as_ptr(), &mut value),
+                UlStatus::Ok
+            );
+            assert_eq!(UlKind::try_from(value.kind), Ok(UlKind::Bool));
+            assert!(value.boolean);
+
+            let missing = CString::new("missing").unwrap();
+            let status = ul_get_global(engine, missing.as_ptr(), &mut value);
+            assert_eq!(status, UlStatus::Error);
+            assert_eq!(
+                CStr::from_ptr(ul_diagnostics(engine)).to_str(),
+                Ok("variable 'missing' is not defined")
+            );
+
+            // Values which C can express but which aren't valid are rejected.
+            let invalid = UlValue { kind: 7, ..empty() };
+            let status = ul_set_global(engine, limit.as_ptr(), &invalid);
+            assert_eq!(status, UlStatus::Error);
+            assert_eq!(
+                CStr::from_ptr(ul_diagnostics(engine)).to_str(),
+                Ok("invalid value kind 7")
+            );
+            let status = ul_set_global(engine, limit.as_ptr(), ptr::null());
+            assert_eq!(status, UlStatus::Error);
+            let null_string = UlValue {
+                kind: UlKind::String as c_int,
+                ..empty()
+            };
+            let status = ul_set_global(engine, limit.as_ptr(), &null_string);
+            assert_eq!(status, UlStatus::Error);
+            assert_eq!(
+                CStr::from_ptr(ul_diagnostics(engine)).to_str(),
+                Ok("string is null")
+            );
+
+            ul_engine_free(engine);
+        }
+    }
+}
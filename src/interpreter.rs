@@ -1,98 +1,1170 @@
+#[cfg(feature = "plugins")]
+use std::path::PathBuf;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    ops::ControlFlow,
+    sync::Arc,
+    time::Duration,
+};
+
 use thiserror::Error;
 
-use self::{environment::Environment, value::Value};
-use crate::compiler::parser::ast::{Decl, Expr, Operator, Script, Stmt};
+use self::{
+    capabilities::Capabilities,
+    class::{Class, ClassBuilder},
+    clock::Clock,
+    debug::DebugHook,
+    environment::Environment,
+    heap::{Heap, HeapStats},
+    instrument::Instrument,
+    interrupt::InterruptHandle,
+    io::{Io, StdIo},
+    module::Module,
+    native::IntoNative,
+    object::{
+        Arity, NativeClosure, NativeFn, Obj, ObjBound, ObjBytes, ObjCell, ObjForeign, ObjFunction,
+        ObjInstance, ObjKind, ObjList, ObjMap, ObjNative, ObjWeak,
+    },
+    random::Rng,
+    table::Table,
+    usage::Usage,
+    value::{Value, ValueKey},
+};
+use crate::compiler::parser::{
+    ast::{
+        Capture, Decl, DeclKind, Expr, ExprKind, Function, Identifier, Literal, Operator, Script,
+        Slot, Spanned, Stmt, StmtKind,
+    },
+    token::Location,
+    Parser,
+};
+use crate::diagnostic::{suggest::suggest, ErrorCode, Label};
 
+mod builtins;
+pub mod capabilities;
+pub mod class;
+pub mod clock;
+pub mod coverage;
+pub mod debug;
+pub mod digest;
+pub mod encoding;
 mod environment;
+pub mod format;
+pub mod heap;
+pub mod inspect;
+pub mod instrument;
+pub mod interrupt;
+pub mod io;
+pub mod iterator;
+pub mod marshal;
+pub mod methods;
+pub mod module;
+pub mod native;
 pub mod object;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod random;
+pub mod table;
+#[cfg(test)]
+mod testing;
+pub mod usage;
 pub mod value;
 
+/// Result of executing a declaration or statement, `Break` is used to unwind out of a function
+/// when a return statement is executed.
+type Exec = Result<ControlFlow<Value>, RuntimeError>;
+
 /// Basic treewalk interpreter, will be replaced later by something more efficient.
-#[derive(Debug, Default)]
+///
+/// Interpreters aren't `Send`: objects are reached through handles without any synchronization, and
+/// values held by the host would still point into the heap once it has moved to another thread.
+/// [`Engine`](crate::Engine)s can be moved since they don't hand out handles, which is why
+/// everything the host gives an interpreter, such as native functions, foreign objects, hooks and
+/// [`Io`], has to be `Send`.
+#[derive(Debug)]
 pub struct Interpreter {
     environment: Environment,
+    heap: Heap,
+    /// Active function calls, innermost last.
+    frames: Vec<Frame>,
+    /// Values which are held outside of the environment while evaluating other expressions, these
+    /// are treated as roots by the garbage collector.
+    temporaries: Vec<Value>,
+    /// Value of the last script run by [`Interpreter::evaluate`], kept as a root until the next
+    /// one so that the reference returned to the host stays valid.
+    result: Value,
+    /// Number of native functions currently being executed, garbage is not collected while
+    /// natives are running since they may hold values the collector can't see. Builtins which
+    /// root everything they hold don't count while their callbacks run, see
+    /// [`Interpreter::call_collecting`].
+    native_depth: usize,
+    /// Maximum number of nested function calls.
+    max_call_depth: usize,
+    /// Number of statements which may still be executed, unlimited if `None`.
+    fuel: Option<u64>,
+    /// Set from other threads to stop the script being run.
+    interrupt: InterruptHandle,
+    /// Statements executed and time spent running scripts since the usage was reset, the rest of
+    /// the usage is read from the heap.
+    usage: Usage,
+    /// Stats of the heap when the usage was reset.
+    usage_heap_start: HeapStats,
+    /// Whether a script is being run, nested scripts run by `eval` aren't timed separately.
+    running: bool,
+    rng: Rng,
+    clock: Clock,
+    io: Box<dyn Io>,
+    #[cfg(feature = "regex")]
+    regex_cache: builtins::regex::RegexCache,
+    capabilities: Capabilities,
+    /// Arguments passed to the script, returned by `args`.
+    args: Vec<String>,
+    /// Global variables saved by `snapshot`, `None` for snapshots which have been discarded.
+    snapshots: Vec<Option<HashMap<String, Value>>>,
+    /// Called before each statement when set, this is taken out while it is being called.
+    debug_hook: Option<Box<dyn DebugHook>>,
+    /// Called before statements when set, this is taken out while it is being called.
+    instrument: Option<Instrument>,
+    /// Index of the first slot of the script being run.
+    script_base: usize,
+    /// Functions of the tests declared by the scripts which have run, in the order they were
+    /// declared.
+    tests: Vec<Value>,
+    /// Classes of the Rust types exposed to scripts, by the id of their type.
+    classes: HashMap<TypeId, Arc<Class>>,
+    /// Modules which scripts can import, by name. Plugins are added once they have been loaded.
+    modules: HashMap<String, Value>,
+    /// Directories searched for plugins which are imported.
+    #[cfg(feature = "plugins")]
+    plugin_paths: Vec<PathBuf>,
+    /// Libraries of the plugins which have been loaded. This is declared last so that they are
+    /// unloaded after everything which may use their code, such as the heap and classes.
+    #[cfg(feature = "plugins")]
+    plugins: Vec<libloading::Library>,
+}
+
+/// Handle to a copy of the global variables taken by [`Interpreter::snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapshot(usize);
+
+/// Function call which is currently being executed.
+#[derive(Debug)]
+pub struct Frame {
+    function: String,
+    /// Location of the call expression, this is `None` if the function was called by a native
+    /// function.
+    call_location: Option<Location>,
+    /// Function being executed, this is `None` for native functions.
+    closure: Option<Obj>,
+    /// Index of the first slot of the call's local variables.
+    base: usize,
+}
+
+impl Frame {
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Returns the declaration of the function being executed, or `None` for native functions.
+    pub fn declaration(&self) -> Option<&Arc<Function>> {
+        let closure = self.closure.as_ref()?;
+        Some(closure.downcast::<ObjFunction>()?.decl())
+    }
+
+    pub fn call_location(&self) -> Option<Location> {
+        self.call_location
+    }
+}
+
+/// Parts of the standard library written in the language itself, loaded by
+/// [`Interpreter::default`].
+const PRELUDE: &str = include_str!("interpreter/prelude.ul");
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        let mut interpreter = Self::without_prelude();
+        let prelude = Parser::new(PRELUDE).parse().expect("prelude should parse");
+        interpreter.interpret(&prelude).expect("prelude should run");
+
+        interpreter
+    }
 }
 
 impl Interpreter {
+    /// Each call in a script uses several frames of the native stack, this is low enough to not
+    /// overflow the 2MiB stack of spawned threads.
+    pub const DEFAULT_MAX_CALL_DEPTH: usize = 200;
+
+    /// Creates an interpreter with only the builtins implemented in Rust, for minimal or
+    /// sandboxed environments which don't want the prelude.
+    pub fn without_prelude() -> Self {
+        let mut interpreter = Self {
+            environment: Environment::default(),
+            heap: Heap::default(),
+            frames: vec![],
+            temporaries: vec![],
+            result: Value::Nil,
+            native_depth: 0,
+            max_call_depth: Self::DEFAULT_MAX_CALL_DEPTH,
+            fuel: None,
+            interrupt: InterruptHandle::default(),
+            usage: Usage::default(),
+            usage_heap_start: HeapStats::default(),
+            running: false,
+            rng: Rng::from_entropy(),
+            clock: Clock::system(),
+            io: Box::new(StdIo),
+            #[cfg(feature = "regex")]
+            regex_cache: builtins::regex::RegexCache::default(),
+            capabilities: Capabilities::default(),
+            args: vec![],
+            snapshots: vec![],
+            debug_hook: None,
+            instrument: None,
+            script_base: 0,
+            tests: vec![],
+            classes: HashMap::new(),
+            modules: HashMap::new(),
+            #[cfg(feature = "plugins")]
+            plugin_paths: vec![],
+            #[cfg(feature = "plugins")]
+            plugins: vec![],
+        };
+
+        builtins::define(&mut interpreter);
+
+        interpreter
+    }
+
+    /// Sets the maximum number of nested function calls, exceeding it raises a stack overflow
+    /// error instead of overflowing the native stack.
+    ///
+    /// Raising the limit above the default may require running the interpreter on a thread with
+    /// a larger stack.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Limits execution to `fuel` statements, once they run out execution stops with an error.
+    /// This is meant for hosts running untrusted scripts which may never terminate, `None`
+    /// removes the limit.
+    ///
+    /// Fuel is shared between calls to `interpret` so it has to be refilled by the host. Builtins
+    /// which do as much work as the script asks for, such as skipping or collecting the items of
+    /// an iterator, use a unit of fuel for each item.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// Returns the remaining fuel, `None` if execution is unlimited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Returns a handle which other threads can use to stop the script being run, see
+    /// [`interrupt`].
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Returns the resources used by scripts since the interpreter was created or the usage was
+    /// reset, see [`usage`].
+    pub fn usage(&self) -> Usage {
+        Usage {
+            peak_bytes_allocated: self.heap.peak_bytes_allocated(),
+            heap: self.heap.stats().since(self.usage_heap_start),
+            ..self.usage
+        }
+    }
+
+    /// Starts counting usage again, the peak starts from the bytes currently allocated.
+    pub fn reset_usage(&mut self) {
+        self.usage = Usage::default();
+        self.usage_heap_start = self.heap.stats();
+        self.heap.reset_peak();
+    }
+
+    /// Makes runs reproducible, the random number generator is seeded with `seed` and time is
+    /// read from a virtual clock which advances by a millisecond every time it is read.
+    ///
+    /// Maps always iterate in insertion order so they don't need any special handling.
+    pub fn set_deterministic(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.clock = Clock::virtual_clock(Duration::from_millis(1));
+    }
+
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    pub fn clock(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    /// Replaces the streams used by builtins such as `print` and `input`, returning the old ones.
+    pub fn set_io(&mut self, io: Box<dyn Io>) -> Box<dyn Io> {
+        std::mem::replace(&mut self.io, io)
+    }
+
+    pub fn io(&mut self) -> &mut dyn Io {
+        self.io.as_mut()
+    }
+
+    /// Sets what scripts are allowed to access, everything is allowed by default. See
+    /// [`capabilities`].
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+        if capabilities.hide_denied {
+            builtins::hide_denied(self);
+        }
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Sets the arguments returned by the `args` builtin.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// Defines a global variable, replacing any existing one with the same name.
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.environment.define_global(name, value);
+    }
+
+    /// Returns the value of a global variable, or `None` if it isn't defined. The value is
+    /// borrowed from the interpreter since it may be collected once the variable is reassigned,
+    /// it can be copied out with [`HostValue::from`](crate::HostValue).
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.environment.globals().get(name)
+    }
+
+    /// Global variables and their values, in no particular order. Like
+    /// [`Interpreter::get_global`], the values are borrowed from the interpreter.
+    pub fn globals(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.environment
+            .globals()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
     pub fn interpret(&mut self, script: &Script) -> Result<(), RuntimeError> {
+        self.evaluate_script(script).map(|_| ())
+    }
+
+    /// Runs a script like [`Interpreter::interpret`], returning the value of the script.
+    ///
+    /// The value of a script is the value it returns from the top level, otherwise the value of
+    /// its last declaration if that is an expression statement, otherwise nil. It is borrowed
+    /// from the interpreter, which keeps it alive until the next script is evaluated, and can be
+    /// copied out with [`HostValue::from`](crate::HostValue).
+    pub fn evaluate(&mut self, script: &Script) -> Result<&Value, RuntimeError> {
+        self.result = self.evaluate_script(script)?;
+
+        Ok(&self.result)
+    }
+
+    fn evaluate_script(&mut self, script: &Script) -> Result<Value, RuntimeError> {
+        let caller_base = self.environment.enter(script.slot_count);
+        let outer_script_base = std::mem::replace(&mut self.script_base, self.environment.base());
+        let outermost = !std::mem::replace(&mut self.running, true);
+        let start = outermost.then(|| self.clock.stopwatch()).flatten();
+        let result = self.script(script);
+        if outermost {
+            self.running = false;
+        }
+        if let Some(start) = start {
+            self.usage.time += start.elapsed();
+        }
+        self.script_base = outer_script_base;
+        self.environment.exit(caller_base);
+
+        result
+    }
+
+    /// Evaluates a script from within the native function which is being called, see
+    /// [`Interpreter::evaluate`]. The value is returned to the caller rather than rooted. Unlike code called back by natives, the script collects garbage
+    /// as it runs unless other natives are running below the caller, since they may hold values
+    /// the collector can't see. The caller's arguments are rooted, anything else it holds has to
+    /// be pushed onto `temporaries`.
+    fn evaluate_in_native(&mut self, script: &Script) -> Result<Value, RuntimeError> {
+        let native_depth = self.native_depth;
+        if native_depth == 1 {
+            self.native_depth = 0;
+        }
+        let result = self.evaluate_script(script);
+        self.native_depth = native_depth;
+
+        result
+    }
+
+    fn script(&mut self, script: &Script) -> Result<Value, RuntimeError> {
+        let mut value = Value::Nil;
         for decl in &script.decls {
-            self.decl(decl)?;
+            if let DeclKind::Stmt(stmt) = &decl.kind {
+                if let StmtKind::Expr(expr) = &stmt.kind {
+                    self.safe_point()?;
+                    self.debug_point(stmt.location)?;
+                    value = self.expr(expr)?;
+                    continue;
+                }
+            }
+
+            // A return statement at the top level ends the script.
+            match self.decl(decl)? {
+                ControlFlow::Break(returned) => return Ok(returned),
+                ControlFlow::Continue(()) => value = Value::Nil,
+            }
         }
 
-        Ok(())
+        Ok(value)
+    }
+
+    /// Frees every object that is no longer reachable from the environment.
+    ///
+    /// Scripts collect garbage as they run, this is for hosts which want to free memory between
+    /// scripts.
+    ///
+    /// # Safety
+    ///
+    /// Values held by the host which aren't reachable from a global variable, a module or a
+    /// snapshot must not be used after calling this, nor may values held by the native functions
+    /// being called.
+    pub unsafe fn collect_garbage(&mut self) {
+        let snapshots = self.snapshots.iter().flatten().flat_map(HashMap::values);
+        let roots = self
+            .environment
+            .values()
+            .chain(&self.temporaries)
+            .chain([&self.result])
+            .chain(&self.tests)
+            .chain(self.modules.values())
+            .chain(snapshots);
+
+        // SAFETY: the caller guarantees that values outside of the interpreter's roots are no
+        // longer used.
+        unsafe { self.heap.collect(roots) };
+        self.run_finalizers();
+    }
+
+    /// Calls the callbacks of finalizers whose targets have been freed.
+    ///
+    /// Finalizers are run in the order they were registered. Errors raised by a finalizer are
+    /// discarded, they don't interrupt the script that triggered the collection nor prevent the
+    /// remaining finalizers from running.
+    fn run_finalizers(&mut self) {
+        let callbacks = self.heap.take_pending_finalizers();
+        let base = self.temporaries.len();
+        self.temporaries.extend_from_slice(&callbacks);
+        for callback in &callbacks {
+            let _ = self.call(callback, &[]);
+        }
+        self.temporaries.truncate(base);
+    }
+
+    /// Saves the current value of every global variable so that they can be restored later.
+    ///
+    /// Only the variables are saved, not the objects they reference, so changes made to a list
+    /// or map after taking the snapshot are not undone by restoring it. Values referenced by the
+    /// snapshot are kept alive until it is discarded.
+    pub fn snapshot(&mut self) -> Snapshot {
+        let globals = Some(self.environment.globals().clone());
+        match self.snapshots.iter().position(Option::is_none) {
+            Some(index) => {
+                self.snapshots[index] = globals;
+                Snapshot(index)
+            }
+            None => {
+                self.snapshots.push(globals);
+                Snapshot(self.snapshots.len() - 1)
+            }
+        }
+    }
+
+    /// Sets the global variables back to the values saved in `snapshot`, variables defined after
+    /// it was taken are removed. The snapshot can be restored again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the snapshot has been discarded.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        let globals = self.snapshots[snapshot.0]
+            .clone()
+            .expect("snapshot should not have been discarded");
+        self.environment.set_globals(globals);
+    }
+
+    /// Frees a snapshot, the handle must not be used afterwards.
+    pub fn discard_snapshot(&mut self, snapshot: Snapshot) {
+        self.snapshots[snapshot.0] = None;
+    }
+
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
+    /// Defines a global native function.
+    pub fn define_native(&mut self, name: &'static str, arity: Arity, function: NativeFn) {
+        let native = self.heap.alloc(ObjNative::new(name, arity, function));
+        self.environment.define_global(name, Value::Obj(native));
+    }
+
+    /// Defines a global native function which may capture state, such as a function registered
+    /// by the host.
+    pub fn define_closure(
+        &mut self,
+        name: &'static str,
+        arity: Arity,
+        function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.define_native_closure(name, arity, Arc::new(function));
+    }
+
+    /// Defines a global native function from a Rust function with typed parameters, see
+    /// [`native`].
+    pub fn define_fn<Args, F: IntoNative<Args>>(&mut self, name: &'static str, function: F) {
+        self.define_native_closure(name, F::ARITY, function.into_native());
+    }
+
+    /// Exposes the Rust type `T` to scripts as a class, defining its constructor as a global if
+    /// it has one. See [`class`] for an example.
+    pub fn define_class<T: Any + Send>(&mut self, class: ClassBuilder<T>) {
+        let (class, constructor) = class.build();
+        let name = class.name();
+        self.classes.insert(TypeId::of::<T>(), Arc::new(class));
+        if let Some(constructor) = constructor {
+            self.define_native_closure(name, constructor.arity, constructor.function);
+        }
+    }
+
+    /// Makes a module importable by scripts as `name`, replacing any existing module with the
+    /// same name. See [`module`] for an example.
+    pub fn define_module(&mut self, name: &str, module: Module) {
+        let mut object = self.heap.alloc(ObjInstance::new());
+        for (function, arity, closure) in module.into_functions(&mut self.classes) {
+            let native = self
+                .heap
+                .alloc(ObjNative::with_closure(function, arity, closure));
+            AsMut::<ObjInstance>::as_mut(&mut object)
+                .fields_mut()
+                .insert(function.to_owned(), Value::Obj(native));
+        }
+
+        self.modules.insert(name.to_owned(), Value::Obj(object));
+    }
+
+    /// Adds a directory to search for plugins when a script imports a module which hasn't been
+    /// defined, directories are searched in the order they were added. See [`plugin`].
+    #[cfg(feature = "plugins")]
+    pub fn add_plugin_path(&mut self, path: PathBuf) {
+        self.plugin_paths.push(path);
+    }
+
+    /// Returns the module called `name`, loading it from a plugin the first time it is imported
+    /// if it isn't defined.
+    fn import(&mut self, name: &str) -> Result<Value, RuntimeErrorKind> {
+        if let Some(module) = self.modules.get(name) {
+            return Ok(module.clone());
+        }
+
+        #[cfg(feature = "plugins")]
+        {
+            if !self.capabilities.plugins {
+                return Err(RuntimeErrorKind::PermissionDenied("plugin"));
+            }
+            let (library, module) = plugin::load(name, &self.plugin_paths).map_err(|reason| {
+                RuntimeErrorKind::Import {
+                    module: name.to_owned(),
+                    reason,
+                }
+            })?;
+            self.plugins.push(library);
+            self.define_module(name, module);
+            Ok(self.modules[name].clone())
+        }
+        #[cfg(not(feature = "plugins"))]
+        Err(RuntimeErrorKind::Import {
+            module: name.to_owned(),
+            reason: "no such module".to_owned(),
+        })
+    }
+
+    /// Wraps a Rust value in a foreign object, which is an object of the class of `T` if one has
+    /// been defined.
+    pub fn alloc_object<T: Any + Send>(&mut self, data: T) -> Value {
+        let object = match self.classes.get(&TypeId::of::<T>()) {
+            Some(class) => ObjForeign::with_class(data, class.clone()),
+            None => ObjForeign::new(data),
+        };
+
+        Value::Obj(self.heap.alloc(object))
+    }
+
+    fn define_native_closure(
+        &mut self,
+        name: &'static str,
+        arity: Arity,
+        function: Arc<NativeClosure>,
+    ) {
+        let native = self
+            .heap
+            .alloc(ObjNative::with_closure(name, arity, function));
+        self.environment.define_global(name, Value::Obj(native));
+    }
+
+    /// Calls `callee` with `args`, this is used by native functions which accept callbacks.
+    pub fn call(&mut self, callee: &Value, args: &[Value]) -> Result<Value, RuntimeError> {
+        let base = self.temporaries.len();
+        self.temporaries.push(callee.clone());
+        self.temporaries.extend_from_slice(args);
+
+        let result = self.call_value(callee, args, None);
+        self.temporaries.truncate(base);
+
+        result
+    }
+
+    /// Calls `callee` like [`Interpreter::call`], from a builtin which keeps every value it holds
+    /// besides its arguments in `temporaries`. Unlike other code called back by natives, the
+    /// callback collects garbage as it runs unless other natives are running below the caller.
+    pub(crate) fn call_collecting(
+        &mut self,
+        callee: &Value,
+        args: &[Value],
+    ) -> Result<Value, RuntimeError> {
+        let native_depth = self.native_depth;
+        if native_depth == 1 {
+            self.native_depth = 0;
+        }
+        let result = self.call(callee, args);
+        self.native_depth = native_depth;
+
+        result
+    }
+
+    /// Returns the names of the tests declared by the scripts which have run, in the order they
+    /// were declared. Tests don't run with the script, see [`Interpreter::run_test`].
+    pub fn tests(&self) -> Vec<&str> {
+        self.tests
+            .iter()
+            .filter_map(|test| Some(test.as_obj()?.downcast::<ObjFunction>()?.decl()))
+            .map(|decl| decl.name.as_ref())
+            .collect()
+    }
+
+    /// Runs the test at the index of [`Interpreter::tests`], it fails if it raises an error.
+    pub fn run_test(&mut self, index: usize) -> Result<(), RuntimeError> {
+        let test = self.tests[index].clone();
+        self.call(&test, &[]).map(|_| ())
+    }
+
+    /// Returns the active function calls, innermost last.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Sets the hook called before each statement, returning the previous one.
+    pub fn set_debug_hook(
+        &mut self,
+        hook: Option<Box<dyn DebugHook>>,
+    ) -> Option<Box<dyn DebugHook>> {
+        std::mem::replace(&mut self.debug_hook, hook)
+    }
+
+    /// Sets the host's instrumentation, returning the previous one. See [`instrument`].
+    pub fn set_instrument(&mut self, instrument: Option<Instrument>) -> Option<Instrument> {
+        std::mem::replace(&mut self.instrument, instrument)
+    }
+
+    /// Returns the value of a local variable of the frame at the given index of
+    /// [`Interpreter::frames`], or of the script being run if `frame` is `None`. Returns `None`
+    /// for globals and variables which can't be read, such as captured variables which haven't
+    /// been declared yet.
+    pub fn frame_variable(&self, frame: Option<usize>, slot: Slot) -> Option<Value> {
+        let (base, closure) = match frame {
+            Some(index) => {
+                let frame = self.frames.get(index)?;
+                (frame.base, frame.closure.as_ref())
+            }
+            None => (self.script_base, None),
+        };
+
+        let cell = match slot {
+            Slot::Global => return None,
+            Slot::Local(slot) => return self.environment.slot(base + slot),
+            Slot::Cell(slot) => self.environment.slot(base + slot)?,
+            Slot::Capture(index) => {
+                let function = closure?.downcast::<ObjFunction>()?;
+                function.captured().get(index)?.clone()
+            }
+        };
+
+        Some(cell.as_obj()?.downcast::<ObjCell>()?.get().clone())
+    }
+
+    /// Calls the debug hook, if there is one, before a statement at the location.
+    fn debug_point(&mut self, location: Location) -> Result<(), RuntimeError> {
+        self.instrument_point(location)?;
+        if self.debug_hook.is_none() {
+            return Ok(());
+        }
+
+        let mut hook = self.debug_hook.take().unwrap();
+        let result = hook.statement(self, location);
+        // The hook may have been replaced while it was running.
+        self.debug_hook.get_or_insert(hook);
+
+        result
+    }
+
+    fn instrument_point(&mut self, location: Location) -> Result<(), RuntimeError> {
+        let Some(mut instrument) = self.instrument.take() else {
+            return Ok(());
+        };
+        let result = instrument.statement(self, location);
+        // The instrumentation may have been replaced while it was running.
+        self.instrument.get_or_insert(instrument);
+
+        result
+    }
+
+    fn call_value(
+        &mut self,
+        callee: &Value,
+        args: &[Value],
+        call_location: Option<Location>,
+    ) -> Result<Value, RuntimeError> {
+        let Some(obj) = callee.as_obj() else {
+            return Err(RuntimeErrorKind::NotCallable(callee.type_name()).into());
+        };
+
+        if self.frames.len() >= self.max_call_depth {
+            return Err(RuntimeErrorKind::StackOverflow(self.max_call_depth).into());
+        }
+
+        match obj.kind() {
+            ObjKind::Function => {
+                let function = obj.downcast::<ObjFunction>().unwrap();
+                check_arity(function.arity(), args.len()).map_err(|error| {
+                    let name = &function.decl().name;
+                    let message = format!("'{}' declared here", name.as_ref());
+                    error.with_label(Label::new(name.location(), message))
+                })?;
+
+                let decl = function.decl().clone();
+                // Parameters occupy the first slots.
+                let caller_base = self.environment.enter(decl.slot_count);
+                for (param, arg) in decl.params.iter().zip(args) {
+                    self.define(param, arg.clone());
+                }
+
+                self.frames.push(Frame {
+                    function: decl.name.as_ref().to_owned(),
+                    call_location,
+                    closure: Some(obj.clone()),
+                    base: self.environment.base(),
+                });
+
+                let result = self.stmt(&decl.body);
+
+                self.environment.exit(caller_base);
+                let frame = self
+                    .frames
+                    .pop()
+                    .expect("call frame should have been pushed");
+
+                let result = result.map_err(|error| error.unwind(frame.function, call_location));
+                match result? {
+                    ControlFlow::Break(value) => Ok(value),
+                    ControlFlow::Continue(()) => Ok(Value::Nil),
+                }
+            }
+            ObjKind::Native => {
+                let native = obj.downcast::<ObjNative>().unwrap();
+                check_arity(native.arity(), args.len())?;
+
+                let name = native.name().to_owned();
+                let function = native.function();
+                self.call_native(name, args, call_location, |interpreter, args| {
+                    function(interpreter, args)
+                })
+            }
+            ObjKind::Bound => {
+                let bound = obj.downcast::<ObjBound>().unwrap();
+                check_arity(bound.arity(), args.len())?;
+
+                let name = bound.name().to_owned();
+                let function = bound.function();
+                // The bound object is rooted by the caller so its captured values stay alive.
+                let captured = bound.captured().to_vec();
+                self.call_native(name, args, call_location, |interpreter, args| {
+                    function(interpreter, &captured, args)
+                })
+            }
+            ObjKind::Weak => {
+                check_arity(Arity::Exact(0), args.len())?;
+                let target = obj.downcast::<ObjWeak>().unwrap().target();
+
+                Ok(target.cloned().map_or(Value::Nil, Value::Obj))
+            }
+            _ => Err(RuntimeErrorKind::NotCallable(callee.type_name()).into()),
+        }
+    }
+
+    /// Calls a native function in a new frame called `name`, arguments should already have been
+    /// checked.
+    fn call_native(
+        &mut self,
+        name: String,
+        args: &[Value],
+        call_location: Option<Location>,
+        function: impl FnOnce(&mut Self, &[Value]) -> Result<Value, RuntimeError>,
+    ) -> Result<Value, RuntimeError> {
+        if self.frames.len() >= self.max_call_depth {
+            return Err(RuntimeErrorKind::StackOverflow(self.max_call_depth).into());
+        }
+
+        self.frames.push(Frame {
+            function: name,
+            call_location,
+            closure: None,
+            base: self.environment.base(),
+        });
+        self.native_depth += 1;
+        let result = function(self, args);
+        self.native_depth -= 1;
+        let frame = self
+            .frames
+            .pop()
+            .expect("call frame should have been pushed");
+
+        result.map_err(|error| error.unwind(frame.function, call_location))
     }
 
-    fn decl(&mut self, decl: &Decl) -> Result<(), RuntimeError> {
-        match decl {
-            Decl::Var(name, init_expr) => {
+    fn decl(&mut self, decl: &Decl) -> Exec {
+        if !matches!(&decl.kind, DeclKind::Stmt(_)) {
+            self.debug_point(decl.location)?;
+        }
+
+        match &decl.kind {
+            DeclKind::Var(name, init_expr) => {
                 let value = if let Some(init_expr) = init_expr {
                     self.expr(init_expr)?
                 } else {
                     Value::Nil
                 };
 
-                self.environment.define(name.as_ref(), value);
+                self.define(name, value);
+            }
+            DeclKind::Import(name) => {
+                let module = self
+                    .import(name.as_ref())
+                    .map_err(|kind| RuntimeError::new(kind, name.location()))?;
+                self.define(name, module);
+            }
+            DeclKind::Func(decl) => {
+                let function = self.heap.alloc(ObjFunction::new(decl.clone(), vec![]));
+                // The function is defined before capturing so that it can capture itself.
+                self.define(&decl.name, Value::Obj(function.clone()));
+                self.capture(function, decl);
+            }
+            DeclKind::Test(decl) => {
+                let function = self.heap.alloc(ObjFunction::new(decl.clone(), vec![]));
+                self.capture(function.clone(), decl);
+                self.tests.push(Value::Obj(function));
+            }
+            DeclKind::Stmt(stmt) => return self.stmt(stmt),
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Stores the variables the function captures in its object.
+    fn capture(&mut self, mut function: Obj, decl: &Function) {
+        let captured = decl
+            .captures
+            .iter()
+            .map(|capture| match *capture {
+                Capture::Local(slot) => self.environment.local(slot),
+                Capture::Capture(index) => self.captured(index),
+            })
+            .collect();
+        *AsMut::<ObjFunction>::as_mut(&mut function).captured_mut() = captured;
+    }
+
+    /// Defines a variable declared by `let` or `func`.
+    fn define(&mut self, name: &Identifier, value: Value) {
+        match name.slot() {
+            Slot::Global => self.environment.define_global(name.as_ref(), value),
+            Slot::Local(slot) => self.environment.set_local(slot, value),
+            Slot::Cell(slot) => {
+                let cell = self.heap.alloc(ObjCell::new(value));
+                self.environment.set_local(slot, Value::Obj(cell));
+            }
+            Slot::Capture(_) => unreachable!("declarations should never be captures"),
+        }
+    }
+
+    fn variable(&self, name: &Identifier) -> Result<Value, RuntimeError> {
+        match name.slot() {
+            Slot::Global => self
+                .environment
+                .get_global(name.as_ref())
+                .ok_or_else(|| self.undefined_variable(name)),
+            Slot::Local(slot) => Ok(self.environment.local(slot)),
+            slot @ (Slot::Cell(_) | Slot::Capture(_)) => {
+                Ok(AsRef::<ObjCell>::as_ref(&self.cell(slot)).get().clone())
+            }
+        }
+    }
+
+    /// Unresolved names are always globals, so the suggestion is the closest global.
+    fn undefined_variable(&self, name: &Identifier) -> RuntimeError {
+        let kind = RuntimeErrorKind::UndefinedVariable(name.as_ref().to_owned());
+        let globals = self.environment.globals().keys().map(String::as_str);
+        let error = RuntimeError::new(kind, name.location());
+        match suggest(name.as_ref(), globals) {
+            Some(suggestion) => error.with_suggestion(suggestion),
+            None => error,
+        }
+    }
+
+    fn assign(&mut self, name: &Identifier, value: Value) -> Result<(), RuntimeError> {
+        match name.slot() {
+            Slot::Global => {
+                self.environment
+                    .set_global(name.as_ref(), value)
+                    .ok_or_else(|| self.undefined_variable(name))?;
+            }
+            Slot::Local(slot) => self.environment.set_local(slot, value),
+            slot @ (Slot::Cell(_) | Slot::Capture(_)) => {
+                AsMut::<ObjCell>::as_mut(&mut self.cell(slot)).set(value);
             }
-            Decl::Stmt(stmt) => self.stmt(stmt)?,
         }
 
         Ok(())
     }
 
-    fn stmt(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
-        match stmt {
-            Stmt::Block(decls) => {
-                self.environment.push();
-                for decl in decls {
-                    self.decl(decl)?;
-                }
-                self.environment.pop();
+    /// Returns the function currently being executed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no script function is being executed.
+    fn closure(&self) -> &Obj {
+        self.frames
+            .last()
+            .and_then(|frame| frame.closure.as_ref())
+            .expect("captured variables should only be used inside functions")
+    }
+
+    /// Returns the cell captured by the current function at `index`.
+    fn captured(&self, index: usize) -> Value {
+        AsRef::<ObjFunction>::as_ref(self.closure()).captured()[index].clone()
+    }
+
+    /// Returns the cell a captured variable is stored in.
+    fn cell(&self, slot: Slot) -> Obj {
+        let cell = match slot {
+            Slot::Cell(slot) => self.environment.local(slot),
+            Slot::Capture(index) => self.captured(index),
+            _ => unreachable!("only captured variables are stored in cells"),
+        };
+
+        cell.as_obj()
+            .cloned()
+            .expect("captured variables should be stored in cells")
+    }
+
+    /// Returns an error if allocating `bytes` more would exceed the memory limit. Builtins which
+    /// allocate as much as a script asks for check this first, since an allocation which is too
+    /// large to ever succeed aborts the process.
+    fn check_allocation(&self, bytes: usize) -> Result<(), RuntimeError> {
+        match self.heap.memory_limit() {
+            Some(limit) if self.heap.bytes_allocated().saturating_add(bytes) > limit => {
+                Err(RuntimeErrorKind::OutOfMemory(limit).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs the checks which happen before every statement: collecting garbage, enforcing the
+    /// memory limit, consuming fuel and stopping if interrupted.
+    fn safe_point(&mut self) -> Result<(), RuntimeError> {
+        // Statement boundaries are the only place where we collect garbage.
+        if self.heap.should_collect() && self.native_depth == 0 {
+            // SAFETY: no natives which may hold values are running, so every value that is in
+            // use is either in the environment or in `temporaries` and every reachable object is
+            // found by tracing them.
+            unsafe { self.collect_garbage() };
+        }
+
+        self.tick()?;
+        self.usage.statements += 1;
+
+        Ok(())
+    }
+
+    /// Runs the checks of [`Interpreter::safe_point`] which don't collect garbage, for natives
+    /// which loop as long as scripts ask them to. Uses a unit of fuel.
+    fn tick(&mut self) -> Result<(), RuntimeError> {
+        // If we are still over the limit after collecting then the memory is actually in use.
+        if self.heap.is_over_limit() {
+            let limit = self.heap.memory_limit().unwrap();
+            return Err(RuntimeErrorKind::OutOfMemory(limit).into());
+        }
+
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                return Err(RuntimeErrorKind::OutOfFuel.into());
             }
-            Stmt::Expr(expr) => {
+            *fuel -= 1;
+        }
+
+        if self.interrupt.take() {
+            return Err(RuntimeErrorKind::Interrupted.into());
+        }
+
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Exec {
+        self.safe_point()?;
+        // Blocks aren't statements of their own to a debugger, only their contents are. They are
+        // instrumented so that hosts see every iteration of loops with empty bodies.
+        if matches!(&stmt.kind, StmtKind::Block(_)) {
+            self.instrument_point(stmt.location)?;
+        } else {
+            self.debug_point(stmt.location)?;
+        }
+
+        match &stmt.kind {
+            StmtKind::Block(decls) => return self.block(decls),
+            StmtKind::Expr(expr) => {
                 self.expr(expr)?;
             }
-            Stmt::If(predicate, consequent, alternative) => {
+            StmtKind::If(predicate, consequent, alternative) => {
                 if self.expr(predicate)?.is_truthy() {
-                    self.stmt(consequent)?;
+                    return self.stmt(consequent);
                 } else if let Some(alternative) = alternative {
-                    self.stmt(alternative)?;
+                    return self.stmt(alternative);
                 }
             }
-            Stmt::While(predicate, consequent) => {
+            StmtKind::While(predicate, consequent) => {
                 while self.expr(predicate)?.is_truthy() {
-                    self.stmt(consequent)?;
+                    let flow = self.stmt(consequent)?;
+                    if flow.is_break() {
+                        return Ok(flow);
+                    }
                 }
             }
+            StmtKind::For(name, iterable, body) => {
+                // The items are kept in `temporaries` so that they aren't collected if the body
+                // removes them from the iterable.
+                let base = self.temporaries.len();
+                let result = self.for_stmt(name, iterable, body, base);
+                self.temporaries.truncate(base);
+
+                return result;
+            }
+            StmtKind::Return(expr, _) => {
+                let value = if let Some(expr) = expr {
+                    self.expr(expr)?
+                } else {
+                    Value::Nil
+                };
+
+                return Ok(ControlFlow::Break(value));
+            }
         }
 
-        Ok(())
+        Ok(ControlFlow::Continue(()))
     }
 
-    fn expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
-        match expr {
-            Expr::Literal(val) => Ok(*val),
-            Expr::Identifier(name) => self
-                .environment
-                .get(name.as_ref())
-                .ok_or(RuntimeError::UndefinedVariable),
-            Expr::Assignment(target, expr) => {
-                let right = self.expr(expr)?;
-                if let Expr::Identifier(name) = target.as_ref() {
-                    self.environment
-                        .set(name.as_ref(), right)
-                        .ok_or(RuntimeError::UndefinedVariable)?;
+    fn for_stmt(&mut self, name: &Identifier, iterable: &Expr, body: &Stmt, base: usize) -> Exec {
+        let iterable = self.expr(iterable)?;
+        let obj = iterable
+            .as_obj()
+            .filter(|obj| matches!(obj.kind(), ObjKind::List | ObjKind::Map | ObjKind::Iterator));
+        let Some(obj) = obj else {
+            return Err(RuntimeErrorKind::NotIterable(iterable.type_name()).into());
+        };
 
-                    Ok(right)
-                } else {
-                    unimplemented!()
+        // Iterators are advanced lazily, the iterator itself is kept alive as a temporary.
+        if obj.kind() == ObjKind::Iterator {
+            self.temporaries.push(iterable.clone());
+            while let Some(item) = iterator::next(self, obj)? {
+                self.define(name, item);
+                let flow = self.stmt(body)?;
+                if flow.is_break() {
+                    return Ok(flow);
                 }
             }
-            Expr::Binary(op, left, right) if *op == Operator::Or || *op == Operator::And => {
+
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        // Changes made to the iterable by the body don't affect which items are visited.
+        match obj.kind() {
+            ObjKind::List => {
+                let items = AsRef::<ObjList>::as_ref(obj).items();
+                self.temporaries.extend_from_slice(items);
+            }
+            ObjKind::Map => {
+                let entries = AsRef::<ObjMap>::as_ref(obj).entries();
+                self.temporaries
+                    .extend(entries.keys().map(|key| key.value().clone()));
+            }
+            _ => unreachable!(),
+        }
+
+        for i in base..self.temporaries.len() {
+            let item = self.temporaries[i].clone();
+            self.define(name, item);
+            let flow = self.stmt(body)?;
+            if flow.is_break() {
+                return Ok(flow);
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn block(&mut self, decls: &[Decl]) -> Exec {
+        for decl in decls {
+            let flow = self.decl(decl)?;
+            if flow.is_break() {
+                return Ok(flow);
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match &expr.kind {
+            ExprKind::Literal(literal) => Ok(self.literal(literal)),
+            ExprKind::Identifier(name) => self.variable(name),
+            ExprKind::Assignment(target, expr) => self.assignment_expr(target, expr),
+            ExprKind::CompoundAssignment(..) => {
+                unreachable!("compound assignments should be lowered before running")
+            }
+            ExprKind::Binary(op, left, right)
+                if op.node == Operator::Or || op.node == Operator::And =>
+            {
                 let left = self.expr(left)?;
                 let mut short_circuit = left.is_truthy();
                 // For the 'and' operator we want to short circuit if the left
                 // operand is not truthy.
-                if *op == Operator::And {
+                if op.node == Operator::And {
                     short_circuit = !short_circuit;
                 }
 
@@ -103,106 +1175,1144 @@ impl Interpreter {
                     Ok(right)
                 }
             }
-            Expr::Binary(op, left, right) => {
-                let left = self.expr(left)?;
-                let right = self.expr(right)?;
-
-                let value = match op {
-                    Operator::Eq => Value::Bool(left == right),
-                    Operator::Ne => Value::Bool(left != right),
-                    Operator::Lt => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Bool(left < right)
-                    }
-                    Operator::Gt => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Bool(left > right)
-                    }
-                    Operator::Le => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Bool(left <= right)
-                    }
-                    Operator::Ge => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Bool(left >= right)
-                    }
-                    Operator::Add => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Number(left + right)
-                    }
-                    Operator::Sub => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Number(left - right)
-                    }
-                    Operator::Mul => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Number(left * right)
-                    }
-                    Operator::Div => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        if right == 0.0 {
-                            return Err(RuntimeError::DivisionByZero);
-                        }
-                        Value::Number(left / right)
-                    }
-                    Operator::Mod => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        if right == 0.0 {
-                            return Err(RuntimeError::DivisionByZero);
-                        }
-                        Value::Number(left % right)
-                    }
-                    Operator::Exp => {
-                        let (left, right) = check_number_operands(&left, &right)?;
-                        Value::Number(left.powf(right))
-                    }
-                    _ => unreachable!(),
-                };
+            ExprKind::Binary(op, left, right) => self.binary_expr(*op, left, right),
+            ExprKind::Unary(op, expr) => self.unary_expr(*op, expr),
+            ExprKind::Call(callee, args, location) => {
+                // The callee and arguments are kept in `temporaries` until the call returns so
+                // they aren't collected while evaluating the remaining arguments.
+                let base = self.temporaries.len();
+                let result = self.call_expr(callee, args, *location, base);
+                self.temporaries.truncate(base);
 
-                Ok(value)
+                // Errors raised by native functions don't have a location so we use the location
+                // of the call.
+                result.map_err(|error| error.or_location(*location))
             }
-            Expr::Unary(op, expr) => {
-                let right = self.expr(expr)?;
-                let value = match op {
-                    Operator::Not => {
-                        if let Value::Bool(b) = right {
-                            Value::Bool(!b)
-                        } else {
-                            return Err(RuntimeError::InvalidOperand);
-                        }
-                    }
-                    Operator::Sub => {
-                        if let Value::Number(n) = right {
-                            Value::Number(-n)
-                        } else {
-                            return Err(RuntimeError::InvalidOperand);
-                        }
-                    }
-                    _ => unreachable!(),
+            ExprKind::Index(object, index, location) => self.index_expr(object, index, *location),
+            ExprKind::Member(object, name) => {
+                let object = self.expr(object)?;
+                let class = object.as_foreign().and_then(ObjForeign::class);
+                match class.and_then(|class| class.property(name.as_ref())) {
+                    Some(getter) => getter.clone()(self, &[object]),
+                    None => get_field(&object, name),
+                }
+            }
+            ExprKind::List(items) => {
+                let base = self.temporaries.len();
+                let result = self.list_expr(items, base);
+                self.temporaries.truncate(base);
+
+                result
+            }
+            ExprKind::Map(entries) => {
+                let base = self.temporaries.len();
+                let result = self.map_expr(entries, base);
+                self.temporaries.truncate(base);
+
+                result
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> Value {
+        match literal {
+            Literal::Number(n) => Value::Number(*n),
+            Literal::String(s) => self.heap.alloc_string(s.as_str()),
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Nil => Value::Nil,
+        }
+    }
+
+    fn binary_expr(
+        &mut self,
+        op: Spanned<Operator>,
+        left: &Expr,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let left = self.expr(left)?;
+        self.temporaries.push(left.clone());
+        let right = self.expr(right);
+        self.temporaries.pop();
+        let right = right?;
+
+        self.binary(op.node, left, right)
+            .map_err(|kind| RuntimeError::new(kind, op.location))
+    }
+
+    fn unary_expr(&mut self, op: Spanned<Operator>, expr: &Expr) -> Result<Value, RuntimeError> {
+        let right = self.expr(expr)?;
+        let value = match (op.node, right) {
+            (Operator::Not, Value::Bool(b)) => Value::Bool(!b),
+            (Operator::Sub, Value::Number(n)) => Value::Number(-n),
+            (operator, right) => {
+                let kind = RuntimeErrorKind::InvalidOperand {
+                    operator,
+                    operand: right.type_name(),
                 };
+                return Err(RuntimeError::new(kind, op.location));
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn assignment_expr(&mut self, target: &Expr, expr: &Expr) -> Result<Value, RuntimeError> {
+        match &target.kind {
+            ExprKind::Identifier(name) => {
+                let right = self.expr(expr)?;
+                self.assign(name, right.clone())?;
+
+                Ok(right)
+            }
+            ExprKind::Index(object, index, location) => {
+                let base = self.temporaries.len();
+                let result = self.set_index_expr(object, index, expr, *location);
+                self.temporaries.truncate(base);
+
+                result
+            }
+            // The parser rejects every other target.
+            _ => unreachable!("invalid assignment target"),
+        }
+    }
+
+    fn index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        location: Location,
+    ) -> Result<Value, RuntimeError> {
+        let object = self.expr(object)?;
+        self.temporaries.push(object.clone());
+        let index = self.expr(index);
+        self.temporaries.pop();
+
+        get_index(&object, &index?).map_err(|kind| RuntimeError::new(kind, location))
+    }
+
+    /// Evaluates the items of a list literal onto `temporaries`, starting at `base`, and then
+    /// moves them into a new list.
+    fn list_expr(&mut self, items: &[Expr], base: usize) -> Result<Value, RuntimeError> {
+        for item in items {
+            let item = self.expr(item)?;
+            self.temporaries.push(item);
+        }
+
+        let items = self.temporaries.drain(base..).collect();
+        Ok(Value::Obj(self.heap.alloc(ObjList::new(items))))
+    }
+
+    /// Same as `list_expr` but for map literals, keys and values are interleaved on
+    /// `temporaries`.
+    fn map_expr(&mut self, entries: &[(Expr, Expr)], base: usize) -> Result<Value, RuntimeError> {
+        for (key, value) in entries {
+            let key = self.expr(key)?;
+            self.temporaries.push(key);
+            let value = self.expr(value)?;
+            self.temporaries.push(value);
+        }
+
+        let mut table = Table::new();
+        let mut values = self.temporaries.drain(base..);
+        while let (Some(key), Some(value)) = (values.next(), values.next()) {
+            table.insert(ValueKey::new(key), value);
+        }
+        drop(values);
+
+        Ok(Value::Obj(self.heap.alloc(ObjMap::new(table))))
+    }
+
+    fn set_index_expr(
+        &mut self,
+        object: &Expr,
+        index: &Expr,
+        value: &Expr,
+        location: Location,
+    ) -> Result<Value, RuntimeError> {
+        let object = self.expr(object)?;
+        self.temporaries.push(object.clone());
+        let index = self.expr(index)?;
+        self.temporaries.push(index.clone());
+        let value = self.expr(value)?;
+
+        set_index(&mut self.heap, &object, index, value.clone())
+            .map_err(|kind| RuntimeError::new(kind, location))?;
+
+        Ok(value)
+    }
+
+    fn binary(
+        &mut self,
+        op: Operator,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, RuntimeErrorKind> {
+        let value = match op {
+            Operator::Eq => Value::Bool(left == right),
+            Operator::Ne => Value::Bool(left != right),
+            Operator::Lt => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Bool(left < right)
+            }
+            Operator::Gt => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Bool(left > right)
+            }
+            Operator::Le => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Bool(left <= right)
+            }
+            Operator::Ge => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Bool(left >= right)
+            }
+            Operator::Add => {
+                if let (Some(left), Some(right)) = (left.as_str(), right.as_str()) {
+                    let string = [left, right].concat();
+                    self.heap.alloc_string(string)
+                } else {
+                    let (left, right) = check_number_operands(op, &left, &right)?;
+                    Value::Number(left + right)
+                }
+            }
+            Operator::Sub => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Number(left - right)
+            }
+            Operator::Mul => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Number(left * right)
+            }
+            Operator::Div => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                if right == 0.0 {
+                    return Err(RuntimeErrorKind::DivisionByZero);
+                }
+                Value::Number(left / right)
+            }
+            Operator::Mod => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                if right == 0.0 {
+                    return Err(RuntimeErrorKind::DivisionByZero);
+                }
+                Value::Number(left % right)
+            }
+            Operator::Exp => {
+                let (left, right) = check_number_operands(op, &left, &right)?;
+                Value::Number(left.powf(right))
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(value)
+    }
+
+    fn call_expr(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        location: Location,
+        base: usize,
+    ) -> Result<Value, RuntimeError> {
+        if let ExprKind::Member(receiver, name) = &callee.kind {
+            return self.method_call_expr(receiver, name, args, location, base);
+        }
 
-                Ok(value)
+        let callee = self.expr(callee)?;
+        self.temporaries.push(callee.clone());
+        for arg in args {
+            let arg = self.expr(arg)?;
+            self.temporaries.push(arg);
+        }
+
+        let args = self.temporaries[base + 1..].to_vec();
+        self.call_value(&callee, &args, Some(location))
+    }
+
+    /// Calls the member `name` of `receiver`, fields of instances take precedence over methods.
+    fn method_call_expr(
+        &mut self,
+        receiver: &Expr,
+        name: &Identifier,
+        args: &[Expr],
+        location: Location,
+        base: usize,
+    ) -> Result<Value, RuntimeError> {
+        let receiver = self.expr(receiver)?;
+        self.temporaries.push(receiver.clone());
+        for arg in args {
+            let arg = self.expr(arg)?;
+            self.temporaries.push(arg);
+        }
+
+        // The receiver is passed to methods as the first argument.
+        let args = self.temporaries[base..].to_vec();
+        if let Some(method) = methods::lookup(&receiver, name.as_ref()) {
+            check_arity(method.arity, args.len() - 1)?;
+            let name = format!("{}.{}", receiver.type_name(), method.name);
+            return self.call_native(name, &args, Some(location), method.function);
+        }
+        let class = receiver.as_foreign().and_then(ObjForeign::class);
+        if let Some(class) = class {
+            if let Some(method) = class.method(name.as_ref()) {
+                check_arity(method.arity, args.len() - 1)?;
+                let name = format!("{}.{}", class.name(), name.as_ref());
+                let function = method.function.clone();
+                return self.call_native(name, &args, Some(location), |interpreter, args| {
+                    function(interpreter, args)
+                });
             }
         }
+
+        let callee = get_field(&receiver, name)?;
+        self.call_value(&callee, &args[1..], Some(location))
+    }
+}
+
+fn check_arity(arity: Arity, found: usize) -> Result<(), RuntimeError> {
+    if arity.accepts(found) {
+        Ok(())
+    } else {
+        Err(RuntimeErrorKind::ArityMismatch {
+            expected: arity,
+            found,
+        }
+        .into())
     }
 }
 
-pub fn check_number_operands(a: &Value, b: &Value) -> Result<(f64, f64), RuntimeError> {
+pub fn check_number_operands(
+    operator: Operator,
+    a: &Value,
+    b: &Value,
+) -> Result<(f64, f64), RuntimeErrorKind> {
     if let (Value::Number(a), Value::Number(b)) = (a, b) {
         Ok((*a, *b))
     } else {
-        Err(RuntimeError::InvalidOperand)
+        Err(RuntimeErrorKind::InvalidOperands {
+            operator,
+            left: a.type_name(),
+            right: b.type_name(),
+        })
     }
 }
 
-// Currently we just keep track of which type of error occured, we need to change this so it
-// contains a span so we can report to the user where the error occured.
-#[derive(Debug, Error, Clone, Copy)]
-pub enum RuntimeError {
-    #[error("unsupported operand type")]
-    InvalidOperand,
+/// The details are boxed to keep results small since runtime errors are returned from almost
+/// every function in the interpreter.
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("{}", .0.kind)]
+pub struct RuntimeError(Box<RuntimeErrorData>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct RuntimeErrorData {
+    kind: RuntimeErrorKind,
+    /// Location of the expression which raised the error, this is only missing for errors
+    /// raised outside of the interpreter, such as those from conversions between values and Rust
+    /// types.
+    location: Option<Location>,
+    /// Function calls the error has unwound through, innermost first.
+    backtrace: Vec<TraceFrame>,
+    /// Location of the call expression of the outermost frame in `backtrace`.
+    outer_call_location: Option<Location>,
+    /// Name which was likely meant, for errors caused by misspelled names.
+    suggestion: Option<String>,
+    /// Other code involved in the error, such as the declaration of a function called with the
+    /// wrong number of arguments.
+    labels: Vec<Label>,
+}
+
+/// Function call which was active when a runtime error was raised.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub function: String,
+    /// Location which was being executed in the function, this is `None` for native functions.
+    pub location: Option<Location>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind, location: Location) -> Self {
+        let mut error = Self::from(kind);
+        error.0.location = Some(location);
+        error
+    }
+
+    pub fn kind(&self) -> &RuntimeErrorKind {
+        &self.0.kind
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.0.location
+    }
+
+    /// Returns the function calls which were active when the error was raised, innermost first.
+    /// The outermost frame is the script itself, unless the error was raised outside of any
+    /// function call in which case there are no frames.
+    pub fn backtrace(&self) -> Vec<TraceFrame> {
+        let mut backtrace = self.0.backtrace.clone();
+        if !backtrace.is_empty() {
+            backtrace.push(TraceFrame {
+                function: "<script>".to_owned(),
+                location: self.0.outer_call_location,
+            });
+        }
+
+        backtrace
+    }
+
+    /// Records that the error unwound out of a call to `function`.
+    fn unwind(mut self, function: String, call_location: Option<Location>) -> Self {
+        // The location within the function is that of the error itself if this is the first
+        // frame, otherwise it's the call expression of the previous frame.
+        let location = if self.0.backtrace.is_empty() {
+            self.0.location
+        } else {
+            self.0.outer_call_location
+        };
+
+        self.0.backtrace.push(TraceFrame { function, location });
+        self.0.outer_call_location = call_location;
+        self
+    }
+
+    pub fn suggestion(&self) -> Option<&str> {
+        self.0.suggestion.as_deref()
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.0.suggestion = Some(suggestion.into());
+        self
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.0.labels
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.0.labels.push(label);
+        self
+    }
+
+    /// Sets the location if the error doesn't have one yet.
+    pub fn or_location(mut self, location: Location) -> Self {
+        self.0.location.get_or_insert(location);
+        self
+    }
+}
+
+impl From<RuntimeErrorKind> for RuntimeError {
+    fn from(kind: RuntimeErrorKind) -> Self {
+        Self(Box::new(RuntimeErrorData {
+            kind,
+            location: None,
+            backtrace: vec![],
+            outer_call_location: None,
+            suggestion: None,
+            labels: vec![],
+        }))
+    }
+}
+
+/// Gets the field `name` of an instance.
+fn get_field(object: &Value, name: &Identifier) -> Result<Value, RuntimeError> {
+    let field = object
+        .as_obj()
+        .and_then(|obj| obj.downcast::<ObjInstance>())
+        .and_then(|instance| instance.fields().get(name.as_ref()).cloned());
+
+    field.ok_or_else(|| {
+        let kind = RuntimeErrorKind::UndefinedMember {
+            type_name: object.type_name(),
+            name: name.as_ref().to_owned(),
+        };
+        let error = RuntimeError::new(kind, name.location());
+
+        let fields: Vec<&str> = object
+            .as_obj()
+            .and_then(|obj| obj.downcast::<ObjInstance>())
+            .map(|instance| instance.fields().keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        let methods = methods::methods_of(object).iter().map(|method| method.name);
+        let members: Vec<&str> = object
+            .as_foreign()
+            .and_then(ObjForeign::class)
+            .map(|class| class.members().collect())
+            .unwrap_or_default();
+        let names = fields.into_iter().chain(methods).chain(members);
+        match suggest(name.as_ref(), names) {
+            Some(suggestion) => error.with_suggestion(suggestion),
+            None => error,
+        }
+    })
+}
+
+/// Looks up `index` in a list, bytes or map, missing map keys evaluate to nil.
+fn get_index(object: &Value, index: &Value) -> Result<Value, RuntimeErrorKind> {
+    let obj = object
+        .as_obj()
+        .ok_or(RuntimeErrorKind::NotIndexable(object.type_name()))?;
+    match obj.kind() {
+        ObjKind::List => {
+            let items = AsRef::<ObjList>::as_ref(obj).items();
+            let i = check_index(index, items.len(), "list")?;
+            Ok(items[i].clone())
+        }
+        ObjKind::Bytes => {
+            let bytes = AsRef::<ObjBytes>::as_ref(obj).bytes();
+            let i = check_index(index, bytes.len(), "bytes")?;
+            Ok(Value::from(i64::from(bytes[i])))
+        }
+        ObjKind::Map => {
+            let entries = AsRef::<ObjMap>::as_ref(obj).entries();
+            Ok(entries
+                .get(&ValueKey::new(index.clone()))
+                .cloned()
+                .unwrap_or_default())
+        }
+        _ => Err(RuntimeErrorKind::NotIndexable(object.type_name())),
+    }
+}
+
+/// Sets `index` of a list or map to `value`, lists can't be extended by assigning past their end.
+fn set_index(
+    heap: &mut Heap,
+    object: &Value,
+    index: Value,
+    value: Value,
+) -> Result<(), RuntimeErrorKind> {
+    let mut obj = object
+        .as_obj()
+        .cloned()
+        .ok_or(RuntimeErrorKind::NotIndexable(object.type_name()))?;
+    match obj.kind() {
+        ObjKind::List => {
+            let items = AsMut::<ObjList>::as_mut(&mut obj).items_mut();
+            let i = check_index(&index, items.len(), "list")?;
+            items[i] = value;
+        }
+        ObjKind::Bytes => {
+            let byte = check_byte(&value)?;
+            let bytes = AsMut::<ObjBytes>::as_mut(&mut obj).bytes_mut();
+            let i = check_index(&index, bytes.len(), "bytes")?;
+            bytes[i] = byte;
+        }
+        ObjKind::Map => {
+            heap.mutate(&obj, |obj| {
+                let entries = AsMut::<ObjMap>::as_mut(obj).entries_mut();
+                entries.insert(ValueKey::new(index), value)
+            });
+        }
+        _ => return Err(RuntimeErrorKind::NotIndexable(object.type_name())),
+    }
+
+    Ok(())
+}
+
+/// Converts a value stored in bytes, which must be an integer from 0 to 255.
+fn check_byte(value: &Value) -> Result<u8, RuntimeErrorKind> {
+    let n = i64::try_from(value.clone()).map_err(|error| error.kind().clone())?;
+    u8::try_from(n)
+        .map_err(|_| RuntimeErrorKind::InvalidArgument(format!("{n} is not a valid byte")))
+}
+
+/// Converts `index` to a position in a list or bytes of length `len`, negative indices count from
+/// the end. `type_name` is the type being indexed, for errors.
+fn check_index(index: &Value, len: usize, type_name: &'static str) -> Result<usize, RuntimeErrorKind> {
+    let Value::Number(n) = *index else {
+        return Err(RuntimeErrorKind::TypeMismatch {
+            expected: "number",
+            found: index.type_name(),
+        });
+    };
+
+    let out_of_range = || RuntimeErrorKind::IndexOutOfRange {
+        index: n,
+        len,
+        type_name,
+    };
+    if n.fract() != 0.0 {
+        return Err(out_of_range());
+    }
+
+    let i = if n < 0.0 { n + len as f64 } else { n };
+    if (0.0..len as f64).contains(&i) {
+        Ok(i as usize)
+    } else {
+        Err(out_of_range())
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    #[error("unsupported operand types for '{operator}': {left} and {right}")]
+    InvalidOperands {
+        operator: Operator,
+        left: &'static str,
+        right: &'static str,
+    },
+    #[error("unsupported operand type for '{operator}': {operand}")]
+    InvalidOperand {
+        operator: Operator,
+        operand: &'static str,
+    },
     #[error("division by zero is undefined")]
     DivisionByZero,
-    #[error("variable is not defined")]
-    UndefinedVariable,
+    #[error("variable '{0}' is not defined")]
+    UndefinedVariable(String),
+    #[error("value of type {0} is not callable")]
+    NotCallable(&'static str),
+    #[error("value of type {0} is not iterable")]
+    NotIterable(&'static str),
+    #[error("value of type {0} can't be indexed")]
+    NotIndexable(&'static str),
+    #[error("index {index} is out of range for {type_name} of length {len}")]
+    IndexOutOfRange {
+        index: f64,
+        len: usize,
+        type_name: &'static str,
+    },
+    #[error("out of memory, exceeded limit of {0} bytes")]
+    OutOfMemory(usize),
+    #[error("execution budget exhausted")]
+    OutOfFuel,
+    /// Raised when the host interrupts the script through an [`InterruptHandle`].
+    #[error("interrupted")]
+    Interrupted,
+    #[error("stack overflow, exceeded maximum call depth of {0}")]
+    StackOverflow(usize),
+    #[error("expected {expected} arguments got {found}")]
+    ArityMismatch { expected: Arity, found: usize },
+    #[error("io error: {0}")]
+    Io(String),
+    #[error("value of type {type_name} has no member '{name}'")]
+    UndefinedMember {
+        type_name: &'static str,
+        name: String,
+    },
+    /// Raised by the `exit` builtin to stop the script.
+    #[error("exited with status {0}")]
+    Exit(i32),
+    #[error("permission denied, {0} access is disabled")]
+    PermissionDenied(&'static str),
+    #[error("assertion failed: {0}")]
+    AssertionFailed(String),
+    #[error("syntax error: {0}")]
+    Syntax(String),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("expected {expected} got {found}")]
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("can't import module '{module}': {reason}")]
+    Import { module: String, reason: String },
+}
+
+impl RuntimeErrorKind {
+    pub fn code(&self) -> ErrorCode {
+        use RuntimeErrorKind::*;
+
+        match self {
+            InvalidOperands { .. } => ErrorCode::INVALID_OPERANDS,
+            InvalidOperand { .. } => ErrorCode::INVALID_OPERAND,
+            DivisionByZero => ErrorCode::DIVISION_BY_ZERO,
+            UndefinedVariable(_) => ErrorCode::UNDEFINED_VARIABLE,
+            NotCallable(_) => ErrorCode::NOT_CALLABLE,
+            NotIterable(_) => ErrorCode::NOT_ITERABLE,
+            NotIndexable(_) => ErrorCode::NOT_INDEXABLE,
+            IndexOutOfRange { .. } => ErrorCode::INDEX_OUT_OF_RANGE,
+            OutOfMemory(_) => ErrorCode::OUT_OF_MEMORY,
+            OutOfFuel => ErrorCode::OUT_OF_FUEL,
+            Interrupted => ErrorCode::INTERRUPTED,
+            StackOverflow(_) => ErrorCode::STACK_OVERFLOW,
+            ArityMismatch { .. } => ErrorCode::ARITY_MISMATCH,
+            Io(_) => ErrorCode::IO,
+            UndefinedMember { .. } => ErrorCode::UNDEFINED_MEMBER,
+            Exit(_) => ErrorCode::EXIT,
+            PermissionDenied(_) => ErrorCode::PERMISSION_DENIED,
+            AssertionFailed(_) => ErrorCode::ASSERTION_FAILED,
+            Syntax(_) => ErrorCode::SYNTAX,
+            InvalidArgument(_) => ErrorCode::INVALID_ARGUMENT,
+            TypeMismatch { .. } => ErrorCode::TYPE_MISMATCH,
+            Import { .. } => ErrorCode::IMPORT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{object::Arity, value::Value, Interpreter, RuntimeError, RuntimeErrorKind};
+    use crate::compiler::parser::token::Location;
+    use crate::compiler::parser::Parser;
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<(), RuntimeError> {
+        let script = Parser::new(source).parse().unwrap();
+        interpreter.interpret(&script)
+    }
+
+    fn collect_garbage(interpreter: &mut Interpreter) {
+        // SAFETY: the tests only hold values returned by `global` while they check them.
+        unsafe { interpreter.collect_garbage() };
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter.get_global(name).unwrap().clone()
+    }
+
+    #[test]
+    fn call_function() {
+        let interpreter = &mut Interpreter::default();
+        let source = "
+            func fib(n) {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+            let x = fib(10);
+        ";
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Number(55.0));
+    }
+
+    #[test]
+    fn define_global() {
+        let interpreter = &mut Interpreter::default();
+        interpreter.define_global("_", Value::Number(2.0));
+        run(interpreter, "let x = _ * 3;").unwrap();
+        assert_eq!(interpreter.get_global("x"), Some(&Value::Number(6.0)));
+        assert_eq!(interpreter.get_global("y"), None);
+
+        interpreter.define_global("x", Value::Bool(true));
+        run(interpreter, "let y = not x;").unwrap();
+        assert_eq!(interpreter.get_global("y"), Some(&Value::Bool(false)));
+    }
+
+    #[test]
+    fn suggestions() {
+        let interpreter = &mut Interpreter::default();
+        let error = run(interpreter, "let length = 1; print(lenght);").unwrap_err();
+        assert_eq!(error.suggestion(), Some("length"));
+
+        let error = run(interpreter, "[].pussh(1);").unwrap_err();
+        assert_eq!(error.suggestion(), Some("push"));
+
+        let source = "let o = object(); set_field(o, \"count\", 1); o.cuont;";
+        let error = run(interpreter, source).unwrap_err();
+        assert_eq!(error.suggestion(), Some("count"));
+    }
+
+    #[test]
+    fn call_native() {
+        fn sum(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+            args.iter()
+                .cloned()
+                .map(f64::try_from)
+                .sum::<Result<f64, _>>()
+                .map(Value::Number)
+        }
+
+        let interpreter = &mut Interpreter::default();
+        interpreter.define_native("sum", Arity::AtLeast(1), sum);
+        run(interpreter, "let x = sum(1, 2, 3);").unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Number(6.0));
+
+        assert_eq!(
+            run(interpreter, "sum();").unwrap_err().kind(),
+            &RuntimeErrorKind::ArityMismatch {
+                expected: Arity::AtLeast(1),
+                found: 0
+            }
+        );
+        assert_eq!(
+            run(interpreter, "x();").unwrap_err().kind(),
+            &RuntimeErrorKind::NotCallable("number")
+        );
+    }
+
+    #[test]
+    fn closure_captures_locals() {
+        let interpreter = &mut Interpreter::default();
+        let source = "
+            func make_adder(n) {
+                func add(x) { return x + n; }
+                return add;
+            }
+            let x = make_adder(2)(3);
+        ";
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Number(5.0));
+    }
+
+    #[test]
+    fn closure_captures_by_reference() {
+        let interpreter = &mut Interpreter::default();
+        let source = "
+            func counter() {
+                let n = 0;
+                func increment() { n = n + 1; return n; }
+                func get() { return n; }
+                return [increment, get];
+            }
+            let c = counter();
+            c[0]();
+            c[0]();
+            let x = c[1]();
+            let y = counter()[1]();
+        ";
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Number(2.0));
+        // Every call creates new variables.
+        assert_eq!(global(interpreter, "y"), Value::Number(0.0));
+
+        // Closures declared in a loop see the variables of their own iteration, and assignments
+        // made after a closure is declared are visible to it.
+        let source = "
+            func make() {
+                let fs = [nil, nil];
+                let i = 0;
+                while i < 2 {
+                    let j = i;
+                    func f() { return j; }
+                    fs[i] = f;
+                    j = j * 10;
+                    i = i + 1;
+                }
+                return fs;
+            }
+            let fs = make();
+            let a = fs[0]();
+            let b = fs[1]();
+        ";
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "a"), Value::Number(0.0));
+        assert_eq!(global(interpreter, "b"), Value::Number(10.0));
+    }
+
+    #[test]
+    fn collect_during_calls() {
+        let interpreter = &mut Interpreter::default();
+        let source = r#"
+            func repeat(s, n) {
+                if n == 0 { return ""; }
+                return s + repeat(s, n - 1);
+            }
+            let i = 0;
+            let x = "";
+            while i < 200 {
+                x = repeat("ab", 100);
+                i = i + 1;
+            }
+        "#;
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x").as_str().unwrap(), "ab".repeat(100));
+
+        let builtins = Interpreter::default().heap().object_count();
+        collect_garbage(interpreter);
+        // Only the string and the function should survive besides the builtins.
+        assert_eq!(interpreter.heap().object_count(), builtins + 2);
+    }
+
+    #[test]
+    fn evaluated_value_is_rooted() {
+        let interpreter = &mut Interpreter::default();
+        let script = Parser::new("[1, 2];").parse().unwrap();
+        assert_eq!(interpreter.evaluate(&script).unwrap().to_string(), "[1, 2]");
+
+        // The list is kept alive until the next script is evaluated.
+        collect_garbage(interpreter);
+        let count = interpreter.heap().object_count();
+        let script = Parser::new("nil;").parse().unwrap();
+        assert_eq!(interpreter.evaluate(&script).unwrap(), &Value::Nil);
+        collect_garbage(interpreter);
+        assert_eq!(interpreter.heap().object_count(), count - 1);
+    }
+
+    #[test]
+    fn error_location() {
+        let interpreter = &mut Interpreter::default();
+        let error = run(interpreter, "let x = 1;\nx = y;").unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::UndefinedVariable("y".to_owned())
+        );
+        assert_eq!(
+            error.location(),
+            Some(Location {
+                start: 15,
+                end: 16,
+                line: 2,
+                column: 5,
+                ..Location::default()
+            })
+        );
+
+        let error = run(interpreter, "func f() { return 1 + nil; }\nf();").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unsupported operand types for '+': number and nil"
+        );
+        assert_eq!(error.location().map(|l| (l.line, l.column)), Some((1, 21)));
+
+        // Errors from natives are reported at the call.
+        let error = run(interpreter, "weakref(1);").unwrap_err();
+        assert_eq!(error.location().map(|l| (l.line, l.column)), Some((1, 8)));
+    }
+
+    #[test]
+    fn backtrace() {
+        let interpreter = &mut Interpreter::default();
+        let source = "
+            func inner() { return weakref(1); }
+            func outer() {
+                return inner();
+            }
+            outer();
+        ";
+        let error = run(interpreter, source).unwrap_err();
+        let backtrace: Vec<_> = error
+            .backtrace()
+            .into_iter()
+            .map(|frame| (frame.function, frame.location.map(|l| (l.line, l.column))))
+            .collect();
+
+        assert_eq!(
+            backtrace,
+            [
+                ("weakref".to_owned(), None),
+                ("inner".to_owned(), Some((2, 42))),
+                ("outer".to_owned(), Some((4, 29))),
+                ("<script>".to_owned(), Some((6, 18))),
+            ]
+        );
+        assert!(interpreter.frames().is_empty());
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let interpreter = &mut Interpreter::default();
+        let source = "
+            func count(n) { return count(n + 1) + 1; }
+            count(0);
+        ";
+        let error = run(interpreter, source).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::StackOverflow(Interpreter::DEFAULT_MAX_CALL_DEPTH)
+        );
+        assert!(interpreter.frames().is_empty());
+
+        interpreter.set_max_call_depth(10);
+        let source = "
+            func depth(n) { if n == 0 { return 0; } return depth(n - 1); }
+            depth(9);
+        ";
+        run(interpreter, source).unwrap();
+        assert!(run(interpreter, "depth(10);").is_err());
+    }
+
+    #[test]
+    fn fuel() {
+        let interpreter = &mut Interpreter::default();
+        interpreter.set_fuel(Some(100));
+        let error = run(interpreter, "while true {}").unwrap_err();
+        assert_eq!(error.kind(), &RuntimeErrorKind::OutOfFuel);
+        assert_eq!(interpreter.fuel(), Some(0));
+
+        interpreter.set_fuel(Some(3));
+        run(interpreter, "let x = 1; x = 2; x = 3;").unwrap();
+        assert_eq!(interpreter.fuel(), Some(1));
+    }
+
+    #[test]
+    fn memory_limit() {
+        let interpreter = &mut Interpreter::default();
+        interpreter.heap_mut().set_memory_limit(Some(64 * 1024));
+
+        // Garbage doesn't count towards the limit.
+        let source = r#"
+            let i = 0;
+            while i < 1000 {
+                let s = "0123456789" + "0123456789";
+                i = i + 1;
+            }
+        "#;
+        run(interpreter, source).unwrap();
+
+        // Callbacks of builtins collect garbage too.
+        let source = r#"
+            func f(i) {
+                let s = "0123456789";
+                for j in range(7) { s = s + s; }
+                return i;
+            }
+            func add(a, b) { return f(a) + b; }
+            let l = [];
+            for i in range(300) { l.push(i); }
+            let x = l.map(f).filter(f).reduce(add);
+        "#;
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Number(44850.0));
+
+        let source = r#"
+            let s = "0123456789";
+            while true { s = s + s; }
+        "#;
+        let error = run(interpreter, source).unwrap_err();
+        assert_eq!(error.kind(), &RuntimeErrorKind::OutOfMemory(64 * 1024));
+
+        // Objects which grow in place count as they grow.
+        for source in [
+            "let l = []; while true { l.push(1); }",
+            "let l = []; while true { l.insert(0, 1); }",
+            "let m = {}; let i = 0; while true { m[i] = i; i = i + 1; }",
+            "let m = {}; let i = 0; while true { m.set(i, i); i = i + 1; }",
+            "let b = bytes(0); while true { b.push(1); }",
+        ] {
+            interpreter.set_fuel(Some(1_000_000));
+            let error = run(interpreter, source).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                &RuntimeErrorKind::OutOfMemory(64 * 1024),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        let mut a = Interpreter::default();
+        let mut b = Interpreter::default();
+        a.set_deterministic(7);
+        b.set_deterministic(7);
+
+        assert_eq!(a.rng().next_u64(), b.rng().next_u64());
+        assert_eq!(a.clock().elapsed(), b.clock().elapsed());
+        assert!(a.clock().now() < a.clock().now());
+    }
+
+    #[test]
+    fn finalize() {
+        let interpreter = &mut Interpreter::default();
+        let source = r#"
+            let finalized = 0;
+            func on_free() { finalized = finalized + 1; }
+            let s = "a" + "b";
+            finalize(s, on_free);
+        "#;
+        run(interpreter, source).unwrap();
+
+        collect_garbage(interpreter);
+        assert_eq!(global(interpreter, "finalized"), Value::Number(0.0));
+
+        run(interpreter, "s = nil;").unwrap();
+        collect_garbage(interpreter);
+        assert_eq!(global(interpreter, "finalized"), Value::Number(1.0));
+
+        // Finalizers only run once.
+        collect_garbage(interpreter);
+        assert_eq!(global(interpreter, "finalized"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn weakref() {
+        let interpreter = &mut Interpreter::default();
+        let source = r#"
+            let s = "a" + "b";
+            let w = weakref(s);
+            let x = w();
+        "#;
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x").as_str(), Some("ab"));
+
+        run(interpreter, "s = nil; x = nil;").unwrap();
+        collect_garbage(interpreter);
+        run(interpreter, "x = w();").unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Nil);
+    }
+
+    #[test]
+    fn index() {
+        let interpreter = &mut Interpreter::default();
+        let source = r#"
+            let l = [1, 2, 3,];
+            l[0] = l[-1];
+            let m = { "a": l, 2: nil };
+            m["b"] = m["a"][1];
+            let x = m["b"] + l[0];
+            let y = m["missing"];
+        "#;
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x"), Value::Number(5.0));
+        assert_eq!(global(interpreter, "y"), Value::Nil);
+        assert_eq!(
+            global(interpreter, "m").to_string(),
+            r#"{ "a": [3, 2, 3], 2: nil, "b": 2 }"#
+        );
+
+        assert_eq!(
+            run(interpreter, "l[3];").unwrap_err().kind(),
+            &RuntimeErrorKind::IndexOutOfRange {
+                index: 3.0,
+                len: 3,
+                type_name: "list"
+            }
+        );
+        assert_eq!(
+            run(interpreter, "x[0];").unwrap_err().kind(),
+            &RuntimeErrorKind::NotIndexable("number")
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        let interpreter = &mut Interpreter::default();
+        run(interpreter, r#"let x = "a" + "b";"#).unwrap();
+        let snapshot = interpreter.snapshot();
+
+        run(interpreter, "x = nil; let y = 1;").unwrap();
+        // The string is only referenced by the snapshot.
+        collect_garbage(interpreter);
+        interpreter.restore(snapshot);
+        assert_eq!(global(interpreter, "x").as_str(), Some("ab"));
+        assert!(interpreter.environment.get_global("y").is_none());
+
+        interpreter.discard_snapshot(snapshot);
+        assert_eq!(interpreter.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn prelude() {
+        let interpreter = &mut Interpreter::default();
+        let source = "
+            func even(n) { return n % 2 == 0; }
+            let x = [sum([1, 2, 3]), any([1, 3], even), find([1, 4, 6], even)];
+        ";
+        run(interpreter, source).unwrap();
+        assert_eq!(global(interpreter, "x").to_string(), "[6, false, 4]");
+
+        let interpreter = &mut Interpreter::without_prelude();
+        assert!(interpreter.environment.get_global("sum").is_none());
+        assert!(interpreter.environment.get_global("print").is_some());
+    }
+
+    #[test]
+    fn tests() {
+        let interpreter = &mut Interpreter::default();
+        let source = r#"
+            let calls = 0;
+            test "passes" { calls = calls + 1; }
+            test "fails" { fail("no"); }
+        "#;
+        run(interpreter, source).unwrap();
+        // Tests only run when asked to.
+        assert_eq!(global(interpreter, "calls"), Value::Number(0.0));
+        assert_eq!(interpreter.tests(), ["passes", "fails"]);
+
+        collect_garbage(interpreter);
+        interpreter.run_test(0).unwrap();
+        assert_eq!(global(interpreter, "calls"), Value::Number(1.0));
+        assert_eq!(
+            interpreter.run_test(1).unwrap_err().kind(),
+            &RuntimeErrorKind::AssertionFailed("no".to_owned())
+        );
+    }
 }
@@ -0,0 +1,304 @@
+//! Native functions defined in the global scope of every interpreter.
+
+use super::{
+    format,
+    inspect::{inspect, InspectOptions},
+    object::{Arity, NativeFn, Obj, ObjInstance, ObjNative, ObjWeak},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+mod assert;
+mod bytes;
+mod convert;
+mod encoding;
+mod env;
+mod error;
+mod eval;
+// Files and processes don't exist on wasm32-unknown-unknown, scripts run there only compute and
+// use the interpreter's IO.
+#[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+mod fs;
+mod functional;
+mod hash;
+#[cfg(feature = "net")]
+mod http;
+pub(super) mod iter;
+mod math;
+#[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+mod process;
+mod random;
+mod reflect;
+#[cfg(feature = "regex")]
+pub(super) mod regex;
+mod time;
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    math::define(interpreter);
+    convert::define(interpreter);
+    assert::define(interpreter);
+    bytes::define(interpreter);
+    encoding::define(interpreter);
+    hash::define(interpreter);
+    error::define(interpreter);
+    eval::define(interpreter);
+    functional::define(interpreter);
+    iter::define(interpreter);
+    #[cfg(all(feature = "fs", not(target_arch = "wasm32")))]
+    fs::define(interpreter);
+    env::define(interpreter);
+    #[cfg(all(feature = "process", not(target_arch = "wasm32")))]
+    process::define(interpreter);
+    #[cfg(feature = "net")]
+    http::define(interpreter);
+    time::define(interpreter);
+    random::define(interpreter);
+    reflect::define(interpreter);
+    #[cfg(feature = "regex")]
+    regex::define(interpreter);
+    interpreter.define_native("weakref", Arity::Exact(1), weakref);
+    interpreter.define_native("finalize", Arity::Exact(2), finalize);
+    interpreter.define_native("debug", Arity::Exact(1), debug);
+    interpreter.define_native("clone", Arity::Exact(1), clone);
+    interpreter.define_native("format", Arity::AtLeast(1), format);
+    interpreter.define_native("print", Arity::AtLeast(0), print);
+    interpreter.define_native("println", Arity::AtLeast(0), println);
+    interpreter.define_native("eprint", Arity::AtLeast(0), eprint);
+    interpreter.define_native("eprintln", Arity::AtLeast(0), eprintln);
+    interpreter.define_native("input", Arity::Range(0, 1), input);
+    interpreter.define_native("readline", Arity::Exact(0), readline);
+    interpreter.define_native("clock", Arity::Exact(0), clock);
+    interpreter.define_native("clock_ms", Arity::Exact(0), clock_ms);
+}
+
+/// Removes the builtins of the capabilities which are denied, see
+/// [`Capabilities::hide_denied`](super::capabilities::Capabilities::hide_denied).
+pub(super) fn hide_denied(interpreter: &mut Interpreter) {
+    let capabilities = interpreter.capabilities;
+    // Members of modules are written as `module.member`.
+    let groups: [(bool, &[&str]); 5] = [
+        (capabilities.fs, &["fs"]),
+        (capabilities.env, &["env"]),
+        (capabilities.process, &["process"]),
+        (capabilities.net, &["http"]),
+        (capabilities.clock, &["clock", "clock_ms", "time.now"]),
+    ];
+    let denied = groups
+        .into_iter()
+        .filter(|(granted, _)| !granted)
+        .flat_map(|(_, names)| names);
+    for name in denied {
+        match name.split_once('.') {
+            Some((module, member)) => {
+                if let Some(Value::Obj(mut module)) = interpreter.environment.get_global(module) {
+                    if module.downcast::<ObjInstance>().is_some() {
+                        AsMut::<ObjInstance>::as_mut(&mut module)
+                            .fields_mut()
+                            .remove(member);
+                    }
+                }
+            }
+            None => {
+                interpreter.environment.remove_global(name);
+            }
+        }
+    }
+}
+
+/// Defines a global instance called `name` with a field for each function, the fields are named
+/// after the part of the function's name which follows the last period.
+fn define_module(
+    interpreter: &mut Interpreter,
+    name: &str,
+    functions: &[(&'static str, Arity, NativeFn)],
+) {
+    let mut module = interpreter.heap.alloc(ObjInstance::new());
+    for &(name, arity, function) in functions {
+        let native = interpreter
+            .heap
+            .alloc(ObjNative::new(name, arity, function));
+        let field = name.rsplit('.').next().unwrap().to_owned();
+        AsMut::<ObjInstance>::as_mut(&mut module)
+            .fields_mut()
+            .insert(field, Value::Obj(native));
+    }
+
+    interpreter
+        .environment
+        .define_global(name, Value::Obj(module));
+}
+
+/// Creates a weak reference to an object, calling the reference returns the object or `nil` if
+/// it has been collected.
+fn weakref(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let target = Obj::try_from(args[0].clone())?;
+    let weak = interpreter.heap_mut().alloc(ObjWeak::new(target));
+
+    Ok(Value::Obj(weak))
+}
+
+/// Registers a function to be called with no arguments once an object has been collected.
+fn finalize(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let target = Obj::try_from(args[0].clone())?;
+    interpreter
+        .heap_mut()
+        .register_finalizer(target, args[1].clone());
+
+    Ok(Value::Nil)
+}
+
+/// Writes the arguments separated by spaces.
+fn print(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let io = interpreter.io();
+    io.write(&join(args)).map_err(io_error)?;
+    // Output without a newline is usually a prompt which should be shown right away.
+    io.flush().map_err(io_error)?;
+
+    Ok(Value::Nil)
+}
+
+/// Same as `print` but followed by a newline.
+fn println(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut line = join(args);
+    line.push('\n');
+    interpreter.io().write(&line).map_err(io_error)?;
+
+    Ok(Value::Nil)
+}
+
+/// Same as `print` but writes to the error stream.
+fn eprint(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let io = interpreter.io();
+    io.write_error(&join(args)).map_err(io_error)?;
+    io.flush().map_err(io_error)?;
+
+    Ok(Value::Nil)
+}
+
+/// Same as `println` but writes to the error stream.
+fn eprintln(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut line = join(args);
+    line.push('\n');
+    interpreter.io().write_error(&line).map_err(io_error)?;
+
+    Ok(Value::Nil)
+}
+
+/// Reads a line without its terminator, returns nil at the end of input. If a prompt is given it
+/// is written first.
+fn input(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    if let Some(prompt) = args.first() {
+        let io = interpreter.io();
+        io.write(&prompt.to_string()).map_err(io_error)?;
+        io.flush().map_err(io_error)?;
+    }
+
+    readline(interpreter, &[])
+}
+
+fn readline(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    let Some(mut line) = interpreter.io().read_line().map_err(io_error)? else {
+        return Ok(Value::Nil);
+    };
+
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+
+    Ok(interpreter.heap_mut().alloc_string(line))
+}
+
+/// Returns the number of seconds elapsed since the interpreter was created, this is monotonic so it
+/// is meant for measuring durations rather than telling the time.
+fn clock(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    time::check_clock(interpreter)?;
+    Ok(Value::Number(interpreter.clock().elapsed().as_secs_f64()))
+}
+
+/// Same as `clock` but in milliseconds.
+fn clock_ms(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    time::check_clock(interpreter)?;
+    let elapsed = interpreter.clock().elapsed();
+    Ok(Value::Number(elapsed.as_secs_f64() * 1000.0))
+}
+
+fn join(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn io_error(error: std::io::Error) -> RuntimeError {
+    RuntimeErrorKind::Io(error.to_string()).into()
+}
+
+/// Formats the arguments into a template, see the `format` module for the syntax.
+fn format(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let template = String::try_from(args[0].clone())?;
+    let formatted = format::format(&template, &args[1..])
+        .map_err(|error| RuntimeErrorKind::InvalidArgument(error.to_string()))?;
+
+    Ok(interpreter.heap.alloc_string(formatted))
+}
+
+/// Prints a detailed rendering of a value and returns it, so that it can be wrapped around any
+/// expression.
+fn debug(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut string = inspect(&args[0], &InspectOptions::default());
+    string.push('\n');
+    interpreter.io().write(&string).map_err(io_error)?;
+
+    Ok(args[0].clone())
+}
+
+/// Deep copies lists, maps and instances, see [`Heap::deep_clone`](super::heap::Heap::deep_clone).
+fn clone(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(interpreter.heap_mut().deep_clone(&args[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compiler::parser::Parser,
+        interpreter::{io::BufferIo, value::Value, Interpreter},
+    };
+
+    #[test]
+    fn print_and_input() {
+        let io = BufferIo::new("first\r\nsecond");
+        let mut interpreter = Interpreter::default();
+        interpreter.set_io(Box::new(io.clone()));
+
+        let source = r#"
+            let a = input("name? ");
+            print("x =", 1.5, nil);
+            println("", a, readline(), readline());
+        "#;
+        let script = Parser::new(source).parse().unwrap();
+        interpreter.interpret(&script).unwrap();
+        assert_eq!(io.take_output(), "name? x = 1.5 nil first second nil\n");
+    }
+
+    #[test]
+    fn clock() {
+        let mut interpreter = Interpreter::default();
+        interpreter.set_deterministic(0);
+        let source = "
+            let start = clock_ms();
+            let end = clock_ms();
+            let seconds = clock();
+        ";
+        let script = Parser::new(source).parse().unwrap();
+        interpreter.interpret(&script).unwrap();
+
+        let global = |name| interpreter.environment.get_global(name).unwrap();
+        assert_eq!(global("start"), Value::Number(1.0));
+        assert_eq!(global("end"), Value::Number(2.0));
+        assert_eq!(global("seconds"), Value::Number(0.003));
+    }
+}
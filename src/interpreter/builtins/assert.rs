@@ -0,0 +1,122 @@
+//! Assertions for scripts which test themselves, failures are runtime errors which show the
+//! values involved rendered by `inspect`.
+
+use crate::interpreter::{
+    inspect::{inspect, InspectOptions},
+    object::{Arity, ObjBytes, ObjKind, ObjList, ObjMap},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+/// Depth past which nested lists and maps are compared by identity, this stops comparisons of
+/// lists which contain themselves from recursing forever.
+const MAX_DEPTH: usize = 64;
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("assert_eq", Arity::Range(2, 3), assert_eq);
+    interpreter.define_native("assert_ne", Arity::Range(2, 3), assert_ne);
+    interpreter.define_native("fail", Arity::Range(0, 1), fail);
+}
+
+/// Fails unless the values are equal, lists and maps are compared by their contents.
+fn assert_eq(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    if equal(&args[0], &args[1], 0) {
+        return Ok(Value::Nil);
+    }
+
+    Err(failure("==", args))
+}
+
+/// Fails if the values are equal, lists and maps are compared by their contents.
+fn assert_ne(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !equal(&args[0], &args[1], 0) {
+        return Ok(Value::Nil);
+    }
+
+    Err(failure("!=", args))
+}
+
+/// Fails unconditionally, with an optional message.
+fn fail(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let message = match args.first() {
+        Some(message) => message.to_string(),
+        None => "explicit failure".to_owned(),
+    };
+
+    Err(RuntimeErrorKind::AssertionFailed(message).into())
+}
+
+/// Builds the error of a failed comparison, the optional third argument is a message.
+fn failure(operator: &str, args: &[Value]) -> RuntimeError {
+    let options = InspectOptions::default();
+    let mut message = format!(
+        "left {operator} right\n  left: {}\n right: {}",
+        inspect(&args[0], &options),
+        inspect(&args[1], &options),
+    );
+    if let Some(note) = args.get(2) {
+        message = format!("{note}\n{message}");
+    }
+
+    RuntimeErrorKind::AssertionFailed(message).into()
+}
+
+fn equal(a: &Value, b: &Value, depth: usize) -> bool {
+    if a == b {
+        return true;
+    }
+
+    let (Some(a), Some(b)) = (a.as_obj(), b.as_obj()) else {
+        return false;
+    };
+    if depth >= MAX_DEPTH || a.kind() != b.kind() {
+        return false;
+    }
+
+    match a.kind() {
+        ObjKind::List => {
+            let a = AsRef::<ObjList>::as_ref(a).items();
+            let b = AsRef::<ObjList>::as_ref(b).items();
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b, depth + 1))
+        }
+        ObjKind::Map => {
+            let a = AsRef::<ObjMap>::as_ref(a).entries();
+            let b = AsRef::<ObjMap>::as_ref(b).entries();
+            a.len() == b.len()
+                && a.iter().all(|(key, value)| {
+                    b.get(key)
+                        .is_some_and(|other| equal(value, other, depth + 1))
+                })
+        }
+        ObjKind::Bytes => {
+            AsRef::<ObjBytes>::as_ref(a).bytes() == AsRef::<ObjBytes>::as_ref(b).bytes()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval, RuntimeErrorKind};
+
+    #[test]
+    fn assertions() {
+        assert!(eval(r#"assert_eq([1, {"a": nil}], [1, {"a": nil}]); assert_ne(1, "1");"#).is_ok());
+        assert_eq!(
+            eval(r#"assert_eq([1], [2], "lists differ");"#),
+            Err(RuntimeErrorKind::AssertionFailed(
+                "lists differ\nleft == right\n  left: [1]\n right: [2]".to_owned()
+            ))
+        );
+        assert_eq!(
+            eval(r#"assert_ne("a", "a");"#),
+            Err(RuntimeErrorKind::AssertionFailed(
+                "left != right\n  left: \"a\"\n right: \"a\"".to_owned()
+            ))
+        );
+        assert_eq!(
+            eval(r#"fail("unreachable");"#),
+            Err(RuntimeErrorKind::AssertionFailed("unreachable".to_owned()))
+        );
+    }
+}
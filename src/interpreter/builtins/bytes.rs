@@ -0,0 +1,107 @@
+//! Constructors of byte buffers, which are otherwise used through their methods and indexing.
+
+use crate::interpreter::{
+    check_byte, encoding,
+    object::{Arity, ObjBytes, ObjKind, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("bytes", Arity::Range(0, 1), bytes);
+    interpreter.define_native("bytes_from_hex", Arity::Exact(1), bytes_from_hex);
+}
+
+/// Converts the value to bytes: strings are encoded as UTF-8, lists must contain numbers from 0
+/// to 255, and a number gives that many zeros. Without an argument the buffer is empty.
+fn bytes(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let bytes = match args.first() {
+        None => vec![],
+        Some(Value::Number(_)) => {
+            let len = i64::try_from(args[0].clone())?;
+            let len = usize::try_from(len)
+                .map_err(|_| RuntimeErrorKind::InvalidArgument(format!("negative length {len}")))?;
+            interpreter.check_allocation(len)?;
+            let mut bytes = vec![];
+            bytes.try_reserve_exact(len).map_err(|_| {
+                RuntimeErrorKind::InvalidArgument(format!("length {len} is too large"))
+            })?;
+            bytes.resize(len, 0);
+            bytes
+        }
+        Some(value) => match value.as_obj().map(|obj| (obj.kind(), obj)) {
+            Some((ObjKind::String, _)) => value.as_str().unwrap().as_bytes().to_vec(),
+            Some((ObjKind::Bytes, obj)) => AsRef::<ObjBytes>::as_ref(obj).bytes().clone(),
+            Some((ObjKind::List, obj)) => AsRef::<ObjList>::as_ref(obj)
+                .items()
+                .iter()
+                .map(check_byte)
+                .collect::<Result<_, _>>()?,
+            _ => {
+                return Err(RuntimeErrorKind::TypeMismatch {
+                    expected: "string, list or number",
+                    found: value.type_name(),
+                }
+                .into())
+            }
+        },
+    };
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjBytes::new(bytes))))
+}
+
+/// Decodes hexadecimal digits, in either case, into bytes.
+fn bytes_from_hex(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = String::try_from(args[0].clone())?;
+    let bytes = encoding::hex_decode(&text)
+        .map_err(|error| RuntimeErrorKind::InvalidArgument(error.to_string()))?;
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjBytes::new(bytes))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval, RuntimeErrorKind};
+
+    #[test]
+    fn buffers() {
+        let source = r#"
+            let b = bytes("hé");
+            b.push(33);
+            b[0] = 72;
+            let x = [
+                b,
+                b.len(),
+                b[-1],
+                b.to_string(),
+                b.slice(1, -1).to_hex(),
+                bytes_from_hex("00FF").to_list(),
+                bytes(2),
+                bytes([1, 2]),
+            ];
+        "#;
+        assert_eq!(
+            eval(source).as_deref(),
+            Ok(r#"[<bytes 48c3a921>, 4, 33, "Hé!", "c3a9", [0, 255], <bytes 0000>, <bytes 0102>]"#)
+        );
+        assert_eq!(
+            eval("let x = bytes([256]);"),
+            Err(RuntimeErrorKind::InvalidArgument(
+                "256 is not a valid byte".to_owned()
+            ))
+        );
+        assert!(matches!(
+            eval("let x = bytes([255]).to_string();"),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+        let out_of_range = |index| {
+            Err(RuntimeErrorKind::IndexOutOfRange {
+                index,
+                len: 2,
+                type_name: "bytes",
+            })
+        };
+        assert_eq!(eval("let x = bytes(2)[2];"), out_of_range(2.0));
+        assert_eq!(eval("let x = bytes(2).slice(3);"), out_of_range(3.0));
+    }
+}
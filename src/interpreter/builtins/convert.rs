@@ -0,0 +1,122 @@
+//! Explicit conversions between types, the language never converts values implicitly.
+//!
+//! Strings which can't be parsed are converted to error values so that scripts can check the
+//! result with `is_err`, values of types which can never be converted raise a type error.
+
+use super::error::error_value;
+use crate::interpreter::{
+    object::Arity, value::Value, Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("number", Arity::Exact(1), number);
+    interpreter.define_native("int", Arity::Exact(1), int);
+    interpreter.define_native("string", Arity::Exact(1), string);
+    interpreter.define_native("bool", Arity::Exact(1), bool);
+}
+
+fn mismatch(expected: &'static str, value: &Value) -> RuntimeError {
+    RuntimeErrorKind::TypeMismatch {
+        expected,
+        found: value.type_name(),
+    }
+    .into()
+}
+
+/// Converts a number, string or bool to a number, `true` is 1 and `false` is 0. Surrounding
+/// whitespace is ignored when parsing strings.
+fn number(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let value = &args[0];
+    match value {
+        Value::Number(_) => Ok(value.clone()),
+        Value::Bool(b) => Ok(Value::Number(f64::from(u8::from(*b)))),
+        _ => {
+            let s = value
+                .as_str()
+                .ok_or_else(|| mismatch("number, string or bool", value))?;
+            match s.trim().parse() {
+                Ok(n) => Ok(Value::Number(n)),
+                Err(_) => {
+                    let message = format!("can't convert {s:?} to a number");
+                    Ok(error_value(interpreter, message))
+                }
+            }
+        }
+    }
+}
+
+/// Converts a number or string to an integer, numbers are truncated towards zero while strings
+/// have to contain an integer. It is an error to convert infinity or NaN.
+fn int(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let value = &args[0];
+    match value {
+        Value::Number(n) if n.is_finite() => Ok(Value::Number(n.trunc())),
+        Value::Number(n) => {
+            let message = format!("can't convert {n} to an integer");
+            Err(RuntimeErrorKind::InvalidArgument(message).into())
+        }
+        _ => {
+            let s = value
+                .as_str()
+                .ok_or_else(|| mismatch("number or string", value))?;
+            match s.trim().parse::<i64>() {
+                Ok(n) => Ok(Value::Number(n as f64)),
+                Err(_) => {
+                    let message = format!("can't convert {s:?} to an integer");
+                    Ok(error_value(interpreter, message))
+                }
+            }
+        }
+    }
+}
+
+/// Converts any value to a string the same way `print` does.
+fn string(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args[0].as_str().is_some() {
+        return Ok(args[0].clone());
+    }
+
+    Ok(interpreter.heap.alloc_string(args[0].to_string()))
+}
+
+/// Whether a value is truthy, only `false` and `nil` aren't.
+fn bool(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Bool(args[0].is_truthy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval_expr, RuntimeErrorKind};
+
+    #[test]
+    fn conversions() {
+        let ok = |s: &str| Ok(s.to_owned());
+        assert_eq!(
+            eval_expr(
+                r#"[number(" 1.5 "), number("x"), number(true), int(-2.7), int("42"), int("4.2")]"#
+            ),
+            ok(
+                r#"[1.5, <error can't convert "x" to a number>, 1, -2, 42, <error can't convert "4.2" to an integer>]"#
+            )
+        );
+        assert_eq!(
+            eval_expr(r#"[is_err(number("")), is_err(int("x")), is_err(int(" 7 "))]"#),
+            ok("[true, true, false]")
+        );
+        assert_eq!(
+            eval_expr(r#"[string(1.5) + "!", string([1]), bool(0), bool(nil)]"#),
+            ok(r#"["1.5!", "[1]", true, false]"#)
+        );
+        assert_eq!(
+            eval_expr("number(nil)"),
+            Err(RuntimeErrorKind::TypeMismatch {
+                expected: "number, string or bool",
+                found: "nil"
+            })
+        );
+        assert!(matches!(
+            eval_expr("int(INF)"),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+//! The `encoding` module. Encoders take strings, which are encoded as UTF-8, or bytes and return
+//! strings, decoders return bytes.
+
+use super::define_module;
+use crate::interpreter::{
+    encoding::{self, DecodeError},
+    object::{Arity, ObjBytes},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_module(
+        interpreter,
+        "encoding",
+        &[
+            ("encoding.base64_encode", Arity::Exact(1), base64_encode),
+            ("encoding.base64_decode", Arity::Exact(1), base64_decode),
+            ("encoding.hex_encode", Arity::Exact(1), hex_encode),
+            ("encoding.hex_decode", Arity::Exact(1), hex_decode),
+        ],
+    );
+}
+
+/// Returns the contents of a string or bytes.
+pub(super) fn data(value: &Value) -> Result<&[u8], RuntimeError> {
+    if let Some(string) = value.as_str() {
+        return Ok(string.as_bytes());
+    }
+
+    match value.as_obj().and_then(|obj| obj.downcast::<ObjBytes>()) {
+        Some(bytes) => Ok(bytes.bytes()),
+        None => Err(RuntimeErrorKind::TypeMismatch {
+            expected: "string or bytes",
+            found: value.type_name(),
+        }
+        .into()),
+    }
+}
+
+/// Converts the result of a decoder into bytes.
+fn decoded(
+    interpreter: &mut Interpreter,
+    result: Result<Vec<u8>, DecodeError>,
+) -> Result<Value, RuntimeError> {
+    let bytes = result.map_err(|error| RuntimeErrorKind::InvalidArgument(error.to_string()))?;
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjBytes::new(bytes))))
+}
+
+fn base64_encode(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let encoded = encoding::base64_encode(data(&args[0])?);
+
+    Ok(interpreter.heap.alloc_string(encoded))
+}
+
+fn base64_decode(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = String::try_from(args[0].clone())?;
+    decoded(interpreter, encoding::base64_decode(&text))
+}
+
+fn hex_encode(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let encoded = encoding::hex_encode(data(&args[0])?);
+
+    Ok(interpreter.heap.alloc_string(encoded))
+}
+
+fn hex_decode(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = String::try_from(args[0].clone())?;
+    decoded(interpreter, encoding::hex_decode(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval, RuntimeErrorKind};
+
+    #[test]
+    fn encodings() {
+        let source = r#"
+            let x = [
+                encoding.base64_encode("hello"),
+                encoding.base64_decode("aGVsbG8=").to_string(),
+                encoding.hex_encode(bytes([1, 171])),
+                encoding.hex_decode("01ab"),
+            ];
+        "#;
+        assert_eq!(
+            eval(source).as_deref(),
+            Ok(r#"["aGVsbG8=", "hello", "01ab", <bytes 01ab>]"#)
+        );
+        assert_eq!(
+            eval(r#"let x = encoding.hex_decode("1");"#),
+            Err(RuntimeErrorKind::InvalidArgument(
+                "invalid length 1".to_owned()
+            ))
+        );
+        assert_eq!(
+            eval("let x = encoding.hex_encode(1);"),
+            Err(RuntimeErrorKind::TypeMismatch {
+                expected: "string or bytes",
+                found: "number"
+            })
+        );
+    }
+}
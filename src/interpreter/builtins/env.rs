@@ -0,0 +1,95 @@
+//! The `env` module and the arguments passed to the script.
+
+use super::define_module;
+use crate::interpreter::{
+    object::{Arity, ObjList, ObjMap},
+    table::Table,
+    value::{Value, ValueKey},
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_module(
+        interpreter,
+        "env",
+        &[
+            ("env.get", Arity::Exact(1), get),
+            ("env.vars", Arity::Exact(0), vars),
+        ],
+    );
+    interpreter.define_native("args", Arity::Exact(0), args);
+}
+
+fn check_env(interpreter: &Interpreter) -> Result<(), RuntimeError> {
+    if interpreter.capabilities.env {
+        Ok(())
+    } else {
+        Err(RuntimeErrorKind::PermissionDenied("environment").into())
+    }
+}
+
+/// Value of an environment variable, or nil if it isn't set or isn't valid unicode.
+fn get(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    check_env(interpreter)?;
+    let name = String::try_from(args[0].clone())?;
+    let value = std::env::var(name)
+        .ok()
+        .map(|value| interpreter.heap.alloc_string(value));
+
+    Ok(value.unwrap_or_default())
+}
+
+/// Map of every environment variable which is valid unicode.
+fn vars(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    check_env(interpreter)?;
+    let mut vars: Vec<_> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect();
+    vars.sort();
+
+    let mut table = Table::new();
+    for (name, value) in vars {
+        let name = interpreter.heap.alloc_string(name);
+        table.insert(ValueKey::new(name), interpreter.heap.alloc_string(value));
+    }
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjMap::new(table))))
+}
+
+/// List of the arguments passed to the script.
+fn args(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    let args = interpreter
+        .args
+        .clone()
+        .into_iter()
+        .map(|arg| interpreter.heap.alloc_string(arg))
+        .collect();
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(args))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{
+        capabilities::Capabilities, testing::run, Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn env_and_args() {
+        let mut interpreter = Interpreter::default();
+        interpreter.set_args(vec!["a".to_owned(), "b c".to_owned()]);
+        let path = std::env::var("PATH").unwrap_or_default();
+        let source = r#"
+            let path = env.get("PATH");
+            let x = [args(), env.get("UNNAMED_LANGUAGE_UNSET"), env.vars()["PATH"] == path];
+        "#;
+        let expected = format!(r#"[["a", "b c"], nil, {}]"#, !path.is_empty());
+        assert_eq!(run(&mut interpreter, source), Ok(expected));
+
+        interpreter.set_capabilities(Capabilities::none());
+        assert_eq!(
+            run(&mut interpreter, r#"env.get("PATH");"#),
+            Err(RuntimeErrorKind::PermissionDenied("environment"))
+        );
+    }
+}
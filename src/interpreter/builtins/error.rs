@@ -0,0 +1,65 @@
+//! Conventions for recoverable failures: functions which can fail return either their result,
+//! or an error value created by `err` which callers check for with `is_err`.
+
+use crate::interpreter::{
+    object::{Arity, ObjError, ObjKind},
+    value::Value,
+    Interpreter, RuntimeError,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("ok", Arity::Exact(1), ok);
+    interpreter.define_native("err", Arity::Exact(1), err);
+    interpreter.define_native("is_err", Arity::Exact(1), is_err);
+}
+
+/// Successful results are plain values, this returns its argument and exists for symmetry with
+/// `err`.
+fn ok(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(args[0].clone())
+}
+
+/// Error value with the message, which can be read back with its `message` method.
+fn err(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let error = ObjError::new(args[0].clone());
+
+    Ok(Value::Obj(interpreter.heap.alloc(error)))
+}
+
+/// Creates the error value which builtins return for recoverable failures, like `err` does.
+pub(super) fn error_value(interpreter: &mut Interpreter, message: String) -> Value {
+    let message = interpreter.heap.alloc_string(message);
+
+    Value::Obj(interpreter.heap.alloc(ObjError::new(message)))
+}
+
+fn is_err(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let is_err = args[0]
+        .as_obj()
+        .is_some_and(|obj| obj.kind() == ObjKind::Error);
+
+    Ok(Value::Bool(is_err))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::testing::eval;
+
+    #[test]
+    fn results() {
+        let source = r#"
+            func parse(s) {
+                let n = number(s);
+                if is_err(n) { return err("not a number: " + s); }
+                return ok(n);
+            }
+            let a = parse("12");
+            let b = parse("x");
+            let x = [is_err(a), a, is_err(b), b.message(), b];
+        "#;
+        assert_eq!(
+            eval(source).unwrap(),
+            r#"[false, 12, true, "not a number: x", <error not a number: x>]"#
+        );
+    }
+}
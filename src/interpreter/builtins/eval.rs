@@ -0,0 +1,61 @@
+//! Running source code from within a script.
+
+use crate::{
+    compiler::parser::Parser,
+    interpreter::{object::Arity, value::Value, Interpreter, RuntimeError, RuntimeErrorKind},
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("eval", Arity::Exact(1), eval);
+}
+
+/// Parses and runs the source in the global scope of the calling script, returning its value as
+/// [`Interpreter::evaluate`] does. Globals defined by the source remain defined afterwards.
+fn eval(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let source = String::try_from(args[0].clone())?;
+    let script = Parser::new(&source)
+        .parse()
+        .map_err(|error| RuntimeErrorKind::Syntax(error.message().to_owned()))?;
+
+    interpreter.evaluate_in_native(&script)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{
+        testing::{eval, run},
+        Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn collection() {
+        // Garbage made by the code is collected while it runs.
+        let mut interpreter = Interpreter::default();
+        interpreter.heap_mut().set_memory_limit(Some(1024 * 1024));
+        let source = r#"
+            let x = eval("let s = nil; for i in range(100000) { s = [i, i, i]; } s[0];");
+        "#;
+        assert_eq!(run(&mut interpreter, source).as_deref(), Ok("99999"));
+    }
+
+    #[test]
+    fn evaluation() {
+        let source = r#"
+            let a = 2;
+            let x = [
+                eval("a * 3;"),
+                eval("let b = 1; func f(n) { return n + b; } f(a);"),
+                eval("return 5; 6;"),
+                eval("let c = 1;"),
+                f(b),
+            ];
+        "#;
+        assert_eq!(eval(source).as_deref(), Ok("[6, 3, 5, nil, 2]"));
+        assert_eq!(
+            eval(r#"let x = eval("1");"#),
+            Err(RuntimeErrorKind::Syntax(
+                "expected ';' got <eof>".to_owned()
+            ))
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! The `fs` module, only usable if the interpreter has the filesystem capability.
+
+use std::fs;
+
+use super::{define_module, io_error};
+use crate::interpreter::{
+    object::{Arity, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_module(
+        interpreter,
+        "fs",
+        &[
+            ("fs.read_text", Arity::Exact(1), read_text),
+            ("fs.write_text", Arity::Exact(2), write_text),
+            ("fs.append", Arity::Exact(2), append),
+            ("fs.exists", Arity::Exact(1), exists),
+            ("fs.remove", Arity::Exact(1), remove),
+            ("fs.list_dir", Arity::Exact(1), list_dir),
+        ],
+    );
+}
+
+/// Checks the capability and converts the path argument.
+fn path(interpreter: &Interpreter, path: &Value) -> Result<String, RuntimeError> {
+    if !interpreter.capabilities.fs {
+        return Err(RuntimeErrorKind::PermissionDenied("filesystem").into());
+    }
+
+    String::try_from(path.clone())
+}
+
+fn read_text(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let path = path(interpreter, &args[0])?;
+    let text = fs::read_to_string(path).map_err(io_error)?;
+
+    Ok(interpreter.heap.alloc_string(text))
+}
+
+/// Creates or truncates the file and writes the text to it.
+fn write_text(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let path = path(interpreter, &args[0])?;
+    let text = String::try_from(args[1].clone())?;
+    fs::write(path, text).map_err(io_error)?;
+
+    Ok(Value::Nil)
+}
+
+/// Appends the text to the file, creating it if it doesn't exist.
+fn append(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    use std::io::Write;
+
+    let path = path(interpreter, &args[0])?;
+    let text = String::try_from(args[1].clone())?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(io_error)?;
+
+    Ok(Value::Nil)
+}
+
+fn exists(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let path = path(interpreter, &args[0])?;
+    let exists = fs::exists(path).map_err(io_error)?;
+
+    Ok(Value::Bool(exists))
+}
+
+/// Removes a file or an empty directory.
+fn remove(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let path = path(interpreter, &args[0])?;
+    let metadata = fs::symlink_metadata(&path).map_err(io_error)?;
+    if metadata.is_dir() {
+        fs::remove_dir(path).map_err(io_error)?;
+    } else {
+        fs::remove_file(path).map_err(io_error)?;
+    }
+
+    Ok(Value::Nil)
+}
+
+/// Sorted list of the names of the entries in a directory.
+fn list_dir(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let path = path(interpreter, &args[0])?;
+    let mut names = vec![];
+    for entry in fs::read_dir(path).map_err(io_error)? {
+        let entry = entry.map_err(io_error)?;
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+
+    let names = names
+        .into_iter()
+        .map(|name| interpreter.heap.alloc_string(name))
+        .collect();
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(names))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{
+        capabilities::Capabilities, testing::run, Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn files() {
+        let dir = std::env::temp_dir().join(format!("unnamed-language-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap().to_owned();
+
+        let source = format!(
+            r#"
+            let path = "{dir}/a.txt";
+            fs.write_text(path, "one");
+            fs.append(path, " two");
+            let x = [fs.read_text(path), fs.exists(path), fs.list_dir("{dir}")];
+            fs.remove(path);
+            x.push(fs.exists(path));
+            fs.remove("{dir}");
+            "#
+        );
+        let result = run(&mut Interpreter::default(), &source);
+        assert_eq!(
+            result.as_deref(),
+            Ok(r#"["one two", true, ["a.txt"], false]"#)
+        );
+
+        assert!(matches!(
+            run(
+                &mut Interpreter::default(),
+                r#"fs.read_text("no/such/file");"#
+            ),
+            Err(RuntimeErrorKind::Io(_))
+        ));
+    }
+
+    #[test]
+    fn disabled() {
+        let mut interpreter = Interpreter::default();
+        interpreter.set_capabilities(Capabilities::none());
+        assert_eq!(
+            run(&mut interpreter, r#"fs.exists(".");"#),
+            Err(RuntimeErrorKind::PermissionDenied("filesystem"))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Higher order functions for a functional style, `map`, `filter` and `reduce` are the list
+//! methods of the same names as free functions.
+
+use crate::interpreter::{
+    methods,
+    object::{Arity, ObjBound, ObjKind},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("map", Arity::Exact(2), map);
+    interpreter.define_native("filter", Arity::Exact(2), filter);
+    interpreter.define_native("reduce", Arity::Range(2, 3), reduce);
+    interpreter.define_native("compose", Arity::AtLeast(1), compose);
+    interpreter.define_native("partial", Arity::AtLeast(1), partial);
+}
+
+/// Calls the list method called `name` with the first argument as the receiver.
+fn list_method(
+    interpreter: &mut Interpreter,
+    name: &str,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let is_list = args[0]
+        .as_obj()
+        .is_some_and(|obj| obj.kind() == ObjKind::List);
+    if !is_list {
+        return Err(RuntimeErrorKind::TypeMismatch {
+            expected: "list",
+            found: args[0].type_name(),
+        }
+        .into());
+    }
+
+    let method = methods::lookup(&args[0], name).expect("lists should have the method");
+    (method.function)(interpreter, args)
+}
+
+fn map(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    list_method(interpreter, "map", args)
+}
+
+fn filter(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    list_method(interpreter, "filter", args)
+}
+
+fn reduce(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    list_method(interpreter, "reduce", args)
+}
+
+/// Returns a function which calls the functions from last to first, passing its arguments to
+/// the last one and the result of each to the one before it.
+fn compose(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let bound = ObjBound::new("composed", Arity::AtLeast(0), call_composed, args.to_vec());
+
+    Ok(Value::Obj(interpreter.heap.alloc(bound)))
+}
+
+fn call_composed(
+    interpreter: &mut Interpreter,
+    functions: &[Value],
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let (last, rest) = functions
+        .split_last()
+        .expect("compose takes at least one function");
+    let mut result = interpreter.call_collecting(last, args)?;
+    for function in rest.iter().rev() {
+        result = interpreter.call_collecting(function, &[result])?;
+    }
+
+    Ok(result)
+}
+
+/// Returns a function which calls the function with the given arguments followed by its own.
+fn partial(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let bound = ObjBound::new("partial", Arity::AtLeast(0), call_partial, args.to_vec());
+
+    Ok(Value::Obj(interpreter.heap.alloc(bound)))
+}
+
+fn call_partial(
+    interpreter: &mut Interpreter,
+    captured: &[Value],
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let (function, bound_args) = captured.split_first().expect("partial captures a function");
+    let args: Vec<_> = bound_args.iter().chain(args).cloned().collect();
+
+    interpreter.call_collecting(function, &args)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval, RuntimeErrorKind};
+
+    #[test]
+    fn helpers() {
+        let source = "
+            func add(a, b) { return a + b; }
+            func double(n) { return n * 2; }
+            func positive(n) { return n > 0; }
+            let add_one = partial(add, 1);
+            let l = [-1, 2, 3];
+            let x = [
+                map(l, add_one),
+                filter(l, positive),
+                reduce(l, add, 10),
+                compose(double, add_one)(4),
+                compose(add_one, add)(1, 2),
+                add_one,
+            ];
+        ";
+        assert_eq!(
+            eval(source).as_deref(),
+            Ok("[[0, 3, 4], [2, 3], 14, 10, 4, <native func partial>]")
+        );
+        assert_eq!(
+            eval("let x = map(1, print);"),
+            Err(RuntimeErrorKind::TypeMismatch {
+                expected: "list",
+                found: "number"
+            })
+        );
+        assert_eq!(
+            eval("func f(a) {} let x = partial(f, 1, 2)();"),
+            Err(RuntimeErrorKind::ArityMismatch {
+                expected: crate::interpreter::object::Arity::Exact(1),
+                found: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! The `hash` module. Each function takes a string, which is hashed as UTF-8, or bytes and
+//! returns the digest as lowercase hexadecimal.
+
+use super::{define_module, encoding::data};
+use crate::interpreter::{
+    digest, encoding, object::Arity, value::Value, Interpreter, RuntimeError,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_module(
+        interpreter,
+        "hash",
+        &[
+            ("hash.sha256", Arity::Exact(1), sha256),
+            ("hash.md5", Arity::Exact(1), md5),
+            ("hash.fnv1a", Arity::Exact(1), fnv1a),
+        ],
+    );
+}
+
+fn sha256(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let digest = digest::sha256(data(&args[0])?);
+
+    Ok(interpreter.heap.alloc_string(encoding::hex_encode(&digest)))
+}
+
+fn md5(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let digest = digest::md5(data(&args[0])?);
+
+    Ok(interpreter.heap.alloc_string(encoding::hex_encode(&digest)))
+}
+
+/// Fast hash which isn't suitable for security purposes.
+fn fnv1a(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let digest = digest::fnv1a(data(&args[0])?);
+
+    Ok(interpreter.heap.alloc_string(format!("{digest:016x}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::testing::eval;
+
+    #[test]
+    fn hashes() {
+        let source = r#"
+            let x = [
+                hash.sha256("abc"),
+                hash.md5(bytes("abc")),
+                hash.fnv1a("a"),
+            ];
+        "#;
+        assert_eq!(
+            eval(source).unwrap(),
+            concat!(
+                r#"["ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", "#,
+                r#""900150983cd24fb0d6963f7d28e17f72", "af63dc4c8601ec8c"]"#
+            )
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! The `http` module, a minimal HTTP/1.1 client over plain TCP. It is only compiled with the
+//! `net` feature and only usable if the interpreter has the network capability.
+//!
+//! Only `http` URLs are supported, there is no TLS.
+
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use super::{define_module, io_error};
+use crate::interpreter::{
+    object::{Arity, ObjMap},
+    table::Table,
+    value::{Value, ValueKey},
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+/// Time connecting, or waiting for the server to send more of the response, may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_module(interpreter, "http", &[("http.get", Arity::Exact(1), get)]);
+}
+
+/// Splits a URL into the address to connect to, the host name and the path.
+fn parse_url(url: &str) -> Result<(String, &str, &str), RuntimeError> {
+    let invalid = |message: &str| RuntimeErrorKind::InvalidArgument(format!("{message}: {url}"));
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid("only http URLs are supported"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(invalid("missing host").into());
+    }
+
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host);
+    let address = if authority.contains(':') {
+        authority.to_owned()
+    } else {
+        format!("{authority}:80")
+    };
+
+    Ok((address, host, path))
+}
+
+/// Sends a GET request, returning a map with the `status`, the `headers` as a map with lowercase
+/// names, and the `body` decoded as UTF-8.
+fn get(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !interpreter.capabilities.net {
+        return Err(RuntimeErrorKind::PermissionDenied("network").into());
+    }
+
+    let url = String::try_from(args[0].clone())?;
+    let (address, host, path) = parse_url(&url)?;
+    // The body is read before it is allocated on the heap, so it is checked against the memory
+    // limit as it arrives.
+    let limit = interpreter.heap.memory_limit();
+    let max_body = limit.map_or(usize::MAX, |limit| {
+        limit.saturating_sub(interpreter.heap.bytes_allocated())
+    });
+    let response = request(&address, host, path, max_body).map_err(|error| match limit {
+        Some(limit) if error.kind() == ErrorKind::OutOfMemory => {
+            RuntimeErrorKind::OutOfMemory(limit).into()
+        }
+        _ => io_error(error),
+    })?;
+
+    let mut headers = Table::new();
+    for (name, value) in response.headers {
+        let name = ValueKey::new(interpreter.heap.alloc_string(name));
+        headers.insert(name, interpreter.heap.alloc_string(value));
+    }
+    let fields = [
+        ("status", Value::Number(response.status.into())),
+        (
+            "headers",
+            Value::Obj(interpreter.heap.alloc(ObjMap::new(headers))),
+        ),
+        (
+            "body",
+            interpreter
+                .heap
+                .alloc_string(String::from_utf8_lossy(&response.body)),
+        ),
+    ];
+    let mut table = Table::new();
+    for (name, value) in fields {
+        table.insert(ValueKey::new(interpreter.heap.alloc_string(name)), value);
+    }
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjMap::new(table))))
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// Sends a GET request, failing with an [`ErrorKind::OutOfMemory`] error if the body is longer
+/// than `max_body` bytes.
+fn request(address: &str, host: &str, path: &str, max_body: usize) -> std::io::Result<Response> {
+    let invalid = |message: &str| std::io::Error::new(ErrorKind::InvalidData, message);
+
+    let stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write!(
+        &stream,
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\nAccept: */*\r\n\r\n"
+    )?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("malformed status line"))?;
+
+    let mut headers = vec![];
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+    }
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let mut body = vec![];
+    if header("transfer-encoding").is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let size = line.trim_end().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16).map_err(|_| invalid("malformed chunk"))?;
+            if size == 0 {
+                break;
+            }
+
+            read_body(&mut reader, Some(size), &mut body, max_body)?;
+            // Skip the line break which ends the chunk.
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+    } else if let Some(length) = header("content-length") {
+        let length = length
+            .parse()
+            .map_err(|_| invalid("malformed content length"))?;
+        read_body(&mut reader, Some(length), &mut body, max_body)?;
+    } else {
+        read_body(&mut reader, None, &mut body, max_body)?;
+    }
+
+    Ok(Response {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Appends `length` bytes of the body to `body`, or everything until the connection is closed if
+/// the length isn't known. The length is given by the server so the body grows as bytes arrive
+/// rather than being allocated up front.
+fn read_body(
+    reader: &mut impl Read,
+    length: Option<usize>,
+    body: &mut Vec<u8>,
+    max_body: usize,
+) -> std::io::Result<()> {
+    let too_large = || std::io::Error::new(ErrorKind::OutOfMemory, "response body is too large");
+    let remaining = max_body.saturating_sub(body.len());
+    let Some(length) = length else {
+        let limit = u64::try_from(remaining).map_or(u64::MAX, |limit| limit.saturating_add(1));
+        reader.take(limit).read_to_end(body)?;
+        if body.len() > max_body {
+            return Err(too_large());
+        }
+        return Ok(());
+    };
+
+    if length > remaining {
+        return Err(too_large());
+    }
+    let end = body.len() + length;
+    reader.take(length as u64).read_to_end(body)?;
+    if body.len() < end {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::parse_url;
+    use crate::interpreter::{
+        capabilities::Capabilities, testing::run, Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn urls() {
+        let (address, host, path) = parse_url("http://example.com").unwrap();
+        assert_eq!(
+            (address.as_str(), host, path),
+            ("example.com:80", "example.com", "/")
+        );
+
+        let (address, host, path) = parse_url("http://localhost:8080/a?b").unwrap();
+        assert_eq!(
+            (address.as_str(), host, path),
+            ("localhost:8080", "localhost", "/a?b")
+        );
+
+        assert!(parse_url("https://example.com").is_err());
+    }
+
+    /// Answers the first request to the returned port with `response`, the server returns the
+    /// request line.
+    fn serve(response: &'static str) -> (u16, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            // Read the rest of the request.
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            // The client may have given up on the response already.
+            let _ = (&stream).write_all(response.as_bytes());
+            request_line
+        });
+
+        (port, server)
+    }
+
+    #[test]
+    fn get() {
+        let (port, server) = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\
+             Transfer-Encoding: chunked\r\n\r\n3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n",
+        );
+
+        let mut interpreter = Interpreter::default();
+        let source = format!(r#"let x = http.get("http://127.0.0.1:{port}/path");"#);
+        assert_eq!(
+            run(&mut interpreter, &source).as_deref(),
+            Ok(
+                r#"{ "status": 200, "headers": { "content-type": "text/plain", "transfer-encoding": "chunked" }, "body": "hello" }"#
+            )
+        );
+        assert_eq!(server.join().unwrap(), "GET /path HTTP/1.1\r\n");
+
+        interpreter.set_capabilities(Capabilities::none());
+        assert_eq!(
+            run(&mut interpreter, &source),
+            Err(RuntimeErrorKind::PermissionDenied("network"))
+        );
+    }
+
+    #[test]
+    fn lengths() {
+        let get = |interpreter: &mut Interpreter, response| {
+            let (port, server) = serve(response);
+            let source = format!(r#"let x = http.get("http://127.0.0.1:{port}/");"#);
+            let result = run(interpreter, &source);
+            server.join().unwrap();
+            result
+        };
+
+        // Lengths given by the server aren't allocated up front.
+        let mut interpreter = Interpreter::default();
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 1000000000000000\r\n\r\nshort";
+        assert!(matches!(
+            get(&mut interpreter, response),
+            Err(RuntimeErrorKind::Io(_))
+        ));
+        let response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                        1\r\na\r\nffffffffffffffff\r\nb\r\n0\r\n\r\n";
+        assert!(matches!(
+            get(&mut interpreter, response),
+            Err(RuntimeErrorKind::Io(_))
+        ));
+
+        interpreter.heap_mut().set_memory_limit(Some(1024 * 1024));
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 2000000\r\n\r\nshort";
+        assert_eq!(
+            get(&mut interpreter, response),
+            Err(RuntimeErrorKind::OutOfMemory(1024 * 1024))
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Lazy iterator helpers, each takes any iterable and returns an iterator which does the work
+//! one item at a time as it is advanced. Iterators also have these as methods, so they can be
+//! chained as in `range(100).skip(10).take(5)`.
+
+use crate::interpreter::{
+    iterator::{self, IterState},
+    object::{Arity, ObjIterator, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("range", Arity::Range(1, 3), range);
+    interpreter.define_native("iter", Arity::Exact(1), iter);
+    interpreter.define_native("zip", Arity::AtLeast(1), zip);
+    interpreter.define_native("enumerate", Arity::Exact(1), enumerate);
+    interpreter.define_native("take", Arity::Exact(2), take);
+    interpreter.define_native("skip", Arity::Exact(2), skip);
+    interpreter.define_native("chain", Arity::AtLeast(1), chain);
+}
+
+fn alloc(interpreter: &mut Interpreter, state: IterState) -> Result<Value, RuntimeError> {
+    let iterator = ObjIterator::new(state);
+    if iterator.depth() > iterator::MAX_DEPTH {
+        let message = format!(
+            "iterators can't be nested more than {} levels deep",
+            iterator::MAX_DEPTH
+        );
+        return Err(RuntimeErrorKind::InvalidArgument(message).into());
+    }
+
+    Ok(Value::Obj(interpreter.heap.alloc(iterator)))
+}
+
+/// Numbers from `start` up to but not including `end`, `range(end)` starts from zero.
+fn range(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (start, end) = match args {
+        [end] => (0.0, f64::try_from(end.clone())?),
+        [start, end, ..] => (f64::try_from(start.clone())?, f64::try_from(end.clone())?),
+        [] => unreachable!("arity should have been checked"),
+    };
+    let step = match args.get(2) {
+        Some(step) => f64::try_from(step.clone())?,
+        None => 1.0,
+    };
+    if step == 0.0 || step.is_nan() {
+        let message = format!("invalid range step {step}");
+        return Err(RuntimeErrorKind::InvalidArgument(message).into());
+    }
+
+    let state = IterState::Range {
+        start,
+        end,
+        step,
+        index: 0,
+    };
+    alloc(interpreter, state)
+}
+
+/// Iterator over a list, a map's keys, or an iterator itself.
+pub(crate) fn iter(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Obj(iterator::iterate(
+        &mut interpreter.heap,
+        &args[0],
+    )?))
+}
+
+/// Lists of the next item of each iterable, stopping at the end of the shortest.
+pub(crate) fn zip(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let inners = args
+        .iter()
+        .map(|arg| iterator::iterate(&mut interpreter.heap, arg))
+        .collect::<Result<_, _>>()?;
+    alloc(interpreter, IterState::Zip(inners))
+}
+
+/// Lists of the index of each item and the item.
+pub(crate) fn enumerate(
+    interpreter: &mut Interpreter,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let inner = iterator::iterate(&mut interpreter.heap, &args[0])?;
+    alloc(interpreter, IterState::Enumerate { inner, index: 0 })
+}
+
+/// The first `n` items.
+pub(crate) fn take(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let inner = iterator::iterate(&mut interpreter.heap, &args[0])?;
+    let remaining = count(&args[1])?;
+    alloc(interpreter, IterState::Take { inner, remaining })
+}
+
+/// Every item after the first `n`.
+pub(crate) fn skip(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let inner = iterator::iterate(&mut interpreter.heap, &args[0])?;
+    let remaining = count(&args[1])?;
+    alloc(interpreter, IterState::Skip { inner, remaining })
+}
+
+/// Every item of each iterable in turn.
+pub(crate) fn chain(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let inners = args
+        .iter()
+        .map(|arg| iterator::iterate(&mut interpreter.heap, arg))
+        .collect::<Result<_, _>>()?;
+    alloc(interpreter, IterState::Chain { inners, index: 0 })
+}
+
+/// Converts a number of items, which can't be negative.
+fn count(value: &Value) -> Result<usize, RuntimeError> {
+    let n = i64::try_from(value.clone())?;
+    usize::try_from(n).map_err(|_| {
+        let message = format!("negative count {n}");
+        RuntimeErrorKind::InvalidArgument(message).into()
+    })
+}
+
+/// Collects the remaining items of an iterable into a list.
+pub(crate) fn to_list(
+    interpreter: &mut Interpreter,
+    args: &[Value],
+) -> Result<Value, RuntimeError> {
+    let iterator = iterator::iterate(&mut interpreter.heap, &args[0])?;
+    let mut items = vec![];
+    while let Some(item) = iterator::next(interpreter, &iterator)? {
+        // Collecting an endless iterator runs as long as an endless loop does.
+        interpreter.tick()?;
+        items.push(item);
+        // Iterators can be endless, so the list is checked against the memory limit as it grows.
+        interpreter.check_allocation(items.len() * std::mem::size_of::<Value>())?;
+    }
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(items))))
+}
+
+/// Advances an iterator, returning nil once it is exhausted.
+pub(crate) fn next(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let iterator = iterator::iterate(&mut interpreter.heap, &args[0])?;
+
+    Ok(iterator::next(interpreter, &iterator)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{
+        testing::{eval, run},
+        Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn helpers() {
+        let source = r#"
+            let x = [
+                range(3).to_list(),
+                range(10, 0, -4).to_list(),
+                zip(["a", "b", "c"], range(1000000000)).to_list(),
+                enumerate({"k": 1}).to_list(),
+                skip(range(1000000000), 5).take(2).to_list(),
+                chain([1], range(2)).to_list(),
+                range(1).next(),
+            ];
+        "#;
+        assert_eq!(
+            eval(source).as_deref(),
+            Ok(
+                r#"[[0, 1, 2], [10, 6, 2], [["a", 0], ["b", 1], ["c", 2]], [[0, "k"]], [5, 6], [1, 0, 1], 0]"#
+            )
+        );
+        assert!(matches!(
+            eval("let x = range(0, 1, 0);"),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+        assert_eq!(
+            eval("let x = take(1, 2);"),
+            Err(RuntimeErrorKind::NotIterable("number"))
+        );
+    }
+
+    #[test]
+    fn nesting() {
+        let source = "let x = range(3); for i in range(255) { x = skip(x, 0); } x = x.to_list();";
+        assert_eq!(eval(source).as_deref(), Ok("[0, 1, 2]"));
+        assert!(matches!(
+            eval(&source.replace("255", "256")),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn for_loop() {
+        let source = "
+            let x = 0;
+            for i in range(1, 1000000000) {
+                if i > 4 { return; }
+                x = x + i;
+            }
+        ";
+        assert_eq!(eval(source).as_deref(), Ok("10"));
+    }
+
+    #[test]
+    fn endless() {
+        for source in [
+            "let x = range(1000000000000000).to_list();",
+            "let x = skip(range(1000000000000000), 100000000000000).next();",
+        ] {
+            let mut interpreter = Interpreter::default();
+            interpreter.set_fuel(Some(1000));
+            assert_eq!(
+                run(&mut interpreter, source),
+                Err(RuntimeErrorKind::OutOfFuel),
+                "{source}"
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Mathematical functions and constants.
+
+use crate::interpreter::{object::Arity, value::Value, Interpreter, RuntimeError};
+
+/// Defines a native function for each named `f64` method which takes no arguments.
+macro_rules! define_unary {
+    ($interpreter:expr, $($name:ident),* $(,)?) => {
+        $(
+            fn $name(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+                Ok(Value::Number(number(&args[0])?.$name()))
+            }
+
+            $interpreter.define_native(stringify!($name), Arity::Exact(1), $name);
+        )*
+    };
+}
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_unary!(
+        interpreter,
+        sqrt,
+        abs,
+        floor,
+        ceil,
+        round,
+        trunc,
+        sin,
+        cos,
+        tan,
+        asin,
+        acos,
+        atan,
+        exp,
+    );
+    interpreter.define_native("atan2", Arity::Exact(2), atan2);
+    interpreter.define_native("log", Arity::Range(1, 2), log);
+    interpreter.define_native("min", Arity::AtLeast(1), min);
+    interpreter.define_native("max", Arity::AtLeast(1), max);
+
+    let constants = [
+        ("PI", std::f64::consts::PI),
+        ("E", std::f64::consts::E),
+        ("INF", f64::INFINITY),
+        ("NAN", f64::NAN),
+    ];
+    for (name, value) in constants {
+        interpreter
+            .environment
+            .define_global(name, Value::Number(value));
+    }
+}
+
+fn number(value: &Value) -> Result<f64, RuntimeError> {
+    f64::try_from(value.clone())
+}
+
+fn atan2(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.atan2(number(&args[1])?)))
+}
+
+/// Natural logarithm, or the logarithm in the given base.
+fn log(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let n = number(&args[0])?;
+    let log = match args.get(1) {
+        Some(base) => n.log(number(base)?),
+        None => n.ln(),
+    };
+
+    Ok(Value::Number(log))
+}
+
+/// Smallest of the arguments, NaN if any of them is NaN.
+fn min(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    fold(args, f64::min)
+}
+
+/// Largest of the arguments, NaN if any of them is NaN.
+fn max(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    fold(args, f64::max)
+}
+
+fn fold(args: &[Value], f: fn(f64, f64) -> f64) -> Result<Value, RuntimeError> {
+    let mut result = number(&args[0])?;
+    for arg in &args[1..] {
+        let n = number(arg)?;
+        // `f64::min` and `f64::max` ignore NaN.
+        result = if n.is_nan() || result.is_nan() {
+            f64::NAN
+        } else {
+            f(result, n)
+        };
+    }
+
+    Ok(Value::Number(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval_expr, RuntimeErrorKind};
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval_expr("sqrt(16)").as_deref(), Ok("4"));
+        assert_eq!(eval_expr("round(-2.5)").as_deref(), Ok("-3"));
+        assert_eq!(eval_expr("log(8, 2)").as_deref(), Ok("3"));
+        assert_eq!(eval_expr("min(3, -1, 2)").as_deref(), Ok("-1"));
+        assert_eq!(eval_expr("max(1, 2) + floor(PI)").as_deref(), Ok("5"));
+        assert_eq!(eval_expr("max(1, NAN)").as_deref(), Ok("nan"));
+        assert_eq!(
+            eval_expr("abs(nil)"),
+            Err(RuntimeErrorKind::TypeMismatch {
+                expected: "number",
+                found: "nil"
+            })
+        );
+    }
+}
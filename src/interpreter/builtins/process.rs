@@ -0,0 +1,119 @@
+//! `exit` and the `process` module.
+
+use std::process::Command;
+
+use super::define_module;
+use crate::interpreter::{
+    object::{Arity, ObjList, ObjMap},
+    table::Table,
+    value::{Value, ValueKey},
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("exit", Arity::Range(0, 1), exit);
+    define_module(
+        interpreter,
+        "process",
+        &[("process.run", Arity::Range(1, 2), run)],
+    );
+}
+
+/// Stops the script with a status code, 0 if not given. The interpreter returns an
+/// `Exit` error which hosts are expected to handle by exiting.
+fn exit(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let code = match args.first() {
+        Some(code) => i64::try_from(code.clone())?,
+        None => 0,
+    };
+    let code = i32::try_from(code).map_err(|_| {
+        RuntimeErrorKind::InvalidArgument(format!("exit code {code} is out of range"))
+    })?;
+
+    Err(RuntimeErrorKind::Exit(code).into())
+}
+
+/// Runs a program with a list of arguments and waits for it to finish, returning a map with its
+/// `status`, or nil if it was killed by a signal, and its `stdout` and `stderr`.
+fn run(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    if !interpreter.capabilities.process {
+        return Err(RuntimeErrorKind::PermissionDenied("process").into());
+    }
+
+    let program = String::try_from(args[0].clone())?;
+    let mut command = Command::new(program);
+    if let Some(list) = args.get(1) {
+        let items = list
+            .as_obj()
+            .and_then(|obj| obj.downcast::<ObjList>())
+            .ok_or(RuntimeErrorKind::TypeMismatch {
+                expected: "list",
+                found: list.type_name(),
+            })?
+            .items();
+        for item in items {
+            command.arg(String::try_from(item.clone())?);
+        }
+    }
+
+    let output = command
+        .output()
+        .map_err(|error| RuntimeErrorKind::Io(error.to_string()))?;
+
+    let mut table = Table::new();
+    let fields = [
+        ("status", output.status.code().map(i64::from).into()),
+        (
+            "stdout",
+            interpreter
+                .heap
+                .alloc_string(String::from_utf8_lossy(&output.stdout)),
+        ),
+        (
+            "stderr",
+            interpreter
+                .heap
+                .alloc_string(String::from_utf8_lossy(&output.stderr)),
+        ),
+    ];
+    for (name, value) in fields {
+        table.insert(ValueKey::new(interpreter.heap.alloc_string(name)), value);
+    }
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjMap::new(table))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{
+        capabilities::Capabilities, testing::run, Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn exit() {
+        let mut interpreter = Interpreter::default();
+        assert_eq!(
+            run(&mut interpreter, "func f() { exit(3); } f();"),
+            Err(RuntimeErrorKind::Exit(3))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn process() {
+        let mut interpreter = Interpreter::default();
+        let source = r#"
+            let x = process.run("sh", ["-c", "echo out; echo err >&2; exit 2"]);
+        "#;
+        assert_eq!(
+            run(&mut interpreter, source).as_deref(),
+            Ok(r#"{ "status": 2, "stdout": "out\n", "stderr": "err\n" }"#)
+        );
+
+        interpreter.set_capabilities(Capabilities::none());
+        assert_eq!(
+            run(&mut interpreter, source),
+            Err(RuntimeErrorKind::PermissionDenied("process"))
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Random numbers drawn from the interpreter's generator, which is seeded from the
+//! deterministic execution mode or by `seed`.
+
+use crate::interpreter::{
+    object::{Arity, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("random", Arity::Exact(0), random);
+    interpreter.define_native("random_int", Arity::Exact(2), random_int);
+    interpreter.define_native("shuffle", Arity::Exact(1), shuffle);
+    interpreter.define_native("seed", Arity::Exact(1), seed);
+}
+
+/// Number in the range `[0, 1)`.
+fn random(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(interpreter.rng().next_f64()))
+}
+
+/// Integer in the range `[a, b]`.
+fn random_int(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let a = i64::try_from(args[0].clone())?;
+    let b = i64::try_from(args[1].clone())?;
+    if a > b {
+        let message = format!("empty range {a}..={b}");
+        return Err(RuntimeErrorKind::InvalidArgument(message).into());
+    }
+
+    // The span only overflows when the range covers every `i64`.
+    let offset = match (b.abs_diff(a)).checked_add(1) {
+        Some(span) => interpreter.rng().next_below(span),
+        None => interpreter.rng().next_u64(),
+    };
+
+    Ok(Value::from(a.wrapping_add_unsigned(offset)))
+}
+
+/// Shuffles a list in place.
+fn shuffle(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut obj = args[0]
+        .as_obj()
+        .filter(|obj| obj.downcast::<ObjList>().is_some())
+        .cloned()
+        .ok_or(RuntimeErrorKind::TypeMismatch {
+            expected: "list",
+            found: args[0].type_name(),
+        })?;
+
+    let items = AsMut::<ObjList>::as_mut(&mut obj).items_mut();
+    // Fisher-Yates.
+    for i in (1..items.len()).rev() {
+        let j = interpreter.rng().next_below(i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+
+    Ok(Value::Nil)
+}
+
+/// Reseeds the generator so that the numbers which follow can be reproduced.
+fn seed(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let seed = i64::try_from(args[0].clone())?;
+    interpreter.rng().seed(seed as u64);
+
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::run, Interpreter};
+
+    #[test]
+    fn seeded() {
+        let source = "
+            seed(42);
+            let l = [1, 2, 3, 4, 5];
+            shuffle(l);
+            let x = [random(), random_int(-3, 3), l];
+        ";
+        let a = run(&mut Interpreter::default(), source).unwrap();
+        let b = run(&mut Interpreter::default(), source).unwrap();
+        assert_eq!(a, b);
+
+        let mut interpreter = Interpreter::default();
+        interpreter.set_deterministic(42);
+        let c = run(&mut interpreter, &source.replace("seed(42);", "")).unwrap();
+        assert_eq!(a, c);
+    }
+
+    #[test]
+    fn ranges() {
+        let mut interpreter = Interpreter::default();
+        let mut number =
+            |source| -> f64 { run(&mut interpreter, source).unwrap().parse().unwrap() };
+        for _ in 0..100 {
+            let n = number("let x = random_int(1, 3);");
+            assert!([1.0, 2.0, 3.0].contains(&n));
+
+            let n = number("let x = random();");
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Reflection, so that tools such as serializers and debuggers can be written in the language.
+//!
+//! Fields belong to instances, such as the modules `fs` and `time` or the objects created by
+//! `object`, while methods belong to the built in types.
+
+use crate::interpreter::{
+    methods,
+    object::{Arity, Obj, ObjInstance, ObjKind, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("type_of", Arity::Exact(1), type_of);
+    interpreter.define_native("object", Arity::Exact(0), object);
+    interpreter.define_native("fields", Arity::Exact(1), fields);
+    interpreter.define_native("methods", Arity::Exact(1), methods);
+    interpreter.define_native("has_field", Arity::Exact(2), has_field);
+    interpreter.define_native("get_field", Arity::Range(2, 3), get_field);
+    interpreter.define_native("set_field", Arity::Exact(3), set_field);
+}
+
+/// Returns the instance, or a type error if the value isn't one.
+fn instance(value: &Value) -> Result<Obj, RuntimeError> {
+    match value.as_obj() {
+        Some(obj) if obj.kind() == ObjKind::Instance => Ok(obj.clone()),
+        _ => Err(RuntimeErrorKind::TypeMismatch {
+            expected: "instance",
+            found: value.type_name(),
+        }
+        .into()),
+    }
+}
+
+/// Name of the type of the value, as used in error messages.
+fn type_of(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(interpreter.heap.alloc_string(args[0].type_name()))
+}
+
+/// New instance without any fields.
+fn object(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Obj(interpreter.heap.alloc(ObjInstance::new())))
+}
+
+/// Names of the fields of an instance, in the order they were added.
+fn fields(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let instance = instance(&args[0])?;
+    let names: Vec<_> = AsRef::<ObjInstance>::as_ref(&instance)
+        .fields()
+        .keys()
+        .cloned()
+        .collect();
+    let names = names
+        .into_iter()
+        .map(|name| interpreter.heap.alloc_string(name))
+        .collect();
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(names))))
+}
+
+/// Names of the methods of the type of the value.
+fn methods(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let names = methods::methods_of(&args[0])
+        .iter()
+        .map(|method| interpreter.heap.alloc_string(method.name))
+        .collect();
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(names))))
+}
+
+/// Whether the value is an instance with the field, values of other types have no fields.
+fn has_field(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let name = String::try_from(args[1].clone())?;
+    let has_field = args[0]
+        .as_obj()
+        .and_then(|obj| obj.downcast::<ObjInstance>())
+        .is_some_and(|instance| instance.fields().contains_key(name.as_str()));
+
+    Ok(Value::Bool(has_field))
+}
+
+/// Field of an instance by name, a missing field is an error unless a default is given.
+fn get_field(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let instance = instance(&args[0])?;
+    let name = String::try_from(args[1].clone())?;
+    let field = AsRef::<ObjInstance>::as_ref(&instance)
+        .fields()
+        .get(name.as_str())
+        .cloned();
+
+    match (field, args.get(2)) {
+        (Some(field), _) => Ok(field),
+        (None, Some(default)) => Ok(default.clone()),
+        (None, None) => Err(RuntimeErrorKind::UndefinedMember {
+            type_name: args[0].type_name(),
+            name,
+        }
+        .into()),
+    }
+}
+
+/// Sets or adds a field of an instance.
+fn set_field(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut instance = instance(&args[0])?;
+    let name = String::try_from(args[1].clone())?;
+    AsMut::<ObjInstance>::as_mut(&mut instance)
+        .fields_mut()
+        .insert(name, args[2].clone());
+
+    Ok(Value::Nil)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval, RuntimeErrorKind};
+
+    #[test]
+    fn reflection() {
+        let source = r#"
+            let o = object();
+            set_field(o, "a", 1);
+            set_field(o, "b", [2]);
+            let x = [
+                type_of(1),
+                type_of(o),
+                fields(o),
+                has_field(o, "a"),
+                has_field(o, "c"),
+                has_field(1, "a"),
+                get_field(o, "b"),
+                get_field(o, "c", nil),
+                o.a,
+                methods({}),
+            ];
+        "#;
+        assert_eq!(
+            eval(source).as_deref(),
+            Ok(
+                r#"["number", "instance", ["a", "b"], true, false, false, [2], nil, 1, ["get", "set", "remove", "has", "keys", "values", "entries", "len"]]"#
+            )
+        );
+        assert_eq!(
+            eval(r#"let x = get_field(object(), "c");"#),
+            Err(RuntimeErrorKind::UndefinedMember {
+                type_name: "instance",
+                name: "c".to_owned()
+            })
+        );
+        assert_eq!(
+            eval("let x = fields([]);"),
+            Err(RuntimeErrorKind::TypeMismatch {
+                expected: "instance",
+                found: "list"
+            })
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Regular expression functions, enabled by the `regex` feature. Patterns use the syntax of the
+//! [`regex`] crate, they are compiled on first use and cached by the interpreter.
+
+use std::{collections::HashMap, sync::Arc};
+
+use regex::{Captures, Regex};
+
+use crate::interpreter::{
+    object::{Arity, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+/// Compiled patterns by source, so that patterns used in loops are only compiled once.
+#[derive(Debug, Default)]
+pub(in crate::interpreter) struct RegexCache {
+    patterns: HashMap<String, Arc<Regex>>,
+}
+
+impl RegexCache {
+    /// Maximum number of patterns kept, the cache is emptied when it is exceeded.
+    const CAPACITY: usize = 64;
+
+    fn get(&mut self, pattern: &str) -> Result<Arc<Regex>, regex::Error> {
+        if let Some(regex) = self.patterns.get(pattern) {
+            return Ok(regex.clone());
+        }
+
+        if self.patterns.len() >= Self::CAPACITY {
+            self.patterns.clear();
+        }
+
+        let regex = Arc::new(Regex::new(pattern)?);
+        self.patterns.insert(pattern.to_owned(), regex.clone());
+
+        Ok(regex)
+    }
+}
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    interpreter.define_native("regex_match", Arity::Exact(2), regex_match);
+    interpreter.define_native("regex_find", Arity::Exact(2), regex_find);
+    interpreter.define_native("regex_captures", Arity::Exact(2), regex_captures);
+    interpreter.define_native("regex_replace", Arity::Exact(3), regex_replace);
+    interpreter.define_native("regex_split", Arity::Exact(2), regex_split);
+}
+
+/// Compiles the pattern and converts the text argument, which every function takes.
+fn compile(
+    interpreter: &mut Interpreter,
+    pattern: &Value,
+    text: &Value,
+) -> Result<(Arc<Regex>, String), RuntimeError> {
+    let pattern = String::try_from(pattern.clone())?;
+    let text = String::try_from(text.clone())?;
+    let regex = interpreter
+        .regex_cache
+        .get(&pattern)
+        .map_err(|error| RuntimeErrorKind::InvalidArgument(error.to_string()))?;
+
+    Ok((regex, text))
+}
+
+fn list(interpreter: &mut Interpreter, items: Vec<Value>) -> Value {
+    Value::Obj(interpreter.heap.alloc(ObjList::new(items)))
+}
+
+/// Whether the pattern matches anywhere in the text, use `^` and `$` to match the whole text.
+fn regex_match(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (regex, text) = compile(interpreter, &args[0], &args[1])?;
+
+    Ok(Value::Bool(regex.is_match(&text)))
+}
+
+/// Text of the first match, or nil if there isn't one.
+fn regex_find(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (regex, text) = compile(interpreter, &args[0], &args[1])?;
+    let found = regex
+        .find(&text)
+        .map(|found| interpreter.heap.alloc_string(found.as_str()));
+
+    Ok(found.unwrap_or_default())
+}
+
+/// List of the whole first match followed by each group, groups which didn't match are nil.
+/// Returns nil if there is no match.
+fn regex_captures(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (regex, text) = compile(interpreter, &args[0], &args[1])?;
+    let Some(captures) = regex.captures(&text) else {
+        return Ok(Value::Nil);
+    };
+
+    let groups = captures
+        .iter()
+        .map(|group| match group {
+            Some(group) => interpreter.heap.alloc_string(group.as_str()),
+            None => Value::Nil,
+        })
+        .collect();
+
+    Ok(list(interpreter, groups))
+}
+
+/// Replaces every match, `$n` in the replacement is replaced by group `n` and `$$` by `$`.
+fn regex_replace(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (regex, text) = compile(interpreter, &args[0], &args[1])?;
+    let replacement = String::try_from(args[2].clone())?;
+
+    let mut replaced = String::new();
+    let mut last = 0;
+    for captures in regex.captures_iter(&text) {
+        let range = captures.get_match().range();
+        replaced.push_str(&text[last..range.start]);
+        expand(&replacement, &captures, &mut replaced);
+        last = range.end;
+        // Each match can add the whole replacement.
+        interpreter.check_allocation(replaced.len())?;
+    }
+    replaced.push_str(&text[last..]);
+
+    Ok(interpreter.heap.alloc_string(replaced))
+}
+
+fn expand(replacement: &str, captures: &Captures, out: &mut String) {
+    let mut rest = replacement;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            out.push('$');
+            continue;
+        }
+
+        // Groups which don't exist or didn't match are replaced by nothing.
+        let group = rest[..digits].parse().ok().and_then(|n| captures.get(n));
+        if let Some(group) = group {
+            out.push_str(group.as_str());
+        }
+        rest = &rest[digits..];
+    }
+    out.push_str(rest);
+}
+
+/// Splits the text around every match.
+fn regex_split(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (regex, text) = compile(interpreter, &args[0], &args[1])?;
+
+    let mut pieces = vec![];
+    let mut last = 0;
+    for found in regex.find_iter(&text) {
+        pieces.push(interpreter.heap.alloc_string(&text[last..found.start()]));
+        last = found.end();
+    }
+    pieces.push(interpreter.heap.alloc_string(&text[last..]));
+
+    Ok(list(interpreter, pieces))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval_expr, RuntimeErrorKind};
+
+    #[test]
+    fn functions() {
+        let ok = |s: &str| Ok(s.to_owned());
+        assert_eq!(eval_expr(r#"regex_match("^\d+$", "123")"#), ok("true"));
+        assert_eq!(eval_expr(r#"regex_find("[a-z]+", "12 ab 3")"#), ok("ab"));
+        assert_eq!(eval_expr(r#"regex_find("x", "abc")"#), ok("nil"));
+        assert_eq!(
+            eval_expr(r#"regex_captures("(\w+)=(\d+)?", "key=")"#),
+            ok(r#"["key=", "key", nil]"#)
+        );
+        assert_eq!(
+            eval_expr(r#"regex_replace("(\w+)@(\w+)", "a@b c@d", "$2@$1 $$")"#),
+            ok("b@a $ d@c $")
+        );
+        assert_eq!(
+            eval_expr(r#"regex_split(",\s*", "a, b,c")"#),
+            ok(r#"["a", "b", "c"]"#)
+        );
+        assert!(matches!(
+            eval_expr(r#"regex_match("(", "")"#),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn matching() {
+        let ok = |s: &str| Ok(s.to_owned());
+        assert_eq!(eval_expr(r#"regex_find("b+?", "abbbc")"#), ok("b"));
+        assert_eq!(
+            eval_expr(r#"regex_find("\bcat\b", "concat cat")"#),
+            ok("cat")
+        );
+        assert_eq!(eval_expr(r#"regex_find("é.", "café!")"#), ok("é!"));
+        // Empty matches are found between characters, but not right after another match.
+        assert_eq!(
+            eval_expr(r#"regex_replace("a*", "baab", "-")"#),
+            ok("-b-b-")
+        );
+        // Matching takes linear time, even for patterns a backtracking engine would take
+        // exponential time on.
+        let text = "a".repeat(10_000);
+        assert_eq!(
+            eval_expr(&format!(r#"regex_match("(a*)*b", "{text}")"#)),
+            ok("false")
+        );
+    }
+}
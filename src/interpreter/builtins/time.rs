@@ -0,0 +1,378 @@
+//! The `time` module, times are represented as numbers of seconds since the unix epoch so that
+//! durations are plain numbers and can be added to and subtracted from times directly.
+//!
+//! There is no time zone database, dates are always in UTC.
+//!
+//! Formats use the following specifiers, any other character is matched literally:
+//! - `%Y` year, `%m` month, `%d` day
+//! - `%H` hour, `%M` minute, `%S` second, `%L` millisecond
+//! - `%b` abbreviated month name, `%a` abbreviated weekday name
+//! - `%%` a percent sign
+
+use super::define_module;
+use crate::interpreter::{
+    object::{Arity, ObjMap},
+    table::Table,
+    value::{Value, ValueKey},
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) fn define(interpreter: &mut Interpreter) {
+    define_module(
+        interpreter,
+        "time",
+        &[
+            ("time.now", Arity::Exact(0), now),
+            ("time.date", Arity::Range(0, 1), date),
+            ("time.format", Arity::Exact(2), format),
+            ("time.parse", Arity::Exact(2), parse),
+            ("time.duration", Arity::Exact(1), duration),
+        ],
+    );
+}
+
+/// Fails unless scripts may read the time, which is needed by `time.now` and the builtins of the
+/// clock.
+pub(super) fn check_clock(interpreter: &Interpreter) -> Result<(), RuntimeError> {
+    if interpreter.capabilities.clock {
+        Ok(())
+    } else {
+        Err(RuntimeErrorKind::PermissionDenied("clock").into())
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Date and time of day in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateTime {
+    year: i64,
+    /// 1 to 12.
+    month: u32,
+    /// 1 to 31.
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    millisecond: u32,
+}
+
+impl DateTime {
+    fn from_timestamp(timestamp: f64) -> Self {
+        let millis = (timestamp * 1000.0).floor() as i64;
+        let days = millis.div_euclid(SECONDS_PER_DAY * 1000);
+        let millis = millis.rem_euclid(SECONDS_PER_DAY * 1000) as u32;
+        let (year, month, day) = civil_from_days(days);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: millis / 3_600_000,
+            minute: millis / 60_000 % 60,
+            second: millis / 1000 % 60,
+            millisecond: millis % 1000,
+        }
+    }
+
+    fn timestamp(&self) -> f64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        let seconds = days * SECONDS_PER_DAY
+            + i64::from(self.hour) * 3600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second);
+
+        seconds as f64 + f64::from(self.millisecond) / 1000.0
+    }
+
+    /// 0 is Sunday.
+    fn weekday(&self) -> usize {
+        // The epoch was a Thursday.
+        (days_from_civil(self.year, self.month, self.day) + 4).rem_euclid(7) as usize
+    }
+}
+
+/// Number of days since the epoch of a date in the proleptic Gregorian calendar, this and
+/// `civil_from_days` are Howard Hinnant's algorithms.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    // Years start in March so that the leap day is at the end.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn invalid(message: String) -> RuntimeError {
+    RuntimeErrorKind::InvalidArgument(message).into()
+}
+
+fn map(interpreter: &mut Interpreter, fields: Vec<(&str, Value)>) -> Value {
+    let mut table = Table::new();
+    for (name, value) in fields {
+        table.insert(ValueKey::new(interpreter.heap.alloc_string(name)), value);
+    }
+
+    Value::Obj(interpreter.heap.alloc(ObjMap::new(table)))
+}
+
+/// Current time, read from the interpreter's clock so that it is reproducible in deterministic
+/// mode.
+fn now(interpreter: &mut Interpreter, _: &[Value]) -> Result<Value, RuntimeError> {
+    check_clock(interpreter)?;
+    Ok(Value::Number(interpreter.clock().now().as_secs_f64()))
+}
+
+/// Map of the components of a time, or of the current time if not given.
+fn date(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let timestamp = match args.first() {
+        Some(timestamp) => f64::try_from(timestamp.clone())?,
+        None => {
+            check_clock(interpreter)?;
+            interpreter.clock().now().as_secs_f64()
+        }
+    };
+    let date = DateTime::from_timestamp(timestamp);
+
+    let fields = vec![
+        ("year", Value::from(date.year)),
+        ("month", Value::from(i64::from(date.month))),
+        ("day", Value::from(i64::from(date.day))),
+        ("hour", Value::from(i64::from(date.hour))),
+        ("minute", Value::from(i64::from(date.minute))),
+        ("second", Value::from(i64::from(date.second))),
+        ("millisecond", Value::from(i64::from(date.millisecond))),
+        ("weekday", Value::from(date.weekday() as i64)),
+    ];
+
+    Ok(map(interpreter, fields))
+}
+
+fn format(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let date = DateTime::from_timestamp(f64::try_from(args[0].clone())?);
+    let format = String::try_from(args[1].clone())?;
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+
+        let spec = chars.next();
+        let field = match spec {
+            Some('Y') => format!("{:04}", date.year),
+            Some('m') => format!("{:02}", date.month),
+            Some('d') => format!("{:02}", date.day),
+            Some('H') => format!("{:02}", date.hour),
+            Some('M') => format!("{:02}", date.minute),
+            Some('S') => format!("{:02}", date.second),
+            Some('L') => format!("{:03}", date.millisecond),
+            Some('b') => MONTHS[date.month as usize - 1].to_owned(),
+            Some('a') => WEEKDAYS[date.weekday()].to_owned(),
+            Some('%') => "%".to_owned(),
+            _ => return Err(invalid(format!("invalid format specifier in {format:?}"))),
+        };
+        formatted.push_str(&field);
+    }
+
+    Ok(interpreter.heap.alloc_string(formatted))
+}
+
+/// Parses a time in the given format, fields which aren't in the format default to those of the
+/// epoch.
+fn parse(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = String::try_from(args[0].clone())?;
+    let format = String::try_from(args[1].clone())?;
+    let mismatch = || invalid(format!("{text:?} doesn't match format {format:?}"));
+
+    let mut date = DateTime::from_timestamp(0.0);
+    let mut rest = text.as_str();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            rest = rest.strip_prefix(c).ok_or_else(mismatch)?;
+            continue;
+        }
+
+        let spec = chars.next();
+        let max_digits = match spec {
+            Some('Y') => 4,
+            Some('m' | 'd' | 'H' | 'M' | 'S') => 2,
+            Some('L') => 3,
+            Some('b' | 'a') => {
+                let names: &[&str] = if spec == Some('b') {
+                    &MONTHS
+                } else {
+                    &WEEKDAYS
+                };
+                let index = names
+                    .iter()
+                    .position(|name| {
+                        rest.get(..3)
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+                    })
+                    .ok_or_else(mismatch)?;
+                rest = &rest[3..];
+                // Weekdays are implied by the date so they are only checked for their syntax.
+                if spec == Some('b') {
+                    date.month = index as u32 + 1;
+                }
+                continue;
+            }
+            Some('%') => {
+                rest = rest.strip_prefix('%').ok_or_else(mismatch)?;
+                continue;
+            }
+            _ => return Err(invalid(format!("invalid format specifier in {format:?}"))),
+        };
+
+        let digits = rest
+            .bytes()
+            .take(max_digits)
+            .take_while(u8::is_ascii_digit)
+            .count();
+        if digits == 0 {
+            return Err(mismatch());
+        }
+        let n: u32 = rest[..digits].parse().unwrap();
+        rest = &rest[digits..];
+
+        match spec {
+            Some('Y') => date.year = i64::from(n),
+            Some('m') => date.month = n,
+            Some('d') => date.day = n,
+            Some('H') => date.hour = n,
+            Some('M') => date.minute = n,
+            Some('S') => date.second = n,
+            Some('L') => date.millisecond = n,
+            _ => unreachable!(),
+        }
+    }
+
+    let is_valid = (1..=12).contains(&date.month)
+        && (1..=days_in_month(date.year, date.month)).contains(&date.day)
+        && date.hour < 24
+        && date.minute < 60
+        && date.second < 60;
+    if !rest.is_empty() || !is_valid {
+        return Err(mismatch());
+    }
+
+    Ok(Value::Number(date.timestamp()))
+}
+
+/// Number of seconds in a map of `weeks`, `days`, `hours`, `minutes`, `seconds` and
+/// `milliseconds`, all of which are optional.
+fn duration(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let map = args[0]
+        .as_obj()
+        .and_then(|obj| obj.downcast::<ObjMap>())
+        .ok_or(RuntimeErrorKind::TypeMismatch {
+            expected: "map",
+            found: args[0].type_name(),
+        })?;
+
+    let mut seconds = 0.0;
+    for (key, value) in map.entries().iter() {
+        let unit = match key.value().as_str() {
+            Some("weeks") => 7.0 * SECONDS_PER_DAY as f64,
+            Some("days") => SECONDS_PER_DAY as f64,
+            Some("hours") => 3600.0,
+            Some("minutes") => 60.0,
+            Some("seconds") => 1.0,
+            Some("milliseconds") => 0.001,
+            _ => return Err(invalid(format!("unknown duration unit {:#}", key.value()))),
+        };
+        seconds += unit * f64::try_from(value.clone())?;
+    }
+
+    Ok(Value::Number(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{civil_from_days, days_from_civil, DateTime};
+    use crate::interpreter::{testing::eval_expr, RuntimeErrorKind};
+
+    #[test]
+    fn calendar() {
+        for days in [-800_000, -1, 0, 59, 19_782, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+
+        let date = DateTime::from_timestamp(1_709_210_096.5);
+        assert_eq!((date.year, date.month, date.day), (2024, 2, 29));
+        assert_eq!((date.hour, date.minute, date.second), (12, 34, 56));
+        assert_eq!(date.millisecond, 500);
+        assert_eq!(date.weekday(), 4);
+        assert_eq!(DateTime::from_timestamp(-1.0).year, 1969);
+    }
+
+    #[test]
+    fn functions() {
+        let ok = |s: &str| Ok(s.to_owned());
+        assert_eq!(
+            eval_expr(r#"time.format(1709210096, "%a %d %b %Y %H:%M:%S.%L %%")"#),
+            ok("Thu 29 Feb 2024 12:34:56.000 %")
+        );
+        assert_eq!(
+            eval_expr(r#"time.parse("2024-02-29 12:34:56", "%Y-%m-%d %H:%M:%S")"#),
+            ok("1709210096")
+        );
+        assert_eq!(
+            eval_expr(r#"time.parse("1 mar 1970", "%d %b %Y")"#),
+            ok("5097600")
+        );
+        assert_eq!(eval_expr(r#"time.date(86400)["weekday"]"#), ok("5"));
+        assert_eq!(
+            eval_expr(r#"time.duration({ "days": 1, "minutes": 1.5 })"#),
+            ok("86490")
+        );
+        for source in [
+            r#"time.parse("2023-02-29", "%Y-%m-%d")"#,
+            r#"time.parse("2024-01-01x", "%Y-%m-%d")"#,
+            r#"time.format(0, "%q")"#,
+        ] {
+            assert!(
+                matches!(eval_expr(source), Err(RuntimeErrorKind::InvalidArgument(_))),
+                "{source}"
+            );
+        }
+    }
+}
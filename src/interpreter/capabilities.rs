@@ -0,0 +1,127 @@
+//! Access to the host system which scripts are granted, embedders can disable anything scripts
+//! shouldn't be able to touch:
+//!
+//! ```
+//! use unnamed_language::{interpreter::capabilities::Capabilities, Engine};
+//!
+//! let capabilities = Capabilities {
+//!     clock: true,
+//!     hide_denied: true,
+//!     ..Capabilities::none()
+//! };
+//! let mut engine = Engine::new().with_capabilities(capabilities);
+//! assert!(engine.eval("clock()").is_ok());
+//! assert!(engine.eval("fs").is_err());
+//! ```
+//!
+//! Builtins which use a denied capability raise a
+//! [`PermissionDenied`](super::RuntimeErrorKind::PermissionDenied) error naming it, or aren't
+//! defined at all if [`hide_denied`](Capabilities::hide_denied) is set.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Reading and writing files and directories, only used with the `fs` feature.
+    pub fs: bool,
+    /// Reading environment variables.
+    pub env: bool,
+    /// Running other programs, only used with the `process` feature.
+    pub process: bool,
+    /// Connecting to other hosts, only used with the `net` feature.
+    pub net: bool,
+    /// Reading the time with `clock`, `clock_ms` and `time.now`, which scripts could use to
+    /// measure the host.
+    pub clock: bool,
+    /// Loading native plugins, only used with the `plugins` feature. Modules registered by the
+    /// host can always be imported.
+    pub plugins: bool,
+    /// Removes the builtins of denied capabilities when the capabilities are set, so that scripts
+    /// see them as undefined variables and members. They aren't defined again if the
+    /// capabilities are granted later.
+    pub hide_denied: bool,
+}
+
+impl Capabilities {
+    /// Grants everything, this is the default.
+    pub fn all() -> Self {
+        Self {
+            fs: true,
+            env: true,
+            process: true,
+            net: true,
+            clock: true,
+            plugins: true,
+            hide_denied: false,
+        }
+    }
+
+    /// Grants nothing, scripts can only compute and use the interpreter's IO.
+    pub fn none() -> Self {
+        Self {
+            fs: false,
+            env: false,
+            process: false,
+            net: false,
+            clock: false,
+            plugins: false,
+            hide_denied: false,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Capabilities;
+    use crate::{interpreter::RuntimeErrorKind, Engine, Error};
+
+    fn error(engine: &mut Engine, source: &str) -> RuntimeErrorKind {
+        let Err(Error::Runtime(error)) = engine.eval(source) else {
+            panic!("error should have been returned");
+        };
+        error.kind().clone()
+    }
+
+    #[test]
+    fn denied() {
+        let mut engine = Engine::new().with_capabilities(Capabilities::none());
+        assert_eq!(
+            error(&mut engine, "env.get(\"HOME\")"),
+            RuntimeErrorKind::PermissionDenied("environment")
+        );
+        for source in ["clock()", "clock_ms()", "time.now()", "time.date()"] {
+            assert_eq!(
+                error(&mut engine, source),
+                RuntimeErrorKind::PermissionDenied("clock")
+            );
+        }
+        // Times given by the script don't need the clock.
+        assert!(engine.eval("time.date(0)[\"year\"]").is_ok());
+    }
+
+    #[test]
+    fn hidden() {
+        let capabilities = Capabilities {
+            env: true,
+            hide_denied: true,
+            ..Capabilities::none()
+        };
+        let mut engine = Engine::new().with_capabilities(capabilities);
+        for name in ["fs", "process", "http", "clock", "clock_ms"] {
+            assert_eq!(
+                error(&mut engine, name),
+                RuntimeErrorKind::UndefinedVariable(name.to_owned())
+            );
+        }
+        assert!(matches!(
+            error(&mut engine, "time.now"),
+            RuntimeErrorKind::UndefinedMember { name, .. } if name == "now"
+        ));
+        assert!(engine.eval("env.get(\"HOME\")").is_ok());
+        assert!(engine.eval("time.format(0, \"%Y\")").is_ok());
+    }
+}
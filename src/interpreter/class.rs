@@ -0,0 +1,232 @@
+//! Exposing Rust types to scripts as classes. Objects of a class are foreign objects wrapping a
+//! value of the Rust type, with methods and properties implemented by Rust functions over that
+//! value:
+//!
+//! ```
+//! use unnamed_language::{interpreter::class::ClassBuilder, Engine};
+//!
+//! struct Sprite {
+//!     x: f64,
+//!     y: f64,
+//! }
+//!
+//! let mut engine = Engine::new();
+//! // SAFETY: the class only converts values to and from numbers, it doesn't keep any.
+//! unsafe {
+//!     engine.register_class(
+//!         ClassBuilder::new("Sprite")
+//!             .constructor(|x: f64, y: f64| Sprite { x, y })
+//!             .method("move", |sprite: &mut Sprite, dx: f64, dy: f64| {
+//!                 sprite.x += dx;
+//!                 sprite.y += dy;
+//!             })
+//!             .property("x", |sprite: &Sprite| sprite.x),
+//!     )
+//! };
+//! let value = engine.eval("let s = Sprite(1, 1); s.move(2, 3); s.x").unwrap();
+//! assert_eq!(value.to_string(), "3");
+//! ```
+//!
+//! Calling the class creates an object, the host can also create objects of a class with
+//! [`Interpreter::alloc_object`]. Properties can only be read since scripts can't assign to
+//! members.
+
+use std::{
+    any::Any, cell::RefMut, collections::HashMap, fmt::Debug, marker::PhantomData, sync::Arc,
+};
+
+use super::{
+    native::{IntoConstructor, IntoMethod},
+    object::{Arity, NativeClosure},
+    value::{IntoValue, Value},
+    Interpreter,
+};
+
+/// Method of a class, called with the object as its first argument.
+#[derive(Clone)]
+pub struct ClassMethod {
+    /// Number of arguments accepted, not counting the object.
+    pub arity: Arity,
+    pub function: Arc<NativeClosure>,
+}
+
+pub struct Class {
+    name: &'static str,
+    methods: HashMap<&'static str, ClassMethod>,
+    /// Getters of the properties, called with the object as their only argument.
+    properties: HashMap<&'static str, Arc<NativeClosure>>,
+}
+
+impl Class {
+    /// Returns the name of the class, this is the type name of its objects.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn method(&self, name: &str) -> Option<&ClassMethod> {
+        self.methods.get(name)
+    }
+
+    pub fn property(&self, name: &str) -> Option<&Arc<NativeClosure>> {
+        self.properties.get(name)
+    }
+
+    /// Names of the methods and properties, in no particular order.
+    pub fn members(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.methods.keys().chain(self.properties.keys()).copied()
+    }
+}
+
+impl Debug for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Class")
+            .field("name", &self.name)
+            .field("methods", &self.methods.keys())
+            .field("properties", &self.properties.keys())
+            .finish()
+    }
+}
+
+/// Builds the class of the Rust type `T`, which is defined by [`Interpreter::define_class`].
+pub struct ClassBuilder<T> {
+    class: Class,
+    constructor: Option<ClassMethod>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Any + Send> ClassBuilder<T> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            class: Class {
+                name,
+                methods: HashMap::new(),
+                properties: HashMap::new(),
+            },
+            constructor: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the function which creates objects when the class is called. Without a constructor
+    /// the class isn't defined as a global, and only the host can create objects.
+    pub fn constructor<Args, F: IntoConstructor<T, Args>>(mut self, constructor: F) -> Self {
+        self.constructor = Some(ClassMethod {
+            arity: F::ARITY,
+            function: constructor.into_constructor(),
+        });
+        self
+    }
+
+    /// Adds a method, which takes the object's value mutably followed by its arguments.
+    pub fn method<Args, F: IntoMethod<T, Args>>(mut self, name: &'static str, method: F) -> Self {
+        let method = ClassMethod {
+            arity: F::ARITY,
+            function: method.into_method(),
+        };
+        self.class.methods.insert(name, method);
+        self
+    }
+
+    /// Adds a read only property whose value is returned by `getter`.
+    pub fn property<R: IntoValue>(
+        mut self,
+        name: &'static str,
+        getter: impl Fn(&T) -> R + Send + Sync + 'static,
+    ) -> Self {
+        let getter: Arc<NativeClosure> = Arc::new(move |interpreter: &mut Interpreter, args| {
+            let value = getter(&receiver::<T>(&args[0]));
+            Ok(value.into_value(interpreter.heap_mut()))
+        });
+        self.class.properties.insert(name, getter);
+        self
+    }
+
+    pub(super) fn build(self) -> (Class, Option<ClassMethod>) {
+        (self.class, self.constructor)
+    }
+}
+
+/// Borrows the value of the object a method or property of the class of `T` was called on.
+///
+/// # Panics
+///
+/// Panics if the object isn't of the class of `T`, or is already borrowed mutably.
+pub(super) fn receiver<T: Any>(object: &Value) -> RefMut<'_, T> {
+    object
+        .as_foreign()
+        .and_then(|foreign| foreign.borrow_mut())
+        .expect("receiver should be an object of the class")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClassBuilder;
+    use crate::{
+        interpreter::{object::Arity, RuntimeErrorKind},
+        Engine, Error, HostValue,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Counter {
+        count: i64,
+        step: i64,
+    }
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        // SAFETY: the class doesn't keep any values.
+        unsafe {
+            engine.register_class(
+                ClassBuilder::new("Counter")
+                    .constructor(|step: i64| Counter { count: 0, step })
+                    .method("increment", |counter: &mut Counter| {
+                        counter.count += counter.step;
+                        counter.count
+                    })
+                    .method("label", |counter: &mut Counter, prefix: String| {
+                        format!("{prefix}{}", counter.count)
+                    })
+                    .property("count", |counter: &Counter| counter.count),
+            )
+        };
+
+        engine
+    }
+
+    #[test]
+    fn members() {
+        let mut engine = engine();
+        let source = "let c = Counter(2); c.increment(); c.increment(); c.label(\"n=\")";
+        assert_eq!(engine.eval(source).unwrap().to_string(), "n=4");
+        assert_eq!(engine.eval("c.count").unwrap(), HostValue::Number(4.0));
+        assert_eq!(engine.eval("type_of(c)").unwrap().to_string(), "Counter");
+        assert_eq!(engine.eval("c").unwrap().to_string(), "<Counter>");
+
+        let Err(Error::Runtime(error)) = engine.eval("c.incremnt()") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(error.suggestion(), Some("increment"));
+        let Err(Error::Runtime(error)) = engine.eval("c.label()") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::ArityMismatch {
+                expected: Arity::Exact(1),
+                found: 0
+            }
+        );
+    }
+
+    #[test]
+    fn host_objects() {
+        let mut engine = engine();
+        engine.set_object("shared", Counter { count: 7, step: 1 });
+        engine.eval("shared.increment();").unwrap();
+        assert_eq!(engine.eval("shared.count").unwrap(), HostValue::Number(8.0));
+        assert_eq!(
+            engine.get_global("shared").unwrap().to_string(),
+            "<Counter>"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of time for the time related builtins.
+#[derive(Debug, Clone)]
+pub enum Clock {
+    System {
+        start: Instant,
+    },
+    /// Clock which starts at the unix epoch and advances by `tick` every time it is read, used to
+    /// make runs reproducible.
+    Virtual {
+        now: Duration,
+        tick: Duration,
+    },
+}
+
+impl Clock {
+    /// Creates a clock which reads the system's time. There is no system clock on
+    /// wasm32-unknown-unknown, reading it panics, so a virtual clock stands in for it there.
+    pub fn system() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            return Self::virtual_clock(Duration::from_millis(1));
+        }
+
+        Self::System {
+            start: Instant::now(),
+        }
+    }
+
+    pub fn virtual_clock(tick: Duration) -> Self {
+        Self::Virtual {
+            now: Duration::ZERO,
+            tick,
+        }
+    }
+
+    /// Returns the monotonic time elapsed since the clock was created.
+    pub fn elapsed(&mut self) -> Duration {
+        match self {
+            Clock::System { start } => start.elapsed(),
+            Clock::Virtual { .. } => self.advance(),
+        }
+    }
+
+    /// Returns the current instant to measure how long something takes with, or `None` for
+    /// virtual clocks since reading them would change the times scripts see.
+    pub(super) fn stopwatch(&self) -> Option<Instant> {
+        match self {
+            Clock::System { .. } => Some(Instant::now()),
+            Clock::Virtual { .. } => None,
+        }
+    }
+
+    /// Returns the wall clock time as a duration since the unix epoch.
+    pub fn now(&mut self) -> Duration {
+        match self {
+            // Times before the epoch are clamped, this only happens if the system clock is wrong.
+            Clock::System { .. } => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            Clock::Virtual { .. } => self.advance(),
+        }
+    }
+
+    fn advance(&mut self) -> Duration {
+        match self {
+            Clock::System { .. } => unreachable!(),
+            Clock::Virtual { now, tick } => {
+                *now += *tick;
+                *now
+            }
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::system()
+    }
+}
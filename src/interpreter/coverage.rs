@@ -0,0 +1,196 @@
+//! Line coverage: a [`DebugHook`] counts how many times each statement of a script runs, and the
+//! counts are reported per line as annotated source or in the LCOV format read by most coverage
+//! tools.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    debug::{collect_functions, is_script_code, DebugHook},
+    Interpreter, RuntimeError,
+};
+use crate::compiler::parser::{
+    ast::{Decl, DeclKind, Expr, Script, Stmt, StmtKind},
+    token::Location,
+    visit::{walk_decl, walk_stmt, Visitor},
+};
+
+/// Coverage of a script, to be filled in by running it with the hook given by [`Self::hook`].
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    counts: Arc<Mutex<Counts>>,
+}
+
+#[derive(Debug)]
+struct Counts {
+    /// Functions declared by the script, only their statements are counted.
+    functions: HashSet<usize>,
+    /// Times each statement ran by line and start offset.
+    statements: BTreeMap<usize, BTreeMap<usize, usize>>,
+}
+
+impl Coverage {
+    pub fn new(script: &Script) -> Self {
+        let mut statements = Statements::default();
+        statements.visit_script(script);
+
+        Self {
+            counts: Arc::new(Mutex::new(Counts {
+                functions: collect_functions(script),
+                statements: statements.0,
+            })),
+        }
+    }
+
+    /// Returns a hook which counts the statements of the script as they run, it shares its
+    /// counts with this.
+    pub fn hook(&self) -> Box<dyn DebugHook> {
+        Box::new(self.clone())
+    }
+
+    /// Returns each line which has a statement with how many times it ran. A line with several
+    /// statements counts as often as the one which ran the most.
+    pub fn lines(&self) -> BTreeMap<usize, usize> {
+        self.counts
+            .lock()
+            .unwrap()
+            .statements
+            .iter()
+            .map(|(&line, statements)| (line, statements.values().copied().max().unwrap_or(0)))
+            .collect()
+    }
+
+    /// Returns how many lines ran and how many lines have statements.
+    pub fn summary(&self) -> (usize, usize) {
+        let lines = self.lines();
+        let hit = lines.values().filter(|&&count| count > 0).count();
+        (hit, lines.len())
+    }
+
+    /// Returns the source with each line prefixed by how many times it ran, `#####` if it never
+    /// did, or `-` if it has no statements.
+    pub fn annotate(&self, source: &str) -> String {
+        let lines = self.lines();
+        let mut annotated = String::new();
+        for (i, text) in source.lines().enumerate() {
+            let count = match lines.get(&(i + 1)) {
+                Some(0) => "#####".to_owned(),
+                Some(count) => count.to_string(),
+                None => "-".to_owned(),
+            };
+            writeln!(annotated, "{count:>9}: {:>4} | {text}", i + 1).unwrap();
+        }
+
+        annotated
+    }
+
+    /// Returns an LCOV tracefile for the script at the path.
+    pub fn lcov(&self, path: &str) -> String {
+        let lines = self.lines();
+        let (hit, found) = self.summary();
+        let mut lcov = format!("TN:\nSF:{path}\n");
+        for (line, count) in lines {
+            writeln!(lcov, "DA:{line},{count}").unwrap();
+        }
+        writeln!(lcov, "LH:{hit}\nLF:{found}\nend_of_record").unwrap();
+
+        lcov
+    }
+}
+
+impl DebugHook for Coverage {
+    fn statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        location: Location,
+    ) -> Result<(), RuntimeError> {
+        let mut counts = self.counts.lock().unwrap();
+        if is_script_code(interpreter, &counts.functions) {
+            *counts
+                .statements
+                .entry(location.line)
+                .or_default()
+                .entry(location.start)
+                .or_default() += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the statements the interpreter calls the hook for, which are those with a location
+/// except blocks.
+#[derive(Default)]
+struct Statements(BTreeMap<usize, BTreeMap<usize, usize>>);
+
+impl Statements {
+    fn insert(&mut self, location: Location) {
+        self.0
+            .entry(location.line)
+            .or_default()
+            .insert(location.start, 0);
+    }
+}
+
+impl Visitor for Statements {
+    fn visit_decl(&mut self, decl: &Decl) {
+        if !matches!(decl.kind, DeclKind::Stmt(_)) {
+            self.insert(decl.location);
+        }
+        walk_decl(self, decl);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if !matches!(stmt.kind, StmtKind::Block(_)) {
+            self.insert(stmt.location);
+        }
+        walk_stmt(self, stmt);
+    }
+
+    // Expressions don't contain statements.
+    fn visit_expr(&mut self, _expr: &Expr) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coverage;
+    use crate::{compiler::parser::Parser, interpreter::Interpreter};
+
+    #[test]
+    fn lines() {
+        let source = "func f(n) {
+    if n > 1 {
+        return 1;
+    }
+    return 2;
+}
+let i = 0;
+while i < 3 { i = i + 1; }
+f(0);
+";
+        let script = Parser::new(source).parse().unwrap();
+        let coverage = Coverage::new(&script);
+        let mut interpreter = Interpreter::default();
+        interpreter.set_debug_hook(Some(coverage.hook()));
+        interpreter.interpret(&script).unwrap();
+
+        let lines: Vec<_> = coverage.lines().into_iter().collect();
+        assert_eq!(
+            lines,
+            [(1, 1), (2, 1), (3, 0), (5, 1), (7, 1), (8, 3), (9, 1)]
+        );
+        assert_eq!(coverage.summary(), (6, 7));
+        assert!(coverage
+            .annotate(source)
+            .starts_with("        1:    1 | func f(n) {\n"));
+        assert!(coverage.annotate(source).contains("    #####:    3 |"));
+        assert!(coverage.annotate(source).contains("        -:    4 |"));
+        assert_eq!(
+            coverage.lcov("a.ul"),
+            "TN:\nSF:a.ul\nDA:1,1\nDA:2,1\nDA:3,0\nDA:5,1\nDA:7,1\nDA:8,3\nDA:9,1\nLH:6\nLF:7\nend_of_record\n"
+        );
+    }
+}
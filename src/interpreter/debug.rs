@@ -0,0 +1,473 @@
+//! Debugging support: the interpreter calls a [`DebugHook`] before each statement, and
+//! [`Debugger`] is a hook which pauses at breakpoints and while stepping, handing control to a
+//! [`Frontend`] such as a console or an editor connected through the Debug Adapter Protocol.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+
+use super::{value::Value, Interpreter, RuntimeError, RuntimeErrorKind};
+use crate::compiler::parser::{
+    ast::{Decl, DeclKind, Function, Identifier, Script, Stmt, StmtKind},
+    token::Location,
+    visit::{walk_function, Visitor},
+};
+
+pub trait DebugHook: Debug + Send {
+    /// Called before each statement which has a location, the statement runs once this returns
+    /// `Ok`. Returning an error stops the script with it.
+    fn statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        location: Location,
+    ) -> Result<(), RuntimeError>;
+}
+
+/// How execution continues after a pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Run until a breakpoint.
+    Continue,
+    /// Pause at the next statement, including statements of called functions.
+    StepInto,
+    /// Pause at the next statement of the current function or its callers.
+    StepOver,
+    /// Pause at the next statement of a caller of the current function.
+    StepOut,
+    /// End the script, as if it called `exit(0)`.
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// Before the first statement of the script.
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+/// Interface the debugger is driven through while the script is paused.
+pub trait Frontend: Debug + Send {
+    fn paused(&mut self, paused: Paused<'_>, reason: StopReason) -> Resume;
+}
+
+/// Hook which pauses the script at breakpoints and while stepping. Only statements of the script
+/// it was created for pause, code from elsewhere such as the prelude runs without stopping.
+#[derive(Debug)]
+pub struct Debugger<F> {
+    script: Arc<Script>,
+    /// Functions declared by the script, identified by address.
+    functions: HashSet<usize>,
+    /// Lines with breakpoints, counted from 1.
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    /// Location and call depth of the last statement of the script which ran, a breakpoint
+    /// only pauses once when several statements on its line run one after another.
+    previous: Option<(Location, usize)>,
+    frontend: F,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Entry,
+    Continue,
+    StepInto,
+    /// Pause once the call depth is at most this.
+    StepOver(usize),
+    /// Pause once the call depth is less than this.
+    StepOut(usize),
+}
+
+impl<F: Frontend> Debugger<F> {
+    /// Creates a debugger which pauses before the first statement of the script.
+    pub fn new(script: Arc<Script>, frontend: F) -> Self {
+        Self {
+            functions: collect_functions(&script),
+            script,
+            breakpoints: BTreeSet::new(),
+            mode: Mode::Entry,
+            previous: None,
+            frontend,
+        }
+    }
+
+    /// Runs until the first breakpoint instead of pausing before the first statement.
+    pub fn without_stop_on_entry(mut self) -> Self {
+        self.mode = Mode::Continue;
+        self
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut BTreeSet<usize> {
+        &mut self.breakpoints
+    }
+}
+
+impl<F: Frontend> DebugHook for Debugger<F> {
+    fn statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        location: Location,
+    ) -> Result<(), RuntimeError> {
+        if !is_script_code(interpreter, &self.functions) {
+            return Ok(());
+        }
+
+        let depth = interpreter.frames().len();
+        // Going back to an earlier statement on the same line, as loops do, enters it again.
+        let new_line = !self.previous.is_some_and(|(previous, previous_depth)| {
+            previous.line == location.line
+                && previous.start < location.start
+                && previous_depth == depth
+        });
+        self.previous = Some((location, depth));
+        let reason = match self.mode {
+            Mode::Entry => StopReason::Entry,
+            _ if new_line && self.breakpoints.contains(&location.line) => StopReason::Breakpoint,
+            Mode::StepInto => StopReason::Step,
+            Mode::StepOver(max) if depth <= max => StopReason::Step,
+            Mode::StepOut(max) if depth < max => StopReason::Step,
+            _ => return Ok(()),
+        };
+
+        let paused = Paused {
+            interpreter,
+            location,
+            script: &self.script,
+            functions: &self.functions,
+            breakpoints: &mut self.breakpoints,
+        };
+        self.mode = match self.frontend.paused(paused, reason) {
+            Resume::Continue => Mode::Continue,
+            Resume::StepInto => Mode::StepInto,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+            Resume::Stop => return Err(RuntimeErrorKind::Exit(0).into()),
+        };
+
+        Ok(())
+    }
+}
+
+/// Whether the statement being run belongs to the script which declared the functions, that is
+/// it is either at the top level or in one of the functions.
+pub(super) fn is_script_code(interpreter: &Interpreter, functions: &HashSet<usize>) -> bool {
+    match interpreter.frames().last() {
+        None => true,
+        Some(frame) => frame
+            .declaration()
+            .is_some_and(|function| functions.contains(&address(function))),
+    }
+}
+
+/// Returns the functions declared by the script.
+pub(super) fn collect_functions(script: &Script) -> HashSet<usize> {
+    #[derive(Default)]
+    struct Functions(HashSet<usize>);
+
+    impl Visitor for Functions {
+        fn visit_function(&mut self, function: &Function) {
+            self.0.insert(address(function));
+            walk_function(self, function);
+        }
+    }
+
+    let mut functions = Functions::default();
+    functions.visit_script(script);
+    functions.0
+}
+
+/// Identifies a function by its address, which doesn't change since functions are shared through
+/// `Arc`s. Addresses are kept rather than pointers so that hooks holding them can be sent between
+/// threads.
+fn address(function: &Function) -> usize {
+    function as *const Function as usize
+}
+
+/// State of a paused script.
+#[derive(Debug)]
+pub struct Paused<'a> {
+    interpreter: &'a mut Interpreter,
+    location: Location,
+    script: &'a Script,
+    functions: &'a HashSet<usize>,
+    breakpoints: &'a mut BTreeSet<usize>,
+}
+
+/// Function call of a paused script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    pub function: String,
+    /// Location being executed, this is `None` for native functions and functions which aren't
+    /// part of the script.
+    pub location: Option<Location>,
+}
+
+impl Paused<'_> {
+    /// Location of the statement which is about to run.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        self.interpreter
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut BTreeSet<usize> {
+        self.breakpoints
+    }
+
+    /// Returns the active function calls, innermost first and ending with the script itself.
+    pub fn stack(&self) -> Vec<StackFrame> {
+        let frames = self.interpreter.frames();
+        let mut stack = vec![];
+        let mut location = Some(self.location);
+        for frame in frames.iter().rev() {
+            let in_script = frame
+                .declaration()
+                .is_some_and(|function| self.functions.contains(&address(function)));
+            stack.push(StackFrame {
+                function: frame.function().to_owned(),
+                location: location.filter(|_| in_script),
+            });
+            location = frame.call_location();
+        }
+        stack.push(StackFrame {
+            function: "<script>".to_owned(),
+            location,
+        });
+
+        stack
+    }
+
+    /// Returns the local variables in scope in a frame of [`Paused::stack`], in the order they
+    /// were declared. Variables which shadow others are listed after them.
+    pub fn locals(&self, frame: usize) -> Vec<(String, Value)> {
+        let frames = self.interpreter.frames();
+        let Some(location) = self.stack().get(frame).and_then(|frame| frame.location) else {
+            return vec![];
+        };
+
+        // Frames are counted from the innermost, `None` is the script itself.
+        let index = frames.len().checked_sub(frame + 1);
+        let mut visible = vec![];
+        match index {
+            Some(index) => {
+                let Some(function) = frames[index].declaration() else {
+                    return vec![];
+                };
+                visible.extend(&function.params);
+                if let StmtKind::Block(decls) = &function.body.kind {
+                    find_visible(decls, location, &mut visible);
+                }
+            }
+            None => {
+                find_visible(&self.script.decls, location, &mut visible);
+            }
+        }
+
+        visible
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.interpreter.frame_variable(index, name.slot())?;
+                Some((name.as_ref().to_owned(), value))
+            })
+            .collect()
+    }
+
+    /// Returns the global variables sorted by name.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<_> = self
+            .interpreter
+            .globals()
+            .map(|(name, value)| (name.to_owned(), value.clone()))
+            .collect();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        globals
+    }
+}
+
+/// Finds the declaration at the location, adding the local variables declared before it in the
+/// blocks which contain it to `visible`. Returns whether it was found.
+fn find_visible<'a>(
+    decls: &'a [Decl],
+    location: Location,
+    visible: &mut Vec<&'a Identifier>,
+) -> bool {
+    for decl in decls {
+        if !matches!(&decl.kind, DeclKind::Stmt(stmt) if matches!(&stmt.kind, StmtKind::Block(_)))
+            && decl.location == location
+        {
+            return true;
+        }
+
+        match &decl.kind {
+            DeclKind::Var(name, _) | DeclKind::Import(name) => visible.push(name),
+            DeclKind::Func(function) => visible.push(&function.name),
+            DeclKind::Test(_) => {}
+            DeclKind::Stmt(stmt) => {
+                if find_visible_in(stmt, location, visible) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+fn find_visible_in<'a>(
+    stmt: &'a Stmt,
+    location: Location,
+    visible: &mut Vec<&'a Identifier>,
+) -> bool {
+    let len = visible.len();
+    let found = match &stmt.kind {
+        StmtKind::If(_, consequent, alternative) => {
+            find_visible_in(consequent, location, visible)
+                || alternative
+                    .as_ref()
+                    .is_some_and(|alternative| find_visible_in(alternative, location, visible))
+        }
+        StmtKind::While(_, body) => find_visible_in(body, location, visible),
+        StmtKind::For(name, _, body) => {
+            visible.push(name);
+            find_visible_in(body, location, visible)
+        }
+        StmtKind::Block(decls) => find_visible(decls, location, visible),
+        StmtKind::Expr(_) | StmtKind::Return(..) => false,
+    };
+
+    // Variables declared in a block are out of scope after it.
+    if !found {
+        visible.truncate(len);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{Debugger, Frontend, Paused, Resume, StopReason};
+    use crate::{
+        compiler::parser::Parser,
+        interpreter::{
+            inspect::{inspect, InspectOptions},
+            Interpreter,
+        },
+    };
+
+    /// Records what is seen at each pause and resumes with the given actions in order.
+    #[derive(Debug)]
+    struct Script {
+        actions: Vec<Resume>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Frontend for Script {
+        fn paused(&mut self, paused: Paused<'_>, reason: StopReason) -> Resume {
+            let stack: Vec<_> = paused
+                .stack()
+                .iter()
+                .map(|frame| frame.function.clone())
+                .collect();
+            let locals: Vec<_> = paused
+                .locals(0)
+                .iter()
+                .map(|(name, value)| {
+                    format!("{name}={}", inspect(value, &InspectOptions::compact()))
+                })
+                .collect();
+            self.log.lock().unwrap().push(format!(
+                "{reason:?} {} [{}] {}",
+                paused.location().line,
+                stack.join(" "),
+                locals.join(" ")
+            ));
+
+            assert!(!self.actions.is_empty(), "unexpected pause");
+            self.actions.remove(0)
+        }
+    }
+
+    fn debug(source: &str, breakpoints: &[usize], actions: Vec<Resume>) -> Vec<String> {
+        let script = Arc::new(Parser::new(source).parse().unwrap());
+        let log = Arc::new(Mutex::new(vec![]));
+        let frontend = Script {
+            actions,
+            log: log.clone(),
+        };
+        let mut debugger = Debugger::new(script.clone(), frontend);
+        debugger.breakpoints_mut().extend(breakpoints);
+
+        let mut interpreter = Interpreter::without_prelude();
+        interpreter.set_debug_hook(Some(Box::new(debugger)));
+        let _ = interpreter.interpret(&script);
+
+        let log = std::mem::take(&mut *log.lock().unwrap());
+        log
+    }
+
+    const SOURCE: &str = "\
+func add(a, b) {
+    let c = a + b;
+    return c;
+}
+for i in [1] {
+    let x = add(i, 2);
+    print(x);
+}
+";
+
+    #[test]
+    fn stepping() {
+        use Resume::*;
+        let log = debug(
+            SOURCE,
+            &[],
+            vec![StepInto, StepInto, StepInto, StepInto, StepOver, Continue],
+        );
+        let expected = [
+            "Entry 1 [<script>] ",
+            "Step 5 [<script>] ",
+            "Step 6 [<script>] i=1",
+            "Step 2 [add <script>] a=1 b=2",
+            "Step 3 [add <script>] a=1 b=2 c=3",
+            "Step 7 [<script>] i=1 x=3",
+        ];
+        assert_eq!(log, expected);
+
+        let log = debug(
+            SOURCE,
+            &[],
+            vec![StepInto, StepInto, StepInto, StepOut, Continue],
+        );
+        assert_eq!(log[4], "Step 7 [<script>] i=1 x=3");
+    }
+
+    #[test]
+    fn breakpoints() {
+        use Resume::*;
+        let source = "let n = 0;\nwhile n < 3 {\n    n = n + 1;\n}\n";
+        let script = Arc::new(Parser::new(source).parse().unwrap());
+        let log = Arc::new(Mutex::new(vec![]));
+        let frontend = Script {
+            actions: vec![Continue; 3],
+            log: log.clone(),
+        };
+        let mut debugger = Debugger::new(script.clone(), frontend).without_stop_on_entry();
+        debugger.breakpoints_mut().insert(3);
+
+        let mut interpreter = Interpreter::without_prelude();
+        interpreter.set_debug_hook(Some(Box::new(debugger)));
+        interpreter.interpret(&script).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 3);
+        assert!(log.lock().unwrap()[2].starts_with("Breakpoint 3"));
+
+        let log = debug(SOURCE, &[3], vec![Continue, Stop]);
+        assert_eq!(log[1], "Breakpoint 3 [add <script>] a=1 b=2 c=3");
+    }
+}
@@ -0,0 +1,135 @@
+//! Conversions between bytes and text encodings of them.
+
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("invalid character {0:?} at offset {1}")]
+    InvalidCharacter(char, usize),
+    #[error("invalid length {0}")]
+    InvalidLength(usize),
+}
+
+/// Lowercase hexadecimal, two digits per byte.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut encoded = String::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        encoded.push(DIGITS[usize::from(byte >> 4)] as char);
+        encoded.push(DIGITS[usize::from(byte & 0xf)] as char);
+    }
+
+    encoded
+}
+
+/// Decodes hexadecimal in either case.
+pub fn hex_decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+    if !text.len().is_multiple_of(2) {
+        return Err(DecodeError::InvalidLength(text.len()));
+    }
+
+    let digit = |offset: usize| {
+        let c = text[offset..].chars().next().unwrap();
+        c.to_digit(16)
+            .map(|digit| digit as u8)
+            .ok_or(DecodeError::InvalidCharacter(c, offset))
+    };
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            // Multibyte characters aren't digits, checking the first byte of the pair catches
+            // them before slicing in the middle of one.
+            let high = digit(i)?;
+            Ok(high << 4 | digit(i + 1)?)
+        })
+        .collect()
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        // Each byte of input needs at least one more character.
+        for i in 0..=chunk.len() {
+            let sextet = (group >> (18 - 6 * i)) & 0x3f;
+            encoded.push(BASE64_ALPHABET[sextet as usize] as char);
+        }
+        for _ in chunk.len()..3 {
+            encoded.push('=');
+        }
+    }
+
+    encoded
+}
+
+/// Decodes standard base64, the padding is optional.
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let data = text.trim_end_matches('=');
+    if text.len() - data.len() > 2 || data.len() % 4 == 1 {
+        return Err(DecodeError::InvalidLength(text.len()));
+    }
+
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let mut group = 0u32;
+    for (offset, c) in data.char_indices() {
+        let sextet = BASE64_ALPHABET
+            .iter()
+            .position(|&digit| digit as char == c)
+            .ok_or(DecodeError::InvalidCharacter(c, offset))?;
+        group = group << 6 | sextet as u32;
+        if offset % 4 == 3 {
+            decoded.extend_from_slice(&group.to_be_bytes()[1..]);
+            group = 0;
+        }
+    }
+
+    // A trailing group of 2 or 3 characters holds 1 or 2 bytes.
+    let rest = data.len() % 4;
+    if rest > 0 {
+        group <<= 6 * (4 - rest);
+        decoded.extend_from_slice(&group.to_be_bytes()[1..rest]);
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_decode, base64_encode, hex_decode, hex_encode, DecodeError};
+
+    #[test]
+    fn base64() {
+        let cases: [(&[u8], &str); 4] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foobar\xff", "Zm9vYmFy/w=="),
+        ];
+        for (bytes, encoded) in cases {
+            assert_eq!(base64_encode(bytes), encoded);
+            assert_eq!(base64_decode(encoded).as_deref(), Ok(bytes));
+        }
+
+        assert_eq!(base64_decode("Zm8").as_deref(), Ok(&b"fo"[..]));
+        assert_eq!(base64_decode("Zm9vY"), Err(DecodeError::InvalidLength(5)));
+        assert_eq!(
+            base64_decode("Zm-v"),
+            Err(DecodeError::InvalidCharacter('-', 2))
+        );
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(hex_encode(&[0x00, 0x7f, 0xab, 0xff]), "007fabff");
+        assert_eq!(hex_decode("007FabfF"), Ok(vec![0x00, 0x7f, 0xab, 0xff]));
+        assert_eq!(hex_decode("abc"), Err(DecodeError::InvalidLength(3)));
+        assert_eq!(hex_decode("0g"), Err(DecodeError::InvalidCharacter('g', 1)));
+    }
+}
@@ -2,46 +2,84 @@ use std::collections::HashMap;
 
 use super::value::Value;
 
+/// Storage for variables, globals are looked up by name while locals are stored in slots assigned
+/// by the resolver.
 #[derive(Debug, Default)]
 pub struct Environment {
-    parent: Option<Box<Environment>>,
-    map: HashMap<String, Value>,
+    globals: HashMap<String, Value>,
+    /// Local variables of every active call, innermost last.
+    slots: Vec<Value>,
+    /// Index of the first slot of the current call.
+    base: usize,
 }
 
 impl Environment {
-    pub fn define(&mut self, name: &str, value: Value) {
-        self.map.insert(name.to_string(), value);
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.to_owned(), value);
     }
 
-    pub fn get(&self, name: &str) -> Option<Value> {
-        self.find(name).copied()
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.get(name).cloned()
     }
 
-    pub fn set(&mut self, name: &str, mut value: Value) -> Option<Value> {
-        std::mem::swap(self.find_mut(name)?, &mut value);
+    /// Sets an existing global variable, returning the old value or `None` if it isn't defined.
+    pub fn set_global(&mut self, name: &str, value: Value) -> Option<Value> {
+        let global = self.globals.get_mut(name)?;
 
-        Some(value)
+        Some(std::mem::replace(global, value))
     }
 
-    fn find(&self, name: &str) -> Option<&Value> {
-        self.map
-            .get(name)
-            .or_else(|| self.parent.as_ref()?.find(name))
+    /// Removes a global variable, returning its value or `None` if it isn't defined.
+    pub fn remove_global(&mut self, name: &str) -> Option<Value> {
+        self.globals.remove(name)
     }
 
-    fn find_mut(&mut self, name: &str) -> Option<&mut Value> {
-        self.map
-            .get_mut(name)
-            .or_else(|| self.parent.as_mut()?.find_mut(name))
+    pub fn globals(&self) -> &HashMap<String, Value> {
+        &self.globals
     }
 
-    pub fn push(&mut self) {
-        let parent = std::mem::take(self);
-        self.parent.replace(Box::new(parent));
+    /// Replaces every global variable.
+    pub fn set_globals(&mut self, globals: HashMap<String, Value>) {
+        self.globals = globals;
     }
 
-    pub fn pop(&mut self) {
-        let parent = self.parent.take().expect("pop called on root environment");
-        let _ = std::mem::replace(self, *parent);
+    /// Index of the first slot of the current call.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// Returns the slot at an index counted from the first slot of the outermost call, rather
+    /// than from the current call.
+    pub fn slot(&self, index: usize) -> Option<Value> {
+        self.slots.get(index).cloned()
+    }
+
+    pub fn local(&self, slot: usize) -> Value {
+        self.slots[self.base + slot].clone()
+    }
+
+    pub fn set_local(&mut self, slot: usize, value: Value) {
+        self.slots[self.base + slot] = value;
+    }
+
+    /// Returns an iterator over the values of every global and every local of every active call.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.globals.values().chain(&self.slots)
+    }
+
+    /// Allocates `slot_count` nil slots for a new call, returning the base of the caller which
+    /// has to be passed to `exit` once the call returns.
+    pub fn enter(&mut self, slot_count: usize) -> usize {
+        let caller_base = self.base;
+        self.base = self.slots.len();
+        self.slots.resize(self.base + slot_count, Value::Nil);
+
+        caller_base
+    }
+
+    /// Frees the slots of the current call and restores those of the caller.
+    pub fn exit(&mut self, caller_base: usize) {
+        self.slots.truncate(self.base);
+        self.base = caller_base;
     }
 }
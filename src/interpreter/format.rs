@@ -0,0 +1,304 @@
+//! Formatting of values into templates such as `"x = {}, hex = {:#x}"`, used by the `format`
+//! builtin.
+//!
+//! Each `{}` in the template is replaced by the next argument, `{n}` by the argument at index `n`
+//! and `{{` and `}}` are literal braces. A spec can follow a colon, as in `{:>8.2}`:
+//!
+//! ```text
+//! [[fill]align][sign]['#']['0'][width]['.' precision][type]
+//! ```
+//!
+//! - `align` is `<`, `>` or `^`, numbers are aligned right by default and everything else left
+//! - `sign` is `+` to always print the sign of numbers
+//! - `#` prefixes numbers printed in another base with `0x`, `0o` or `0b`
+//! - `0` pads numbers with zeros after the sign
+//! - `precision` is the number of decimals of numbers, or the maximum length of other values
+//! - `type` is `x` or `X` for hexadecimal, `o` for octal, `b` for binary, `e` for scientific
+//!   notation, or `?` to quote strings like `debug` does
+
+use thiserror::Error;
+
+use super::value::Value;
+
+/// Largest width and precision, which is as much as Rust's formatting supports and keeps
+/// scripts from allocating huge strings with a short template.
+const MAX_WIDTH: usize = u16::MAX as usize;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    #[error("unmatched '{0}' in format string")]
+    Unmatched(char),
+    #[error("missing format argument {0}")]
+    MissingArgument(usize),
+    #[error("invalid format spec {0:?}")]
+    InvalidSpec(String),
+    #[error("format type '{0}' requires an integer")]
+    NotInteger(char),
+}
+
+/// Replaces the placeholders in `template` with `args`.
+pub fn format(template: &str, args: &[Value]) -> Result<String, FormatError> {
+    let mut formatted = String::new();
+    let mut next_arg = 0;
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        formatted.push_str(&rest[..i]);
+        let brace = rest.as_bytes()[i] as char;
+        rest = &rest[i + 1..];
+
+        // Doubled braces are escapes.
+        if let Some(after) = rest.strip_prefix(brace) {
+            formatted.push(brace);
+            rest = after;
+            continue;
+        }
+        if brace == '}' {
+            return Err(FormatError::Unmatched('}'));
+        }
+
+        let end = rest.find('}').ok_or(FormatError::Unmatched('{'))?;
+        let placeholder = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let (index, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+        let index = if index.is_empty() {
+            next_arg += 1;
+            next_arg - 1
+        } else {
+            index
+                .parse()
+                .map_err(|_| FormatError::InvalidSpec(placeholder.to_owned()))?
+        };
+        let arg = args.get(index).ok_or(FormatError::MissingArgument(index))?;
+
+        let spec = Spec::parse(spec)?;
+        formatted.push_str(&spec.apply(arg)?);
+    }
+    formatted.push_str(rest);
+
+    Ok(formatted)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<Align>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self, FormatError> {
+        let invalid = || FormatError::InvalidSpec(spec.to_owned());
+        let align = |c| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+
+        let mut parsed = Spec::default();
+        let mut rest = spec;
+
+        let mut chars = rest.chars();
+        let (first, second) = (chars.next(), chars.next());
+        if let Some(a) = second.and_then(align) {
+            parsed.fill = first;
+            parsed.align = Some(a);
+            rest = &rest[first.unwrap().len_utf8() + 1..];
+        } else if let Some(a) = first.and_then(align) {
+            parsed.align = Some(a);
+            rest = &rest[1..];
+        }
+
+        if let Some(after) = rest.strip_prefix('+') {
+            parsed.plus = true;
+            rest = after;
+        }
+        if let Some(after) = rest.strip_prefix('#') {
+            parsed.alternate = true;
+            rest = after;
+        }
+        if let Some(after) = rest.strip_prefix('0') {
+            parsed.zero = true;
+            rest = after;
+        }
+
+        let (width, after) = number(rest).ok_or_else(invalid)?;
+        parsed.width = width.unwrap_or(0);
+        rest = after;
+
+        if let Some(after) = rest.strip_prefix('.') {
+            let (precision, after) = number(after).ok_or_else(invalid)?;
+            parsed.precision = Some(precision.ok_or_else(invalid)?);
+            rest = after;
+        }
+        if parsed.width > MAX_WIDTH || parsed.precision.is_some_and(|p| p > MAX_WIDTH) {
+            return Err(invalid());
+        }
+
+        let mut chars = rest.chars();
+        parsed.kind = chars.next();
+        if !matches!(parsed.kind, None | Some('x' | 'X' | 'o' | 'b' | 'e' | '?'))
+            || chars.next().is_some()
+        {
+            return Err(invalid());
+        }
+
+        Ok(parsed)
+    }
+
+    fn apply(&self, value: &Value) -> Result<String, FormatError> {
+        let Value::Number(n) = *value else {
+            if let Some(kind @ ('x' | 'X' | 'o' | 'b' | 'e')) = self.kind {
+                return Err(FormatError::NotInteger(kind));
+            }
+
+            let mut string = if self.kind == Some('?') {
+                format!("{value:#}")
+            } else {
+                value.to_string()
+            };
+            if let Some(precision) = self.precision {
+                string = string.chars().take(precision).collect();
+            }
+
+            return Ok(self.pad("", &string, Align::Left));
+        };
+
+        let digits = match self.kind {
+            Some(kind @ ('x' | 'X' | 'o' | 'b')) => {
+                if n.fract() != 0.0 || n.abs() >= 2f64.powi(63) {
+                    return Err(FormatError::NotInteger(kind));
+                }
+
+                let n = (n as i64).unsigned_abs();
+                let (prefix, digits) = match kind {
+                    'x' => ("0x", format!("{n:x}")),
+                    'X' => ("0x", format!("{n:X}")),
+                    'o' => ("0o", format!("{n:o}")),
+                    _ => ("0b", format!("{n:b}")),
+                };
+                if self.alternate {
+                    format!("{prefix}{digits}")
+                } else {
+                    digits
+                }
+            }
+            Some('e') => match self.precision {
+                Some(precision) => format!("{:.precision$e}", n.abs()),
+                None => format!("{:e}", n.abs()),
+            },
+            _ => match self.precision {
+                Some(precision) => format!("{:.precision$}", n.abs()),
+                None => Value::Number(n.abs()).to_string(),
+            },
+        };
+
+        let sign = if n.is_sign_negative() && n != 0.0 {
+            "-"
+        } else if self.plus {
+            "+"
+        } else {
+            ""
+        };
+
+        Ok(self.pad(sign, &digits, Align::Right))
+    }
+
+    /// Pads `sign` followed by `body` to the width, zero padding goes between them.
+    fn pad(&self, sign: &str, body: &str, default_align: Align) -> String {
+        let len = sign.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(len);
+        if self.zero && self.align.is_none() && default_align == Align::Right {
+            return format!("{sign}{}{body}", "0".repeat(padding));
+        }
+
+        let fill = self.fill.unwrap_or(' ').to_string();
+        let (before, after) = match self.align.unwrap_or(default_align) {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+
+        format!("{}{sign}{body}{}", fill.repeat(before), fill.repeat(after))
+    }
+}
+
+/// Parses a decimal number at the start of `s`, returning it, or `None` if `s` doesn't start with
+/// a digit, along with the rest of `s`. Returns `None` if the number is too large for a `usize`.
+fn number(s: &str) -> Option<(Option<usize>, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, rest) = s.split_at(end);
+    if digits.is_empty() {
+        return Some((None, rest));
+    }
+
+    Some((Some(digits.parse().ok()?), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format, FormatError};
+    use crate::interpreter::{heap::Heap, value::Value};
+
+    #[test]
+    fn specs() {
+        let mut heap = Heap::default();
+        let s = heap.alloc_string("ab");
+        let args = [Value::Number(255.0), Value::Number(-1.23456), s, Value::Nil];
+
+        let cases = [
+            ("x = {}, hex = {0:x}", "x = 255, hex = ff"),
+            (
+                "{1:.2} {1:+08.1} {0:#X} {0:b}",
+                "-1.23 -00001.2 0xFF 11111111",
+            ),
+            (
+                "[{2:>4}] [{2:*^6}] [{2:.1}] [{2:?}]",
+                r#"[  ab] [**ab**] [a] ["ab"]"#,
+            ),
+            ("{0:5} {0:<5}| {0:+} {3}", "  255 255  | +255 nil"),
+            ("{{{}}} {:e}", "{255} -1.23456e0"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                format(template, &args).as_deref(),
+                Ok(expected),
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let args = [Value::Number(1.5)];
+        assert_eq!(format("{", &args), Err(FormatError::Unmatched('{')));
+        assert_eq!(format("}", &args), Err(FormatError::Unmatched('}')));
+        assert_eq!(format("{} {}", &args), Err(FormatError::MissingArgument(1)));
+        assert_eq!(format("{:x}", &args), Err(FormatError::NotInteger('x')));
+        for template in [
+            "{:q}",
+            "{:100000}",
+            "{:.100000}",
+            "{:99999999999999999999}",
+            "{:.99999999999999999999}",
+        ] {
+            assert!(matches!(
+                format(template, &args),
+                Err(FormatError::InvalidSpec(_))
+            ));
+        }
+    }
+}
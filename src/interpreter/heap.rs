@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+
+use super::{
+    object::{
+        Obj, ObjBytes, ObjForeign, ObjInstance, ObjKind, ObjList, ObjMap, ObjString, ObjWeak,
+        SubObject,
+    },
+    table::Table,
+    value::{Value, ValueKey},
+};
+
+/// Owns every object allocated by the interpreter and frees the ones that are no longer reachable
+/// using a simple mark and sweep collector.
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Obj>,
+    bytes_allocated: usize,
+    /// Most bytes allocated at once since the heap was created or the peak was reset.
+    peak_bytes_allocated: usize,
+    /// Number of allocated bytes that will trigger the next collection.
+    next_gc: usize,
+    /// Maximum number of bytes which may be allocated, unlimited if `None`.
+    memory_limit: Option<usize>,
+    /// Script finalizers, pairs of target objects and the callbacks to run once they have been
+    /// freed. The targets are not traced, the callbacks are.
+    finalizers: Vec<(Obj, Value)>,
+    /// Callbacks of finalizers whose targets have been freed but which haven't been run yet.
+    pending_finalizers: Vec<Value>,
+    stats: HeapStats,
+}
+
+/// Running totals of the work done by a heap since it was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeapStats {
+    pub allocations: u64,
+    /// Total size of every object allocated, including ones which have since been freed.
+    pub bytes_allocated: u64,
+    pub collections: u64,
+    pub objects_freed: u64,
+}
+
+impl HeapStats {
+    /// Work done between `earlier` and these stats.
+    pub fn since(self, earlier: HeapStats) -> HeapStats {
+        HeapStats {
+            allocations: self.allocations - earlier.allocations,
+            bytes_allocated: self.bytes_allocated - earlier.bytes_allocated,
+            collections: self.collections - earlier.collections,
+            objects_freed: self.objects_freed - earlier.objects_freed,
+        }
+    }
+}
+
+impl Heap {
+    const INITIAL_GC_THRESHOLD: usize = 1024 * 1024;
+    const GC_GROWTH_FACTOR: usize = 2;
+
+    pub fn alloc<T: SubObject>(&mut self, object: T) -> Obj {
+        self.bytes_allocated += object.size();
+        self.peak_bytes_allocated = self.peak_bytes_allocated.max(self.bytes_allocated);
+        self.stats.allocations += 1;
+        self.stats.bytes_allocated += object.size() as u64;
+        let obj = Obj::from(Box::new(object));
+        self.objects.push(obj.clone());
+
+        obj
+    }
+
+    /// Runs `mutate` on `obj` and counts any change in its size, for lists, maps and bytes which
+    /// grow in place rather than being allocated again.
+    pub fn mutate<R>(&mut self, obj: &Obj, mutate: impl FnOnce(&mut Obj) -> R) -> R {
+        let mut obj = obj.clone();
+        let before = obj.size();
+        let result = mutate(&mut obj);
+        let after = obj.size();
+        if after > before {
+            self.bytes_allocated += after - before;
+            self.peak_bytes_allocated = self.peak_bytes_allocated.max(self.bytes_allocated);
+            self.stats.bytes_allocated += (after - before) as u64;
+        } else {
+            self.bytes_allocated = self.bytes_allocated.saturating_sub(before - after);
+        }
+
+        result
+    }
+
+    /// Allocates a string object and returns it as a value.
+    pub fn alloc_string(&mut self, string: impl Into<String>) -> Value {
+        Value::Obj(self.alloc(ObjString::new(string.into())))
+    }
+
+    /// Wraps a Rust value in a foreign object and returns it as a value.
+    pub fn alloc_foreign<T: std::any::Any + Send>(&mut self, data: T) -> Value {
+        Value::Obj(self.alloc(ObjForeign::new(data)))
+    }
+
+    /// Copies `value` along with every list, map and instance reachable from it.
+    ///
+    /// Objects referenced several times are only copied once, so the copy has the same shape as
+    /// the original, including any cycles. Strings, functions and other objects which can't be
+    /// mutated by scripts are shared rather than copied.
+    pub fn deep_clone(&mut self, value: &Value) -> Value {
+        // Copies are filled in from a worklist rather than recursively, so deeply nested values
+        // can't overflow the stack.
+        let mut copies = HashMap::new();
+        let mut pending = vec![];
+        let copy = self.clone_shallow(value, &mut copies, &mut pending);
+        while let Some((obj, mut copy)) = pending.pop() {
+            let empty_size = copy.size();
+            match obj.kind() {
+                ObjKind::List => {
+                    let items: Vec<_> = AsRef::<ObjList>::as_ref(&obj)
+                        .items()
+                        .iter()
+                        .map(|item| self.clone_shallow(item, &mut copies, &mut pending))
+                        .collect();
+                    *AsMut::<ObjList>::as_mut(&mut copy).items_mut() = items;
+                }
+                ObjKind::Map => {
+                    let entries: Table<_, _> = AsRef::<ObjMap>::as_ref(&obj)
+                        .entries()
+                        .iter()
+                        .map(|(key, value)| {
+                            let key = self.clone_shallow(key.value(), &mut copies, &mut pending);
+                            let value = self.clone_shallow(value, &mut copies, &mut pending);
+                            (ValueKey::new(key), value)
+                        })
+                        .collect();
+                    *AsMut::<ObjMap>::as_mut(&mut copy).entries_mut() = entries;
+                }
+                ObjKind::Instance => {
+                    let fields: Table<_, _> = AsRef::<ObjInstance>::as_ref(&obj)
+                        .fields()
+                        .iter()
+                        .map(|(name, value)| {
+                            let value = self.clone_shallow(value, &mut copies, &mut pending);
+                            (name.clone(), value)
+                        })
+                        .collect();
+                    *AsMut::<ObjInstance>::as_mut(&mut copy).fields_mut() = fields;
+                }
+                ObjKind::Bytes => {
+                    let bytes = AsRef::<ObjBytes>::as_ref(&obj).bytes().clone();
+                    *AsMut::<ObjBytes>::as_mut(&mut copy).bytes_mut() = bytes;
+                }
+                _ => unreachable!(),
+            }
+
+            // Only the size of the empty copy was counted when it was allocated.
+            self.bytes_allocated += copy.size() - empty_size;
+            self.peak_bytes_allocated = self.peak_bytes_allocated.max(self.bytes_allocated);
+            self.stats.bytes_allocated += (copy.size() - empty_size) as u64;
+        }
+
+        copy
+    }
+
+    /// Returns the copy of `value`, allocating an empty one and adding it to `pending` to be
+    /// filled in if it hasn't been copied yet.
+    fn clone_shallow(
+        &mut self,
+        value: &Value,
+        copies: &mut HashMap<Obj, Obj>,
+        pending: &mut Vec<(Obj, Obj)>,
+    ) -> Value {
+        let Some(obj) = value.as_obj() else {
+            return value.clone();
+        };
+
+        if let Some(copy) = copies.get(obj) {
+            return Value::Obj(copy.clone());
+        }
+
+        // The copy is registered before its contents are copied so that cycles lead back to it.
+        let copy = match obj.kind() {
+            ObjKind::List => self.alloc(ObjList::new(vec![])),
+            ObjKind::Map => self.alloc(ObjMap::new(Table::new())),
+            ObjKind::Instance => self.alloc(ObjInstance::new()),
+            ObjKind::Bytes => self.alloc(ObjBytes::new(vec![])),
+            _ => return value.clone(),
+        };
+        copies.insert(obj.clone(), copy.clone());
+        pending.push((obj.clone(), copy.clone()));
+
+        Value::Obj(copy)
+    }
+
+    /// Registers `callback` to be called once `target` has been freed.
+    ///
+    /// The callback keeps everything it references alive, so if it references the target then
+    /// the target will never be freed.
+    pub fn register_finalizer(&mut self, target: Obj, callback: Value) {
+        self.finalizers.push((target, callback));
+    }
+
+    /// Takes the callbacks of finalizers whose targets have been freed, in the order the
+    /// finalizers were registered.
+    pub fn take_pending_finalizers(&mut self) -> Vec<Value> {
+        std::mem::take(&mut self.pending_finalizers)
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes_allocated
+    }
+
+    /// Returns the most bytes which have been allocated at once, since the heap was created or
+    /// [`Heap::reset_peak`] was called.
+    pub fn peak_bytes_allocated(&self) -> usize {
+        self.peak_bytes_allocated
+    }
+
+    /// Starts measuring the peak again from the bytes currently allocated.
+    pub fn reset_peak(&mut self) {
+        self.peak_bytes_allocated = self.bytes_allocated;
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        self.stats
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Sets the maximum number of bytes which may be allocated.
+    ///
+    /// The heap itself never refuses an allocation, it is up to the owner to check
+    /// `is_over_limit` after collecting garbage.
+    pub fn set_memory_limit(&mut self, memory_limit: Option<usize>) {
+        self.memory_limit = memory_limit;
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    /// Returns true if more memory is allocated than the limit allows.
+    pub fn is_over_limit(&self) -> bool {
+        self.memory_limit
+            .is_some_and(|limit| self.bytes_allocated > limit)
+    }
+
+    /// Returns true if enough memory has been allocated since the last collection that another
+    /// one should be performed.
+    pub fn should_collect(&self) -> bool {
+        self.bytes_allocated > self.next_gc || self.is_over_limit()
+    }
+
+    /// Frees every object that is not reachable from `roots`.
+    ///
+    /// # Safety
+    ///
+    /// Handles to objects which are not reachable from the roots must not be used after calling
+    /// this.
+    pub unsafe fn collect<'v>(&mut self, roots: impl IntoIterator<Item = &'v Value>) {
+        let mut gray: Vec<Obj> = roots
+            .into_iter()
+            .filter_map(Value::as_obj)
+            .cloned()
+            .collect();
+
+        let finalizer_callbacks = self.finalizers.iter().map(|(_, callback)| callback);
+        gray.extend(
+            finalizer_callbacks
+                .chain(&self.pending_finalizers)
+                .filter_map(Value::as_obj)
+                .cloned(),
+        );
+
+        // Mark.
+        while let Some(mut obj) = gray.pop() {
+            if obj.marked {
+                continue;
+            }
+
+            obj.marked = true;
+            obj.trace(&mut gray);
+        }
+
+        // Queue the finalizers of objects which are about to be freed.
+        let (finalized, finalizers) = std::mem::take(&mut self.finalizers)
+            .into_iter()
+            .partition(|(target, _)| !target.marked);
+        self.finalizers = finalizers;
+        self.pending_finalizers
+            .extend(finalized.into_iter().map(|(_, callback)| callback));
+
+        // Weak references to objects which are about to be freed have to be cleared before
+        // sweeping.
+        for obj in &mut self.objects {
+            if obj.marked && obj.kind() == ObjKind::Weak {
+                AsMut::<ObjWeak>::as_mut(obj).clear_if_unmarked();
+            }
+        }
+
+        // Sweep. The sizes of the objects which are kept are added up again, in case any of them
+        // changed size without being counted.
+        let mut bytes_kept = 0;
+        let count = self.objects.len();
+        self.objects.retain_mut(|obj| {
+            if obj.marked {
+                obj.marked = false;
+                bytes_kept += obj.size();
+                true
+            } else {
+                // Safety: the object is unreachable so no other handle to it can be used.
+                unsafe { obj.clone().free() };
+                false
+            }
+        });
+
+        self.stats.collections += 1;
+        self.stats.objects_freed += (count - self.objects.len()) as u64;
+        self.bytes_allocated = bytes_kept;
+        self.next_gc =
+            (self.bytes_allocated * Self::GC_GROWTH_FACTOR).max(Self::INITIAL_GC_THRESHOLD);
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            objects: vec![],
+            bytes_allocated: 0,
+            peak_bytes_allocated: 0,
+            next_gc: Self::INITIAL_GC_THRESHOLD,
+            memory_limit: None,
+            finalizers: vec![],
+            pending_finalizers: vec![],
+            stats: HeapStats::default(),
+        }
+    }
+}
+
+impl Drop for Heap {
+    fn drop(&mut self) {
+        for obj in self.objects.drain(..) {
+            // Safety: the heap is being dropped so none of its objects can be used again.
+            unsafe { obj.free() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    };
+
+    use super::Heap;
+    use crate::interpreter::{
+        object::{ObjForeign, ObjInstance, ObjList, ObjString, ObjWeak},
+        value::Value,
+    };
+
+    fn collect<'v>(heap: &mut Heap, roots: impl IntoIterator<Item = &'v Value>) {
+        // SAFETY: the tests don't use objects after they have been collected.
+        unsafe { heap.collect(roots) };
+    }
+
+    #[test]
+    fn collect_unreachable() {
+        let mut heap = Heap::default();
+        let kept = Value::Obj(heap.alloc(ObjString::new("kept".to_owned())));
+        heap.alloc(ObjString::new("garbage".to_owned()));
+        assert_eq!(heap.object_count(), 2);
+
+        let before = heap.stats();
+        collect(&mut heap, [&kept]);
+        assert_eq!(heap.object_count(), 1);
+        assert_eq!(kept.as_obj().unwrap().as_string().unwrap().as_str(), "kept");
+
+        let stats = heap.stats();
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.since(before).collections, 1);
+        assert_eq!(stats.since(before).objects_freed, 1);
+
+        // The peak includes the garbage until it is reset.
+        assert!(heap.peak_bytes_allocated() > heap.bytes_allocated());
+        heap.reset_peak();
+        assert_eq!(heap.peak_bytes_allocated(), heap.bytes_allocated());
+    }
+
+    #[test]
+    fn mutate() {
+        let mut heap = Heap::default();
+        let list = heap.alloc(ObjList::new(vec![]));
+        let empty = heap.bytes_allocated();
+        heap.mutate(&list, |list| {
+            let items = AsMut::<ObjList>::as_mut(list).items_mut();
+            items.resize(1000, Value::Nil);
+        });
+        assert!(heap.bytes_allocated() >= empty + 1000 * std::mem::size_of::<Value>());
+        assert_eq!(heap.peak_bytes_allocated(), heap.bytes_allocated());
+
+        // Growth which isn't counted is found when collecting.
+        let mut obj = list.clone();
+        AsMut::<ObjList>::as_mut(&mut obj)
+            .items_mut()
+            .reserve(10_000);
+        let size = obj.size();
+        collect(&mut heap, [&Value::Obj(list)]);
+        assert_eq!(heap.bytes_allocated(), size);
+    }
+
+    #[test]
+    fn collect_traces_children() {
+        let mut heap = Heap::default();
+        let child = heap.alloc(ObjString::new("child".to_owned()));
+        let mut parent = heap.alloc(ObjInstance::new());
+        AsMut::<ObjInstance>::as_mut(&mut parent)
+            .fields_mut()
+            .insert("child".to_owned(), Value::Obj(child));
+
+        collect(&mut heap, [&Value::Obj(parent)]);
+        assert_eq!(heap.object_count(), 2);
+
+        collect(&mut heap, []);
+        assert_eq!(heap.object_count(), 0);
+        assert_eq!(heap.bytes_allocated(), 0);
+    }
+
+    #[test]
+    fn foreign_finalizer() {
+        let freed = Arc::new(AtomicI32::new(0));
+        let mut heap = Heap::default();
+        let freed_clone = freed.clone();
+        let foreign =
+            ObjForeign::with_finalizer(5, move |n| freed_clone.store(n, Ordering::Relaxed));
+        let foreign = Value::Obj(heap.alloc(foreign));
+
+        collect(&mut heap, [&foreign]);
+        assert_eq!(freed.load(Ordering::Relaxed), 0);
+
+        collect(&mut heap, []);
+        assert_eq!(freed.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn collect_queues_finalizers() {
+        let mut heap = Heap::default();
+        let target = heap.alloc(ObjInstance::new());
+        let callback = heap.alloc_string("callback");
+        heap.register_finalizer(target.clone(), callback);
+
+        collect(&mut heap, [&Value::Obj(target)]);
+        assert!(heap.take_pending_finalizers().is_empty());
+
+        collect(&mut heap, []);
+        // The callback must survive until it is taken.
+        let pending = heap.take_pending_finalizers();
+        assert_eq!(pending[0].as_str(), Some("callback"));
+        assert_eq!(heap.object_count(), 1);
+    }
+
+    #[test]
+    fn collect_clears_weak() {
+        let mut heap = Heap::default();
+        let target = heap.alloc(ObjString::new("target".to_owned()));
+        let weak = Value::Obj(heap.alloc(ObjWeak::new(target.clone())));
+        let target = Value::Obj(target);
+        let weak_target = || {
+            let weak = weak.as_obj().unwrap().downcast::<ObjWeak>().unwrap();
+            weak.target().cloned()
+        };
+
+        collect(&mut heap, [&weak, &target]);
+        assert!(weak_target().is_some());
+
+        collect(&mut heap, [&weak]);
+        assert!(weak_target().is_none());
+        assert_eq!(heap.object_count(), 1);
+    }
+
+    #[test]
+    fn deep_clone() {
+        let mut heap = Heap::default();
+        let string = heap.alloc_string("shared");
+        let list = Value::Obj(heap.alloc(ObjList::new(vec![string.clone()])));
+        let mut obj = list.as_obj().unwrap().clone();
+        AsMut::<ObjList>::as_mut(&mut obj)
+            .items_mut()
+            .push(list.clone());
+
+        let copy = heap.deep_clone(&list);
+        assert_ne!(copy, list);
+        let items = copy
+            .as_obj()
+            .unwrap()
+            .downcast::<ObjList>()
+            .unwrap()
+            .items();
+        assert_eq!(items[0].as_obj(), string.as_obj());
+        // The cycle leads back to the copy rather than the original.
+        assert_eq!(items[1], copy);
+        assert_eq!(heap.object_count(), 3);
+    }
+}
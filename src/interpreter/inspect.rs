@@ -0,0 +1,335 @@
+//! Human readable rendering of values, used when echoing values back to the user.
+//!
+//! Unlike `Display` this looks inside lists, maps and instances, breaking them over several lines
+//! when they don't fit in the configured width. Objects which contain themselves are rendered as
+//! `<cycle>` where they would be repeated.
+
+use std::collections::HashSet;
+
+use super::{
+    object::{Obj, ObjInstance, ObjKind, ObjList, ObjMap},
+    value::Value,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InspectOptions {
+    /// Number of spaces nested values are indented by when rendered over several lines.
+    pub indent: usize,
+    /// Depth past which the contents of lists, maps and instances are elided.
+    pub max_depth: usize,
+    /// Width containers may take up before they are broken over several lines.
+    pub max_width: usize,
+}
+
+impl InspectOptions {
+    /// Options which always render values on a single line without eliding anything, these are
+    /// used by `Display` so nothing is lost when values are printed or converted to strings.
+    pub fn compact() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_width: usize::MAX,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            max_depth: 6,
+            max_width: 80,
+        }
+    }
+}
+
+/// Renders `value`, strings are quoted.
+pub fn inspect(value: &Value, options: &InspectOptions) -> String {
+    if options.max_width == usize::MAX {
+        return single_line(value, options.max_depth);
+    }
+
+    let mut inspector = Inspector {
+        options,
+        ancestors: vec![],
+    };
+
+    inspector.value(value, 0)
+}
+
+struct Inspector<'a> {
+    options: &'a InspectOptions,
+    /// Containers currently being rendered, outermost first.
+    ancestors: Vec<Obj>,
+}
+
+impl Inspector<'_> {
+    fn value(&mut self, value: &Value, depth: usize) -> String {
+        let Some(obj) = value.as_obj() else {
+            return format!("{value:#}");
+        };
+
+        let (open, close, is_empty) = match obj.kind() {
+            ObjKind::List => ("[", "]", AsRef::<ObjList>::as_ref(obj).items().is_empty()),
+            ObjKind::Map => ("{", "}", AsRef::<ObjMap>::as_ref(obj).entries().is_empty()),
+            ObjKind::Instance => (
+                "instance {",
+                "}",
+                AsRef::<ObjInstance>::as_ref(obj).fields().is_empty(),
+            ),
+            _ => return format!("{value:#}"),
+        };
+
+        if self.ancestors.contains(obj) {
+            return "<cycle>".to_owned();
+        }
+
+        if is_empty {
+            return format!("{open}{close}");
+        }
+
+        if depth >= self.options.max_depth {
+            return format!("{open}...{close}");
+        }
+
+        self.ancestors.push(obj.clone());
+        let items = self.items(obj, depth + 1);
+        self.ancestors.pop();
+
+        self.layout(open, close, items, depth)
+    }
+
+    fn items(&mut self, obj: &Obj, depth: usize) -> Vec<String> {
+        match obj.kind() {
+            ObjKind::List => AsRef::<ObjList>::as_ref(obj)
+                .items()
+                .iter()
+                .map(|item| self.value(item, depth))
+                .collect(),
+            ObjKind::Map => AsRef::<ObjMap>::as_ref(obj)
+                .entries()
+                .iter()
+                .map(|(key, value)| {
+                    let key = self.value(key.value(), depth);
+                    format!("{key}: {}", self.value(value, depth))
+                })
+                .collect(),
+            ObjKind::Instance => AsRef::<ObjInstance>::as_ref(obj)
+                .fields()
+                .iter()
+                .map(|(name, value)| format!("{name}: {}", self.value(value, depth)))
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Puts the container on a single line if it fits, otherwise every item goes on its own line.
+    fn layout(&self, open: &str, close: &str, items: Vec<String>, depth: usize) -> String {
+        // Maps and instances have padding inside their braces, lists don't.
+        let padding = if close == "}" { " " } else { "" };
+        let single_line = format!("{open}{padding}{}{padding}{close}", items.join(", "));
+
+        let width = depth * self.options.indent + single_line.len();
+        if width <= self.options.max_width && !single_line.contains('\n') {
+            return single_line;
+        }
+
+        let outer = " ".repeat(depth * self.options.indent);
+        let inner = " ".repeat((depth + 1) * self.options.indent);
+        let mut string = open.to_owned();
+        for item in items {
+            string.push('\n');
+            string.push_str(&inner);
+            string.push_str(&item);
+            string.push(',');
+        }
+        string.push('\n');
+        string.push_str(&outer);
+        string.push_str(close);
+
+        string
+    }
+}
+
+/// Delimiters and contents of a list, map or instance.
+struct Contents {
+    open: &'static str,
+    close: &'static str,
+    /// Space inside the delimiters of maps and instances which aren't empty.
+    padding: &'static str,
+    /// Values it contains, each preceded by the separator or field name which comes before it.
+    items: Vec<(String, Value)>,
+}
+
+impl Contents {
+    fn of(obj: &Obj) -> Option<Self> {
+        let separator = |i| if i == 0 { "" } else { ", " };
+        let (open, close, padding, items) = match obj.kind() {
+            ObjKind::List => {
+                let items = AsRef::<ObjList>::as_ref(obj).items().iter().enumerate();
+                let items = items.map(|(i, item)| (separator(i).to_owned(), item.clone()));
+                ("[", "]", "", items.collect())
+            }
+            ObjKind::Map => {
+                let entries = AsRef::<ObjMap>::as_ref(obj).entries().iter().enumerate();
+                let entries = entries.flat_map(|(i, (key, value))| {
+                    [
+                        (separator(i).to_owned(), key.value().clone()),
+                        (": ".to_owned(), value.clone()),
+                    ]
+                });
+                ("{", "}", " ", entries.collect())
+            }
+            ObjKind::Instance => {
+                let fields = AsRef::<ObjInstance>::as_ref(obj)
+                    .fields()
+                    .iter()
+                    .enumerate();
+                let fields = fields
+                    .map(|(i, (name, value))| (format!("{}{name}: ", separator(i)), value.clone()));
+                ("instance {", "}", " ", fields.collect())
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            open,
+            close,
+            padding,
+            items,
+        })
+    }
+}
+
+/// Container which [`single_line`] is rendering the items of.
+struct Open {
+    obj: Obj,
+    close: &'static str,
+    padding: &'static str,
+    items: std::vec::IntoIter<(String, Value)>,
+}
+
+/// Renders `value` on a single line like [`Inspector`] would if it had room, but without
+/// recursing so that values nested deeper than the stack allows can be rendered.
+fn single_line(value: &Value, max_depth: usize) -> String {
+    let mut string = String::new();
+    let mut stack: Vec<Open> = vec![];
+    // Containers on the stack, to find cycles without searching it.
+    let mut ancestors = HashSet::new();
+    let mut next = Some(value.clone());
+    loop {
+        if let Some(value) = next.take() {
+            let container = value
+                .as_obj()
+                .and_then(|obj| Some((obj.clone(), Contents::of(obj)?)));
+            match container {
+                None => string.push_str(&format!("{value:#}")),
+                Some((obj, _)) if ancestors.contains(&obj) => string.push_str("<cycle>"),
+                Some((_, contents)) if contents.items.is_empty() => {
+                    string.push_str(contents.open);
+                    string.push_str(contents.close);
+                }
+                Some((_, contents)) if stack.len() >= max_depth => {
+                    string.push_str(&format!("{}...{}", contents.open, contents.close));
+                }
+                Some((obj, contents)) => {
+                    string.push_str(contents.open);
+                    string.push_str(contents.padding);
+                    ancestors.insert(obj.clone());
+                    stack.push(Open {
+                        obj,
+                        close: contents.close,
+                        padding: contents.padding,
+                        items: contents.items.into_iter(),
+                    });
+                }
+            }
+        }
+
+        let Some(open) = stack.last_mut() else {
+            return string;
+        };
+        match open.items.next() {
+            Some((prefix, item)) => {
+                string.push_str(&prefix);
+                next = Some(item);
+            }
+            None => {
+                string.push_str(open.padding);
+                string.push_str(open.close);
+                ancestors.remove(&open.obj);
+                stack.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inspect, InspectOptions};
+    use crate::interpreter::{
+        heap::Heap,
+        object::{ObjList, ObjMap},
+        table::Table,
+        value::{Value, ValueKey},
+    };
+
+    fn list(heap: &mut Heap, items: Vec<Value>) -> Value {
+        Value::Obj(heap.alloc(ObjList::new(items)))
+    }
+
+    #[test]
+    fn nested() {
+        let mut heap = Heap::default();
+        let a = heap.alloc_string("a");
+        let inner = list(&mut heap, vec![Value::Number(1.0), a]);
+        let mut table = Table::new();
+        table.insert(ValueKey::new(heap.alloc_string("key")), inner);
+        let map = Value::Obj(heap.alloc(ObjMap::new(table)));
+
+        let compact = InspectOptions::compact();
+        assert_eq!(inspect(&map, &compact), r#"{ "key": [1, "a"] }"#);
+
+        let narrow = InspectOptions {
+            max_width: 10,
+            ..InspectOptions::default()
+        };
+        assert_eq!(inspect(&map, &narrow), "{\n  \"key\": [1, \"a\"],\n}");
+    }
+
+    #[test]
+    fn cycle() {
+        let mut heap = Heap::default();
+        let value = list(&mut heap, vec![Value::Nil]);
+        let mut obj = value.as_obj().unwrap().clone();
+        AsMut::<ObjList>::as_mut(&mut obj).items_mut()[0] = value.clone();
+
+        assert_eq!(inspect(&value, &InspectOptions::default()), "[<cycle>]");
+        assert_eq!(value.to_string(), "[<cycle>]");
+    }
+
+    #[test]
+    fn max_depth() {
+        let mut heap = Heap::default();
+        let empty = list(&mut heap, vec![]);
+        let inner = list(&mut heap, vec![empty]);
+        let outer = list(&mut heap, vec![inner]);
+
+        let options = InspectOptions {
+            max_depth: 1,
+            ..InspectOptions::default()
+        };
+        assert_eq!(inspect(&outer, &options), "[[...]]");
+        // Values which aren't containers are never elided.
+        assert_eq!(inspect(&Value::Number(1.0), &options), "1");
+
+        // Compact rendering, which values are displayed with, never elides anything.
+        let mut value = Value::Number(1.0);
+        for _ in 0..10 {
+            value = list(&mut heap, vec![value]);
+        }
+        let expected = format!("{}1{}", "[".repeat(10), "]".repeat(10));
+        assert_eq!(inspect(&value, &InspectOptions::compact()), expected);
+        assert_eq!(value.to_string(), expected);
+    }
+}
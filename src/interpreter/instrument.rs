@@ -0,0 +1,137 @@
+//! Instrumentation for hosts: a callback run before statements with the location of the statement,
+//! for tracing, progress reporting, watchdogs or tools which visualize execution. It runs
+//! alongside any [`DebugHook`](super::debug::DebugHook), so scripts can be instrumented while
+//! they are being debugged or their coverage measured.
+
+use std::fmt::Debug;
+
+use super::{Interpreter, RuntimeError};
+use crate::compiler::Location;
+
+type Callback = Box<dyn FnMut(&mut Interpreter, Location) -> Result<(), RuntimeError> + Send>;
+
+pub struct Instrument {
+    callback: Callback,
+    /// Number of statements between calls.
+    interval: u64,
+    /// Statements left to run before the next call.
+    countdown: u64,
+}
+
+impl Instrument {
+    /// Calls `callback` before every statement, including blocks so that every iteration of a
+    /// loop is seen. Returning an error stops the script with it, which is how a watchdog can
+    /// cancel a script.
+    pub fn new(
+        callback: impl FnMut(&mut Interpreter, Location) -> Result<(), RuntimeError> + Send + 'static,
+    ) -> Self {
+        Self::every(1, callback)
+    }
+
+    /// Calls `callback` before every `interval`th statement, starting with the first, for
+    /// callbacks which are too expensive to run before each one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn every(
+        interval: u64,
+        callback: impl FnMut(&mut Interpreter, Location) -> Result<(), RuntimeError> + Send + 'static,
+    ) -> Self {
+        assert!(interval > 0, "interval should be at least one statement");
+        Self {
+            callback: Box::new(callback),
+            interval,
+            countdown: 0,
+        }
+    }
+
+    pub(super) fn statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        location: Location,
+    ) -> Result<(), RuntimeError> {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return Ok(());
+        }
+        self.countdown = self.interval - 1;
+
+        (self.callback)(interpreter, location)
+    }
+}
+
+impl Debug for Instrument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Instrument")
+            .field("interval", &self.interval)
+            .field("countdown", &self.countdown)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::Instrument;
+    use crate::{
+        interpreter::{Interpreter, RuntimeErrorKind},
+        Engine, Error,
+    };
+
+    #[test]
+    fn callbacks() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let recorded = lines.clone();
+        let instrument = Instrument::new(move |interpreter: &mut Interpreter, location| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((location.line, interpreter.frames().len()));
+            Ok(())
+        });
+        // SAFETY: the callback doesn't keep any values.
+        let mut engine = unsafe { Engine::new().with_instrument(instrument) };
+        engine
+            .eval("func f() {\n  return 1;\n}\nlet x = f();\nx;")
+            .unwrap();
+        // The body of `f` is a block, which is seen before its statements.
+        assert_eq!(
+            *lines.lock().unwrap(),
+            [(1, 0), (4, 0), (1, 1), (2, 1), (5, 0)]
+        );
+
+        let count = Arc::new(Mutex::new(0));
+        let counted = count.clone();
+        let instrument = Instrument::every(10, move |_: &mut Interpreter, _| {
+            *counted.lock().unwrap() += 1;
+            Ok(())
+        });
+        // SAFETY: the callback doesn't keep any values.
+        let mut engine = unsafe { Engine::new().with_instrument(instrument) };
+        engine.eval("for i in range(45) {}").unwrap();
+        assert_eq!(*count.lock().unwrap(), 5);
+    }
+
+    #[test]
+    fn watchdog() {
+        let mut statements = 0;
+        let instrument = Instrument::new(move |_: &mut Interpreter, _| {
+            statements += 1;
+            if statements > 100 {
+                return Err(RuntimeErrorKind::InvalidArgument("cancelled".to_owned()).into());
+            }
+            Ok(())
+        });
+        // SAFETY: the callback doesn't keep any values.
+        let mut engine = unsafe { Engine::new().with_instrument(instrument) };
+        let Err(Error::Runtime(error)) = engine.eval("while true {}") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::InvalidArgument("cancelled".to_owned())
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Cancelling scripts from other threads. An [`InterruptHandle`] can be sent to another thread,
+//! such as a UI thread or a watchdog, which interrupts the script when it should stop:
+//!
+//! ```
+//! use std::{thread, time::Duration};
+//!
+//! use unnamed_language::{interpreter::RuntimeErrorKind, Engine, Error};
+//!
+//! let mut engine = Engine::new();
+//! let handle = engine.interrupt_handle();
+//! thread::spawn(move || {
+//!     thread::sleep(Duration::from_millis(10));
+//!     handle.interrupt();
+//! });
+//! let Err(Error::Runtime(error)) = engine.eval("while true {}") else {
+//!     panic!("script should have been interrupted");
+//! };
+//! assert_eq!(error.kind(), &RuntimeErrorKind::Interrupted);
+//! ```
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Handle which stops the script an interpreter is running, it is cheap to clone and can be
+/// shared between threads.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    /// Requests that the script stops, it does so before its next statement with an
+    /// [`Interrupted`](super::RuntimeErrorKind::Interrupted) error. If no script is running the
+    /// next one to run is stopped instead.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether an interrupt has been requested which the interpreter hasn't stopped for
+    /// yet.
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Consumes a pending interrupt, returning whether there was one. Each interrupt stops only
+    /// one script so the interpreter can be used again afterwards.
+    pub(super) fn take(&self) -> bool {
+        // Checked before every statement, the load avoids writing to the shared cache line.
+        self.is_interrupted() && self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::{interpreter::RuntimeErrorKind, Engine, Error};
+
+    #[test]
+    fn interrupt() {
+        let mut engine = Engine::new();
+        let handle = engine.interrupt_handle();
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            handle.interrupt();
+        });
+        let Err(Error::Runtime(error)) = engine.eval("let n = 0; while true { n = n + 1; }") else {
+            panic!("error should have been returned");
+        };
+        interrupter.join().unwrap();
+        assert_eq!(error.kind(), &RuntimeErrorKind::Interrupted);
+        assert!(!engine.interrupt_handle().is_interrupted());
+
+        // The interrupt has been consumed so the engine can keep being used.
+        assert_eq!(engine.eval("n > 0").unwrap().to_string(), "true");
+
+        // Interrupts requested while nothing is running stop the next script.
+        engine.interrupt_handle().interrupt();
+        assert!(engine.eval("1").is_err());
+        assert!(engine.eval("1").is_ok());
+    }
+}
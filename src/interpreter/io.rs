@@ -0,0 +1,234 @@
+//! Input and output used by builtins, hosts which embed the interpreter can replace the standard
+//! streams to capture output or feed input to scripts. [`StreamIo`] connects scripts to any
+//! readers and writers the host provides, such as a GUI console or a log file.
+
+use std::{
+    fmt::Debug,
+    io::{BufRead, BufReader, Read, Write},
+    sync::{Arc, Mutex},
+};
+
+pub trait Io: Debug + Send {
+    /// Reads a line including its terminator, returns `None` at the end of input.
+    fn read_line(&mut self) -> std::io::Result<Option<String>>;
+
+    fn write(&mut self, s: &str) -> std::io::Result<()>;
+
+    /// Writes to the error stream, used by `eprint`. This is the output stream unless the
+    /// implementation has a separate one.
+    fn write_error(&mut self, s: &str) -> std::io::Result<()> {
+        self.write(s)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>;
+}
+
+/// Reads from stdin and writes to stdout.
+#[derive(Debug, Default)]
+pub struct StdIo;
+
+impl Io for StdIo {
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        let mut line = String::new();
+        match std::io::stdin().lock().read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+
+    fn write(&mut self, s: &str) -> std::io::Result<()> {
+        std::io::stdout().lock().write_all(s.as_bytes())
+    }
+
+    fn write_error(&mut self, s: &str) -> std::io::Result<()> {
+        std::io::stderr().lock().write_all(s.as_bytes())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().lock().flush()
+    }
+}
+
+/// Reads from a string and collects everything written into another, useful for tests. Writes to
+/// the error stream are collected separately.
+///
+/// Clones share their buffers, so the host can keep a clone to inspect the output after handing
+/// the original to the interpreter, even if the interpreter is on another thread.
+#[derive(Debug, Default, Clone)]
+pub struct BufferIo {
+    input: Arc<Mutex<String>>,
+    output: Arc<Mutex<String>>,
+    errors: Arc<Mutex<String>>,
+    /// Most bytes the output and errors may each hold, writes which would exceed it fail.
+    output_limit: Option<usize>,
+}
+
+impl BufferIo {
+    pub fn new(input: impl Into<String>) -> Self {
+        Self {
+            input: Arc::new(Mutex::new(input.into())),
+            output: Arc::default(),
+            errors: Arc::default(),
+            output_limit: None,
+        }
+    }
+
+    /// Makes writes fail once the output would hold more than `limit` bytes, so scripts can't
+    /// use up the host's memory by printing.
+    pub fn with_output_limit(mut self, limit: usize) -> Self {
+        self.output_limit = Some(limit);
+        self
+    }
+
+    /// Takes everything written so far.
+    pub fn take_output(&self) -> String {
+        std::mem::take(&mut self.output.lock().unwrap())
+    }
+
+    /// Takes everything written to the error stream so far.
+    pub fn take_errors(&self) -> String {
+        std::mem::take(&mut self.errors.lock().unwrap())
+    }
+
+    fn push(&self, buffer: &Mutex<String>, s: &str) -> std::io::Result<()> {
+        let mut buffer = buffer.lock().unwrap();
+        if let Some(limit) = self.output_limit {
+            if buffer.len().saturating_add(s.len()) > limit {
+                let message = format!("output exceeded the limit of {limit} bytes");
+                return Err(std::io::Error::other(message));
+            }
+        }
+        buffer.push_str(s);
+
+        Ok(())
+    }
+}
+
+impl Io for BufferIo {
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        let mut input = self.input.lock().unwrap();
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let len = input.find('\n').map_or(input.len(), |i| i + 1);
+        Ok(Some(input.drain(..len).collect()))
+    }
+
+    fn write(&mut self, s: &str) -> std::io::Result<()> {
+        self.push(&self.output, s)
+    }
+
+    fn write_error(&mut self, s: &str) -> std::io::Result<()> {
+        self.push(&self.errors, s)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads from and writes to streams provided by the host.
+pub struct StreamIo {
+    input: Box<dyn BufRead + Send>,
+    output: Box<dyn Write + Send>,
+    /// Separate error stream, errors are written to the output if there isn't one.
+    error: Option<Box<dyn Write + Send>>,
+}
+
+impl StreamIo {
+    /// Creates streams which read from `input` and write to `output`, the input is buffered.
+    pub fn new(input: impl Read + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        Self {
+            input: Box::new(BufReader::new(input)),
+            output: Box::new(output),
+            error: None,
+        }
+    }
+
+    /// Writes errors to `error` rather than the output.
+    pub fn with_error(mut self, error: impl Write + Send + 'static) -> Self {
+        self.error = Some(Box::new(error));
+        self
+    }
+}
+
+impl Debug for StreamIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamIo").finish_non_exhaustive()
+    }
+}
+
+impl Io for StreamIo {
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        let mut line = String::new();
+        match self.input.read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+
+    fn write(&mut self, s: &str) -> std::io::Result<()> {
+        self.output.write_all(s.as_bytes())
+    }
+
+    fn write_error(&mut self, s: &str) -> std::io::Result<()> {
+        match &mut self.error {
+            Some(error) => error.write_all(s.as_bytes()),
+            None => self.output.write_all(s.as_bytes()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.output.flush()?;
+        match &mut self.error {
+            Some(error) => error.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use super::StreamIo;
+    use crate::Engine;
+
+    /// Writer whose contents can be read after it has been handed to the interpreter.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn streams() {
+        let (output, error) = (Shared::default(), Shared::default());
+        let io =
+            StreamIo::new("Ada\nLovelace\n".as_bytes(), output.clone()).with_error(error.clone());
+        let mut engine = Engine::new().with_io(Box::new(io));
+        let source =
+            "let first = input(\"first: \"); println(first, readline()); eprintln(\"done\");";
+        engine.eval(source).unwrap();
+        assert_eq!(*output.0.lock().unwrap(), b"first: Ada Lovelace\n");
+        assert_eq!(*error.0.lock().unwrap(), b"done\n");
+        assert_eq!(engine.eval("readline()").unwrap().to_string(), "nil");
+
+        let output = Shared::default();
+        let io = StreamIo::new(std::io::empty(), output.clone());
+        let mut engine = Engine::new().with_io(Box::new(io));
+        engine.eval("print(1); eprint(2);").unwrap();
+        assert_eq!(*output.0.lock().unwrap(), b"12");
+    }
+}
@@ -0,0 +1,294 @@
+//! Lazy iterators, which produce their items one at a time when they are advanced instead of
+//! building a list up front.
+//!
+//! Every iterable value can be turned into an iterator with [`iterate`], lists and maps are
+//! iterable as well as iterators themselves. Iterators over lists read the list as they go so
+//! they see items pushed while iterating, iterators over maps visit the keys the map had when
+//! the iterator was created.
+
+use super::{
+    heap::Heap,
+    object::{Obj, ObjBytes, ObjIterator, ObjKind, ObjList, ObjMap},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+/// Where an iterator is up to.
+#[derive(Debug)]
+pub enum IterState {
+    /// Numbers from `start` up to but not including `end`, `index` is the number of steps taken
+    /// so far. Each number is computed from the start rather than by adding up steps, which
+    /// would accumulate rounding errors.
+    Range {
+        start: f64,
+        end: f64,
+        step: f64,
+        index: u64,
+    },
+    List {
+        list: Obj,
+        index: usize,
+    },
+    Values {
+        values: Vec<Value>,
+        index: usize,
+    },
+    /// Lists of the next item of each iterator, until any of them is exhausted.
+    Zip(Vec<Obj>),
+    /// Lists of the index and the item.
+    Enumerate {
+        inner: Obj,
+        index: usize,
+    },
+    Take {
+        inner: Obj,
+        remaining: usize,
+    },
+    Skip {
+        inner: Obj,
+        remaining: usize,
+    },
+    /// Every item of each iterator in turn.
+    Chain {
+        inners: Vec<Obj>,
+        index: usize,
+    },
+}
+
+impl IterState {
+    /// Pushes the objects referenced by the state onto `gray`.
+    pub(super) fn trace(&self, gray: &mut Vec<Obj>) {
+        match self {
+            IterState::Range { .. } => {}
+            IterState::List { list, .. } => gray.push(list.clone()),
+            IterState::Values { values, .. } => {
+                gray.extend(values.iter().filter_map(Value::as_obj).cloned());
+            }
+            IterState::Zip(inners) | IterState::Chain { inners, .. } => {
+                gray.extend(inners.iter().cloned());
+            }
+            IterState::Enumerate { inner, .. }
+            | IterState::Take { inner, .. }
+            | IterState::Skip { inner, .. } => gray.push(inner.clone()),
+        }
+    }
+
+    /// Returns the iterators this one advances.
+    pub(super) fn inners(&self) -> &[Obj] {
+        match self {
+            IterState::Range { .. } | IterState::List { .. } | IterState::Values { .. } => &[],
+            IterState::Zip(inners) | IterState::Chain { inners, .. } => inners,
+            IterState::Enumerate { inner, .. }
+            | IterState::Take { inner, .. }
+            | IterState::Skip { inner, .. } => std::slice::from_ref(inner),
+        }
+    }
+}
+
+/// Deepest nesting of iterators, advancing an iterator advances the ones nested in it
+/// recursively so this keeps it from overflowing the stack.
+pub const MAX_DEPTH: usize = 256;
+
+/// Returns an iterator over `value`, iterators are returned as they are.
+pub fn iterate(heap: &mut Heap, value: &Value) -> Result<Obj, RuntimeErrorKind> {
+    let not_iterable = || RuntimeErrorKind::NotIterable(value.type_name());
+    let obj = value.as_obj().ok_or_else(not_iterable)?;
+    let state = match obj.kind() {
+        ObjKind::Iterator => return Ok(obj.clone()),
+        ObjKind::List => IterState::List {
+            list: obj.clone(),
+            index: 0,
+        },
+        ObjKind::Map => {
+            let entries = AsRef::<ObjMap>::as_ref(obj).entries();
+            IterState::Values {
+                values: entries.keys().map(|key| key.value().clone()).collect(),
+                index: 0,
+            }
+        }
+        ObjKind::Bytes => {
+            let bytes = AsRef::<ObjBytes>::as_ref(obj).bytes();
+            IterState::Values {
+                values: bytes
+                    .iter()
+                    .map(|&byte| Value::from(i64::from(byte)))
+                    .collect(),
+                index: 0,
+            }
+        }
+        _ => return Err(not_iterable()),
+    };
+
+    Ok(heap.alloc(ObjIterator::new(state)))
+}
+
+/// Advances an iterator, returning `None` once it is exhausted.
+///
+/// Skipping items can take as long as running a loop does, so each item skipped uses fuel and
+/// checks for interrupts like a statement.
+pub fn next(interpreter: &mut Interpreter, iterator: &Obj) -> Result<Option<Value>, RuntimeError> {
+    let mut obj = iterator.clone();
+    let state = AsMut::<ObjIterator>::as_mut(&mut obj).state_mut();
+    let item = match state {
+        IterState::Range {
+            start,
+            end,
+            step,
+            index,
+        } => {
+            let item = *start + *index as f64 * *step;
+            let in_range = if *step > 0.0 {
+                item < *end
+            } else {
+                item > *end
+            };
+            if !in_range {
+                return Ok(None);
+            }
+
+            *index += 1;
+            Value::Number(item)
+        }
+        IterState::List { list, index } => {
+            let Some(item) = AsRef::<ObjList>::as_ref(list).items().get(*index).cloned() else {
+                return Ok(None);
+            };
+            *index += 1;
+            item
+        }
+        IterState::Values { values, index } => {
+            let Some(item) = values.get(*index).cloned() else {
+                return Ok(None);
+            };
+            *index += 1;
+            item
+        }
+        IterState::Zip(inners) => {
+            let inners = inners.clone();
+            let mut items = Vec::with_capacity(inners.len());
+            for inner in &inners {
+                let Some(item) = next(interpreter, inner)? else {
+                    return Ok(None);
+                };
+                items.push(item);
+            }
+            Value::Obj(interpreter.heap.alloc(ObjList::new(items)))
+        }
+        IterState::Enumerate { inner, index } => {
+            let i = *index;
+            let inner = inner.clone();
+            *index += 1;
+            let Some(item) = next(interpreter, &inner)? else {
+                return Ok(None);
+            };
+            let pair = vec![Value::Number(i as f64), item];
+            Value::Obj(interpreter.heap.alloc(ObjList::new(pair)))
+        }
+        IterState::Take { inner, remaining } => {
+            if *remaining == 0 {
+                return Ok(None);
+            }
+            *remaining -= 1;
+            let inner = inner.clone();
+            return next(interpreter, &inner);
+        }
+        IterState::Skip { inner, remaining } => {
+            let inner = inner.clone();
+            // Items are skipped one at a time, so that the rest are still skipped if skipping is
+            // stopped.
+            while *remaining > 0 {
+                interpreter.tick()?;
+                *remaining -= 1;
+                if next(interpreter, &inner)?.is_none() {
+                    *remaining = 0;
+                    return Ok(None);
+                }
+            }
+            return next(interpreter, &inner);
+        }
+        IterState::Chain { inners, index } => loop {
+            let Some(inner) = inners.get(*index).cloned() else {
+                return Ok(None);
+            };
+            if let Some(item) = next(interpreter, &inner)? {
+                break item;
+            }
+            *index += 1;
+        },
+    };
+
+    Ok(Some(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{iterate, next, IterState};
+    use crate::interpreter::{
+        object::{ObjIterator, ObjList},
+        value::Value,
+        Interpreter, RuntimeErrorKind,
+    };
+
+    fn collect(interpreter: &mut Interpreter, state: IterState) -> Vec<Value> {
+        let iterator = interpreter.heap.alloc(ObjIterator::new(state));
+        std::iter::from_fn(|| next(interpreter, &iterator).unwrap()).collect()
+    }
+
+    fn range(start: f64, end: f64, step: f64) -> IterState {
+        IterState::Range {
+            start,
+            end,
+            step,
+            index: 0,
+        }
+    }
+
+    #[test]
+    fn adapters() {
+        let mut interpreter = Interpreter::without_prelude();
+        let heap = &mut interpreter.heap;
+        let inner = heap.alloc(ObjIterator::new(range(0.0, 1e9, 1.0)));
+        let skip = heap.alloc(ObjIterator::new(IterState::Skip {
+            inner,
+            remaining: 2,
+        }));
+        let items = collect(
+            &mut interpreter,
+            IterState::Take {
+                inner: skip,
+                remaining: 2,
+            },
+        );
+        assert_eq!(items, [Value::Number(2.0), Value::Number(3.0)]);
+
+        let heap = &mut interpreter.heap;
+        let list = heap.alloc(ObjList::new(vec![Value::Nil]));
+        let list = iterate(heap, &Value::Obj(list)).unwrap();
+        let inners = vec![list, heap.alloc(ObjIterator::new(range(0.0, 2.0, 1.0)))];
+        let items = collect(&mut interpreter, IterState::Chain { inners, index: 0 });
+        assert_eq!(items, [Value::Nil, Value::Number(0.0), Value::Number(1.0)]);
+    }
+
+    #[test]
+    fn range_steps() {
+        // Adding up steps of 0.1 ten times falls just short of 1.
+        let mut interpreter = Interpreter::without_prelude();
+        let items = collect(&mut interpreter, range(0.0, 1.0, 0.1));
+        assert_eq!(items.len(), 10);
+        assert_eq!(items[3], Value::Number(0.1 * 3.0));
+    }
+
+    #[test]
+    fn skip_uses_fuel() {
+        let mut interpreter = Interpreter::without_prelude();
+        interpreter.set_fuel(Some(100));
+        let heap = &mut interpreter.heap;
+        let inner = heap.alloc(ObjIterator::new(range(0.0, 1e15, 1.0)));
+        let skip = heap.alloc(ObjIterator::new(IterState::Skip {
+            inner,
+            remaining: 1 << 50,
+        }));
+        let error = next(&mut interpreter, &skip).unwrap_err();
+        assert_eq!(error.kind(), &RuntimeErrorKind::OutOfFuel);
+    }
+}
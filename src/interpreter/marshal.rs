@@ -0,0 +1,478 @@
+//! Conversion of values to and from data which outlives the interpreter.
+//!
+//! Only plain data can be marshaled: nil, booleans, numbers, strings, lists and maps. Functions,
+//! instances and foreign objects are rejected, as are values which contain themselves.
+//!
+//! Two formats are supported, JSON for interoperability and a compact binary format which, unlike
+//! JSON, can represent every number and map keys of any marshalable type.
+
+use thiserror::Error;
+
+use super::{
+    heap::Heap,
+    object::{Obj, ObjKind, ObjList, ObjMap},
+    table::Table,
+    value::{Value, ValueKey},
+};
+use crate::json::{self, Json, JsonError};
+
+/// Deepest nesting of lists and maps which can be marshaled, this keeps reading and writing from
+/// overflowing the stack.
+pub const MAX_DEPTH: usize = json::MAX_DEPTH;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum MarshalError {
+    #[error("values of type {0} can't be marshaled")]
+    Unsupported(&'static str),
+    #[error("value contains itself")]
+    Cycle,
+    #[error("lists and maps are nested too deeply")]
+    TooDeep,
+    #[error("{0} can't be represented in JSON")]
+    NotJson(String),
+    #[error("invalid data at offset {offset}: {message}")]
+    Invalid { offset: usize, message: String },
+}
+
+/// Serializes `value` as JSON, maps must only have string keys.
+pub fn to_json(value: &Value) -> Result<String, MarshalError> {
+    let mut string = String::new();
+    JsonWriter {
+        out: &mut string,
+        ancestors: vec![],
+    }
+    .value(value)?;
+
+    Ok(string)
+}
+
+/// Parses JSON into a value, objects become maps which keep their keys in the order they appear.
+pub fn from_json(heap: &mut Heap, json: &str) -> Result<Value, MarshalError> {
+    let json = Json::parse(json).map_err(|error| match error {
+        JsonError::Invalid { offset, message } => MarshalError::Invalid { offset, message },
+        JsonError::TooDeep => MarshalError::TooDeep,
+    })?;
+
+    Ok(value_from_json(heap, json))
+}
+
+/// Serializes `value` to the binary format.
+pub fn to_bytes(value: &Value) -> Result<Vec<u8>, MarshalError> {
+    let mut bytes = MAGIC.to_vec();
+    BinaryWriter {
+        out: &mut bytes,
+        ancestors: vec![],
+    }
+    .value(value)?;
+
+    Ok(bytes)
+}
+
+/// Deserializes a value written by [`to_bytes`].
+pub fn from_bytes(heap: &mut Heap, bytes: &[u8]) -> Result<Value, MarshalError> {
+    let mut reader = BinaryReader {
+        heap,
+        bytes,
+        pos: 0,
+        depth: 0,
+    };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(reader.error("missing header"));
+    }
+
+    let value = reader.value()?;
+    if reader.pos != bytes.len() {
+        return Err(reader.error("trailing bytes"));
+    }
+
+    Ok(value)
+}
+
+/// Containers of `value` if it is one.
+enum Contents<'a> {
+    List(&'a [Value]),
+    Map(&'a Table<ValueKey, Value>),
+}
+
+fn contents(value: &Value) -> Result<Option<(&Obj, Contents<'_>)>, MarshalError> {
+    let Some(obj) = value.as_obj() else {
+        return Ok(None);
+    };
+
+    let contents = match obj.kind() {
+        ObjKind::String => return Ok(None),
+        ObjKind::List => Contents::List(AsRef::<ObjList>::as_ref(obj).items()),
+        ObjKind::Map => Contents::Map(AsRef::<ObjMap>::as_ref(obj).entries()),
+        _ => return Err(MarshalError::Unsupported(value.type_name())),
+    };
+
+    Ok(Some((obj, contents)))
+}
+
+/// Pushes `obj` onto the stack of containers being written, failing if it is already on it or
+/// the stack is full.
+fn enter(ancestors: &mut Vec<Obj>, obj: &Obj) -> Result<(), MarshalError> {
+    if ancestors.contains(obj) {
+        return Err(MarshalError::Cycle);
+    }
+    if ancestors.len() == MAX_DEPTH {
+        return Err(MarshalError::TooDeep);
+    }
+
+    ancestors.push(obj.clone());
+    Ok(())
+}
+
+/// Counts a list or map being read, failing if that is too many.
+fn descend(depth: &mut usize) -> Result<(), MarshalError> {
+    if *depth == MAX_DEPTH {
+        return Err(MarshalError::TooDeep);
+    }
+    *depth += 1;
+
+    Ok(())
+}
+
+struct JsonWriter<'a> {
+    out: &'a mut String,
+    ancestors: Vec<Obj>,
+}
+
+impl JsonWriter<'_> {
+    fn value(&mut self, value: &Value) -> Result<(), MarshalError> {
+        if let Some((obj, contents)) = contents(value)? {
+            enter(&mut self.ancestors, obj)?;
+            match contents {
+                Contents::List(items) => {
+                    self.out.push('[');
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push(',');
+                        }
+                        self.value(item)?;
+                    }
+                    self.out.push(']');
+                }
+                Contents::Map(entries) => {
+                    self.out.push('{');
+                    for (i, (key, value)) in entries.iter().enumerate() {
+                        if i > 0 {
+                            self.out.push(',');
+                        }
+                        let Some(key) = key.value().as_str() else {
+                            let key = format!("map key {:#}", key.value());
+                            return Err(MarshalError::NotJson(key));
+                        };
+                        self.string(key);
+                        self.out.push(':');
+                        self.value(value)?;
+                    }
+                    self.out.push('}');
+                }
+            }
+            self.ancestors.pop();
+
+            return Ok(());
+        }
+
+        match value {
+            Value::Number(n) if !n.is_finite() => return Err(MarshalError::NotJson(n.to_string())),
+            Value::Obj(_) => self.string(value.as_str().unwrap()),
+            Value::Nil => self.out.push_str("null"),
+            // Finite numbers and booleans are displayed the same way they're written in JSON.
+            _ => self.out.push_str(&value.to_string()),
+        }
+
+        Ok(())
+    }
+
+    fn string(&mut self, string: &str) {
+        self.out.push('"');
+        for c in string.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if c.is_control() => self.out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+/// Allocates a parsed document, its nesting was already limited by the parser.
+fn value_from_json(heap: &mut Heap, json: Json) -> Value {
+    match json {
+        Json::Null => Value::Nil,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Number(n) => Value::Number(n),
+        Json::String(s) => heap.alloc_string(s),
+        Json::Array(items) => {
+            let items = items
+                .into_iter()
+                .map(|item| value_from_json(heap, item))
+                .collect();
+            Value::Obj(heap.alloc(ObjList::new(items)))
+        }
+        Json::Object(members) => {
+            let mut entries = Table::new();
+            for (key, value) in members {
+                let key = heap.alloc_string(key);
+                let value = value_from_json(heap, value);
+                entries.insert(ValueKey::new(key), value);
+            }
+            Value::Obj(heap.alloc(ObjMap::new(entries)))
+        }
+    }
+}
+
+/// Start of every value written in the binary format, the last byte is the format version.
+const MAGIC: &[u8] = b"ULV\x01";
+
+mod tag {
+    pub const NIL: u8 = 0;
+    pub const FALSE: u8 = 1;
+    pub const TRUE: u8 = 2;
+    pub const NUMBER: u8 = 3;
+    pub const STRING: u8 = 4;
+    pub const LIST: u8 = 5;
+    pub const MAP: u8 = 6;
+}
+
+struct BinaryWriter<'a> {
+    out: &'a mut Vec<u8>,
+    ancestors: Vec<Obj>,
+}
+
+impl BinaryWriter<'_> {
+    fn value(&mut self, value: &Value) -> Result<(), MarshalError> {
+        if let Some((obj, contents)) = contents(value)? {
+            enter(&mut self.ancestors, obj)?;
+            match contents {
+                Contents::List(items) => {
+                    self.out.push(tag::LIST);
+                    self.len(items.len());
+                    for item in items {
+                        self.value(item)?;
+                    }
+                }
+                Contents::Map(entries) => {
+                    self.out.push(tag::MAP);
+                    self.len(entries.len());
+                    for (key, value) in entries.iter() {
+                        self.value(key.value())?;
+                        self.value(value)?;
+                    }
+                }
+            }
+            self.ancestors.pop();
+
+            return Ok(());
+        }
+
+        match value {
+            Value::Nil => self.out.push(tag::NIL),
+            Value::Bool(false) => self.out.push(tag::FALSE),
+            Value::Bool(true) => self.out.push(tag::TRUE),
+            Value::Number(n) => {
+                self.out.push(tag::NUMBER);
+                self.out.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::Obj(_) => {
+                let string = value.as_str().unwrap();
+                self.out.push(tag::STRING);
+                self.len(string.len());
+                self.out.extend_from_slice(string.as_bytes());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lengths are written as little endian base 128 varints.
+    fn len(&mut self, mut len: usize) {
+        loop {
+            let byte = (len & 0x7f) as u8;
+            len >>= 7;
+            if len == 0 {
+                self.out.push(byte);
+                break;
+            }
+            self.out.push(byte | 0x80);
+        }
+    }
+}
+
+struct BinaryReader<'a> {
+    heap: &'a mut Heap,
+    bytes: &'a [u8],
+    pos: usize,
+    /// Number of lists and maps being read.
+    depth: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn error(&self, message: &str) -> MarshalError {
+        MarshalError::Invalid {
+            offset: self.pos,
+            message: message.to_owned(),
+        }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MarshalError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(n))
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += n;
+
+        Ok(bytes)
+    }
+
+    fn len(&mut self) -> Result<usize, MarshalError> {
+        let mut len = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.take(1)?[0];
+            len |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                // Every element takes at least a byte, this rejects absurd lengths before
+                // anything is allocated for them.
+                if len > self.bytes.len() - self.pos {
+                    return Err(self.error("length out of range"));
+                }
+                return Ok(len);
+            }
+        }
+
+        Err(self.error("length out of range"))
+    }
+
+    fn value(&mut self) -> Result<Value, MarshalError> {
+        let value = match self.take(1)?[0] {
+            tag::NIL => Value::Nil,
+            tag::FALSE => Value::Bool(false),
+            tag::TRUE => Value::Bool(true),
+            tag::NUMBER => {
+                let bytes = self.take(8)?.try_into().unwrap();
+                Value::Number(f64::from_le_bytes(bytes))
+            }
+            tag::STRING => {
+                let len = self.len()?;
+                let bytes = self.take(len)?;
+                let string = std::str::from_utf8(bytes).map_err(|_| self.error("invalid utf-8"))?;
+                self.heap.alloc_string(string)
+            }
+            tag::LIST => {
+                let len = self.len()?;
+                descend(&mut self.depth)?;
+                let items = (0..len).map(|_| self.value()).collect::<Result<_, _>>()?;
+                self.depth -= 1;
+                Value::Obj(self.heap.alloc(ObjList::new(items)))
+            }
+            tag::MAP => {
+                let len = self.len()?;
+                descend(&mut self.depth)?;
+                let mut entries = Table::new();
+                for _ in 0..len {
+                    let key = self.value()?;
+                    let value = self.value()?;
+                    entries.insert(ValueKey::new(key), value);
+                }
+                self.depth -= 1;
+                Value::Obj(self.heap.alloc(ObjMap::new(entries)))
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown tag"));
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, from_json, to_bytes, to_json, MarshalError, MAGIC, MAX_DEPTH};
+    use crate::interpreter::{
+        heap::Heap,
+        object::{ObjInstance, ObjList},
+        value::Value,
+    };
+
+    #[test]
+    fn json_round_trip() {
+        let mut heap = Heap::default();
+        let json = r#"{"a":[1,2.5,-3e-7],"b":{"c":null,"d":true},"e":"line\n\"é\" 😀"}"#;
+        let value = from_json(&mut heap, json).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{ "a": [1, 2.5, -0.0000003], "b": { "c": nil, "d": true }, "e": "line\n\"é\" 😀" }"#
+        );
+        assert_eq!(
+            to_json(&value).unwrap(),
+            r#"{"a":[1,2.5,-0.0000003],"b":{"c":null,"d":true},"e":"line\n\"é\" 😀"}"#
+        );
+
+        assert!(from_json(&mut heap, "[1,]").is_err());
+        assert!(from_json(&mut heap, "[1] 2").is_err());
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut heap = Heap::default();
+        let value = from_json(&mut heap, r#"{"a":[1,"two",null,false]}"#).unwrap();
+        let nan = Value::Number(f64::NAN);
+        let list = Value::Obj(heap.alloc(ObjList::new(vec![value, nan])));
+
+        let bytes = to_bytes(&list).unwrap();
+        let copy = from_bytes(&mut heap, &bytes).unwrap();
+        assert_eq!(
+            copy.to_string(),
+            r#"[{ "a": [1, "two", nil, false] }, nan]"#
+        );
+        assert_ne!(copy, list);
+
+        assert!(from_bytes(&mut heap, &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut heap = Heap::default();
+        let instance = Value::Obj(heap.alloc(ObjInstance::new()));
+        assert_eq!(
+            to_json(&instance),
+            Err(MarshalError::Unsupported("instance"))
+        );
+
+        let list = Value::Obj(heap.alloc(ObjList::new(vec![])));
+        let mut obj = list.as_obj().unwrap().clone();
+        AsMut::<ObjList>::as_mut(&mut obj)
+            .items_mut()
+            .push(list.clone());
+        assert_eq!(to_bytes(&list), Err(MarshalError::Cycle));
+        assert!(to_json(&Value::Number(f64::INFINITY)).is_err());
+    }
+
+    #[test]
+    fn too_deep() {
+        let mut heap = Heap::default();
+        let json = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let value = from_json(&mut heap, &json(MAX_DEPTH)).unwrap();
+        assert_eq!(to_json(&value).unwrap(), json(MAX_DEPTH));
+        let bytes = to_bytes(&value).unwrap();
+        assert!(from_bytes(&mut heap, &bytes).is_ok());
+
+        let deeper = Value::Obj(heap.alloc(ObjList::new(vec![value])));
+        assert_eq!(to_json(&deeper), Err(MarshalError::TooDeep));
+        assert_eq!(to_bytes(&deeper), Err(MarshalError::TooDeep));
+        let json = json(100_000);
+        assert_eq!(from_json(&mut heap, &json), Err(MarshalError::TooDeep));
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([5, 1].repeat(100_000));
+        assert_eq!(from_bytes(&mut heap, &bytes), Err(MarshalError::TooDeep));
+    }
+}
@@ -0,0 +1,58 @@
+//! Methods of the built in types, such as `256.log2()`.
+//!
+//! Methods are native functions which receive the value they were called on as their first
+//! argument.
+
+use super::{
+    object::{Arity, NativeFn, ObjKind},
+    value::Value,
+};
+
+mod bytes;
+mod error;
+mod iterator;
+mod list;
+mod map;
+mod number;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Method {
+    pub name: &'static str,
+    /// Number of arguments accepted, not counting the receiver.
+    pub arity: Arity,
+    pub function: NativeFn,
+}
+
+impl Method {
+    const fn new(name: &'static str, arity: Arity, function: NativeFn) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+}
+
+/// Finds the method called `name` of the type of `receiver`.
+pub fn lookup(receiver: &Value, name: &str) -> Option<Method> {
+    methods_of(receiver)
+        .iter()
+        .find(|method| method.name == name)
+        .copied()
+}
+
+/// All methods of the type of `receiver`.
+pub fn methods_of(receiver: &Value) -> &'static [Method] {
+    match receiver {
+        Value::Number(_) => number::METHODS,
+        Value::Obj(obj) => match obj.kind() {
+            ObjKind::List => list::METHODS,
+            ObjKind::Map => map::METHODS,
+            ObjKind::Bytes => bytes::METHODS,
+            ObjKind::Error => error::METHODS,
+            ObjKind::Iterator => iterator::METHODS,
+            _ => &[],
+        },
+        _ => &[],
+    }
+}
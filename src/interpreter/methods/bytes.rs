@@ -0,0 +1,105 @@
+use super::Method;
+use crate::interpreter::{
+    check_byte, check_index, encoding,
+    object::{Arity, Obj, ObjBytes, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) const METHODS: &[Method] = &[
+    Method::new("len", Arity::Exact(0), len),
+    Method::new("push", Arity::Exact(1), push),
+    Method::new("slice", Arity::Range(1, 2), slice),
+    Method::new("to_string", Arity::Exact(0), to_string),
+    Method::new("to_hex", Arity::Exact(0), to_hex),
+    Method::new("to_list", Arity::Exact(0), to_list),
+];
+
+/// Returns the receiver, methods are only looked up for bytes so this can't fail.
+fn receiver(value: &Value) -> Obj {
+    value.as_obj().expect("receiver should be bytes").clone()
+}
+
+fn bytes(value: &Value) -> &[u8] {
+    let obj = value.as_obj().expect("receiver should be bytes");
+    AsRef::<ObjBytes>::as_ref(obj).bytes()
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(bytes(&args[0]).len() as f64))
+}
+
+/// Appends a byte, which must be a number from 0 to 255.
+fn push(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let byte = check_byte(&args[1])?;
+    interpreter.heap.mutate(&receiver(&args[0]), |obj| {
+        AsMut::<ObjBytes>::as_mut(obj).bytes_mut().push(byte)
+    });
+
+    Ok(Value::Nil)
+}
+
+/// Copies the bytes from `start` up to but not including `end`, or the end of the buffer.
+/// Negative positions count from the end.
+fn slice(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let bytes = bytes(&args[0]);
+    let len = bytes.len();
+    let start = position(&args[1], len)?;
+    let end = match args.get(2) {
+        Some(end) => position(end, len)?,
+        None => len,
+    };
+    if start > end {
+        let message = format!("slice start {start} is after its end {end}");
+        return Err(RuntimeErrorKind::InvalidArgument(message).into());
+    }
+
+    let slice = bytes[start..end].to_vec();
+    Ok(Value::Obj(interpreter.heap.alloc(ObjBytes::new(slice))))
+}
+
+/// Converts a position between bytes, which may be the length of the buffer. Negative positions
+/// count from the end.
+fn position(value: &Value, len: usize) -> Result<usize, RuntimeErrorKind> {
+    let limit = match *value {
+        Value::Number(n) if n < 0.0 => len,
+        _ => len + 1,
+    };
+    // Errors report the length of the buffer rather than the limit.
+    check_index(value, limit, "bytes").map_err(|error| match error {
+        RuntimeErrorKind::IndexOutOfRange {
+            index, type_name, ..
+        } => RuntimeErrorKind::IndexOutOfRange {
+            index,
+            len,
+            type_name,
+        },
+        error => error,
+    })
+}
+
+/// Decodes the bytes as UTF-8, it is an error if they aren't valid.
+fn to_string(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let text = std::str::from_utf8(bytes(&args[0]))
+        .map_err(|error| RuntimeErrorKind::InvalidArgument(error.to_string()))?
+        .to_owned();
+
+    Ok(interpreter.heap.alloc_string(text))
+}
+
+/// Lowercase hexadecimal, two digits per byte.
+fn to_hex(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let hex = encoding::hex_encode(bytes(&args[0]));
+
+    Ok(interpreter.heap.alloc_string(hex))
+}
+
+/// List of the bytes as numbers.
+fn to_list(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let items = bytes(&args[0])
+        .iter()
+        .map(|&byte| Value::from(i64::from(byte)))
+        .collect();
+
+    Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(items))))
+}
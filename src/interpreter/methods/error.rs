@@ -0,0 +1,15 @@
+use super::Method;
+use crate::interpreter::{
+    object::{Arity, ObjError},
+    value::Value,
+    Interpreter, RuntimeError,
+};
+
+pub(super) const METHODS: &[Method] = &[Method::new("message", Arity::Exact(0), message)];
+
+/// The message the error was created with.
+fn message(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let obj = args[0].as_obj().expect("receiver should be an error");
+
+    Ok(AsRef::<ObjError>::as_ref(obj).message().clone())
+}
@@ -0,0 +1,12 @@
+use super::Method;
+use crate::interpreter::{builtins::iter, object::Arity};
+
+pub(super) const METHODS: &[Method] = &[
+    Method::new("next", Arity::Exact(0), iter::next),
+    Method::new("to_list", Arity::Exact(0), iter::to_list),
+    Method::new("zip", Arity::AtLeast(0), iter::zip),
+    Method::new("enumerate", Arity::Exact(0), iter::enumerate),
+    Method::new("take", Arity::Exact(1), iter::take),
+    Method::new("skip", Arity::Exact(1), iter::skip),
+    Method::new("chain", Arity::AtLeast(0), iter::chain),
+];
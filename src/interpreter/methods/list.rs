@@ -0,0 +1,280 @@
+use std::ops::Range;
+
+use super::Method;
+use crate::interpreter::{
+    check_index,
+    object::{Arity, Obj, ObjList},
+    value::Value,
+    Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) const METHODS: &[Method] = &[
+    Method::new("push", Arity::Exact(1), push),
+    Method::new("pop", Arity::Exact(0), pop),
+    Method::new("insert", Arity::Exact(2), insert),
+    Method::new("remove", Arity::Exact(1), remove),
+    Method::new("len", Arity::Exact(0), len),
+    Method::new("contains", Arity::Exact(1), contains),
+    Method::new("index_of", Arity::Exact(1), index_of),
+    Method::new("reverse", Arity::Exact(0), reverse),
+    Method::new("join", Arity::Range(0, 1), join),
+    Method::new("map", Arity::Exact(1), map),
+    Method::new("filter", Arity::Exact(1), filter),
+    Method::new("reduce", Arity::Range(1, 2), reduce),
+];
+
+/// Returns the receiver, methods are only looked up for lists so this can't fail.
+fn list(value: &Value) -> Obj {
+    value.as_obj().expect("receiver should be a list").clone()
+}
+
+fn items(value: &Value) -> &[Value] {
+    let obj = value.as_obj().expect("receiver should be a list");
+    AsRef::<ObjList>::as_ref(obj).items()
+}
+
+fn items_mut(obj: &mut Obj) -> &mut Vec<Value> {
+    AsMut::<ObjList>::as_mut(obj).items_mut()
+}
+
+/// Appends a value to the end of the list.
+fn push(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    interpreter
+        .heap
+        .mutate(&list(&args[0]), |obj| items_mut(obj).push(args[1].clone()));
+
+    Ok(Value::Nil)
+}
+
+/// Removes and returns the last item, it is an error to pop from an empty list.
+fn pop(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    items_mut(&mut list(&args[0])).pop().ok_or_else(|| {
+        RuntimeErrorKind::IndexOutOfRange {
+            index: -1.0,
+            len: 0,
+            type_name: "list",
+        }
+        .into()
+    })
+}
+
+/// Inserts a value before the item at the index, the index may also be the length of the list
+/// to append the value.
+fn insert(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    interpreter.heap.mutate(&list(&args[0]), |obj| {
+        let items = items_mut(obj);
+        let i = check_index(&args[1], items.len() + 1, "list")?;
+        items.insert(i, args[2].clone());
+
+        Ok(Value::Nil)
+    })
+}
+
+/// Removes and returns the item at the index.
+fn remove(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let mut obj = list(&args[0]);
+    let items = items_mut(&mut obj);
+    let i = check_index(&args[1], items.len(), "list")?;
+
+    Ok(items.remove(i))
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(items(&args[0]).len() as f64))
+}
+
+fn contains(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Bool(items(&args[0]).contains(&args[1])))
+}
+
+/// Index of the first item equal to the value, or nil if there isn't one.
+fn index_of(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let index = items(&args[0]).iter().position(|item| *item == args[1]);
+
+    Ok(index.map(|i| i as i64).into())
+}
+
+/// Reverses the list in place.
+fn reverse(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    items_mut(&mut list(&args[0])).reverse();
+
+    Ok(Value::Nil)
+}
+
+/// Concatenates the items as they would be printed, separated by the separator if given.
+fn join(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let separator = match args.get(1) {
+        Some(separator) => String::try_from(separator.clone())?,
+        None => String::new(),
+    };
+    let strings: Vec<_> = items(&args[0]).iter().map(Value::to_string).collect();
+    let separators = separator
+        .len()
+        .saturating_mul(strings.len().saturating_sub(1));
+    let len = strings.iter().map(String::len).sum::<usize>();
+    interpreter.check_allocation(len.saturating_add(separators))?;
+
+    Ok(interpreter.heap.alloc_string(strings.join(&separator)))
+}
+
+// The callbacks of `map`, `filter` and `reduce` may modify the list, they see the items as they
+// were when the method was called.
+
+/// Pushes the items of `list` onto `temporaries` and runs `f` with their indices, anything else
+/// `f` pushes stays rooted until it returns too. This keeps the items alive while callbacks
+/// collect garbage, even if they are removed from the list.
+fn with_rooted_items(
+    interpreter: &mut Interpreter,
+    list: &Value,
+    f: impl FnOnce(&mut Interpreter, Range<usize>) -> Result<Value, RuntimeError>,
+) -> Result<Value, RuntimeError> {
+    let base = interpreter.temporaries.len();
+    interpreter.temporaries.extend_from_slice(items(list));
+    let indices = base..interpreter.temporaries.len();
+    let result = f(interpreter, indices);
+    interpreter.temporaries.truncate(base);
+
+    result
+}
+
+/// Returns a new list with the result of calling the function on each item.
+fn map(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    with_rooted_items(interpreter, &args[0], |interpreter, indices| {
+        let end = indices.end;
+        for i in indices {
+            let item = interpreter.temporaries[i].clone();
+            let mapped = interpreter.call_collecting(&args[1], &[item])?;
+            interpreter.temporaries.push(mapped);
+        }
+
+        let mapped = interpreter.temporaries.drain(end..).collect();
+        Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(mapped))))
+    })
+}
+
+/// Returns a new list with the items for which the function returns a truthy value.
+fn filter(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    with_rooted_items(interpreter, &args[0], |interpreter, indices| {
+        let mut filtered = vec![];
+        for i in indices {
+            let item = interpreter.temporaries[i].clone();
+            if interpreter
+                .call_collecting(&args[1], std::slice::from_ref(&item))?
+                .is_truthy()
+            {
+                filtered.push(item);
+            }
+        }
+
+        Ok(Value::Obj(interpreter.heap.alloc(ObjList::new(filtered))))
+    })
+}
+
+/// Combines the items from first to last by calling the function with the result so far and
+/// the next item, starting with the initial value if given or the first item otherwise.
+fn reduce(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    with_rooted_items(interpreter, &args[0], |interpreter, mut indices| {
+        let initial = match args.get(2) {
+            Some(initial) => Some(initial.clone()),
+            None => indices.next().map(|i| interpreter.temporaries[i].clone()),
+        };
+        let Some(mut accumulator) = initial else {
+            let message = "reduce of empty list with no initial value".to_owned();
+            return Err(RuntimeErrorKind::InvalidArgument(message).into());
+        };
+
+        // The accumulator is only held between calls, which root their arguments.
+        for i in indices {
+            let item = interpreter.temporaries[i].clone();
+            accumulator = interpreter.call_collecting(&args[1], &[accumulator, item])?;
+        }
+
+        Ok(accumulator)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{
+        testing::{eval, run},
+        Interpreter, RuntimeErrorKind,
+    };
+
+    #[test]
+    fn mutation() {
+        let source = "
+            let x = [1, 2];
+            x.push(3);
+            x.insert(0, 0);
+            x.insert(-1, 4);
+            x.remove(1);
+            x.pop();
+            x.reverse();
+        ";
+        assert_eq!(eval(source).as_deref(), Ok("[3, 2, 0]"));
+        assert_eq!(
+            eval("let x = []; x.pop();"),
+            Err(RuntimeErrorKind::IndexOutOfRange {
+                index: -1.0,
+                len: 0,
+                type_name: "list"
+            })
+        );
+        assert_eq!(
+            eval("let x = [1]; x.insert(2, 0);"),
+            Err(RuntimeErrorKind::IndexOutOfRange {
+                index: 2.0,
+                len: 2,
+                type_name: "list"
+            })
+        );
+    }
+
+    #[test]
+    fn queries() {
+        let source = r#"
+            let l = ["a", "b"];
+            let x = [l.len(), l.contains("b"), l.index_of("b"), l.index_of("c"), l.join(", ")];
+        "#;
+        assert_eq!(eval(source).as_deref(), Ok(r#"[2, true, 1, nil, "a, b"]"#));
+    }
+
+    #[test]
+    fn higher_order() {
+        let source = "
+            func double(n) { return n * 2; }
+            func odd(n) { return n % 2 == 1; }
+            func add(a, b) { return a + b; }
+            let l = [1, 2, 3];
+            let x = [l.map(double), l.filter(odd), l.reduce(add), l.reduce(add, 10)];
+        ";
+        assert_eq!(eval(source).as_deref(), Ok("[[2, 4, 6], [1, 3], 6, 16]"));
+        assert!(matches!(
+            eval("func f(a, b) {} let x = [].reduce(f);"),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn collected_in_callbacks() {
+        // The limit makes the callbacks collect garbage after the list has been cleared, the
+        // items and results held by `map` stay alive.
+        let interpreter = &mut Interpreter::default();
+        interpreter.heap_mut().set_memory_limit(Some(64 * 1024));
+        let source = r#"
+            let l = ["a" + "b", "c" + "d", "e" + "f"];
+            func f(s) {
+                while l.len() > 0 { l.pop(); }
+                let t = "0123456789";
+                for i in range(11) { t = t + t; }
+                return s + "!";
+            }
+            let x = l.map(f);
+        "#;
+        assert_eq!(
+            run(interpreter, source).as_deref(),
+            Ok(r#"["ab!", "cd!", "ef!"]"#)
+        );
+        assert!(interpreter.heap().stats().collections > 0);
+    }
+}
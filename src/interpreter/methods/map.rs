@@ -0,0 +1,148 @@
+use super::Method;
+use crate::interpreter::{
+    object::{Arity, Obj, ObjList, ObjMap},
+    table::Table,
+    value::{Value, ValueKey},
+    Interpreter, RuntimeError,
+};
+
+pub(super) const METHODS: &[Method] = &[
+    Method::new("get", Arity::Range(1, 2), get),
+    Method::new("set", Arity::Exact(2), set),
+    Method::new("remove", Arity::Exact(1), remove),
+    Method::new("has", Arity::Exact(1), has),
+    Method::new("keys", Arity::Exact(0), keys),
+    Method::new("values", Arity::Exact(0), values),
+    Method::new("entries", Arity::Exact(0), entries),
+    Method::new("len", Arity::Exact(0), len),
+];
+
+/// Returns the receiver, methods are only looked up for maps so this can't fail.
+fn map(value: &Value) -> Obj {
+    value.as_obj().expect("receiver should be a map").clone()
+}
+
+fn entries_of(value: &Value) -> &Table<ValueKey, Value> {
+    let obj = value.as_obj().expect("receiver should be a map");
+    AsRef::<ObjMap>::as_ref(obj).entries()
+}
+
+fn entries_mut(obj: &mut Obj) -> &mut Table<ValueKey, Value> {
+    AsMut::<ObjMap>::as_mut(obj).entries_mut()
+}
+
+fn list(interpreter: &mut Interpreter, items: Vec<Value>) -> Value {
+    Value::Obj(interpreter.heap.alloc(ObjList::new(items)))
+}
+
+/// Value of the key, or the default if given and nil otherwise when the key isn't present.
+fn get(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let value = entries_of(&args[0]).get(&ValueKey::new(args[1].clone()));
+    let default = args.get(2).cloned().unwrap_or_default();
+
+    Ok(value.cloned().unwrap_or(default))
+}
+
+fn set(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    interpreter.heap.mutate(&map(&args[0]), |obj| {
+        entries_mut(obj).insert(ValueKey::new(args[1].clone()), args[2].clone())
+    });
+
+    Ok(Value::Nil)
+}
+
+/// Removes the key, returning its value or nil if it wasn't present.
+fn remove(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let removed = interpreter.heap.mutate(&map(&args[0]), |obj| {
+        entries_mut(obj).remove(&ValueKey::new(args[1].clone()))
+    });
+
+    Ok(removed.unwrap_or_default())
+}
+
+fn has(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let has = entries_of(&args[0]).contains_key(&ValueKey::new(args[1].clone()));
+
+    Ok(Value::Bool(has))
+}
+
+/// List of the keys in insertion order.
+fn keys(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let keys = entries_of(&args[0])
+        .keys()
+        .map(|key| key.value().clone())
+        .collect();
+
+    Ok(list(interpreter, keys))
+}
+
+/// List of the values in insertion order.
+fn values(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let values = entries_of(&args[0]).values().cloned().collect();
+
+    Ok(list(interpreter, values))
+}
+
+/// List of `[key, value]` lists in insertion order.
+fn entries(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let pairs: Vec<_> = entries_of(&args[0])
+        .iter()
+        .map(|(key, value)| vec![key.value().clone(), value.clone()])
+        .collect();
+    // The heap can't collect while a native function is running so the pairs don't need to be
+    // rooted.
+    let entries = pairs
+        .into_iter()
+        .map(|pair| list(interpreter, pair))
+        .collect();
+
+    Ok(list(interpreter, entries))
+}
+
+fn len(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(entries_of(&args[0]).len() as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::testing::eval;
+
+    #[test]
+    fn methods() {
+        let source = r#"
+            let m = { "a": 1, "b": 2 };
+            m.set("c", 3);
+            let removed = m.remove("a");
+            let x = [
+                removed,
+                m.get("b"),
+                m.get("a"),
+                m.get("a", 0),
+                m.has("c"),
+                m.len(),
+                m.keys(),
+                m.values(),
+                m.entries(),
+            ];
+        "#;
+        assert_eq!(
+            eval(source).as_deref(),
+            Ok(r#"[1, 2, nil, 0, true, 2, ["b", "c"], [2, 3], [["b", 2], ["c", 3]]]"#)
+        );
+    }
+
+    #[test]
+    fn iteration() {
+        // Keys added while iterating aren't visited.
+        let source = r#"
+            let m = { "a": 1, "b": 2 };
+            let x = [];
+            for key in m {
+                x.push(key);
+                x.push(m[key]);
+                m.set(key + key, 0);
+            }
+        "#;
+        assert_eq!(eval(source).as_deref(), Ok(r#"["a", 1, "b", 2]"#));
+    }
+}
@@ -0,0 +1,91 @@
+use super::Method;
+use crate::interpreter::{
+    object::Arity, value::Value, Interpreter, RuntimeError, RuntimeErrorKind,
+};
+
+pub(super) const METHODS: &[Method] = &[
+    Method::new("abs", Arity::Exact(0), abs),
+    Method::new("floor", Arity::Exact(0), floor),
+    Method::new("ceil", Arity::Exact(0), ceil),
+    Method::new("round", Arity::Exact(0), round),
+    Method::new("trunc", Arity::Exact(0), trunc),
+    Method::new("sqrt", Arity::Exact(0), sqrt),
+    Method::new("log2", Arity::Exact(0), log2),
+    Method::new("clamp", Arity::Exact(2), clamp),
+    Method::new("to_string", Arity::Exact(0), to_string),
+];
+
+fn number(value: &Value) -> Result<f64, RuntimeError> {
+    f64::try_from(value.clone())
+}
+
+fn abs(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.abs()))
+}
+
+fn floor(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.floor()))
+}
+
+fn ceil(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.ceil()))
+}
+
+fn round(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.round()))
+}
+
+fn trunc(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.trunc()))
+}
+
+fn sqrt(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.sqrt()))
+}
+
+fn log2(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(Value::Number(number(&args[0])?.log2()))
+}
+
+/// Restricts the number to the range `min..=max`.
+fn clamp(_: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (n, min, max) = (number(&args[0])?, number(&args[1])?, number(&args[2])?);
+    // `f64::clamp` panics in these cases.
+    if min > max || min.is_nan() || max.is_nan() {
+        let message = format!("invalid clamp range {min}..{max}");
+        return Err(RuntimeErrorKind::InvalidArgument(message).into());
+    }
+
+    Ok(Value::Number(n.clamp(min, max)))
+}
+
+fn to_string(interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, RuntimeError> {
+    Ok(interpreter.heap.alloc_string(args[0].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::{testing::eval_expr, RuntimeErrorKind};
+
+    #[test]
+    fn methods() {
+        assert_eq!(eval_expr("256.log2()").as_deref(), Ok("8"));
+        assert_eq!(eval_expr("(-2.5).abs().floor()").as_deref(), Ok("2"));
+        assert_eq!(eval_expr("12.clamp(0, 10)").as_deref(), Ok("10"));
+        assert_eq!(
+            eval_expr("1.5.to_string() == \"1.5\"").as_deref(),
+            Ok("true")
+        );
+        assert!(matches!(
+            eval_expr("1.clamp(2, 1)"),
+            Err(RuntimeErrorKind::InvalidArgument(_))
+        ));
+        assert_eq!(
+            eval_expr("1.foo()"),
+            Err(RuntimeErrorKind::UndefinedMember {
+                type_name: "number",
+                name: "foo".to_owned()
+            })
+        );
+    }
+}
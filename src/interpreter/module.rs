@@ -0,0 +1,169 @@
+//! Native modules, which scripts load with `import "name";`. A module is a set of functions and
+//! classes implemented in Rust, imported as an object with a field for each of them:
+//!
+//! ```
+//! use unnamed_language::{interpreter::module::Module, Engine};
+//!
+//! let mut module = Module::new();
+//! module.function("double", |x: f64| x * 2.0);
+//! let mut engine = Engine::new();
+//! // SAFETY: the function only converts values to and from numbers, it doesn't keep any.
+//! unsafe { engine.register_module("numbers", module) };
+//! let value = engine.eval("import \"numbers\"; numbers.double(4)").unwrap();
+//! assert_eq!(value.to_string(), "8");
+//! ```
+//!
+//! Unlike globals registered by the host, modules are only visible to scripts which import them.
+//! Modules can also be compiled separately and loaded when they are imported, see
+//! [`plugin`](super::plugin).
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+    sync::Arc,
+};
+
+use super::{
+    class::{Class, ClassBuilder, ClassMethod},
+    native::IntoNative,
+    object::{Arity, NativeClosure},
+    value::Value,
+    Interpreter, RuntimeError,
+};
+
+#[derive(Default)]
+pub struct Module {
+    functions: Vec<(&'static str, Arity, Arc<NativeClosure>)>,
+    /// Classes along with their constructors, by the id of the Rust type they expose.
+    classes: Vec<(TypeId, Class, Option<ClassMethod>)>,
+}
+
+impl Module {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a function with typed parameters, see [`native`](super::native).
+    pub fn function<Args, F: IntoNative<Args>>(&mut self, name: &'static str, function: F) {
+        self.functions
+            .push((name, F::ARITY, function.into_native()));
+    }
+
+    /// Adds a function which takes its arguments unconverted, along with the interpreter to
+    /// allocate its results with.
+    pub fn native(
+        &mut self,
+        name: &'static str,
+        arity: Arity,
+        function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.functions.push((name, arity, Arc::new(function)));
+    }
+
+    /// Adds a class, see [`class`](super::class). Its constructor is a field of the module rather
+    /// than a global.
+    pub fn class<T: Any + Send>(&mut self, class: ClassBuilder<T>) {
+        let (class, constructor) = class.build();
+        self.classes.push((TypeId::of::<T>(), class, constructor));
+    }
+
+    /// Returns the functions of the module, including the constructors of its classes, and
+    /// registers its classes with the interpreter.
+    pub(super) fn into_functions(
+        self,
+        classes: &mut HashMap<TypeId, Arc<Class>>,
+    ) -> Vec<(&'static str, Arity, Arc<NativeClosure>)> {
+        let mut functions = self.functions;
+        for (id, class, constructor) in self.classes {
+            if let Some(constructor) = constructor {
+                functions.push((class.name(), constructor.arity, constructor.function));
+            }
+            classes.insert(id, Arc::new(class));
+        }
+
+        functions
+    }
+}
+
+impl Debug for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let functions: Vec<_> = self.functions.iter().map(|(name, ..)| name).collect();
+        let classes: Vec<_> = self
+            .classes
+            .iter()
+            .map(|(_, class, _)| class.name())
+            .collect();
+        f.debug_struct("Module")
+            .field("functions", &functions)
+            .field("classes", &classes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Module;
+    use crate::{
+        interpreter::{class::ClassBuilder, object::Arity, RuntimeErrorKind},
+        Engine, Error, HostValue,
+    };
+
+    struct Counter(f64);
+
+    #[test]
+    fn import() {
+        let mut module = Module::new();
+        module.function("add", |a: f64, b: f64| a + b);
+        module.native("first", Arity::AtLeast(1), |_, args| Ok(args[0].clone()));
+        module.class(
+            ClassBuilder::new("Counter")
+                .constructor(|| Counter(0.0))
+                .method("increment", |counter: &mut Counter| counter.0 += 1.0)
+                .property("count", |counter: &Counter| counter.0),
+        );
+        let mut engine = Engine::new();
+        // SAFETY: `first` returns its argument rather than keeping it, nothing else sees values.
+        unsafe { engine.register_module("util", module) };
+
+        // Nothing is visible until the module is imported.
+        assert!(engine.eval("add").is_err());
+        assert!(engine.eval("Counter").is_err());
+
+        let value = engine
+            .eval(
+                "import \"util\";
+                let c = util.Counter();
+                c.increment();
+                [util.add(1, 2), util.first(\"a\", \"b\"), c.count]",
+            )
+            .unwrap();
+        assert_eq!(value.to_string(), "[3, \"a\", 1]");
+
+        // Modules are only created once, importing them again returns the same object.
+        let value = engine
+            .eval("func f() { import \"util\"; return util; } f() == util")
+            .unwrap();
+        assert_eq!(value, HostValue::Bool(true));
+    }
+
+    #[test]
+    fn unknown_module() {
+        let mut engine = Engine::new();
+        let Err(Error::Runtime(error)) = engine.eval("import \"missing\";") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::Import {
+                module: "missing".to_owned(),
+                reason: "no such module".to_owned(),
+            }
+        );
+
+        assert!(engine.eval("import \"not valid\";").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! Adapting Rust functions with typed parameters into native functions, so that hosts can expose
+//! their own functions to scripts without checking and converting arguments themselves:
+//!
+//! ```
+//! use unnamed_language::Engine;
+//!
+//! let mut engine = Engine::new();
+//! // SAFETY: the function only converts values to and from numbers, it doesn't keep any.
+//! unsafe { engine.register_fn("hypot", |x: f64, y: f64| x.hypot(y)) };
+//! assert_eq!(engine.eval("hypot(3, 4)").unwrap().to_string(), "5");
+//! ```
+//!
+//! Arguments are converted with [`FromValue`] and return values with [`IntoValue`], so functions
+//! can take and return strings, lists and structs as well as numbers. A function may also return
+//! a `Result` to report its own errors.
+
+use std::{any::Any, sync::Arc};
+
+use super::{
+    class,
+    object::{Arity, NativeClosure},
+    value::{FromValue, IntoValue, Value},
+    Interpreter, RuntimeError,
+};
+
+/// Rust function which can be adapted into a native function, `Args` is the tuple of its
+/// parameter types. This is implemented for functions of up to six parameters.
+pub trait IntoNative<Args> {
+    /// Number of arguments the function accepts.
+    const ARITY: Arity;
+
+    fn into_native(self) -> Arc<NativeClosure>;
+}
+
+/// Rust function which can be adapted into a method of the class of `T`, it takes the object's
+/// value mutably followed by the arguments. See [`class`].
+pub trait IntoMethod<T, Args> {
+    /// Number of arguments the method accepts, not counting the object.
+    const ARITY: Arity;
+
+    fn into_method(self) -> Arc<NativeClosure>;
+}
+
+/// Rust function which can be adapted into the constructor of the class of `T`.
+pub trait IntoConstructor<T, Args> {
+    const ARITY: Arity;
+
+    fn into_constructor(self) -> Arc<NativeClosure>;
+}
+
+/// Value returned by a function adapted by [`IntoNative`] or [`IntoMethod`].
+pub trait NativeReturn {
+    fn into_result(self, interpreter: &mut Interpreter) -> Result<Value, RuntimeError>;
+}
+
+impl<T: IntoValue> NativeReturn for T {
+    fn into_result(self, interpreter: &mut Interpreter) -> Result<Value, RuntimeError> {
+        Ok(self.into_value(interpreter.heap_mut()))
+    }
+}
+
+impl<T: IntoValue> NativeReturn for Result<T, RuntimeError> {
+    fn into_result(self, interpreter: &mut Interpreter) -> Result<Value, RuntimeError> {
+        Ok(self?.into_value(interpreter.heap_mut()))
+    }
+}
+
+macro_rules! impl_into_native {
+    ($count:literal $(, $param:ident $arg:ident)*) => {
+        impl<F, R, $($param),*> IntoNative<($($param,)*)> for F
+        where
+            F: Fn($($param),*) -> R + Send + Sync + 'static,
+            R: NativeReturn,
+            $($param: FromValue,)*
+        {
+            const ARITY: Arity = Arity::Exact($count);
+
+            fn into_native(self) -> Arc<NativeClosure> {
+                Arc::new(move |interpreter, args| {
+                    let [$($arg),*] = args else {
+                        unreachable!("arity should have been checked");
+                    };
+                    self($($param::from_value($arg)?),*).into_result(interpreter)
+                })
+            }
+        }
+
+        impl<T: Any + Send, F, R, $($param),*> IntoMethod<T, ($($param,)*)> for F
+        where
+            F: Fn(&mut T, $($param),*) -> R + Send + Sync + 'static,
+            R: NativeReturn,
+            $($param: FromValue,)*
+        {
+            const ARITY: Arity = Arity::Exact($count);
+
+            fn into_method(self) -> Arc<NativeClosure> {
+                Arc::new(move |interpreter, args| {
+                    let [receiver, $($arg),*] = args else {
+                        unreachable!("arity should have been checked");
+                    };
+                    // Arguments are converted before the object is borrowed, the method can't
+                    // run scripts so the object isn't borrowed again until it returns.
+                    $(let $arg = $param::from_value($arg)?;)*
+                    let result = self(&mut *class::receiver(receiver), $($arg),*);
+                    result.into_result(interpreter)
+                })
+            }
+        }
+
+        impl<T: Any + Send, F, $($param),*> IntoConstructor<T, ($($param,)*)> for F
+        where
+            F: Fn($($param),*) -> T + Send + Sync + 'static,
+            $($param: FromValue,)*
+        {
+            const ARITY: Arity = Arity::Exact($count);
+
+            fn into_constructor(self) -> Arc<NativeClosure> {
+                Arc::new(move |interpreter, args| {
+                    let [$($arg),*] = args else {
+                        unreachable!("arity should have been checked");
+                    };
+                    let data = self($($param::from_value($arg)?),*);
+                    Ok(interpreter.alloc_object(data))
+                })
+            }
+        }
+    };
+}
+
+impl_into_native!(0);
+impl_into_native!(1, A a);
+impl_into_native!(2, A a, B b);
+impl_into_native!(3, A a, B b, C c);
+impl_into_native!(4, A a, B b, C c, D d);
+impl_into_native!(5, A a, B b, C c, D d, E e);
+impl_into_native!(6, A a, B b, C c, D d, E e, G g);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        interpreter::{object::Arity, value::Value, RuntimeErrorKind},
+        Engine, Error, HostValue,
+    };
+
+    #[test]
+    fn typed() {
+        let mut engine = Engine::new();
+        // SAFETY: none of the functions keep the values they are given.
+        unsafe {
+            engine.register_fn("answer", || 42i64);
+            engine.register_fn("repeat", |s: String, n: i64| s.repeat(n as usize));
+            engine.register_fn("is_nil", |value: Value| value == Value::Nil);
+            engine.register_fn("checked_sqrt", |x: f64| {
+                if x < 0.0 {
+                    Err(RuntimeErrorKind::InvalidArgument("negative".to_owned()).into())
+                } else {
+                    Ok(x.sqrt())
+                }
+            });
+        }
+
+        assert_eq!(engine.eval("answer()").unwrap(), HostValue::Number(42.0));
+        assert_eq!(
+            engine.eval("repeat(\"ab\", 2)").unwrap().to_string(),
+            "abab"
+        );
+        assert_eq!(engine.eval("is_nil(nil)").unwrap(), HostValue::Bool(true));
+        assert_eq!(
+            engine.eval("checked_sqrt(9)").unwrap(),
+            HostValue::Number(3.0)
+        );
+
+        let Err(Error::Runtime(error)) = engine.eval("checked_sqrt(-1)") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::InvalidArgument("negative".to_owned())
+        );
+        let Err(Error::Runtime(error)) = engine.eval("repeat(1, 2)") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::TypeMismatch {
+                expected: "string",
+                found: "number"
+            }
+        );
+        let Err(Error::Runtime(error)) = engine.eval("answer(1)") else {
+            panic!("error should have been returned");
+        };
+        assert_eq!(
+            error.kind(),
+            &RuntimeErrorKind::ArityMismatch {
+                expected: Arity::Exact(0),
+                found: 1
+            }
+        );
+    }
+
+    #[test]
+    fn captures() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut engine = Engine::new();
+        let counter = count.clone();
+        // SAFETY: the function only counts its arguments.
+        unsafe {
+            engine.register_native("count", Arity::AtLeast(0), move |_, args| {
+                counter.fetch_add(args.len(), Ordering::Relaxed);
+                Ok(Value::Nil)
+            })
+        };
+
+        engine.eval("count(1, 2); count(); count(3);").unwrap();
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+    }
+}
@@ -1,16 +1,32 @@
 use std::{
-    collections::HashMap,
+    any::Any,
+    cell::{Ref, RefCell, RefMut},
+    fmt::{Debug, Display},
     ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::Arc,
 };
 
-use super::value::Value;
+use super::{
+    class::Class,
+    iterator::IterState,
+    table::Table,
+    value::{Value, ValueKey},
+    Interpreter, RuntimeError,
+};
+use crate::compiler::parser::ast::Function;
 
 /// Object pointer.
-pub struct Obj(*mut ObjCommon);
+///
+/// Objects are owned by the [`Heap`](super::heap::Heap) that allocated them, an `Obj` is only a
+/// handle and does not free the object when dropped.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Obj(NonNull<ObjCommon>);
 
 impl<T: SubObject> From<Box<T>> for Obj {
     fn from(value: Box<T>) -> Self {
-        Obj(Box::into_raw(value) as *mut ObjCommon)
+        // Safety: `Box::into_raw` never returns a null pointer.
+        Obj(unsafe { NonNull::new_unchecked(Box::into_raw(value) as *mut ObjCommon) })
     }
 }
 
@@ -18,23 +34,23 @@ impl Deref for Obj {
     type Target = ObjCommon;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+        unsafe { self.0.as_ref() }
     }
 }
 
 impl DerefMut for Obj {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.0 }
+        unsafe { self.0.as_mut() }
     }
 }
 
 impl<T: SubObject> AsRef<T> for Obj {
     fn as_ref(&self) -> &T {
         unsafe {
-            assert_eq!((*self.0).kind, T::KIND);
+            assert_eq!(self.0.as_ref().kind, T::KIND);
 
-            let ptr = self.0 as *mut T;
-            &mut *ptr
+            let ptr = self.0.as_ptr() as *mut T;
+            &*ptr
         }
     }
 }
@@ -42,41 +58,174 @@ impl<T: SubObject> AsRef<T> for Obj {
 impl<T: SubObject> AsMut<T> for Obj {
     fn as_mut(&mut self) -> &mut T {
         unsafe {
-            assert_eq!((*self.0).kind, T::KIND);
+            assert_eq!(self.0.as_ref().kind, T::KIND);
 
-            let ptr = self.0 as *mut T;
+            let ptr = self.0.as_ptr() as *mut T;
             &mut *ptr
         }
     }
 }
 
-impl Drop for Obj {
-    fn drop(&mut self) {
-        unsafe {
-            match (*self.0).kind {
-                ObjKind::String => {
-                    let _ = Box::from_raw(self.0 as *mut ObjString);
-                }
-                ObjKind::Function => {
-                    let _ = Box::from_raw(self.0 as *mut ObjFunction);
-                }
-                ObjKind::Instance => {
-                    let _ = Box::from_raw(self.0 as *mut ObjInstance);
-                }
+impl Debug for Obj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind() {
+            ObjKind::String => write!(f, "{:?}", self.as_string().unwrap().as_str()),
+            kind => write!(f, "<{kind:?} at {:p}>", self.0),
+        }
+    }
+}
+
+impl Obj {
+    pub fn kind(&self) -> ObjKind {
+        self.kind
+    }
+
+    /// Returns a reference to the object if it is of type `T`.
+    pub fn downcast<T: SubObject>(&self) -> Option<&T> {
+        (self.kind == T::KIND).then(|| self.as_ref())
+    }
+
+    pub fn as_string(&self) -> Option<&ObjString> {
+        self.downcast()
+    }
+
+    /// Pushes all objects directly referenced by this object onto `gray`.
+    pub(super) fn trace(&self, gray: &mut Vec<Obj>) {
+        match self.kind {
+            ObjKind::String => AsRef::<ObjString>::as_ref(self).trace(gray),
+            ObjKind::Function => AsRef::<ObjFunction>::as_ref(self).trace(gray),
+            ObjKind::Instance => AsRef::<ObjInstance>::as_ref(self).trace(gray),
+            ObjKind::Foreign => AsRef::<ObjForeign>::as_ref(self).trace(gray),
+            ObjKind::Native => AsRef::<ObjNative>::as_ref(self).trace(gray),
+            ObjKind::Weak => AsRef::<ObjWeak>::as_ref(self).trace(gray),
+            ObjKind::List => AsRef::<ObjList>::as_ref(self).trace(gray),
+            ObjKind::Map => AsRef::<ObjMap>::as_ref(self).trace(gray),
+            ObjKind::Cell => AsRef::<ObjCell>::as_ref(self).trace(gray),
+            ObjKind::Bound => AsRef::<ObjBound>::as_ref(self).trace(gray),
+            ObjKind::Iterator => AsRef::<ObjIterator>::as_ref(self).trace(gray),
+            ObjKind::Error => AsRef::<ObjError>::as_ref(self).trace(gray),
+            ObjKind::Bytes => AsRef::<ObjBytes>::as_ref(self).trace(gray),
+        }
+    }
+
+    /// Returns the number of bytes owned by this object.
+    pub(super) fn size(&self) -> usize {
+        match self.kind {
+            ObjKind::String => AsRef::<ObjString>::as_ref(self).size(),
+            ObjKind::Function => AsRef::<ObjFunction>::as_ref(self).size(),
+            ObjKind::Instance => AsRef::<ObjInstance>::as_ref(self).size(),
+            ObjKind::Foreign => AsRef::<ObjForeign>::as_ref(self).size(),
+            ObjKind::Native => AsRef::<ObjNative>::as_ref(self).size(),
+            ObjKind::Weak => AsRef::<ObjWeak>::as_ref(self).size(),
+            ObjKind::List => AsRef::<ObjList>::as_ref(self).size(),
+            ObjKind::Map => AsRef::<ObjMap>::as_ref(self).size(),
+            ObjKind::Cell => AsRef::<ObjCell>::as_ref(self).size(),
+            ObjKind::Bound => AsRef::<ObjBound>::as_ref(self).size(),
+            ObjKind::Iterator => AsRef::<ObjIterator>::as_ref(self).size(),
+            ObjKind::Error => AsRef::<ObjError>::as_ref(self).size(),
+            ObjKind::Bytes => AsRef::<ObjBytes>::as_ref(self).size(),
+        }
+    }
+
+    /// Frees the object.
+    ///
+    /// # Safety
+    ///
+    /// The object must not be used after calling this, this includes all other handles pointing
+    /// to it.
+    pub(super) unsafe fn free(self) {
+        match self.kind {
+            ObjKind::String => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjString);
+            }
+            ObjKind::Function => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjFunction);
+            }
+            ObjKind::Instance => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjInstance);
+            }
+            ObjKind::Foreign => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjForeign);
+            }
+            ObjKind::Native => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjNative);
+            }
+            ObjKind::Weak => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjWeak);
+            }
+            ObjKind::List => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjList);
+            }
+            ObjKind::Map => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjMap);
+            }
+            ObjKind::Cell => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjCell);
+            }
+            ObjKind::Bound => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjBound);
+            }
+            ObjKind::Iterator => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjIterator);
+            }
+            ObjKind::Error => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjError);
+            }
+            ObjKind::Bytes => {
+                let _ = Box::from_raw(self.0.as_ptr() as *mut ObjBytes);
             }
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum ObjKind {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ObjKind {
     String,
     Function,
     Instance,
+    Foreign,
+    Native,
+    Weak,
+    List,
+    Map,
+    Cell,
+    Bound,
+    Iterator,
+    Error,
+    Bytes,
 }
 
-trait SubObject {
+impl ObjKind {
+    /// Returns the name of the type as seen by scripts.
+    pub fn type_name(self) -> &'static str {
+        match self {
+            ObjKind::String => "string",
+            ObjKind::Function => "function",
+            ObjKind::Instance => "instance",
+            ObjKind::Foreign => "foreign",
+            ObjKind::Native => "function",
+            ObjKind::Weak => "weakref",
+            ObjKind::List => "list",
+            ObjKind::Map => "map",
+            ObjKind::Cell => "cell",
+            ObjKind::Bound => "function",
+            ObjKind::Iterator => "iterator",
+            ObjKind::Error => "error",
+            ObjKind::Bytes => "bytes",
+        }
+    }
+}
+
+pub trait SubObject: Sized {
     const KIND: ObjKind;
+
+    /// Pushes all objects directly referenced by this object onto `gray`.
+    fn trace(&self, _gray: &mut Vec<Obj>) {}
+
+    /// Returns the number of bytes owned by this object, including the object itself.
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+    }
 }
 
 #[repr(C)]
@@ -102,54 +251,591 @@ pub struct ObjString {
 
 impl SubObject for ObjString {
     const KIND: ObjKind = ObjKind::String;
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.data.capacity()
+    }
 }
 
 impl ObjString {
-    pub fn obj(data: String) -> Obj {
-        Box::new(Self {
+    pub fn new(data: String) -> Self {
+        Self {
             obj: ObjCommon::new(Self::KIND),
             data,
-        })
-        .into()
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.data
     }
 }
 
 #[repr(C)]
 pub struct ObjFunction {
     pub obj: ObjCommon,
-    arity: u8,
+    decl: Arc<Function>,
+    /// Cells holding the variables of enclosing functions used by the function, in the order
+    /// given by `decl.captures`.
+    captured: Vec<Value>,
 }
 
 impl SubObject for ObjFunction {
     const KIND: ObjKind = ObjKind::Function;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        gray.extend(self.captured.iter().filter_map(Value::as_obj).cloned());
+    }
 }
 
 impl ObjFunction {
-    pub fn obj(arity: u8) -> Obj {
-        Box::new(Self {
+    pub fn new(decl: Arc<Function>, captured: Vec<Value>) -> Self {
+        Self {
             obj: ObjCommon::new(Self::KIND),
+            decl,
+            captured,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.decl.name.as_ref()
+    }
+
+    pub fn arity(&self) -> Arity {
+        Arity::Exact(self.decl.params.len() as u8)
+    }
+
+    pub fn decl(&self) -> &Arc<Function> {
+        &self.decl
+    }
+
+    pub fn captured(&self) -> &[Value] {
+        &self.captured
+    }
+
+    pub fn captured_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.captured
+    }
+}
+
+/// Signature of functions implemented in Rust.
+pub type NativeFn = fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError>;
+
+/// Signature of functions implemented in Rust which may capture state, such as the closures
+/// hosts register with [`Interpreter::define_closure`]. They must be `Send` and `Sync` so that
+/// [engines](crate::Engine) can be moved between threads.
+pub type NativeClosure =
+    dyn Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError> + Send + Sync;
+
+/// Number of arguments accepted by a function.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Arity {
+    Exact(u8),
+    AtLeast(u8),
+    /// Inclusive range.
+    Range(u8, u8),
+}
+
+impl Arity {
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => count == n as usize,
+            Arity::AtLeast(min) => count >= min as usize,
+            Arity::Range(min, max) => (min as usize..=max as usize).contains(&count),
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{n}"),
+            Arity::AtLeast(min) => write!(f, "at least {min}"),
+            Arity::Range(min, max) => write!(f, "{min} to {max}"),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct ObjNative {
+    pub obj: ObjCommon,
+    name: &'static str,
+    arity: Arity,
+    function: Arc<NativeClosure>,
+}
+
+impl SubObject for ObjNative {
+    const KIND: ObjKind = ObjKind::Native;
+}
+
+impl ObjNative {
+    pub fn new(name: &'static str, arity: Arity, function: NativeFn) -> Self {
+        Self::with_closure(name, arity, Arc::new(function))
+    }
+
+    pub fn with_closure(name: &'static str, arity: Arity, function: Arc<NativeClosure>) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            name,
             arity,
-        })
-        .into()
+            function,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    /// Returns a handle to the function which can be called while the interpreter is borrowed
+    /// mutably.
+    pub fn function(&self) -> Arc<NativeClosure> {
+        self.function.clone()
     }
 }
 
 #[repr(C)]
 pub struct ObjInstance {
     pub obj: ObjCommon,
-    fields: HashMap<String, Value>,
+    fields: Table<String, Value>,
 }
 
 impl SubObject for ObjInstance {
     const KIND: ObjKind = ObjKind::Instance;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        gray.extend(self.fields.values().filter_map(Value::as_obj).cloned());
+    }
 }
 
 impl ObjInstance {
-    pub fn obj() -> Obj {
-        Box::new(Self {
+    pub fn new() -> Self {
+        Self {
             obj: ObjCommon::new(Self::KIND),
-            fields: HashMap::new(),
-        })
-        .into()
+            fields: Table::new(),
+        }
+    }
+
+    pub fn fields(&self) -> &Table<String, Value> {
+        &self.fields
+    }
+
+    pub fn fields_mut(&mut self) -> &mut Table<String, Value> {
+        &mut self.fields
+    }
+}
+
+impl Default for ObjInstance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[repr(C)]
+pub struct ObjList {
+    pub obj: ObjCommon,
+    items: Vec<Value>,
+}
+
+impl SubObject for ObjList {
+    const KIND: ObjKind = ObjKind::List;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        gray.extend(self.items.iter().filter_map(Value::as_obj).cloned());
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.items.capacity() * std::mem::size_of::<Value>()
+    }
+}
+
+impl ObjList {
+    pub fn new(items: Vec<Value>) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            items,
+        }
+    }
+
+    pub fn items(&self) -> &Vec<Value> {
+        &self.items
+    }
+
+    pub fn items_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.items
+    }
+}
+
+/// Mutable buffer of bytes.
+#[repr(C)]
+pub struct ObjBytes {
+    pub obj: ObjCommon,
+    bytes: Vec<u8>,
+}
+
+impl SubObject for ObjBytes {
+    const KIND: ObjKind = ObjKind::Bytes;
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.bytes.capacity()
+    }
+}
+
+impl ObjBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            bytes,
+        }
+    }
+
+    pub fn bytes(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.bytes
+    }
+}
+
+/// Map from values to values which remembers the order its keys were inserted in.
+#[repr(C)]
+pub struct ObjMap {
+    pub obj: ObjCommon,
+    entries: Table<ValueKey, Value>,
+}
+
+impl SubObject for ObjMap {
+    const KIND: ObjKind = ObjKind::Map;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        for (key, value) in self.entries.iter() {
+            gray.extend(key.value().as_obj().cloned());
+            gray.extend(value.as_obj().cloned());
+        }
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.entries.len() * std::mem::size_of::<(ValueKey, Value)>()
+    }
+}
+
+impl ObjMap {
+    pub fn new(entries: Table<ValueKey, Value>) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            entries,
+        }
+    }
+
+    pub fn entries(&self) -> &Table<ValueKey, Value> {
+        &self.entries
+    }
+
+    pub fn entries_mut(&mut self) -> &mut Table<ValueKey, Value> {
+        &mut self.entries
+    }
+}
+
+/// Box holding a local variable which has been captured by a function, shared by every function
+/// which captured it. Cells are never visible to scripts.
+#[repr(C)]
+pub struct ObjCell {
+    pub obj: ObjCommon,
+    value: Value,
+}
+
+impl SubObject for ObjCell {
+    const KIND: ObjKind = ObjKind::Cell;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        gray.extend(self.value.as_obj().cloned());
+    }
+}
+
+impl ObjCell {
+    pub fn new(value: Value) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            value,
+        }
+    }
+
+    pub fn get(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn set(&mut self, value: Value) {
+        self.value = value;
+    }
+}
+
+/// Recoverable failure returned by `err`, carrying a message which is usually a string.
+#[repr(C)]
+pub struct ObjError {
+    pub obj: ObjCommon,
+    message: Value,
+}
+
+impl SubObject for ObjError {
+    const KIND: ObjKind = ObjKind::Error;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        gray.extend(self.message.as_obj().cloned());
+    }
+}
+
+impl ObjError {
+    pub fn new(message: Value) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            message,
+        }
+    }
+
+    pub fn message(&self) -> &Value {
+        &self.message
+    }
+}
+
+/// Lazy iterator, see the `iterator` module.
+#[repr(C)]
+pub struct ObjIterator {
+    pub obj: ObjCommon,
+    state: IterState,
+    /// Number of iterators advanced by advancing this one, including itself.
+    depth: usize,
+}
+
+impl SubObject for ObjIterator {
+    const KIND: ObjKind = ObjKind::Iterator;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        self.state.trace(gray);
+    }
+}
+
+impl ObjIterator {
+    pub fn new(state: IterState) -> Self {
+        let depth = state
+            .inners()
+            .iter()
+            .filter_map(|inner| inner.downcast::<ObjIterator>())
+            .map(|inner| inner.depth)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            state,
+            depth,
+        }
+    }
+
+    /// Returns how deeply iterators are nested in this one, which is how deep advancing it
+    /// recurses.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn state(&self) -> &IterState {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut IterState {
+        &mut self.state
+    }
+}
+
+/// Native function which is passed values captured when it was created along with its
+/// arguments, used by builtins which return new functions such as `partial`.
+pub type BoundFn = fn(&mut Interpreter, &[Value], &[Value]) -> Result<Value, RuntimeError>;
+
+/// Native function together with the values it captured.
+#[repr(C)]
+pub struct ObjBound {
+    pub obj: ObjCommon,
+    name: &'static str,
+    arity: Arity,
+    function: BoundFn,
+    captured: Vec<Value>,
+}
+
+impl SubObject for ObjBound {
+    const KIND: ObjKind = ObjKind::Bound;
+
+    fn trace(&self, gray: &mut Vec<Obj>) {
+        gray.extend(self.captured.iter().filter_map(Value::as_obj).cloned());
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.captured.capacity() * std::mem::size_of::<Value>()
+    }
+}
+
+impl ObjBound {
+    pub fn new(name: &'static str, arity: Arity, function: BoundFn, captured: Vec<Value>) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            name,
+            arity,
+            function,
+            captured,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    pub fn function(&self) -> BoundFn {
+        self.function
+    }
+
+    pub fn captured(&self) -> &[Value] {
+        &self.captured
+    }
+}
+
+/// Wraps an arbitrary `Send` Rust value so that it can be passed through scripts by the host.
+///
+/// Scripts can't inspect the wrapped value, they can only hold on to it and pass it back to the
+/// host (or to native functions) which can then borrow it as its original type.
+#[repr(C)]
+pub struct ObjForeign {
+    pub obj: ObjCommon,
+    type_name: &'static str,
+    data: RefCell<Box<dyn Any + Send>>,
+    finalizer: Option<Finalizer>,
+    /// Class of the wrapped type if it has been exposed to scripts as one.
+    class: Option<Arc<Class>>,
+}
+
+type Finalizer = Box<dyn FnOnce(Box<dyn Any + Send>) + Send>;
+
+impl SubObject for ObjForeign {
+    const KIND: ObjKind = ObjKind::Foreign;
+}
+
+impl ObjForeign {
+    pub fn new<T: Any + Send>(data: T) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            type_name: std::any::type_name::<T>(),
+            data: RefCell::new(Box::new(data)),
+            finalizer: None,
+            class: None,
+        }
+    }
+
+    /// Creates an object of `class`, which must be the class of `T`.
+    pub fn with_class<T: Any + Send>(data: T, class: Arc<Class>) -> Self {
+        let mut foreign = Self::new(data);
+        foreign.class = Some(class);
+        foreign
+    }
+
+    /// Creates a foreign object which passes its value to `finalizer` when it is freed.
+    ///
+    /// Finalizers are run while the collector is sweeping, in the order the objects were
+    /// allocated, they must not panic. Since they only receive the wrapped value there is no way
+    /// for them to resurrect the object.
+    pub fn with_finalizer<T: Any + Send>(
+        data: T,
+        finalizer: impl FnOnce(T) + Send + 'static,
+    ) -> Self {
+        let mut foreign = Self::new(data);
+        foreign.finalizer = Some(Box::new(move |data: Box<dyn Any + Send>| {
+            finalizer(
+                *data
+                    .downcast()
+                    .expect("type of foreign data should not change"),
+            )
+        }));
+
+        foreign
+    }
+
+    /// Returns the name of the wrapped Rust type, this is only meant for diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn class(&self) -> Option<&Arc<Class>> {
+        self.class.as_ref()
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.data.borrow().is::<T>()
+    }
+
+    /// Borrows the wrapped value if it is of type `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed.
+    pub fn borrow<T: Any>(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.data.borrow(), |data| data.downcast_ref()).ok()
+    }
+
+    /// Mutably borrows the wrapped value if it is of type `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently borrowed.
+    pub fn borrow_mut<T: Any>(&self) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.data.borrow_mut(), |data| data.downcast_mut()).ok()
+    }
+}
+
+impl Drop for ObjForeign {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer.take() {
+            let data = std::mem::replace(self.data.get_mut(), Box::new(()));
+            finalizer(data);
+        }
+    }
+}
+
+/// Reference to an object which does not keep it alive, once the object is collected the
+/// reference is cleared.
+#[repr(C)]
+pub struct ObjWeak {
+    pub obj: ObjCommon,
+    target: Option<Obj>,
+}
+
+impl SubObject for ObjWeak {
+    const KIND: ObjKind = ObjKind::Weak;
+
+    // The target is deliberately not traced.
+}
+
+impl ObjWeak {
+    pub fn new(target: Obj) -> Self {
+        Self {
+            obj: ObjCommon::new(Self::KIND),
+            target: Some(target),
+        }
+    }
+
+    /// Returns the target if it hasn't been collected yet.
+    pub fn target(&self) -> Option<&Obj> {
+        self.target.as_ref()
+    }
+
+    /// Clears the reference if its target is about to be freed, called by the collector after
+    /// marking.
+    pub(super) fn clear_if_unmarked(&mut self) {
+        if self.target.as_ref().is_some_and(|target| !target.marked) {
+            self.target = None;
+        }
     }
 }
@@ -0,0 +1,169 @@
+//! Native modules compiled separately from the interpreter, enabled by the `plugins` feature. A
+//! plugin is a `cdylib` crate which depends on this one and exports a [`Module`] with
+//! [`export_plugin!`](crate::export_plugin):
+//!
+//! ```ignore
+//! use unnamed_language::{export_plugin, interpreter::module::Module};
+//!
+//! fn register(module: &mut Module) {
+//!     module.function("shout", |s: String| s.to_uppercase());
+//! }
+//!
+//! export_plugin!(register);
+//! ```
+//!
+//! When a script runs `import "text";` and no module called `text` has been registered, the
+//! interpreter looks for `libtext.so` (`text.dll` on Windows, `libtext.dylib` on macOS) in its
+//! [plugin paths](super::Interpreter::add_plugin_path). Loading a plugin runs native code with
+//! the same access as the host, so scripts can only do so if they have the `plugins`
+//! [capability](super::capabilities::Capabilities).
+//!
+//! Functions are passed between the plugin and the interpreter as Rust types, which have no
+//! stable layout. Plugins have to be built with the same compiler, version of this crate and
+//! features as the interpreter loading them, the version and features are checked before the
+//! plugin is registered but the compiler can't be.
+
+use std::{
+    env::consts::{DLL_PREFIX, DLL_SUFFIX},
+    path::Path,
+};
+
+use libloading::Library;
+
+use super::module::Module;
+
+/// Version of [`PluginDeclaration`], changed whenever its layout changes.
+pub const ABI_VERSION: u32 = 1;
+/// Version of the crate the interpreter or plugin was built with.
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Features the interpreter or plugin was built with, each one of them changes which functions
+/// and types are compiled.
+pub const FEATURES: u32 = cfg!(feature = "derive") as u32
+    | (cfg!(feature = "ffi") as u32) << 1
+    | (cfg!(feature = "net") as u32) << 2
+    | (cfg!(feature = "serde") as u32) << 3;
+
+/// Name of the static every plugin exports.
+const SYMBOL: &[u8] = b"UNNAMED_LANGUAGE_PLUGIN\0";
+
+/// Describes a plugin, this is exported by [`export_plugin!`](crate::export_plugin) rather than
+/// written by hand.
+#[repr(C)]
+pub struct PluginDeclaration {
+    /// Kept first so that it can be read from plugins with a different layout.
+    pub abi_version: u32,
+    pub crate_version: &'static str,
+    pub features: u32,
+    /// Adds the functions and classes of the plugin to its module.
+    pub register: fn(&mut Module),
+}
+
+/// Exports the function which registers the module of a plugin, see [`plugin`].
+///
+/// [`plugin`]: crate::interpreter::plugin
+#[macro_export]
+macro_rules! export_plugin {
+    ($register:expr) => {
+        #[no_mangle]
+        pub static UNNAMED_LANGUAGE_PLUGIN: $crate::interpreter::plugin::PluginDeclaration =
+            $crate::interpreter::plugin::PluginDeclaration {
+                abi_version: $crate::interpreter::plugin::ABI_VERSION,
+                crate_version: $crate::interpreter::plugin::CRATE_VERSION,
+                features: $crate::interpreter::plugin::FEATURES,
+                register: $register,
+            };
+    };
+}
+
+/// Loads the plugin called `name` from the first of `paths` which contains it, returning the
+/// library along with its module. The library has to outlive every function of the module.
+pub(super) fn load(name: &str, paths: &[impl AsRef<Path>]) -> Result<(Library, Module), String> {
+    let file = format!("{DLL_PREFIX}{name}{DLL_SUFFIX}");
+    let path = paths
+        .iter()
+        .map(|dir| dir.as_ref().join(&file))
+        .find(|path| path.is_file())
+        .ok_or("no such module")?;
+
+    // SAFETY: loading a library runs its initializers, plugins are trusted as much as the host
+    // is, which is why scripts need the `plugins` capability to load them.
+    let library = unsafe { Library::new(&path) }.map_err(|error| error.to_string())?;
+    // SAFETY: the symbol is only ever exported by `export_plugin!`, which declares it with this
+    // type.
+    let declaration = unsafe {
+        let symbol = library
+            .get::<*const PluginDeclaration>(SYMBOL)
+            .map_err(|_| format!("{} is not a plugin", path.display()))?;
+        &**symbol
+    };
+    if declaration.abi_version != ABI_VERSION {
+        return Err(format!(
+            "plugin ABI version {} is not supported, expected {ABI_VERSION}",
+            declaration.abi_version
+        ));
+    }
+    if declaration.crate_version != CRATE_VERSION || declaration.features != FEATURES {
+        return Err(format!(
+            "plugin was built for version {} of the interpreter with different features, expected \
+             version {CRATE_VERSION}",
+            declaration.crate_version
+        ));
+    }
+
+    let mut module = Module::new();
+    (declaration.register)(&mut module);
+
+    Ok((library, module))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{
+        interpreter::{capabilities::Capabilities, RuntimeErrorKind},
+        Engine, Error,
+    };
+
+    fn import_error(engine: &mut Engine, module: &str) -> RuntimeErrorKind {
+        let Err(Error::Runtime(error)) = engine.eval(&format!("import \"{module}\";")) else {
+            panic!("error should have been returned");
+        };
+        error.kind().clone()
+    }
+
+    #[test]
+    fn load() {
+        let dir = env::temp_dir().join(format!("unnamed-language-plugins-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = format!(
+            "{}garbage{}",
+            env::consts::DLL_PREFIX,
+            env::consts::DLL_SUFFIX
+        );
+        fs::write(dir.join(file), "not a library").unwrap();
+
+        let mut engine = Engine::new().with_plugin_path(&dir);
+        assert!(matches!(
+            import_error(&mut engine, "garbage"),
+            RuntimeErrorKind::Import { module, .. } if module == "garbage"
+        ));
+        assert_eq!(
+            import_error(&mut engine, "missing"),
+            RuntimeErrorKind::Import {
+                module: "missing".to_owned(),
+                reason: "no such module".to_owned(),
+            }
+        );
+
+        let mut engine = Engine::new()
+            .with_plugin_path(&dir)
+            .with_capabilities(Capabilities::none());
+        assert_eq!(
+            import_error(&mut engine, "garbage"),
+            RuntimeErrorKind::PermissionDenied("plugin")
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// Small non-cryptographic pseudo random number generator (SplitMix64), used by the random
+/// builtins so that runs can be reproduced from a seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator with a seed that differs between runs.
+    pub fn from_entropy() -> Self {
+        // `RandomState` is seeded randomly by the standard library.
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.state = seed;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        // Use the top 53 bits since that is the precision of an `f64`.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a number in the range `[0, bound)`, `bound` must not be zero.
+    pub fn next_below(&mut self, bound: u64) -> u64 {
+        assert!(bound != 0, "bound must not be zero");
+        // Reject values from the incomplete range at the top to avoid bias.
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let n = self.next_u64();
+            if n < zone {
+                return n % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn seeded() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        for _ in 0..100 {
+            let n = a.next_f64();
+            assert!((0.0..1.0).contains(&n));
+            assert!(a.next_below(6) < 6);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+/// Hash map which iterates over its entries in insertion order, this is used for every map that
+/// scripts can iterate over so that iteration order is the same between runs.
+#[derive(Debug, Clone)]
+pub struct Table<K, V> {
+    entries: Vec<(K, V)>,
+    indices: HashMap<K, usize>,
+}
+
+impl<K: Hash + Eq + Clone, V> Table<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = *self.indices.get(key)?;
+        Some(&self.entries[index].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = *self.indices.get(key)?;
+        Some(&mut self.entries[index].1)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.indices.contains_key(key)
+    }
+
+    /// Inserts a value returning the old one, replacing a value keeps its original position.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.indices.get(&key) {
+            Some(&index) => Some(std::mem::replace(&mut self.entries[index].1, value)),
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes an entry preserving the order of the remaining ones, this is linear in the number
+    /// of entries.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+        for (moved, _) in &self.entries[index..] {
+            *self.indices.get_mut::<K>(moved).unwrap() -= 1;
+        }
+
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Default for Table<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> FromIterator<(K, V)> for Table<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut table = Self::new();
+        for (key, value) in iter {
+            table.insert(key, value);
+        }
+
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Table;
+
+    #[test]
+    fn insertion_order() {
+        let mut table = Table::new();
+        for key in ["c", "a", "d", "b"] {
+            table.insert(key.to_owned(), key.len());
+        }
+
+        table.insert("a".to_owned(), 10);
+        assert_eq!(table.remove("d"), Some(1));
+        assert_eq!(table.keys().collect::<Vec<_>>(), ["c", "a", "b"]);
+        assert_eq!(table.get("a"), Some(&10));
+        assert_eq!(table.get("b"), Some(&1));
+        assert_eq!(table.len(), 3);
+    }
+}
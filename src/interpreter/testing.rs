@@ -0,0 +1,30 @@
+//! Helpers shared by the tests of builtins and methods, whose scripts store the value being
+//! checked in a global called `x`.
+
+use super::{Interpreter, RuntimeErrorKind};
+use crate::compiler::parser::Parser;
+
+/// Runs `source` with `interpreter` and returns the value of `x` printed, `nil` if the script
+/// doesn't define it.
+pub(crate) fn run(interpreter: &mut Interpreter, source: &str) -> Result<String, RuntimeErrorKind> {
+    let script = Parser::new(source).parse().unwrap();
+    interpreter
+        .interpret(&script)
+        .map_err(|error| error.kind().clone())?;
+
+    Ok(interpreter
+        .get_global("x")
+        .cloned()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Runs `source` with a new interpreter, see [`run`].
+pub(crate) fn eval(source: &str) -> Result<String, RuntimeErrorKind> {
+    run(&mut Interpreter::default(), source)
+}
+
+/// Evaluates an expression with a new interpreter and returns its value printed.
+pub(crate) fn eval_expr(expr: &str) -> Result<String, RuntimeErrorKind> {
+    eval(&format!("let x = {expr};"))
+}
@@ -0,0 +1,73 @@
+//! Resources used by scripts, for hosts which bill, log or adapt limits per script. Usage is
+//! counted from when the interpreter was created, or from the last reset:
+//!
+//! ```
+//! use unnamed_language::Engine;
+//!
+//! let mut engine = Engine::new();
+//! engine.reset_usage();
+//! engine.eval("let list = []; for i in range(10) { list.push(i); }").unwrap();
+//! let usage = engine.usage();
+//! assert!(usage.statements > 10);
+//! assert!(usage.heap.allocations > 0);
+//! ```
+//!
+//! It can also be read while a script is running, by native functions and
+//! [instrumentation](super::instrument).
+
+use std::time::Duration;
+
+use super::heap::HeapStats;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    /// Statements executed, each of which used a unit of fuel.
+    pub statements: u64,
+    /// Allocations and collections made by the heap.
+    pub heap: HeapStats,
+    /// Most bytes allocated at once.
+    pub peak_bytes_allocated: usize,
+    /// Time spent running scripts, always zero for deterministic interpreters which don't read
+    /// the system's clock.
+    pub time: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Engine;
+
+    #[test]
+    fn usage() {
+        let mut engine = Engine::new();
+        engine.reset_usage();
+        assert_eq!(engine.usage().statements, 0);
+        assert_eq!(engine.usage().heap.allocations, 0);
+
+        engine
+            .eval("let s = \"\"; for i in range(100) { s = s + \"ab\"; }")
+            .unwrap();
+        let usage = engine.usage();
+        // The loop, then its body's block and assignment on each iteration. Declarations aren't
+        // statements.
+        assert_eq!(usage.statements, 201);
+        assert!(usage.heap.allocations >= 100);
+        assert!(usage.peak_bytes_allocated >= 200);
+
+        engine.eval("1").unwrap();
+        assert_eq!(engine.usage().statements, 202);
+
+        // Lists which grow in place count towards the peak.
+        engine.reset_usage();
+        engine
+            .eval("let l = []; for i in range(1000) { l.push(i); }")
+            .unwrap();
+        let size = 1000 * std::mem::size_of::<crate::interpreter::value::Value>();
+        assert!(engine.usage().peak_bytes_allocated >= size);
+
+        // The prelude ran before the engine was made deterministic.
+        let mut engine = Engine::new().with_deterministic(0);
+        engine.reset_usage();
+        engine.eval("for i in range(10) {}").unwrap();
+        assert_eq!(engine.usage().time, std::time::Duration::ZERO);
+    }
+}
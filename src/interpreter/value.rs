@@ -1,9 +1,27 @@
-#[derive(Debug, Default, PartialEq, Clone, Copy)]
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use super::{
+    encoding,
+    inspect::{inspect, InspectOptions},
+    object::{Obj, ObjBound, ObjBytes, ObjError, ObjForeign, ObjFunction, ObjKind, ObjNative},
+};
+
+mod convert;
+
+pub use convert::{Fields, FromValue, IntoValue, MapBuilder};
+#[cfg(feature = "derive")]
+pub use unnamed_language_derive::{FromValue, IntoValue};
+
+#[derive(Debug, Default, Clone)]
 pub enum Value {
     Number(f64),
     Bool(bool),
     #[default]
     Nil,
+    Obj(Obj),
 }
 
 impl Value {
@@ -12,6 +30,115 @@ impl Value {
             Value::Number(_) => true,
             Value::Bool(b) => b,
             Value::Nil => false,
+            Value::Obj(_) => true,
+        }
+    }
+
+    /// Returns the name of the value's type as seen by scripts.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::Obj(obj) => match self.as_foreign().and_then(ObjForeign::class) {
+                Some(class) => class.name(),
+                None => obj.kind().type_name(),
+            },
+        }
+    }
+
+    pub fn as_obj(&self) -> Option<&Obj> {
+        if let Value::Obj(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the wrapped foreign object if the value is one.
+    pub fn as_foreign(&self) -> Option<&ObjForeign> {
+        self.as_obj()?.downcast()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        Some(self.as_obj()?.as_string()?.as_str())
+    }
+}
+
+/// Strings are written without quotes, the alternate flag (`{:#}`) can be used to quote them, this
+/// is used when a string is nested inside another value or when echoing values back to the user.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => fmt_number(*n, f),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Nil => f.write_str("nil"),
+            Value::Obj(obj) => match obj.kind() {
+                ObjKind::String if f.alternate() => write!(f, "{:?}", self.as_str().unwrap()),
+                ObjKind::String => f.write_str(self.as_str().unwrap()),
+                ObjKind::Function => {
+                    write!(
+                        f,
+                        "<func {}>",
+                        obj.downcast::<ObjFunction>().unwrap().name()
+                    )
+                }
+                ObjKind::Native => {
+                    write!(
+                        f,
+                        "<native func {}>",
+                        obj.downcast::<ObjNative>().unwrap().name()
+                    )
+                }
+                ObjKind::Bound => {
+                    write!(
+                        f,
+                        "<native func {}>",
+                        obj.downcast::<ObjBound>().unwrap().name()
+                    )
+                }
+                ObjKind::Bytes => {
+                    let bytes = AsRef::<ObjBytes>::as_ref(obj).bytes();
+                    write!(f, "<bytes {}>", encoding::hex_encode(bytes))
+                }
+                ObjKind::Error => {
+                    write!(f, "<error {}>", AsRef::<ObjError>::as_ref(obj).message())
+                }
+                ObjKind::List | ObjKind::Map => {
+                    f.write_str(&inspect(self, &InspectOptions::compact()))
+                }
+                _ => write!(f, "<{}>", self.type_name()),
+            },
+        }
+    }
+}
+
+/// Integral numbers are written without a fractional part.
+fn fmt_number(n: f64, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if n.is_nan() {
+        f.write_str("nan")
+    } else if n.is_infinite() {
+        f.write_str(if n > 0.0 { "inf" } else { "-inf" })
+    } else {
+        // Rust already omits the fractional part of integral floats.
+        write!(f, "{n}")
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        use Value::*;
+        match (self, other) {
+            (Number(a), Number(b)) => a == b,
+            (Bool(a), Bool(b)) => a == b,
+            (Nil, Nil) => true,
+            // Strings are immutable so they are compared by value, every other object is
+            // compared by identity.
+            (Obj(a), Obj(b)) if a.kind() == ObjKind::String && b.kind() == ObjKind::String => {
+                self.as_str() == other.as_str()
+            }
+            (Obj(a), Obj(b)) => a == b,
+            _ => false,
         }
     }
 }
@@ -26,8 +153,142 @@ impl PartialOrd for Value {
                 (Number(a), Number(b)) => a.partial_cmp(b),
                 (Bool(_), Bool(_)) => None,
                 (Nil, Nil) => None,
+                (Obj(_), Obj(_)) => self.as_str()?.partial_cmp(other.as_str()?),
                 _ => unreachable!(),
             }
         }
     }
 }
+
+/// Wrapper around a value which allows it to be used as a key in a hash map.
+///
+/// Keys are compared the same way values are with the exception of NaN, which is equal to itself
+/// so that it can be looked up again. Since `-0.0 == 0.0` both are hashed the same way, strings are
+/// hashed by their contents and all other objects by identity.
+#[derive(Debug, Clone)]
+pub struct ValueKey(Value);
+
+impl ValueKey {
+    pub fn new(value: Value) -> Self {
+        Self(value)
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Value> for ValueKey {
+    fn from(value: Value) -> Self {
+        Self::new(value)
+    }
+}
+
+impl PartialEq for ValueKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Value::Number(a), Value::Number(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for ValueKey {}
+
+impl Hash for ValueKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(&self.0).hash(state);
+        match &self.0 {
+            Value::Number(n) => {
+                let bits = if n.is_nan() {
+                    f64::NAN.to_bits()
+                } else if *n == 0.0 {
+                    0.0f64.to_bits()
+                } else {
+                    n.to_bits()
+                };
+
+                bits.hash(state);
+            }
+            Value::Bool(b) => b.hash(state),
+            Value::Nil => {}
+            Value::Obj(obj) => match obj.as_string() {
+                Some(string) => string.as_str().hash(state),
+                None => obj.hash(state),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Value, ValueKey};
+    use crate::interpreter::{heap::Heap, object::ObjString};
+
+    #[test]
+    fn display_number() {
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+        assert_eq!(Value::Number(-0.5).to_string(), "-0.5");
+        assert_eq!(Value::Number(1e21).to_string(), "1000000000000000000000");
+        assert_eq!(Value::Number(f64::NAN).to_string(), "nan");
+        assert_eq!(Value::Number(f64::NEG_INFINITY).to_string(), "-inf");
+    }
+
+    #[test]
+    fn display_string() {
+        let mut heap = Heap::default();
+        let value = Value::Obj(heap.alloc(ObjString::new("say \"hi\"".to_owned())));
+        assert_eq!(format!("{value}"), "say \"hi\"");
+        assert_eq!(format!("{value:#}"), "\"say \\\"hi\\\"\"");
+        assert_eq!(value.type_name(), "string");
+    }
+
+    #[test]
+    fn value_key() {
+        let mut heap = Heap::default();
+        let mut map = HashMap::new();
+        map.insert(ValueKey::new(Value::Number(f64::NAN)), 1);
+        map.insert(ValueKey::new(Value::Number(-0.0)), 2);
+        map.insert(
+            ValueKey::new(Value::Obj(heap.alloc(ObjString::new("key".to_owned())))),
+            3,
+        );
+
+        let lookup = |value: Value| map.get(&ValueKey::new(value)).copied();
+        assert_eq!(lookup(Value::Number(f64::NAN)), Some(1));
+        assert_eq!(lookup(Value::Number(0.0)), Some(2));
+        // Strings are keyed by contents rather than identity.
+        let key = Value::Obj(heap.alloc(ObjString::new("key".to_owned())));
+        assert_eq!(lookup(key), Some(3));
+        assert_eq!(lookup(Value::Nil), None);
+        assert_eq!(lookup(Value::Bool(false)), None);
+    }
+
+    #[test]
+    fn foreign() {
+        struct Handle(u32);
+
+        let mut heap = Heap::default();
+        let value = heap.alloc_foreign(Handle(7));
+        let foreign = value.as_foreign().unwrap();
+        assert!(foreign.is::<Handle>());
+        assert!(foreign.borrow::<String>().is_none());
+
+        foreign.borrow_mut::<Handle>().unwrap().0 += 1;
+        assert_eq!(foreign.borrow::<Handle>().unwrap().0, 8);
+        assert_eq!(value.type_name(), "foreign");
+    }
+
+    #[test]
+    fn display_other() {
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Bool(false).type_name(), "bool");
+    }
+}
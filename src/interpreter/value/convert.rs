@@ -0,0 +1,494 @@
+//! Conversions between values and Rust types.
+//!
+//! Strings have to be allocated on the heap so there is no `From<&str>` for `Value`, use
+//! [`Heap::alloc_string`](crate::interpreter::heap::Heap::alloc_string) instead.
+//!
+//! [`IntoValue`] and [`FromValue`] also convert types which need the heap, such as strings,
+//! lists and structs, which are converted to and from maps with a key for each field. With the
+//! `derive` feature they can be derived for structs with named fields, and for enums with only
+//! unit variants, which are converted to and from the names of their variants.
+
+use std::collections::HashMap;
+
+use super::{Value, ValueKey};
+use crate::interpreter::{
+    heap::Heap,
+    object::{Obj, ObjInstance, ObjKind, ObjList, ObjMap},
+    table::Table,
+    RuntimeError, RuntimeErrorKind,
+};
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Nil
+    }
+}
+
+impl From<Obj> for Value {
+    fn from(value: Obj) -> Self {
+        Value::Obj(value)
+    }
+}
+
+/// `None` is converted to `nil`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Nil, Into::into)
+    }
+}
+
+fn mismatch(expected: &'static str, value: &Value) -> RuntimeError {
+    RuntimeErrorKind::TypeMismatch {
+        expected,
+        found: value.type_name(),
+    }
+    .into()
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) => Ok(n),
+            _ => Err(mismatch("number", &value)),
+        }
+    }
+}
+
+/// Only numbers without a fractional part which fit in an `i64` can be converted.
+impl TryFrom<Value> for i64 {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Ok(n as i64)
+            }
+            _ => Err(mismatch("integer", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(mismatch("bool", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Nil => Ok(()),
+            _ => Err(mismatch("nil", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value
+            .as_str()
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| mismatch("string", &value))
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = RuntimeError;
+
+    fn try_from(value: &'a Value) -> Result<Self, Self::Error> {
+        value.as_str().ok_or_else(|| mismatch("string", value))
+    }
+}
+
+impl TryFrom<Value> for Obj {
+    type Error = RuntimeError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Obj(obj) => Ok(obj),
+            _ => Err(mismatch("object", &value)),
+        }
+    }
+}
+
+/// A blanket implementation for `Option<T>` would overlap with the standard library's reflexive
+/// implementation, so `nil` to `None` conversions are implemented for each type individually.
+macro_rules! try_from_value_for_option {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<Value> for Option<$t> {
+                type Error = RuntimeError;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    match value {
+                        Value::Nil => Ok(None),
+                        value => value.try_into().map(Some),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+try_from_value_for_option!(f64, i64, bool, String, Obj);
+
+/// Conversion of a Rust value into a value, allocating any objects it needs on the heap.
+///
+/// # Safety
+///
+/// Implementations must not keep the value they return, or any other value allocated on the heap,
+/// since hosts pass values to an [`Engine`](crate::Engine) with this trait and the engine relies
+/// on no handles into its heap being reachable from outside of it.
+pub unsafe trait IntoValue {
+    fn into_value(self, heap: &mut Heap) -> Value;
+}
+
+/// Conversion of a value into a Rust value, failing if the value has the wrong type.
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError>;
+}
+
+macro_rules! value_conversions {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl IntoValue for $t {
+                fn into_value(self, _: &mut Heap) -> Value {
+                    self.into()
+                }
+            }
+
+            impl FromValue for $t {
+                fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+                    value.clone().try_into()
+                }
+            }
+        )*
+    };
+}
+
+value_conversions!(f64, i64, bool, ());
+
+unsafe impl IntoValue for Value {
+    fn into_value(self, _: &mut Heap) -> Value {
+        self
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        Ok(value.clone())
+    }
+}
+
+unsafe impl IntoValue for String {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        heap.alloc_string(self)
+    }
+}
+
+unsafe impl IntoValue for &str {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        heap.alloc_string(self)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        <&str>::try_from(value).map(ToOwned::to_owned)
+    }
+}
+
+/// `None` is converted to `nil`.
+unsafe impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        self.map_or(Value::Nil, |value| value.into_value(heap))
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// Vectors are converted to lists.
+unsafe impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        let items = self.into_iter().map(|item| item.into_value(heap)).collect();
+        Value::Obj(heap.alloc(ObjList::new(items)))
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        let list = value
+            .as_obj()
+            .and_then(|obj| obj.downcast::<ObjList>())
+            .ok_or_else(|| mismatch("list", value))?;
+
+        list.items().iter().map(T::from_value).collect()
+    }
+}
+
+/// Hash maps are converted to maps with string keys.
+unsafe impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        let mut map = MapBuilder::new();
+        for (key, value) in self {
+            map.field(heap, &key, value);
+        }
+
+        map.build(heap)
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: &Value) -> Result<Self, RuntimeError> {
+        Fields::new(value)?
+            .fields
+            .into_iter()
+            .map(|(key, value)| Ok((key.to_owned(), T::from_value(value)?)))
+            .collect()
+    }
+}
+
+/// Builds the map a struct is converted to, with a string key for each field.
+pub struct MapBuilder {
+    entries: Table<ValueKey, Value>,
+}
+
+impl MapBuilder {
+    pub fn new() -> Self {
+        Self {
+            entries: Table::new(),
+        }
+    }
+
+    pub fn field(&mut self, heap: &mut Heap, name: &str, value: impl IntoValue) -> &mut Self {
+        let key = ValueKey::new(heap.alloc_string(name));
+        let value = value.into_value(heap);
+        self.entries.insert(key, value);
+        self
+    }
+
+    pub fn build(self, heap: &mut Heap) -> Value {
+        Value::Obj(heap.alloc(ObjMap::new(self.entries)))
+    }
+}
+
+impl Default for MapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fields of a map with string keys, or of an instance, which a struct is converted from.
+pub struct Fields<'a> {
+    fields: HashMap<&'a str, &'a Value>,
+}
+
+impl<'a> Fields<'a> {
+    /// Returns the fields of a map or instance, keys of maps which aren't strings are ignored.
+    pub fn new(value: &'a Value) -> Result<Self, RuntimeError> {
+        let fields = match value.as_obj() {
+            Some(obj) if obj.kind() == ObjKind::Map => obj
+                .downcast::<ObjMap>()
+                .unwrap()
+                .entries()
+                .iter()
+                .filter_map(|(key, value)| Some((key.value().as_str()?, value)))
+                .collect(),
+            Some(obj) if obj.kind() == ObjKind::Instance => obj
+                .downcast::<ObjInstance>()
+                .unwrap()
+                .fields()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value))
+                .collect(),
+            _ => return Err(mismatch("map", value)),
+        };
+
+        Ok(Self { fields })
+    }
+
+    /// Converts the field called `name`, a missing field is converted from nil so that optional
+    /// fields can be left out.
+    pub fn get<T: FromValue>(&self, name: &str) -> Result<T, RuntimeError> {
+        let value = self.fields.get(name).copied().unwrap_or(&Value::Nil);
+        T::from_value(value).map_err(|error| {
+            RuntimeErrorKind::InvalidArgument(format!("field '{name}': {error}")).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Fields, FromValue, IntoValue, MapBuilder};
+    use crate::interpreter::{heap::Heap, value::Value, RuntimeErrorKind};
+
+    #[test]
+    fn into_value() {
+        assert_eq!(Value::from(1.5), Value::Number(1.5));
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from(None::<f64>), Value::Nil);
+        assert_eq!(Value::from(Some(2i64)), Value::Number(2.0));
+    }
+
+    #[test]
+    fn try_from_value() {
+        assert_eq!(f64::try_from(Value::Number(1.5)).unwrap(), 1.5);
+        assert_eq!(i64::try_from(Value::Number(-3.0)).unwrap(), -3);
+        assert_eq!(
+            i64::try_from(Value::Number(1.5)).unwrap_err().kind(),
+            &RuntimeErrorKind::TypeMismatch {
+                expected: "integer",
+                found: "number"
+            }
+        );
+        assert_eq!(Option::<bool>::try_from(Value::Nil).unwrap(), None);
+        assert_eq!(
+            Option::<bool>::try_from(Value::Bool(true)).unwrap(),
+            Some(true)
+        );
+
+        let mut heap = Heap::default();
+        let value = heap.alloc_string("text");
+        assert_eq!(<&str>::try_from(&value).unwrap(), "text");
+        assert_eq!(String::try_from(value).unwrap(), "text");
+        assert!(String::try_from(Value::Nil).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: f64,
+        label: Option<String>,
+    }
+
+    unsafe impl IntoValue for Point {
+        fn into_value(self, heap: &mut Heap) -> Value {
+            let mut map = MapBuilder::new();
+            map.field(heap, "x", self.x)
+                .field(heap, "label", self.label);
+            map.build(heap)
+        }
+    }
+
+    impl FromValue for Point {
+        fn from_value(value: &Value) -> Result<Self, crate::interpreter::RuntimeError> {
+            let fields = Fields::new(value)?;
+            Ok(Self {
+                x: fields.get("x")?,
+                label: fields.get("label")?,
+            })
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let mut heap = Heap::default();
+        let points = vec![
+            Point {
+                x: 1.0,
+                label: Some("a".to_owned()),
+            },
+            Point {
+                x: 2.0,
+                label: None,
+            },
+        ];
+        let value = points.into_value(&mut heap);
+        assert_eq!(
+            value.to_string(),
+            "[{ \"x\": 1, \"label\": \"a\" }, { \"x\": 2, \"label\": nil }]"
+        );
+        let points = Vec::<Point>::from_value(&value).unwrap();
+        assert_eq!(
+            points[1],
+            Point {
+                x: 2.0,
+                label: None
+            }
+        );
+
+        let value = HashMap::from([("label".to_owned(), 1.0)]).into_value(&mut heap);
+        assert_eq!(
+            Point::from_value(&value).unwrap_err().kind(),
+            &RuntimeErrorKind::InvalidArgument("field 'x': expected number got nil".to_owned())
+        );
+        assert!(Point::from_value(&Value::Number(1.0)).is_err());
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive() {
+        use crate::interpreter::value::{FromValue, IntoValue};
+
+        #[derive(Debug, PartialEq, IntoValue, FromValue)]
+        enum Shape {
+            Circle,
+            Square,
+        }
+
+        #[derive(Debug, PartialEq, IntoValue, FromValue)]
+        struct Sprite {
+            name: String,
+            shape: Shape,
+            position: Vec<f64>,
+            hidden: Option<bool>,
+        }
+
+        let mut heap = Heap::default();
+        let sprite = Sprite {
+            name: "ball".to_owned(),
+            shape: Shape::Circle,
+            position: vec![1.0, 2.0],
+            hidden: None,
+        };
+        let value = sprite.into_value(&mut heap);
+        assert_eq!(
+            value.to_string(),
+            "{ \"name\": \"ball\", \"shape\": \"Circle\", \"position\": [1, 2], \"hidden\": nil }"
+        );
+        assert_eq!(Sprite::from_value(&value).unwrap().shape, Shape::Circle);
+
+        let value = heap.alloc_string("Triangle");
+        assert_eq!(
+            Shape::from_value(&value).unwrap_err().kind(),
+            &RuntimeErrorKind::InvalidArgument("unknown variant 'Triangle' of Shape".to_owned())
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! JSON documents independent of the interpreter's values, used by tools which talk to other
+//! programs such as the language server. Scripts convert between values and JSON with the
+//! [marshal](crate::interpreter::marshal) module instead.
+
+use std::fmt::{Display, Write};
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members are kept in the order they were added or parsed in.
+    Object(Vec<(String, Json)>),
+}
+
+/// Deepest nesting of arrays and objects which can be parsed, this keeps parsing from
+/// overflowing the stack.
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum JsonError {
+    #[error("invalid JSON at byte {offset}: {message}")]
+    Invalid { offset: usize, message: String },
+    #[error("arrays and objects are nested too deeply")]
+    TooDeep,
+}
+
+impl Json {
+    /// Creates an object from its members.
+    pub fn object<'k>(members: impl IntoIterator<Item = (&'k str, Json)>) -> Self {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Self, JsonError> {
+        let mut parser = JsonParser {
+            text,
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != text.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    /// Returns the value of the member with the given key if this is an object which has one.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a non-negative integer.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| n.fract() == 0.0 && *n >= 0.0)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Self {
+        Json::Number(n)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(items: Vec<Json>) -> Self {
+        Json::Array(items)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+/// Writes the document compactly, without any whitespace.
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            // JSON has no representation for infinities or NaN.
+            Json::Number(n) if !n.is_finite() => f.write_str("null"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(members) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut impl Write, s: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// Number of arrays and objects being parsed.
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError::Invalid {
+            offset: self.pos,
+            message: message.to_owned(),
+        }
+    }
+
+    /// Counts an array or object being parsed, failing if that is too many.
+    fn descend(&mut self) -> Result<(), JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(JsonError::TooDeep);
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{expected}'")))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.descend()?;
+                let object = self.object()?;
+                self.depth -= 1;
+                Ok(object)
+            }
+            Some('[') => {
+                self.descend()?;
+                let array = self.array()?;
+                self.depth -= 1;
+                Ok(array)
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => {
+                for (literal, value) in [
+                    ("null", Json::Null),
+                    ("true", Json::Bool(true)),
+                    ("false", Json::Bool(false)),
+                ] {
+                    if self.text[self.pos..].starts_with(literal) {
+                        self.pos += literal.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut members = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+            self.pos += 1;
+        }
+
+        self.text[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| JsonError::Invalid {
+                offset: start,
+                message: "invalid number".to_owned(),
+            })
+    }
+
+    /// Parses a string, the next character is expected to be the opening quote.
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Parses the digits of a `\u` escape, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.text[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("invalid code point"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("expected 4 hex digits"))?;
+        let code =
+            u32::from_str_radix(digits, 16).map_err(|_| self.error("expected 4 hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, JsonError, MAX_DEPTH};
+
+    #[test]
+    fn round_trip() {
+        let text = r#"{"a":[1,2.5,-3e2],"b":{"c":null,"d":true},"e":"x\"\n\u00e9\ud83d\ude00"}"#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(
+            json.get("a").unwrap().as_array().unwrap()[2],
+            Json::Number(-300.0)
+        );
+        assert_eq!(json.get("e").unwrap().as_str(), Some("x\"\né😀"));
+        assert_eq!(
+            json.to_string(),
+            r#"{"a":[1,2.5,-300],"b":{"c":null,"d":true},"e":"x\"\né😀"}"#
+        );
+        assert_eq!(Json::parse(&json.to_string()), Ok(json));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Json::parse("[1,]"),
+            Err(JsonError::Invalid { offset: 3, .. })
+        ));
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("\"abc").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert!(Json::parse("\"\\ud800\"").is_err());
+    }
+
+    #[test]
+    fn too_deep() {
+        let json = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&json(MAX_DEPTH)).is_ok());
+        assert_eq!(Json::parse(&json(MAX_DEPTH + 1)), Err(JsonError::TooDeep));
+        assert_eq!(Json::parse(&json(100_000)), Err(JsonError::TooDeep));
+    }
+}
@@ -1,2 +1,177 @@
+// Lets code generated by the derive macros, which refers to this crate by name, be used here.
+extern crate self as unnamed_language;
+
 pub mod compiler;
+pub mod diagnostic;
+pub mod engine;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod interpreter;
+pub mod json;
+pub mod source_map;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+use compiler::Parser;
+use diagnostic::Diagnostic;
+pub use engine::{Engine, HostValue};
+pub use error::Error;
+use interpreter::{capabilities::Capabilities, io::BufferIo, Interpreter};
+
+/// Statements a script run by [`run_str`] may execute.
+const UNTRUSTED_FUEL: u64 = 100_000;
+/// Bytes a script run by [`run_str`] may allocate, and separately print.
+const UNTRUSTED_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+/// Nested calls a script run by [`run_str`] may make, each call can evaluate expressions nested
+/// as deep as the parser allows so this is lower than the default.
+const UNTRUSTED_MAX_CALL_DEPTH: usize = 64;
+/// Stack of the thread scripts run by [`run_str`] run on, enough for every call up to the call
+/// depth limit to evaluate expressions nested as deep as possible.
+const UNTRUSTED_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Parses and runs untrusted source, returning what it printed or the first error.
+///
+/// The script can't touch the host, it has no input, runs deterministically and is stopped once
+/// it runs out of fuel or memory, so any source returns without side effects. No
+/// source can panic or overflow the stack, which makes this a suitable target for fuzzers:
+///
+/// ```ignore
+/// fuzz_target!(|data: &[u8]| {
+///     if let Ok(source) = std::str::from_utf8(data) {
+///         let _ = unnamed_language::run_str(source);
+///     }
+/// });
+/// ```
+///
+/// The diagnostic is boxed to keep the result small.
+pub fn run_str(source: &str) -> Result<String, Box<Diagnostic>> {
+    // The stack of the calling thread may be too small for deeply nested code, in the unlikely
+    // case that a thread can't be spawned we take our chances on it.
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(UNTRUSTED_STACK_SIZE)
+            .spawn_scoped(scope, || run_untrusted(source))
+            .map_or_else(
+                |_| run_untrusted(source),
+                |thread| {
+                    thread
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                },
+            )
+    })
+}
+
+fn run_untrusted(source: &str) -> Result<String, Box<Diagnostic>> {
+    let script = Parser::new(source)
+        .parse()
+        .map_err(|error| Box::new(Diagnostic::from(&error)))?;
+
+    let mut interpreter = Interpreter::default();
+    let io = BufferIo::new("").with_output_limit(UNTRUSTED_MEMORY_LIMIT);
+    interpreter.set_io(Box::new(io.clone()));
+    // The clock is virtual once the interpreter is deterministic.
+    interpreter.set_capabilities(Capabilities {
+        clock: true,
+        ..Capabilities::none()
+    });
+    interpreter.set_deterministic(0);
+    interpreter.set_fuel(Some(UNTRUSTED_FUEL));
+    interpreter.set_max_call_depth(UNTRUSTED_MAX_CALL_DEPTH);
+    interpreter
+        .heap_mut()
+        .set_memory_limit(Some(UNTRUSTED_MEMORY_LIMIT));
+
+    interpreter
+        .interpret(&script)
+        .map_err(|error| Box::new(Diagnostic::from(&error)))?;
+
+    Ok(io.take_output())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_str;
+    use crate::{compiler::parser::MAX_DEPTH, diagnostic::ErrorCode};
+
+    #[test]
+    fn output_and_errors() {
+        assert_eq!(run_str("print(1 + 2);").unwrap(), "3");
+        let error = run_str("print(1 +);").unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::UNEXPECTED_TOKEN));
+        let error = run_str("while true {}").unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::OUT_OF_FUEL));
+        #[cfg(feature = "fs")]
+        {
+            let error = run_str("fs.read_text(\"/etc/passwd\");").unwrap_err();
+            assert_eq!(error.code, Some(ErrorCode::PERMISSION_DENIED));
+        }
+        let source =
+            "let s = \"abcdefgh\"; for i in range(20) { s = s + s; } while true { print(s); }";
+        let error = run_str(source).unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::IO));
+    }
+
+    #[test]
+    fn endless_builtins() {
+        // Builtins which loop as many times as they are asked to still run out of fuel.
+        for source in [
+            "for x in skip(range(1000000000000000), 100000000000000) {}",
+            "range(1000000000000000).to_list();",
+        ] {
+            let error = run_str(source).unwrap_err();
+            assert_eq!(error.code, Some(ErrorCode::OUT_OF_FUEL), "{source}");
+        }
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let nested = |depth: usize| format!("{}1{};", "(".repeat(depth), ")".repeat(depth));
+        // The statement and the literal are nested too.
+        assert!(run_str(&nested(MAX_DEPTH - 2)).is_ok());
+        let error = run_str(&nested(100_000)).unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::TOO_DEEPLY_NESTED));
+        let error = run_str(&format!("1{};", " + 1".repeat(100_000))).unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::TOO_DEEPLY_NESTED));
+
+        // Recursing from inside the deepest expression allowed.
+        let depth = MAX_DEPTH - 10;
+        let source = format!(
+            "func f() {{ return {}f(){}; }} f();",
+            "-(".repeat(depth / 2),
+            ")".repeat(depth / 2)
+        );
+        let error = run_str(&source).unwrap_err();
+        assert_eq!(error.code, Some(ErrorCode::STACK_OVERFLOW));
+
+        // Values can be nested deeper than expressions, they are still printed in full.
+        let source = "let l = 1; for i in range(40000) { l = [l]; } print(l);";
+        let expected = format!("{}1{}", "[".repeat(40_000), "]".repeat(40_000));
+        assert_eq!(run_str(source), Ok(expected));
+    }
+
+    /// Runs sources made of random tokens, none of which should panic.
+    #[test]
+    fn token_soup() {
+        let tokens: Vec<_> = "let func test if else while for in return and or not true false nil \
+            x f print len bytes range format \"a\" \"\" \"{}\" 0 1 2.5 1000000000 ( ) [ ] { } \
+            ; , : . = == != < > <= >= + - * / % ** -= *= /= %= @ \""
+            .split_whitespace()
+            .collect();
+
+        // Deterministic xorshift so failures can be reproduced.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize
+        };
+        for _ in 0..2000 {
+            let len = next() % 40;
+            let source: Vec<_> = (0..len).map(|_| tokens[next() % tokens.len()]).collect();
+            let _ = run_str(&source.join(" "));
+        }
+    }
+}
@@ -0,0 +1,760 @@
+//! Language server speaking the Language Server Protocol over stdin and stdout.
+//!
+//! Documents are synchronized incrementally and only the declarations a change touches are parsed
+//! again, after which they are checked and their diagnostics published. Go to definition and hover are answered from the references recorded
+//! by the resolver, document symbols from the declarations of the syntax tree, and semantic
+//! tokens from the classification used for highlighting.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use unnamed_language::{
+    compiler::{
+        check::check,
+        highlight::{semantic_tokens, Class, Role, SemanticToken},
+        incremental::Document,
+        parser::{
+            ast::{Decl, DeclKind, Script, Stmt, StmtKind},
+            token::Location,
+        },
+        resolver::Reference,
+        warnings::Levels,
+    },
+    diagnostic::{Diagnostic, Severity},
+    json::Json,
+};
+
+/// Error code for requests with a method the server doesn't handle.
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+/// Serves requests until the client sends `exit`, returning whether it asked the server to shut
+/// down first.
+pub fn run() -> bool {
+    let mut server = Server::default();
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => return false,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return false;
+            }
+        };
+
+        match server.handle(&message, &mut output) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Exit) => return server.shut_down,
+            Err(error) => {
+                eprintln!("error: {error}");
+                return false;
+            }
+        }
+    }
+}
+
+/// Reads a message and its header, returning `None` at the end of input. The debug adapter uses
+/// the same framing.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid_data("missing Content-Length header"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+    let content = String::from_utf8(content).map_err(|error| invalid_data(&error.to_string()))?;
+    let message = Json::parse(&content).map_err(|error| invalid_data(&error.to_string()))?;
+
+    Ok(Some(message))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+enum Flow {
+    Continue,
+    Exit,
+}
+
+#[derive(Default)]
+struct Server {
+    /// Open documents by URI.
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+}
+
+impl Server {
+    fn handle(&mut self, message: &Json, output: &mut impl Write) -> io::Result<Flow> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params, output);
+        };
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shut_down = true;
+                Some(Json::Null)
+            }
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/documentSymbol" => Some(self.document_symbols(params)),
+            "textDocument/semanticTokens/full" => Some(self.semantic_tokens(params)),
+            _ => None,
+        };
+
+        let response = match result {
+            Some(result) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                ("result", result),
+            ]),
+            None => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                (
+                    "error",
+                    Json::object([
+                        ("code", METHOD_NOT_FOUND.into()),
+                        ("message", format!("unknown method '{method}'").into()),
+                    ]),
+                ),
+            ]),
+        };
+        write_message(output, &response)?;
+
+        Ok(Flow::Continue)
+    }
+
+    fn notification(
+        &mut self,
+        method: &str,
+        params: &Json,
+        output: &mut impl Write,
+    ) -> io::Result<Flow> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_owned();
+
+        match method {
+            "exit" => return Ok(Flow::Exit),
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.documents
+                    .insert(uri.clone(), Document::new(text.to_owned()));
+                self.publish_diagnostics(&uri, output)?;
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Json::as_array);
+                if let (Some(document), Some(changes)) = (self.documents.get_mut(&uri), changes) {
+                    for change in changes {
+                        apply_change(document, change);
+                    }
+                    self.publish_diagnostics(&uri, output)?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish_diagnostics(&uri, output)?;
+            }
+            _ => {}
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    fn publish_diagnostics(&self, uri: &str, output: &mut impl Write) -> io::Result<()> {
+        let diagnostics = match self.documents.get(uri) {
+            Some(document) => check(document.source(), &Levels::default())
+                .iter()
+                .map(|diagnostic| lsp_diagnostic(document.source(), diagnostic))
+                .collect(),
+            None => vec![],
+        };
+
+        let notification = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+            ),
+        ]);
+        write_message(output, &notification)
+    }
+
+    /// Returns the document of a request about a position, along with the offset of the
+    /// position and its parsed script, if it parses.
+    fn document_at<'a>(
+        &'a self,
+        params: &'a Json,
+    ) -> Option<(&'a str, &'a str, usize, &'a Script)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let document = self.documents.get(uri)?;
+        let source = document.source();
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_usize()?;
+        let character = position.get("character")?.as_usize()?;
+        let offset = offset_of(source, line, character);
+        let script = document.script()?;
+
+        Some((uri, source, offset, script))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let definition = self
+            .document_at(params)
+            .and_then(|(uri, source, offset, script)| {
+                let declaration = reference_at(script, offset)?.declaration?;
+                Some(Json::object([
+                    ("uri", uri.into()),
+                    ("range", range(source, declaration)),
+                ]))
+            });
+
+        definition.into()
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let hover = self
+            .document_at(params)
+            .and_then(|(_, source, offset, script)| {
+                let reference = reference_at(script, offset)?;
+                let name = &source[reference.usage.start..reference.usage.end];
+                let description = match reference.declaration {
+                    Some(declaration) => describe(&script.decls, declaration)?,
+                    None => format!("global {name}"),
+                };
+
+                Some(Json::object([
+                    (
+                        "contents",
+                        Json::object([
+                            ("kind", "markdown".into()),
+                            ("value", format!("```\n{description}\n```").into()),
+                        ]),
+                    ),
+                    ("range", range(source, reference.usage)),
+                ]))
+            });
+
+        hover.into()
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let symbols = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .and_then(|uri| self.documents.get(uri))
+            .and_then(|document| {
+                let script = document.script()?;
+                Some(symbols(document.source(), &script.decls))
+            });
+
+        symbols.map(Json::from).into()
+    }
+
+    fn semantic_tokens(&self, params: &Json) -> Json {
+        let tokens = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .and_then(|uri| self.documents.get(uri))
+            .map(|document| {
+                let source = document.source();
+                let tokens = semantic_tokens(source, document.script());
+                Json::object([("data", encode_tokens(source, &tokens).into())])
+            });
+
+        tokens.into()
+    }
+}
+
+/// Token types of the semantic tokens legend, indexed by [`token_type`].
+const TOKEN_TYPES: &[&str] = &[
+    "keyword",
+    "function",
+    "parameter",
+    "variable",
+    "property",
+    "number",
+    "string",
+    "operator",
+];
+/// Token modifiers of the semantic tokens legend, as bits of [`token_type`].
+const TOKEN_MODIFIERS: &[&str] = &["declaration", "defaultLibrary"];
+
+/// Index into [`TOKEN_TYPES`] and modifier bits of a class, classes which aren't highlighted
+/// have no type.
+fn token_type(class: Class) -> Option<(usize, usize)> {
+    let token_type = match class {
+        Class::Keyword | Class::Constant => 0,
+        Class::Identifier { role, declaration } => {
+            let token_type = match role {
+                Role::Function => 1,
+                Role::Parameter => 2,
+                Role::Local | Role::Global | Role::Builtin | Role::Unknown => 3,
+                Role::Member => 4,
+            };
+            let modifiers = usize::from(declaration) | usize::from(role == Role::Builtin) << 1;
+            return Some((token_type, modifiers));
+        }
+        Class::Number => 5,
+        Class::String => 6,
+        Class::Operator => 7,
+        Class::Punctuation | Class::Invalid => return None,
+    };
+
+    Some((token_type, 0))
+}
+
+/// Encodes tokens as relative positions in the five integer form of the protocol. Tokens may
+/// not span lines so strings are split at newlines.
+fn encode_tokens(source: &str, tokens: &[SemanticToken]) -> Vec<Json> {
+    let mut data = vec![];
+    let (mut previous_line, mut previous_character) = (0, 0);
+    let mut line = 0;
+    let mut line_start = 0;
+    let mut scanned = 0;
+    for token in tokens {
+        let Some((token_type, modifiers)) = token_type(token.class) else {
+            continue;
+        };
+
+        let mut start = token.location.start;
+        let text = &source[start..token.location.end];
+        for piece in text.split('\n') {
+            for (i, _) in source[scanned..start].match_indices('\n') {
+                line += 1;
+                line_start = scanned + i + 1;
+            }
+            scanned = start;
+
+            let character: usize = source[line_start..start].chars().map(char::len_utf16).sum();
+            let length: usize = piece.chars().map(char::len_utf16).sum();
+            if length > 0 {
+                let delta_character = match line == previous_line {
+                    true => character - previous_character,
+                    false => character,
+                };
+                data.extend([
+                    line - previous_line,
+                    delta_character,
+                    length,
+                    token_type,
+                    modifiers,
+                ]);
+                (previous_line, previous_character) = (line, character);
+            }
+            start += piece.len() + 1;
+        }
+    }
+
+    data.into_iter().map(Json::from).collect()
+}
+
+fn legend(names: &[&str]) -> Json {
+    names
+        .iter()
+        .map(|&name| name.into())
+        .collect::<Vec<Json>>()
+        .into()
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // Incremental synchronization.
+                ("textDocumentSync", 2.0.into()),
+                ("definitionProvider", true.into()),
+                ("hoverProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                (
+                    "semanticTokensProvider",
+                    Json::object([
+                        (
+                            "legend",
+                            Json::object([
+                                ("tokenTypes", legend(TOKEN_TYPES)),
+                                ("tokenModifiers", legend(TOKEN_MODIFIERS)),
+                            ]),
+                        ),
+                        ("full", true.into()),
+                    ]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", env!("CARGO_PKG_NAME").into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+/// Applies a change to a document, changes without a range replace the whole text.
+fn apply_change(document: &mut Document, change: &Json) {
+    let Some(text) = change.get("text").and_then(Json::as_str) else {
+        return;
+    };
+    let Some(range) = change.get("range") else {
+        *document = Document::new(text.to_owned());
+        return;
+    };
+
+    let source = document.source();
+    let offset = |position: Option<&Json>| {
+        let line = position?.get("line")?.as_usize()?;
+        let character = position?.get("character")?.as_usize()?;
+        Some(offset_of(source, line, character))
+    };
+    if let (Some(start), Some(end)) = (offset(range.get("start")), offset(range.get("end"))) {
+        document.edit(start.min(end)..end, text);
+    }
+}
+
+fn lsp_diagnostic(source: &str, diagnostic: &Diagnostic) -> Json {
+    let severity = match diagnostic.severity {
+        Severity::Error => 1.0,
+        Severity::Warning => 2.0,
+        Severity::Note => 3.0,
+    };
+    let range = match diagnostic.location {
+        Some(location) => range(source, location),
+        None => range(source, Location::default()),
+    };
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push('\n');
+        message.push_str(note);
+    }
+
+    Json::object([
+        ("range", range),
+        ("severity", severity.into()),
+        ("code", diagnostic.code.map(|code| code.to_string()).into()),
+        ("source", env!("CARGO_PKG_NAME").into()),
+        ("message", message.into()),
+    ])
+}
+
+/// Finds the reference containing the offset, a cursor just after an identifier is considered
+/// to be on it.
+fn reference_at(script: &Script, offset: usize) -> Option<&Reference> {
+    script
+        .references
+        .iter()
+        .find(|reference| (reference.usage.start..=reference.usage.end).contains(&offset))
+}
+
+/// Describes the declaration at the location, such as `func add(a, b)` for a function.
+fn describe(decls: &[Decl], declaration: Location) -> Option<String> {
+    decls.iter().find_map(|decl| match &decl.kind {
+        DeclKind::Var(name, _) if name.location() == declaration => {
+            Some(format!("let {}", name.as_ref()))
+        }
+        DeclKind::Import(name) if name.location() == declaration => {
+            Some(format!("import \"{}\"", name.as_ref()))
+        }
+        DeclKind::Var(..) | DeclKind::Import(_) => None,
+        DeclKind::Func(function) => {
+            let params: Vec<_> = function.params.iter().map(AsRef::as_ref).collect();
+            if function.name.location() == declaration {
+                return Some(format!(
+                    "func {}({})",
+                    function.name.as_ref(),
+                    params.join(", ")
+                ));
+            }
+            if let Some(param) = function
+                .params
+                .iter()
+                .find(|param| param.location() == declaration)
+            {
+                return Some(format!(
+                    "parameter {} of {}",
+                    param.as_ref(),
+                    function.name.as_ref()
+                ));
+            }
+            describe_stmt(&function.body, declaration)
+        }
+        DeclKind::Test(test) => describe_stmt(&test.body, declaration),
+        DeclKind::Stmt(stmt) => describe_stmt(stmt, declaration),
+    })
+}
+
+fn describe_stmt(stmt: &Stmt, declaration: Location) -> Option<String> {
+    match &stmt.kind {
+        StmtKind::If(_, consequent, alternative) => {
+            describe_stmt(consequent, declaration).or_else(|| {
+                alternative
+                    .as_ref()
+                    .and_then(|alternative| describe_stmt(alternative, declaration))
+            })
+        }
+        StmtKind::While(_, body) => describe_stmt(body, declaration),
+        StmtKind::For(name, _, _) if name.location() == declaration => {
+            Some(format!("for {} in ...", name.as_ref()))
+        }
+        StmtKind::For(_, _, body) => describe_stmt(body, declaration),
+        StmtKind::Block(decls) => describe(decls, declaration),
+        StmtKind::Expr(_) | StmtKind::Return(..) => None,
+    }
+}
+
+/// Returns the symbols declared by the declarations, functions contain the symbols declared in
+/// their bodies.
+fn symbols(source: &str, decls: &[Decl]) -> Vec<Json> {
+    // Symbol kinds defined by the protocol.
+    const MODULE: f64 = 2.0;
+    const FUNCTION: f64 = 12.0;
+    const VARIABLE: f64 = 13.0;
+
+    let mut result = vec![];
+    for decl in decls {
+        let (name, kind, children) = match &decl.kind {
+            DeclKind::Var(name, _) => (name, VARIABLE, vec![]),
+            DeclKind::Import(name) => (name, MODULE, vec![]),
+            // Tests are listed as functions named after them, the protocol has no kind for them.
+            DeclKind::Func(function) | DeclKind::Test(function) => {
+                let children = match &function.body.kind {
+                    StmtKind::Block(decls) => symbols(source, decls),
+                    _ => vec![],
+                };
+                (&function.name, FUNCTION, children)
+            }
+            DeclKind::Stmt(_) => continue,
+        };
+
+        let range = range(source, name.location());
+        result.push(Json::object([
+            ("name", name.as_ref().into()),
+            ("kind", kind.into()),
+            ("range", range.clone()),
+            ("selectionRange", range),
+            ("children", children.into()),
+        ]));
+    }
+
+    result
+}
+
+fn range(source: &str, location: Location) -> Json {
+    Json::object([
+        ("start", position(source, location.start)),
+        ("end", position(source, location.end)),
+    ])
+}
+
+/// Converts a byte offset to a protocol position, which counts lines from zero and characters
+/// in UTF-16 code units.
+fn position(source: &str, offset: usize) -> Json {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count();
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+/// Converts a protocol position to a byte offset, positions past the end of a line are clamped
+/// to it.
+fn offset_of(source: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => match source.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return source.len(),
+        },
+    };
+
+    let mut units = 0;
+    for (i, c) in source[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+
+    source.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_language::json::Json;
+
+    use super::{offset_of, position, read_message, write_message, Server};
+
+    #[test]
+    fn positions() {
+        let source = "let é = 1;\nlet 😀x = 2;";
+        assert_eq!(
+            position(source, 6).to_string(),
+            r#"{"line":0,"character":5}"#
+        );
+        assert_eq!(offset_of(source, 0, 5), 6);
+        assert_eq!(
+            position(source, 20).to_string(),
+            r#"{"line":1,"character":6}"#
+        );
+        assert_eq!(offset_of(source, 1, 6), 20);
+        assert_eq!(offset_of(source, 0, 100), 11);
+        assert_eq!(offset_of(source, 5, 0), source.len());
+    }
+
+    /// Sends the messages to a server and returns its responses.
+    fn exchange(messages: &[&str]) -> Vec<Json> {
+        let mut server = Server::default();
+        let mut output = vec![];
+        for message in messages {
+            server
+                .handle(&Json::parse(message).unwrap(), &mut output)
+                .unwrap();
+        }
+
+        let mut input = output.as_slice();
+        let mut responses = vec![];
+        while let Some(response) = read_message(&mut input).unwrap() {
+            responses.push(response);
+        }
+        responses
+    }
+
+    #[test]
+    fn requests() {
+        let open = r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+            {"uri":"file:///a.ul","text":"func add(a, b) {\n  let c = 1;\n  return a + b;\n}\nadd(1, 2);"}}}"#;
+        let definition = r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/definition","params":
+            {"textDocument":{"uri":"file:///a.ul"},"position":{"line":2,"character":13}}}"#;
+        let hover = r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/hover","params":
+            {"textDocument":{"uri":"file:///a.ul"},"position":{"line":4,"character":1}}}"#;
+        let symbols = r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/documentSymbol","params":
+            {"textDocument":{"uri":"file:///a.ul"}}}"#;
+        let unknown = r#"{"jsonrpc":"2.0","id":4,"method":"unknown"}"#;
+        let responses = exchange(&[open, definition, hover, symbols, unknown]);
+
+        let diagnostics = responses[0]
+            .get("params")
+            .unwrap()
+            .get("diagnostics")
+            .unwrap();
+        let diagnostic = &diagnostics.as_array().unwrap()[0];
+        assert_eq!(
+            diagnostic.get("range").unwrap().to_string(),
+            r#"{"start":{"line":1,"character":6},"end":{"line":1,"character":7}}"#
+        );
+        assert_eq!(diagnostic.get("severity"), Some(&Json::Number(2.0)));
+
+        assert_eq!(
+            responses[1].get("result").unwrap().to_string(),
+            r#"{"uri":"file:///a.ul","range":{"start":{"line":0,"character":12},"end":{"line":0,"character":13}}}"#
+        );
+
+        let contents = responses[2].get("result").unwrap().get("contents").unwrap();
+        assert_eq!(
+            contents.get("value").unwrap().as_str(),
+            Some("```\nfunc add(a, b)\n```")
+        );
+
+        let symbols = responses[3].get("result").unwrap().as_array().unwrap();
+        assert_eq!(symbols.len(), 1);
+        let children = symbols[0].get("children").unwrap().as_array().unwrap();
+        assert_eq!(children[0].get("name").unwrap().as_str(), Some("c"));
+
+        assert!(responses[4].get("error").is_some());
+    }
+
+    #[test]
+    fn changes() {
+        let open = r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+            {"uri":"file:///a.ul","text":"let a = 1;\nprint(a);"}}}"#;
+        let change = r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{
+            "textDocument":{"uri":"file:///a.ul","version":2},"contentChanges":[
+            {"range":{"start":{"line":0,"character":0},"end":{"line":0,"character":0}},
+            "text":"let b = 2;\n"},
+            {"range":{"start":{"line":2,"character":6},"end":{"line":2,"character":7}},
+            "text":"b"}]}}"#;
+        let definition = r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/definition","params":
+            {"textDocument":{"uri":"file:///a.ul"},"position":{"line":2,"character":6}}}"#;
+        let responses = exchange(&[open, change, definition]);
+
+        let diagnostics = responses[1].get("params").unwrap().get("diagnostics");
+        assert_eq!(diagnostics.unwrap().as_array().unwrap().len(), 0);
+        assert_eq!(
+            responses[2]
+                .get("result")
+                .unwrap()
+                .get("range")
+                .unwrap()
+                .to_string(),
+            r#"{"start":{"line":0,"character":4},"end":{"line":0,"character":5}}"#
+        );
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        let open = r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":
+            {"uri":"file:///a.ul","text":"let x = \"😀\nb\";\nprint(x);"}}}"#;
+        let tokens = r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/semanticTokens/full",
+            "params":{"textDocument":{"uri":"file:///a.ul"}}}"#;
+        let responses = exchange(&[open, tokens]);
+
+        // `let`, `x` declared, `=`, both lines of the string, then `print` and `x`.
+        assert_eq!(
+            responses[1]
+                .get("result")
+                .unwrap()
+                .get("data")
+                .unwrap()
+                .to_string(),
+            "[0,0,3,0,0,0,4,1,3,1,0,2,1,7,0,0,2,3,6,0,1,0,2,6,0,1,0,5,3,2,0,6,1,3,0]"
+        );
+    }
+
+    #[test]
+    fn framing() {
+        let mut output = vec![];
+        write_message(&mut output, &Json::Null).unwrap();
+        assert_eq!(output, b"Content-Length: 4\r\n\r\nnull");
+        assert!(read_message(&mut &b"Content-Type: x\r\n\r\n"[..]).is_err());
+    }
+}
@@ -1,78 +1,1053 @@
 use std::{
     fs::File,
-    io::{Read, Write},
-    path::Path,
+    io::{IsTerminal, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
 };
 
-use unnamed_language::{compiler::parser::Parser, interpreter::Interpreter};
+use unnamed_language::{
+    compiler::{
+        check, formatter,
+        sexpr::{self, Options as SexprOptions},
+        warnings::{Level, Levels},
+        Parser, Scanner, Script,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Renderer},
+    interpreter::{
+        capabilities::Capabilities,
+        coverage::Coverage,
+        inspect::{inspect, InspectOptions},
+        value::Value,
+        Interpreter, RuntimeError, RuntimeErrorKind,
+    },
+};
+
+mod dap;
+mod debugger;
+mod editor;
+mod lsp;
+mod repl;
+mod test_runner;
+
+const USAGE: &str = "\
+usage: unnamed-language [command] [options]
+
+commands:
+  run <file> [args...]  run a script, arguments after the file are passed to it
+  run -e <code> [args...]
+                        run code given on the command line and print its value, the final
+                        semicolon may be left out
+  repl                  start an interactive session, enter :help for its commands
+  debug <file> [args...]
+                        run a script in the console debugger, enter help for its commands
+  check <file>...       report the errors and warnings of scripts without running them
+  test <file>...        run the tests declared by scripts with `test \"name\" { ... }`
+  fmt <file>            print a script in the canonical layout
+  disasm <file>         print the bytecode of a script
+  lsp                   start a language server communicating over stdin and stdout
+  dap                   start a debug adapter communicating over stdin and stdout
+  help                  print this message, also printed by -h or --help after any command
+  --version             print the version of the interpreter
+  --explain <code>      print a description of the error with the given code, such as E0203
+
+Running `unnamed-language <file> [args...]` is short for `run`, and without any arguments a
+session is started as with `repl`. Arguments passed to scripts are returned by `args()`, `--`
+ends the options so that files starting with `-` can be given.
+
+options of run, debug and repl:
+  --no-prelude          don't load the parts of the standard library written in the language
+  --sandbox             deny access to the filesystem, environment, processes, network and
+                        plugins
+  --dump-tokens         print the tokens of the code instead of running it
+  --time                print how long the code took, how many statements it ran and how much
+                        memory it allocated
+  --dump-ast            print the syntax tree of the code as an S-expression instead of running it
+  --dump-ast=json       print the syntax tree as JSON instead, if built with the serde feature
+  --warn <name>         report a warning, such as unused_variable, this is the default
+  --deny <name>         treat a warning as an error, so the code isn't run if it is found
+  --allow <name>        don't report a warning, `all` can be given instead of a name
+
+options of check:
+  --warn, --deny and --allow as for run
+
+options of test:
+  --no-prelude, --sandbox, --warn, --deny and --allow as for run
+  --filter <text>       only run the tests whose names contain the text
+
+options of run:
+  --watch               run the script again whenever it changes
+  --coverage            print the script with how many times each line ran once it ends
+  --lcov <file>         write how many times each line ran to a file in the LCOV format
+
+options of fmt:
+  --write               overwrite the file instead of printing it
+  --check               only report whether the file is formatted";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Run {
+        path: PathBuf,
+        args: Vec<String>,
+        options: RunOptions,
+    },
+    Eval {
+        source: String,
+        args: Vec<String>,
+        options: RunOptions,
+    },
+    Repl(RunOptions),
+    Debug {
+        path: PathBuf,
+        args: Vec<String>,
+        options: RunOptions,
+    },
+    Test {
+        paths: Vec<PathBuf>,
+        filter: Option<String>,
+        options: RunOptions,
+    },
+    Check {
+        paths: Vec<PathBuf>,
+        warnings: Levels,
+    },
+    Fmt {
+        path: PathBuf,
+        mode: FmtMode,
+    },
+    Disasm(PathBuf),
+    Lsp,
+    Dap,
+    Explain(String),
+    Help,
+    Version,
+}
 
-fn main() {
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() > 2 {
-        eprintln!("usage: {} [filename]", env!("CARGO_BIN_NAME"));
-        return;
+/// Options shared by the commands which run code.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RunOptions {
+    no_prelude: bool,
+    sandbox: bool,
+    dump_tokens: bool,
+    dump_ast: Option<AstFormat>,
+    time: bool,
+    warnings: Levels,
+    /// Only used by run with a file.
+    watch: bool,
+    coverage: bool,
+    lcov: Option<PathBuf>,
+}
+
+impl RunOptions {
+    /// Parses the option if it is one of these, returning whether it was.
+    fn parse(&mut self, arg: &str) -> bool {
+        match arg {
+            "--no-prelude" => self.no_prelude = true,
+            "--sandbox" => self.sandbox = true,
+            "--dump-tokens" => self.dump_tokens = true,
+            "--dump-ast" => self.dump_ast = Some(AstFormat::Sexpr),
+            #[cfg(feature = "serde")]
+            "--dump-ast=json" => self.dump_ast = Some(AstFormat::Json),
+            "--time" => self.time = true,
+            _ => return false,
+        }
+
+        true
     }
 
-    if let Some(path) = args.get(1).map(Path::new) {
-        run_from_file(path);
-    } else {
-        repl();
+    fn interpreter(&self) -> Interpreter {
+        let mut interpreter = if self.no_prelude {
+            Interpreter::without_prelude()
+        } else {
+            Interpreter::default()
+        };
+        if self.sandbox {
+            // Sandboxed scripts can still time themselves.
+            interpreter.set_capabilities(Capabilities {
+                clock: true,
+                ..Capabilities::none()
+            });
+        }
+
+        interpreter
     }
 }
 
-fn repl() {
-    let interpreter = &mut Interpreter::default();
-    loop {
-        print!("> ");
-        if let Err(error) = std::io::stdout().flush() {
-            eprintln!("error: {error}");
-            return;
+/// How running a script ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    /// Errors were found and reported.
+    Failure,
+    /// The script called `exit` with the code, nothing is reported.
+    Exit(i32),
+}
+
+impl From<bool> for Status {
+    fn from(success: bool) -> Self {
+        if success {
+            Status::Success
+        } else {
+            Status::Failure
         }
+    }
+}
 
-        let mut buf = String::new();
-        match std::io::stdin().read_line(&mut buf) {
-            Ok(0) => {
-                println!("exiting...");
-                break;
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Success => ExitCode::SUCCESS,
+            Status::Failure => ExitCode::FAILURE,
+            // Only the low byte of the code is seen by the parent process, as with
+            // `std::process::exit`.
+            Status::Exit(code) => ExitCode::from(code as u8),
+        }
+    }
+}
+
+/// Format `--dump-ast` prints the syntax tree in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum AstFormat {
+    Sexpr,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+enum FmtMode {
+    #[default]
+    Print,
+    Write,
+    Check,
+}
+
+/// Parses the command line arguments, not including the program name.
+fn parse_args(args: Vec<String>) -> Result<Command, String> {
+    let mut args = args.into_iter().peekable();
+    let command = match args.peek().map(String::as_str) {
+        None => return Ok(Command::Repl(RunOptions::default())),
+        Some("--explain") => {
+            args.next();
+            let code = args.next().ok_or("--explain requires an error code")?;
+            if let Some(arg) = args.next() {
+                return Err(format!("unexpected argument '{arg}' for --explain"));
+            }
+            return Ok(Command::Explain(code));
+        }
+        Some(
+            "run" | "repl" | "debug" | "test" | "check" | "fmt" | "disasm" | "lsp" | "dap" | "help",
+        ) => args.next().unwrap(),
+        // Anything else is a file to run, or options of run.
+        Some(_) => "run".to_owned(),
+    };
+
+    let unknown = |arg: &str| format!("unknown option '{arg}' for {command}");
+    let mut path = None;
+    let mut rest = vec![];
+    let mut options = RunOptions::default();
+    let mut fmt_mode = FmtMode::default();
+    let mut eval = None;
+    let mut filter = None;
+    let mut options_ended = false;
+    while let Some(arg) = args.next() {
+        if (path.is_some() || eval.is_some()) && (command == "run" || command == "debug") {
+            // Everything after the file or code belongs to the script.
+            rest.push(arg);
+            continue;
+        }
+
+        if !options_ended {
+            match arg.as_str() {
+                "--" => {
+                    options_ended = true;
+                    continue;
+                }
+                "-h" | "--help" => return Ok(Command::Help),
+                "--version" => return Ok(Command::Version),
+                _ => {}
+            }
+        }
+
+        match command.as_str() {
+            _ if options_ended && path.is_none() => path = Some(PathBuf::from(arg)),
+            "run" if arg == "-e" || arg == "--eval" => {
+                let source = args.next().ok_or_else(|| format!("{arg} requires code"))?;
+                eval = Some(source);
+            }
+            "run" if arg == "--watch" => options.watch = true,
+            "run" if arg == "--coverage" => options.coverage = true,
+            "run" if arg == "--lcov" => {
+                let file = args.next().ok_or("--lcov requires a file")?;
+                options.lcov = Some(PathBuf::from(file));
             }
-            Ok(_) => {
-                run(buf, interpreter);
+            "run" | "repl" | "debug" | "test" | "check"
+                if matches!(arg.as_str(), "--warn" | "--deny" | "--allow") =>
+            {
+                let level = match arg.as_str() {
+                    "--warn" => Level::Warn,
+                    "--deny" => Level::Deny,
+                    _ => Level::Allow,
+                };
+                let name = args
+                    .next()
+                    .ok_or_else(|| format!("{arg} requires a warning name"))?;
+                if !options.warnings.set_by_name(&name, level) {
+                    return Err(format!("unknown warning '{name}'"));
+                }
             }
-            Err(error) => eprintln!("error: {error}"),
+            "run" | "repl" | "debug" if options.parse(&arg) => {}
+            "test" if matches!(arg.as_str(), "--no-prelude" | "--sandbox") => {
+                options.parse(&arg);
+            }
+            "test" if arg == "--filter" => {
+                filter = Some(args.next().ok_or("--filter requires text to match")?);
+            }
+            "fmt" if arg == "--write" => fmt_mode = FmtMode::Write,
+            "fmt" if arg == "--check" => fmt_mode = FmtMode::Check,
+            "check" | "test" if path.is_some() && (options_ended || !arg.starts_with('-')) => {
+                rest.push(arg)
+            }
+            _ if arg.starts_with('-') => return Err(unknown(&arg)),
+            "repl" | "lsp" | "dap" | "help" => {
+                return Err(format!("unexpected argument '{arg}' for {command}"))
+            }
+            _ if path.is_some() => return Err(format!("unexpected argument '{arg}'")),
+            _ => path = Some(PathBuf::from(arg)),
         }
     }
+
+    let path = || path.ok_or_else(|| format!("{command} requires a file"));
+    let command = match command.as_str() {
+        "run" if eval.is_some() && options.watch => {
+            return Err("--watch requires a file".to_owned())
+        }
+        "run" if eval.is_some() && (options.coverage || options.lcov.is_some()) => {
+            return Err("coverage requires a file".to_owned())
+        }
+        "run" if eval.is_some() => Command::Eval {
+            source: eval.unwrap(),
+            args: rest,
+            options,
+        },
+        "run" => Command::Run {
+            path: path()?,
+            args: rest,
+            options,
+        },
+        "repl" => Command::Repl(options),
+        "debug" => Command::Debug {
+            path: path()?,
+            args: rest,
+            options,
+        },
+        "test" => Command::Test {
+            paths: std::iter::once(path()?)
+                .chain(rest.into_iter().map(PathBuf::from))
+                .collect(),
+            filter,
+            options,
+        },
+        "check" => Command::Check {
+            paths: std::iter::once(path()?)
+                .chain(rest.into_iter().map(PathBuf::from))
+                .collect(),
+            warnings: options.warnings,
+        },
+        "fmt" => Command::Fmt {
+            path: path()?,
+            mode: fmt_mode,
+        },
+        "disasm" => Command::Disasm(path()?),
+        "lsp" => Command::Lsp,
+        "dap" => Command::Dap,
+        _ => Command::Help,
+    };
+
+    Ok(command)
+}
+
+fn main() -> ExitCode {
+    let command = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let status = match command {
+        Command::Run {
+            path,
+            args,
+            options,
+        } if options.watch => watch(&path, args, &options),
+        Command::Run {
+            path,
+            args,
+            options,
+        } => run_from_file(&path, args, &options),
+        Command::Eval {
+            source,
+            args,
+            options,
+        } => eval(source, args, &options),
+        Command::Repl(options) => repl::run(&options),
+        Command::Test {
+            paths,
+            filter,
+            options,
+        } => test_runner::run(&paths, filter.as_deref(), &options).into(),
+        Command::Check { paths, warnings } => {
+            // Every file is checked even after one fails.
+            let mut success = true;
+            for path in &paths {
+                success &= check(path, &warnings);
+            }
+            success.into()
+        }
+        Command::Fmt { path, mode } => fmt(&path, mode).into(),
+        Command::Disasm(_) => {
+            eprintln!("error: scripts are interpreted directly, there is no bytecode to show");
+            Status::Failure
+        }
+        Command::Debug {
+            path,
+            args,
+            options,
+        } => debugger::run(&path, args, &options),
+        Command::Lsp => lsp::run().into(),
+        Command::Dap => dap::run().into(),
+        Command::Explain(code) => explain(&code).into(),
+        Command::Help => {
+            println!("{USAGE}");
+            Status::Success
+        }
+        Command::Version => {
+            println!("unnamed-language {}", env!("CARGO_PKG_VERSION"));
+            Status::Success
+        }
+    };
+
+    status.into()
 }
 
-fn run_from_file(path: &Path) {
+/// Reads a script, reporting an error if it can't be.
+fn read_source(path: &Path) -> Option<String> {
     if !path.is_file() {
         eprintln!("error: file {:?} not found", path);
-        return;
+        return None;
     }
 
     let Ok(mut file) = File::open(path) else {
         eprintln!("error: file {:?} could not be opened", path);
-        return;
+        return None;
     };
 
     let mut source = String::new();
     if file.read_to_string(&mut source).is_err() {
         eprintln!("error: file {:?} could not be read", path);
-        return;
+        return None;
+    }
+
+    Some(source)
+}
+
+fn run_from_file(path: &Path, args: Vec<String>, options: &RunOptions) -> Status {
+    let Some(source) = read_source(path) else {
+        return Status::Failure;
+    };
+    let name = path.display().to_string();
+    if options.dump_tokens {
+        return dump_tokens(&source, &name).into();
+    }
+    if let Some(format) = options.dump_ast {
+        return dump_ast(&source, &name, format).into();
+    }
+
+    let mut interpreter = options.interpreter();
+    interpreter.set_args(args);
+    // Plugins are looked for next to the script importing them.
+    #[cfg(feature = "plugins")]
+    interpreter.add_plugin_path(path.parent().unwrap_or(Path::new("")).to_owned());
+    timed(options.time, &mut interpreter, |interpreter| {
+        run(&source, &name, interpreter, options)
+    })
+}
+
+/// Runs the script each time it is modified until interrupted, clearing the screen first and
+/// reporting how long it took afterwards. Scripts which call `exit` only end the run, not the
+/// watch.
+fn watch(path: &Path, args: Vec<String>, options: &RunOptions) -> Status {
+    const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+    let modified = || {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    let mut last_modified = modified();
+    loop {
+        print!("\x1b[2J\x1b[H");
+        let start = Instant::now();
+        let status = match run_from_file(path, args.clone(), options) {
+            Status::Success => "finished".to_owned(),
+            Status::Failure => "failed".to_owned(),
+            Status::Exit(code) => format!("exited with code {code}"),
+        };
+        println!(
+            "\n[{status} in {:.3}s, waiting for changes to {}]",
+            start.elapsed().as_secs_f64(),
+            path.display()
+        );
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = modified();
+            // A file which is being replaced may briefly not exist.
+            if current.is_some() && current != last_modified {
+                last_modified = current;
+                break;
+            }
+        }
+    }
+}
+
+/// Name diagnostics use for code given on the command line.
+const EVAL_NAME: &str = "<eval>";
+
+/// Runs code given on the command line.
+fn eval(source: String, args: Vec<String>, options: &RunOptions) -> Status {
+    let source = complete_statement(source);
+    if options.dump_tokens {
+        return dump_tokens(&source, EVAL_NAME).into();
     }
+    if let Some(format) = options.dump_ast {
+        return dump_ast(&source, EVAL_NAME, format).into();
+    }
+
+    let mut interpreter = options.interpreter();
+    interpreter.set_args(args);
+    timed(options.time, &mut interpreter, |interpreter| {
+        evaluate(source, EVAL_NAME, interpreter, &options.warnings)
+    })
+}
+
+/// Runs `f`, printing how long it took and how much the heap was used afterwards if `enabled`.
+fn timed<T>(
+    enabled: bool,
+    interpreter: &mut Interpreter,
+    f: impl FnOnce(&mut Interpreter) -> T,
+) -> T {
+    if !enabled {
+        return f(interpreter);
+    }
+
+    interpreter.reset_usage();
+    let start = Instant::now();
+    let result = f(interpreter);
+    let elapsed = start.elapsed();
+    let usage = interpreter.usage();
+    let stats = usage.heap;
+    eprintln!(
+        "time: {elapsed:.2?}, statements: {}, allocations: {} ({} bytes, peak {} bytes), \
+         collections: {} ({} objects freed)",
+        usage.statements,
+        stats.allocations,
+        stats.bytes_allocated,
+        usage.peak_bytes_allocated,
+        stats.collections,
+        stats.objects_freed
+    );
 
-    run(source, &mut Interpreter::default());
+    result
 }
 
-fn run(source: String, interpreter: &mut Interpreter) {
-    let mut parser = Parser::new(&source);
-    match parser.parse() {
+/// Adds the semicolon which one-liners usually leave out after the final statement.
+fn complete_statement(mut source: String) -> String {
+    let trimmed = source.trim_end();
+    if !trimmed.is_empty() && !trimmed.ends_with([';', '}']) {
+        source.truncate(trimmed.len());
+        source.push(';');
+    }
+
+    source
+}
+
+/// Runs the code, printing its value unless it is nil.
+fn evaluate(
+    source: String,
+    name: &str,
+    interpreter: &mut Interpreter,
+    warnings: &Levels,
+) -> Status {
+    match evaluate_value(source, name, interpreter, warnings) {
+        Ok(Value::Nil) => Status::Success,
+        Ok(value) => {
+            println!("{}", inspect(value, &InspectOptions::default()));
+            Status::Success
+        }
+        Err(status) => status,
+    }
+}
+
+/// Runs the code, returning its value or reporting its errors.
+fn evaluate_value<'i>(
+    source: String,
+    name: &str,
+    interpreter: &'i mut Interpreter,
+    warnings: &Levels,
+) -> Result<&'i Value, Status> {
+    let source = complete_statement(source);
+    let script = match Parser::new(&source).parse() {
+        Ok(script) => script,
+        Err(error) => {
+            report(&Diagnostic::from(&error), &source, name);
+            return Err(Status::Failure);
+        }
+    };
+    if !report_warnings(&script, &source, name, warnings) {
+        return Err(Status::Failure);
+    }
+
+    interpreter
+        .evaluate(&script)
+        .map_err(|error| report_runtime_error(&error, &source, name))
+}
+
+/// Prints the location, kind and text of each token, returning whether there weren't any
+/// scanning errors.
+fn dump_tokens(source: &str, name: &str) -> bool {
+    let mut success = true;
+    for token in Scanner::new(source) {
+        match token {
+            Ok(token) => {
+                let location = token.location().to_string();
+                let kind = format!("{:?}", token.kind());
+                println!("{location:<8} {kind:<12} {}", token.slice());
+            }
+            Err(error) => {
+                report(&Diagnostic::from(&error), source, name);
+                success = false;
+            }
+        }
+    }
+
+    success
+}
+
+/// Prints the syntax tree with the locations of its nodes.
+fn dump_ast(source: &str, name: &str, format: AstFormat) -> bool {
+    match Parser::new(source).parse() {
         Ok(script) => {
-            if let Err(err) = interpreter.interpret(&script) {
-                eprintln!("runtime error: {}", err);
+            match format {
+                AstFormat::Sexpr => {
+                    let options = SexprOptions {
+                        spans: true,
+                        ..SexprOptions::default()
+                    };
+                    println!("{}", sexpr::script(&script, &options));
+                }
+                #[cfg(feature = "serde")]
+                AstFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&script).expect("syntax trees should serialize")
+                ),
             }
+            true
+        }
+        Err(error) => {
+            report(&Diagnostic::from(&error), source, name);
+            false
         }
+    }
+}
+
+fn explain(code: &str) -> bool {
+    let explanation = ErrorCode::parse(code).and_then(|code| Some((code, code.explanation()?)));
+    match explanation {
+        Some((code, explanation)) => {
+            println!("{code}\n\n{explanation}");
+            true
+        }
+        None => {
+            eprintln!("error: '{code}' is not an error code");
+            false
+        }
+    }
+}
+
+/// Reports the diagnostics of the script without running it, returning whether it has no
+/// errors.
+fn check(path: &Path, warnings: &Levels) -> bool {
+    let Some(source) = read_source(path) else {
+        return false;
+    };
+
+    let name = path.display().to_string();
+    let diagnostics = check::check(&source, warnings);
+    for diagnostic in &diagnostics {
+        report(diagnostic, &source, &name);
+    }
+
+    !check::has_errors(&diagnostics)
+}
+
+fn fmt(path: &Path, mode: FmtMode) -> bool {
+    let Some(source) = read_source(path) else {
+        return false;
+    };
+
+    let script = match Parser::new(&source).parse_syntax() {
+        Ok(script) => script,
         Err(error) => {
-            eprintln!("parsing error: {}", error.message());
+            report(
+                &Diagnostic::from(&error),
+                &source,
+                &path.display().to_string(),
+            );
+            return false;
+        }
+    };
+
+    let formatted = formatter::format(&script);
+    match mode {
+        FmtMode::Print => {
+            print!("{formatted}");
+            true
+        }
+        FmtMode::Write => {
+            if formatted == source {
+                return true;
+            }
+
+            match std::fs::write(path, formatted) {
+                Ok(()) => true,
+                Err(error) => {
+                    eprintln!("error: file {:?} could not be written: {error}", path);
+                    false
+                }
+            }
+        }
+        FmtMode::Check => {
+            let formatted_already = formatted == source;
+            if !formatted_already {
+                eprintln!("{} is not formatted", path.display());
+            }
+            formatted_already
         }
     }
 }
+
+/// Prints the diagnostic along with the part of the source it is about, in color when writing
+/// to a terminal unless `NO_COLOR` is set.
+fn report(diagnostic: &Diagnostic, source: &str, name: &str) {
+    let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let renderer = Renderer::new(source, name).with_color(color);
+    eprint!("{}", renderer.render(diagnostic));
+}
+
+/// Reports the warnings of the script which aren't allowed, returning false if any of them are
+/// denied.
+fn report_warnings(script: &Script, source: &str, name: &str, levels: &Levels) -> bool {
+    let diagnostics: Vec<_> = script
+        .warnings
+        .iter()
+        .filter_map(|warning| Diagnostic::from_warning(warning, levels))
+        .collect();
+    for diagnostic in &diagnostics {
+        report(diagnostic, source, name);
+    }
+
+    !check::has_errors(&diagnostics)
+}
+
+/// Runs the source, reporting any errors.
+fn run(source: &str, name: &str, interpreter: &mut Interpreter, options: &RunOptions) -> Status {
+    let mut parser = Parser::new(source);
+    let script = match parser.parse() {
+        Ok(script) => script,
+        Err(error) => {
+            report(&Diagnostic::from(&error), source, name);
+            return Status::Failure;
+        }
+    };
+    if !report_warnings(&script, source, name, &options.warnings) {
+        return Status::Failure;
+    }
+
+    let coverage = (options.coverage || options.lcov.is_some()).then(|| Coverage::new(&script));
+    if let Some(coverage) = &coverage {
+        interpreter.set_debug_hook(Some(coverage.hook()));
+    }
+    let result = interpreter.interpret(&script);
+    // Reported even if the script called `exit`.
+    if let Some(coverage) = &coverage {
+        if !report_coverage(coverage, source, name, options) {
+            return Status::Failure;
+        }
+    }
+
+    match result {
+        Ok(()) => Status::Success,
+        Err(error) => report_runtime_error(&error, source, name),
+    }
+}
+
+/// Prints or writes the coverage of a script as requested by the options, returning whether the
+/// report could be written.
+fn report_coverage(coverage: &Coverage, source: &str, name: &str, options: &RunOptions) -> bool {
+    if options.coverage {
+        let (hit, total) = coverage.summary();
+        let percent = if total == 0 {
+            100.0
+        } else {
+            hit as f64 / total as f64 * 100.0
+        };
+        eprint!("{}", coverage.annotate(source));
+        eprintln!("coverage: {hit} of {total} lines ran ({percent:.1}%)");
+    }
+
+    if let Some(path) = &options.lcov {
+        if let Err(error) = std::fs::write(path, coverage.lcov(name)) {
+            eprintln!("error: coverage could not be written to {path:?}: {error}");
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Reports an error and its backtrace, unless the script called `exit` in which case its code is
+/// returned instead.
+fn report_runtime_error(error: &RuntimeError, source: &str, name: &str) -> Status {
+    if let RuntimeErrorKind::Exit(code) = error.kind() {
+        return Status::Exit(*code);
+    }
+
+    report_error(error, source, name);
+    Status::Failure
+}
+
+/// Reports an error and its backtrace.
+fn report_error(error: &RuntimeError, source: &str, name: &str) {
+    report(&Diagnostic::from(error), source, name);
+
+    // Deep recursion produces huge backtraces, only the ends are interesting.
+    const MAX_FRAMES: usize = 20;
+    let backtrace = error.backtrace();
+    for (i, frame) in backtrace.iter().enumerate() {
+        if backtrace.len() > MAX_FRAMES {
+            let omitted = backtrace.len() - MAX_FRAMES;
+            if i == MAX_FRAMES / 2 {
+                eprintln!("  ... {omitted} frames omitted");
+            }
+            if (MAX_FRAMES / 2..MAX_FRAMES / 2 + omitted).contains(&i) {
+                continue;
+            }
+        }
+
+        match frame.location {
+            Some(location) => eprintln!("  in {} at {location}", frame.function),
+            None => eprintln!("  in {}", frame.function),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use unnamed_language::compiler::warnings::{Level, Levels};
+
+    use super::{
+        complete_statement, parse_args, run, AstFormat, Command, FmtMode, RunOptions, Status,
+    };
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|&arg| arg.to_owned()).collect())
+    }
+
+    #[test]
+    fn completing_statements() {
+        assert_eq!(complete_statement("1 + 2\n".to_owned()), "1 + 2;");
+        assert_eq!(complete_statement("x = 1;\n".to_owned()), "x = 1;\n");
+        assert_eq!(complete_statement("if x { }".to_owned()), "if x { }");
+        assert_eq!(complete_statement(" \n".to_owned()), " \n");
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(parse(&[]), Ok(Command::Repl(RunOptions::default())));
+        assert_eq!(
+            parse(&["--sandbox", "a.ul", "--no-prelude", "x"]),
+            Ok(Command::Run {
+                path: PathBuf::from("a.ul"),
+                args: vec!["--no-prelude".to_owned(), "x".to_owned()],
+                options: RunOptions {
+                    sandbox: true,
+                    ..RunOptions::default()
+                },
+            })
+        );
+        assert_eq!(
+            parse(&["fmt", "--check", "a.ul"]),
+            Ok(Command::Fmt {
+                path: PathBuf::from("a.ul"),
+                mode: FmtMode::Check
+            })
+        );
+        assert_eq!(
+            parse(&["-e", "args()", "x"]),
+            Ok(Command::Eval {
+                source: "args()".to_owned(),
+                args: vec!["x".to_owned()],
+                options: RunOptions::default(),
+            })
+        );
+        assert_eq!(
+            parse(&["repl", "--dump-ast"]),
+            Ok(Command::Repl(RunOptions {
+                dump_ast: Some(AstFormat::Sexpr),
+                ..RunOptions::default()
+            }))
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            parse(&["repl", "--dump-ast=json"]),
+            Ok(Command::Repl(RunOptions {
+                dump_ast: Some(AstFormat::Json),
+                ..RunOptions::default()
+            }))
+        );
+        assert_eq!(
+            parse(&["--watch", "a.ul"]),
+            Ok(Command::Run {
+                path: PathBuf::from("a.ul"),
+                args: vec![],
+                options: RunOptions {
+                    watch: true,
+                    ..RunOptions::default()
+                },
+            })
+        );
+        let mut warnings = Levels::default();
+        warnings.set_by_name("all", Level::Allow);
+        warnings.set_by_name("unused_variable", Level::Deny);
+        assert_eq!(
+            parse(&["repl", "--allow", "all", "--deny", "unused_variable"]),
+            Ok(Command::Repl(RunOptions {
+                warnings,
+                ..RunOptions::default()
+            }))
+        );
+        assert!(parse(&["--warn", "unused", "a.ul"]).is_err());
+        assert!(parse(&["--deny"]).is_err());
+        assert!(parse(&["--watch", "-e", "1"]).is_err());
+        assert_eq!(
+            parse(&["--coverage", "--lcov", "out.info", "a.ul"]),
+            Ok(Command::Run {
+                path: PathBuf::from("a.ul"),
+                args: vec![],
+                options: RunOptions {
+                    coverage: true,
+                    lcov: Some(PathBuf::from("out.info")),
+                    ..RunOptions::default()
+                },
+            })
+        );
+        assert!(parse(&["--coverage", "-e", "1"]).is_err());
+        assert!(parse(&["--lcov"]).is_err());
+        assert!(parse(&["repl", "--watch"]).is_err());
+        assert_eq!(
+            parse(&["--explain", "E0203"]),
+            Ok(Command::Explain("E0203".to_owned()))
+        );
+        assert!(parse(&["--explain"]).is_err());
+        assert_eq!(parse(&["help"]), Ok(Command::Help));
+        for command in [
+            "run", "repl", "debug", "test", "check", "fmt", "disasm", "lsp", "dap",
+        ] {
+            assert_eq!(parse(&[command, "--help"]), Ok(Command::Help), "{command}");
+            assert_eq!(parse(&[command, "-h"]), Ok(Command::Help), "{command}");
+            assert_eq!(
+                parse(&[command, "--version"]),
+                Ok(Command::Version),
+                "{command}"
+            );
+        }
+        assert_eq!(parse(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse(&["--version"]), Ok(Command::Version));
+        assert_eq!(parse(&["check", "a.ul", "--help"]), Ok(Command::Help));
+        // Options after the file belong to the script.
+        assert_eq!(
+            parse(&["a.ul", "--help"]),
+            Ok(Command::Run {
+                path: PathBuf::from("a.ul"),
+                args: vec!["--help".to_owned()],
+                options: RunOptions::default(),
+            })
+        );
+        assert!(matches!(parse(&["--", "--help"]), Ok(Command::Run { .. })));
+        assert_eq!(parse(&["lsp"]), Ok(Command::Lsp));
+        assert_eq!(parse(&["dap"]), Ok(Command::Dap));
+        assert_eq!(
+            parse(&["test", "--sandbox", "--filter", "add", "a.ul", "b.ul"]),
+            Ok(Command::Test {
+                paths: vec![PathBuf::from("a.ul"), PathBuf::from("b.ul")],
+                filter: Some("add".to_owned()),
+                options: RunOptions {
+                    sandbox: true,
+                    ..RunOptions::default()
+                },
+            })
+        );
+        assert!(parse(&["test", "--time", "a.ul"]).is_err());
+        assert_eq!(
+            parse(&["debug", "--sandbox", "a.ul", "x"]),
+            Ok(Command::Debug {
+                path: PathBuf::from("a.ul"),
+                args: vec!["x".to_owned()],
+                options: RunOptions {
+                    sandbox: true,
+                    ..RunOptions::default()
+                },
+            })
+        );
+        assert!(parse(&["lsp", "a.ul"]).is_err());
+        assert!(parse(&["run", "--eval"]).is_err());
+        assert_eq!(
+            parse(&["--", "-a.ul", "--", "-b"]),
+            Ok(Command::Run {
+                path: PathBuf::from("-a.ul"),
+                args: vec!["--".to_owned(), "-b".to_owned()],
+                options: RunOptions::default(),
+            })
+        );
+        assert!(parse(&["check"]).is_err());
+        let mut denied = Levels::default();
+        denied.set_by_name("all", Level::Deny);
+        assert_eq!(
+            parse(&["check", "--deny", "all", "a.ul", "--", "-b.ul"]),
+            Ok(Command::Check {
+                paths: vec![PathBuf::from("a.ul"), PathBuf::from("-b.ul")],
+                warnings: denied,
+            })
+        );
+        assert!(parse(&["check", "--write", "a.ul"]).is_err());
+        assert!(parse(&["repl", "a.ul"]).is_err());
+    }
+
+    #[test]
+    fn exit() {
+        // Scripts which call `exit` return its code rather than ending the process, so that
+        // `--watch` can carry on.
+        let options = RunOptions::default();
+        let mut interpreter = options.interpreter();
+        let status = run("exit(3); print(1);", "<test>", &mut interpreter, &options);
+        assert_eq!(status, Status::Exit(3));
+        assert_eq!(
+            run("1 +;", "<test>", &mut interpreter, &options),
+            Status::Failure
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Interactive sessions, code is read a statement at a time and the values of expressions are
+//! printed.
+//!
+//! Lines starting with `:` are commands to the REPL itself rather than code, see [`COMMANDS`].
+//! The value of the last expression which wasn't nil is kept in the variable `_`.
+
+use std::{collections::HashSet, path::Path};
+
+use unnamed_language::{
+    compiler::Parser,
+    interpreter::{
+        inspect::{inspect, InspectOptions},
+        value::Value,
+        Interpreter,
+    },
+};
+
+use crate::{
+    complete_statement, dump_ast, dump_tokens, editor::Editor, evaluate_value, read_source,
+    run as run_source, timed, RunOptions, Status,
+};
+
+/// Name diagnostics use for code entered in the REPL.
+const REPL_NAME: &str = "<repl>";
+
+/// Names, arguments and descriptions of the commands, listed by `:help`.
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("help", "", "print this message"),
+    (
+        "env",
+        "",
+        "list the variables defined in this session and their values",
+    ),
+    ("load", "<file>", "run a script in this session"),
+    ("clear", "", "start over with a new session"),
+    (
+        "type",
+        "<expr>",
+        "print the type of the value of an expression",
+    ),
+    (
+        "time",
+        "<code>",
+        "run code and print how long it took and how much it allocated",
+    ),
+    ("quit", "", "end the session"),
+];
+
+/// Runs a session until it is ended, or until code entered calls `exit`, returning its code.
+pub fn run(options: &RunOptions) -> Status {
+    let mut session = Session::new(options);
+    let mut editor = match Editor::new(Editor::default_history_path()) {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("error: {error}");
+            return Status::Failure;
+        }
+    };
+    let mut buf = String::new();
+    loop {
+        let prompt = if buf.is_empty() { "> " } else { ". " };
+        match editor.read_line(prompt) {
+            Ok(None) => break,
+            Ok(Some(line)) => {
+                editor.add_history(&line);
+                if buf.is_empty() && line.trim_start().starts_with(':') {
+                    match session.command(line.trim()) {
+                        Flow::Continue => continue,
+                        Flow::Quit => break,
+                        Flow::Exit(code) => return Status::Exit(code),
+                    }
+                }
+
+                // A blank line ends input which is still incomplete, reporting the error.
+                let blank = line.trim().is_empty();
+                buf.push_str(&line);
+                buf.push('\n');
+                if !blank && needs_more_input(&buf) {
+                    continue;
+                }
+
+                let time = session.options.time;
+                if let Flow::Exit(code) = session.input(std::mem::take(&mut buf), time) {
+                    return Status::Exit(code);
+                }
+            }
+            Err(error) => {
+                eprintln!("error: {error}");
+                return Status::Failure;
+            }
+        }
+    }
+
+    println!("exiting...");
+    Status::Success
+}
+
+enum Flow {
+    Continue,
+    Quit,
+    /// Code called `exit` with the code.
+    Exit(i32),
+}
+
+impl From<Status> for Flow {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Exit(code) => Flow::Exit(code),
+            Status::Success | Status::Failure => Flow::Continue,
+        }
+    }
+}
+
+struct Session<'a> {
+    interpreter: Interpreter,
+    options: &'a RunOptions,
+    /// Globals which were defined before any input, these are left out by `:env`.
+    builtins: HashSet<String>,
+}
+
+impl<'a> Session<'a> {
+    fn new(options: &'a RunOptions) -> Self {
+        let interpreter = options.interpreter();
+        let builtins = interpreter
+            .globals()
+            .map(|(name, _)| name.to_owned())
+            .collect();
+
+        Self {
+            interpreter,
+            options,
+            builtins,
+        }
+    }
+
+    fn input(&mut self, source: String, time: bool) -> Flow {
+        if self.options.dump_tokens {
+            dump_tokens(&source, REPL_NAME);
+            return Flow::Continue;
+        }
+        if let Some(format) = self.options.dump_ast {
+            dump_ast(&source, REPL_NAME, format);
+            return Flow::Continue;
+        }
+
+        timed(
+            time,
+            &mut self.interpreter,
+            |interpreter| match evaluate_value(
+                source,
+                REPL_NAME,
+                interpreter,
+                &self.options.warnings,
+            ) {
+                Ok(Value::Nil) => Flow::Continue,
+                Ok(value) => {
+                    println!("{}", inspect(value, &InspectOptions::default()));
+                    let value = value.clone();
+                    interpreter.define_global("_", value);
+                    Flow::Continue
+                }
+                Err(status) => status.into(),
+            },
+        )
+    }
+
+    /// Runs a command, the line is expected to start with `:`.
+    fn command(&mut self, line: &str) -> Flow {
+        let line = &line[1..];
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+
+        match (name, arg) {
+            ("help", "") => {
+                println!("commands:");
+                for (name, arg, description) in COMMANDS {
+                    let usage = format!(":{name} {arg}");
+                    println!("  {usage:<16} {description}");
+                }
+            }
+            ("env", "") => self.env(),
+            ("load", path) if !path.is_empty() => {
+                if let Some(source) = read_source(Path::new(path)) {
+                    return run_source(&source, path, &mut self.interpreter, self.options).into();
+                }
+            }
+            ("clear", "") => *self = Self::new(self.options),
+            ("type", expr) if !expr.is_empty() => {
+                let warnings = &self.options.warnings;
+                match evaluate_value(expr.to_owned(), REPL_NAME, &mut self.interpreter, warnings) {
+                    Ok(value) => println!("{}", value.type_name()),
+                    Err(status) => return status.into(),
+                }
+            }
+            ("time", code) if !code.is_empty() => return self.input(code.to_owned(), true),
+            ("quit" | "q", "") => return Flow::Quit,
+            _ => match COMMANDS.iter().find(|(command, ..)| *command == name) {
+                Some((_, arg, _)) => eprintln!("error: usage: :{name} {arg}"),
+                None => eprintln!("error: unknown command ':{name}', see :help"),
+            },
+        }
+
+        Flow::Continue
+    }
+
+    fn env(&self) {
+        let mut globals: Vec<_> = self
+            .interpreter
+            .globals()
+            .filter(|(name, _)| !self.builtins.contains(*name))
+            .collect();
+        globals.sort_by_key(|(name, _)| *name);
+
+        for (name, value) in globals {
+            println!("{name} = {}", inspect(value, &InspectOptions::compact()));
+        }
+    }
+}
+
+/// Whether the source is the start of valid code, such as a block which hasn't been closed yet,
+/// rather than complete or invalid.
+fn needs_more_input(source: &str) -> bool {
+    match Parser::new(source).parse() {
+        Ok(_) => false,
+        // The missing semicolon after a final expression is added when evaluating it.
+        Err(error) if error.is_incomplete() => Parser::new(&complete_statement(source.to_owned()))
+            .parse()
+            .is_err(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::needs_more_input;
+
+    #[test]
+    fn more_input() {
+        assert!(needs_more_input("func f() {\n"));
+        assert!(needs_more_input("let x = 1 +\n"));
+        assert!(needs_more_input("print(1,\n"));
+        assert!(!needs_more_input("1 + 2\n"));
+        assert!(!needs_more_input("if x { }\n"));
+        assert!(!needs_more_input("let 1;\n"));
+    }
+}
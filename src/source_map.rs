@@ -0,0 +1,201 @@
+//! Sources of the files a program is made of.
+//!
+//! Spans and locations refer to their file by a [`FileId`] rather than borrowing its source, so
+//! errors and diagnostics can outlive the source, be sent across threads and refer to several
+//! files. The source is looked up in the [`SourceMap`] when it is needed, such as to render a
+//! diagnostic.
+
+use std::iter;
+
+use crate::{
+    compiler::parser::{
+        token::{Location, Span},
+        Parser,
+    },
+    diagnostic::Renderer,
+};
+
+/// Index of a file in a [`SourceMap`]. The default id is that of the first file added, which is
+/// also the file of sources parsed without a source map.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileId(u32);
+
+#[derive(Debug)]
+pub struct SourceFile {
+    /// Name the file is referred to by, usually its path.
+    name: String,
+    source: String,
+    lines: LineIndex,
+}
+
+impl SourceFile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn lines(&self) -> &LineIndex {
+        &self.lines
+    }
+}
+
+/// Offsets at which the lines of a source start, so the line and column of an offset can be
+/// found without scanning the source from the beginning.
+#[derive(Debug)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let starts = iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { starts }
+    }
+
+    /// Number, starting at 1, of the line the offset is on.
+    pub fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+
+    /// Finds the line and column of the span in the source the index was made from.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span doesn't start at a character boundary of the source.
+    pub fn location(&self, source: &str, span: Span) -> Location {
+        let line = self.line(span.start());
+        let column = source[self.starts[line - 1]..span.start()].chars().count() + 1;
+        Location {
+            file: span.file(),
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let id = u32::try_from(self.files.len()).expect("too many files in the source map");
+        let source = source.into();
+        self.files.push(SourceFile {
+            name: name.into(),
+            lines: LineIndex::new(&source),
+            source,
+        });
+
+        FileId(id)
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0 as usize)
+    }
+
+    /// Returns a parser for the file, whose locations refer to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file isn't in this source map.
+    pub fn parser(&self, file: FileId) -> Parser<'_> {
+        let source = self
+            .get(file)
+            .expect("file should be in the source map")
+            .source();
+        Parser::new(source).with_file(file)
+    }
+
+    /// Returns the text of the span, or `None` if it isn't in any file of this source map.
+    pub fn slice(&self, span: Span) -> Option<&str> {
+        self.get(span.file())?.source.get(span.start()..span.end())
+    }
+
+    /// Finds the line and column of the span, or returns `None` if it isn't in any file of this
+    /// source map.
+    pub fn location(&self, span: Span) -> Option<Location> {
+        let file = self.get(span.file())?;
+        file.source.get(span.start()..span.end())?;
+        Some(file.lines.location(&file.source, span))
+    }
+
+    /// Returns a renderer for diagnostics which shows the source of whichever file of this
+    /// source map they are in, and names the file for diagnostics without a location.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file isn't in this source map.
+    pub fn renderer(&self, file: FileId) -> Renderer<'_> {
+        let default = self.get(file).expect("file should be in the source map");
+        Renderer::new(default.source(), default.name()).with_source_map(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineIndex, SourceMap};
+    use crate::{
+        compiler::parser::token::{Location, Span},
+        diagnostic::Diagnostic,
+    };
+
+    #[test]
+    fn lines() {
+        let source = "a\n\nbé c\n";
+        let lines = LineIndex::new(source);
+        for offset in [0, 1, 2, 3, 6, 7, 8, 9] {
+            let span = Span::new(Default::default(), offset, offset);
+            assert_eq!(
+                lines.location(source, span),
+                Location::new(source, span),
+                "{offset}"
+            );
+        }
+        assert_eq!(lines.line(9), 4);
+    }
+
+    #[test]
+    fn files() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.ul", "print(1);");
+        let lib = map.add("lib.ul", "let x = 1;\nlet y = 1 2;");
+        assert_ne!(main, lib);
+        assert!(map.parser(main).parse().is_ok());
+
+        let error = map.parser(lib).parse().unwrap_err();
+        assert_eq!(error.location().file, lib);
+        assert_eq!(map.slice(error.location().span()), Some("2"));
+
+        // Errors don't borrow the source, so they can outlive it and be sent to other threads.
+        let error = std::thread::spawn(move || Diagnostic::from(&error))
+            .join()
+            .unwrap();
+        let expected = "\
+error[E0100]: expected ';' got <number>
+ --> lib.ul:2:11
+  |
+2 | let y = 1 2;
+  |           ^
+";
+        assert_eq!(map.renderer(main).render(&error), expected);
+        let expected = "error: oops\n --> main.ul\n";
+        assert_eq!(
+            map.renderer(main).render(&Diagnostic::error("oops", None)),
+            expected
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Runs the tests scripts declare with `test "name" { ... }`. Each script runs in an interpreter
+//! of its own, its top level code runs first and its tests then run one after another in the
+//! order they were declared, so tests see the variables and functions of the script.
+
+use std::path::Path;
+
+use unnamed_language::{
+    compiler::Parser,
+    diagnostic::Diagnostic,
+    interpreter::{RuntimeError, RuntimeErrorKind},
+};
+
+use crate::{read_source, report, report_error, report_warnings, RunOptions};
+
+/// Counts of the tests of all scripts.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+    filtered_out: usize,
+    /// Scripts which couldn't be read, parsed or run, so none of their tests ran.
+    broken: usize,
+}
+
+/// Runs the tests of the scripts whose names contain `filter`, returning whether they all
+/// passed.
+pub fn run(paths: &[impl AsRef<Path>], filter: Option<&str>, options: &RunOptions) -> bool {
+    let mut summary = Summary::default();
+    for path in paths {
+        run_script(path.as_ref(), filter, options, &mut summary);
+    }
+
+    let result = if summary.failed == 0 && summary.broken == 0 {
+        "ok"
+    } else {
+        "FAILED"
+    };
+    print!(
+        "\ntest result: {result}. {} passed, {} failed, {} filtered out",
+        summary.passed, summary.failed, summary.filtered_out
+    );
+    if summary.broken > 0 {
+        print!(", {} scripts failed to run", summary.broken);
+    }
+    println!();
+
+    result == "ok"
+}
+
+fn run_script(path: &Path, filter: Option<&str>, options: &RunOptions, summary: &mut Summary) {
+    let Some(source) = read_source(path) else {
+        summary.broken += 1;
+        return;
+    };
+    let name = path.display().to_string();
+    let script = match Parser::new(&source).parse() {
+        Ok(script) => script,
+        Err(error) => {
+            report(&Diagnostic::from(&error), &source, &name);
+            summary.broken += 1;
+            return;
+        }
+    };
+    if !report_warnings(&script, &source, &name, &options.warnings) {
+        summary.broken += 1;
+        return;
+    }
+
+    let mut interpreter = options.interpreter();
+    if let Err(error) = interpreter.interpret(&script) {
+        report_failure(&error, &source, &name);
+        summary.broken += 1;
+        return;
+    }
+
+    let selected: Vec<_> = interpreter
+        .tests()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, test)| filter.is_none_or(|filter| test.contains(filter)))
+        .map(|(index, test)| (index, test.to_owned()))
+        .collect();
+    summary.filtered_out += interpreter.tests().len() - selected.len();
+
+    println!("running {} tests in {name}", selected.len());
+    for (index, test) in selected {
+        match interpreter.run_test(index) {
+            Ok(()) => {
+                println!("test {test} ... ok");
+                summary.passed += 1;
+            }
+            Err(error) => {
+                println!("test {test} ... FAILED");
+                report_failure(&error, &source, &name);
+                summary.failed += 1;
+            }
+        }
+    }
+}
+
+/// Reports why a test or script failed, calling `exit` is a failure rather than ending the run.
+fn report_failure(error: &RuntimeError, source: &str, name: &str) {
+    match error.kind() {
+        RuntimeErrorKind::Exit(code) => eprintln!("error: exit was called with code {code}"),
+        _ => report_error(error, source, name),
+    }
+}
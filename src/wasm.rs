@@ -0,0 +1,76 @@
+//! JavaScript bindings, built when compiling to wasm32-unknown-unknown so that scripts can run in
+//! the browser, for example in a playground. Build them with `wasm-pack build --target web`:
+//!
+//! ```js
+//! import init, { evaluate } from "./pkg/unnamed_language.js";
+//!
+//! await init();
+//! const result = evaluate("print(1 + 2);");
+//! console.log(result.output, result.diagnostics);
+//! ```
+//!
+//! There are no files or processes in the browser, so the `fs` and `process` builtins aren't
+//! defined.
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    diagnostic::{Diagnostic, Renderer},
+    interpreter::{capabilities::Capabilities, io::BufferIo},
+    Engine,
+};
+
+/// Statements a script may execute, scripts can't be interrupted on the browser's main thread so
+/// this is what stops infinite loops.
+const FUEL: u64 = 10_000_000;
+/// Bytes a script may allocate, and separately print.
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+/// Nested calls a script may make, the stack of WebAssembly is much smaller than that of native
+/// threads.
+const MAX_CALL_DEPTH: usize = 64;
+/// Name scripts are referred to by in diagnostics.
+const NAME: &str = "main.ul";
+
+/// Result of [`evaluate`].
+#[wasm_bindgen(getter_with_clone)]
+pub struct Evaluation {
+    /// Everything the script printed with `print`, `println` and `input`.
+    pub output: String,
+    /// Everything the script printed to the error stream, with `eprint`.
+    pub errors: String,
+    /// Rendered error which stopped the script, empty if it succeeded.
+    pub diagnostics: String,
+    /// Value of the final expression, `undefined` if the script failed.
+    pub value: Option<String>,
+}
+
+/// Runs a script in a fresh engine, collecting its output and errors.
+#[wasm_bindgen]
+pub fn evaluate(source: &str) -> Evaluation {
+    let io = BufferIo::new("").with_output_limit(MEMORY_LIMIT);
+    let mut engine = Engine::new()
+        .with_io(Box::new(io.clone()))
+        // The clock is virtual on wasm32 so it reveals nothing about the host.
+        .with_capabilities(Capabilities {
+            clock: true,
+            ..Capabilities::none()
+        })
+        .with_fuel(Some(FUEL))
+        .with_memory_limit(Some(MEMORY_LIMIT))
+        .with_max_call_depth(MAX_CALL_DEPTH);
+
+    let (value, diagnostics) = match engine.eval(source) {
+        Ok(value) => (Some(value.to_string()), String::new()),
+        Err(error) => {
+            let diagnostic = Diagnostic::from(&error);
+            (None, Renderer::new(source, NAME).render(&diagnostic))
+        }
+    };
+
+    Evaluation {
+        output: io.take_output(),
+        errors: io.take_errors(),
+        diagnostics,
+        value,
+    }
+}